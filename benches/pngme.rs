@@ -0,0 +1,155 @@
+//! Benchmarks over synthetic PNGs generated on the fly. The default sizes
+//! run in a few minutes; set `PNGME_BENCH_LARGE=1` to add files of up to
+//! 500 MB.
+
+use std::str::FromStr;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::parse_options::ParseOptions;
+use pngme::png::Png;
+
+const KB: usize = 1 << 10;
+const MB: usize = 1 << 20;
+/// The IDAT size most encoders split image data at.
+const IDAT_SIZE: usize = 64 * KB;
+
+fn sizes() -> Vec<usize> {
+    let mut sizes = vec![KB, 64 * KB, MB, 16 * MB];
+    if std::env::var_os("PNGME_BENCH_LARGE").is_some() {
+        sizes.extend([100 * MB, 500 * MB]);
+    }
+    sizes
+}
+
+fn label(size: usize) -> String {
+    if size >= MB {
+        format!("{}MB", size / MB)
+    } else {
+        format!("{}KB", size / KB)
+    }
+}
+
+fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+    Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+}
+
+/// A PNG of roughly `size` bytes whose image data is split into IDAT chunks,
+/// with a payload chunk just before IEND. The pixels are not valid deflate
+/// data, which none of the benchmarked operations look at.
+fn synthetic_png(size: usize) -> Png {
+    let mut chunks = vec![chunk("IHDR", vec![0, 0, 1, 0, 0, 0, 1, 0, 8, 6, 0, 0, 0])];
+    let mut remaining = size;
+    let mut seed = 0x2545_f491_u32;
+    while remaining > 0 {
+        let length = remaining.min(IDAT_SIZE);
+        let data = (0..length)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect();
+        chunks.push(chunk("IDAT", data));
+        remaining -= length;
+    }
+    chunks.push(chunk("ruSt", b"a hidden message".to_vec()));
+    chunks.push(chunk("IEND", Vec::new()));
+    Png::from_chunks(chunks)
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    let lazy = ParseOptions {
+        lazy_crc: true,
+        ..ParseOptions::default()
+    };
+    for size in sizes() {
+        let bytes = synthetic_png(size).as_bytes();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("checked", label(size)),
+            &bytes,
+            |b, bytes| b.iter(|| Png::try_from(black_box(&bytes[..])).unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("lazy_crc", label(size)),
+            &bytes,
+            |b, bytes| b.iter(|| Png::parse_with_options(black_box(bytes), &lazy).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn serialize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialize");
+    for size in sizes() {
+        let png = synthetic_png(size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(label(size)), &png, |b, png| {
+            b.iter(|| black_box(png).as_bytes())
+        });
+    }
+    group.finish();
+}
+
+fn crc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("crc");
+    let chunk_type = ChunkType::from_str("IDAT").unwrap();
+    for size in [KB, IDAT_SIZE, MB] {
+        let data = vec![0xa5; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(label(size)),
+            &data,
+            |b, data| b.iter(|| Chunk::new(chunk_type.clone(), black_box(data.clone())).crc()),
+        );
+    }
+    group.finish();
+}
+
+fn chunk_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chunk_by_type");
+    for size in sizes() {
+        // The payload chunk sits after every IDAT chunk, where a linear
+        // search would find it last and the type index finds it directly.
+        let png = synthetic_png(size);
+        group.bench_with_input(BenchmarkId::from_parameter(label(size)), &png, |b, png| {
+            b.iter(|| black_box(png).chunk("ruSt").unwrap().length())
+        });
+    }
+    group.finish();
+}
+
+fn encode_round_trip(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode_round_trip");
+    for size in sizes() {
+        let png = synthetic_png(size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(label(size)), &png, |b, png| {
+            b.iter(|| {
+                let mut png = png.clone();
+                png.append_chunk(chunk("seCr", b"round trip".to_vec()));
+                let decoded = Png::try_from(&png.as_bytes()[..]).unwrap();
+                decoded
+                    .chunk("seCr")
+                    .unwrap()
+                    .data_as_string()
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    parse,
+    serialize,
+    crc,
+    chunk_lookup,
+    encode_round_trip
+);
+criterion_main!(benches);
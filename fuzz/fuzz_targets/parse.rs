@@ -0,0 +1,33 @@
+//! Feeds arbitrary bytes to every parser that reads untrusted input. Any
+//! panic is a bug: malformed input must come back as an error.
+//!
+//! Run with `cargo +nightly fuzz run parse` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pngme::{metadata::MetadataMap, parse_options::ParseOptions, png};
+
+fuzz_target!(|data: &[u8]| {
+    for options in [ParseOptions::default(), ParseOptions::raw()] {
+        for chunk in png::scan_with_options(data, &options) {
+            let _ = chunk;
+        }
+        let Ok(png) = png::Png::parse_with_options(data, &options) else {
+            continue;
+        };
+        let _ = png.ihdr();
+        let _ = png.icc_profile();
+        let _ = png.exif();
+        let _ = png.gamma();
+        let _ = png.chromaticities();
+        let _ = png.physical_dimensions();
+        let _ = png.palette();
+        let _ = png.transparency();
+        let _ = png.background();
+        let _ = png.srgb();
+        let _ = MetadataMap::from_png(&png);
+        let _ = png.as_bytes();
+    }
+    let _ = pngme::detect::detect(data);
+});
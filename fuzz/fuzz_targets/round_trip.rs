@@ -0,0 +1,15 @@
+//! Builds structured PNGs with the `arbitrary` feature and checks that they
+//! survive serializing and parsing unchanged.
+//!
+//! Run with `cargo +nightly fuzz run round_trip` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pngme::png::Png;
+
+fuzz_target!(|png: Png| {
+    let bytes = png.as_bytes();
+    let parsed = Png::try_from(&bytes[..]).expect("serialized PNGs parse");
+    assert_eq!(parsed.as_bytes(), bytes);
+});
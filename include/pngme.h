@@ -0,0 +1,76 @@
+#ifndef PNGME_H
+#define PNGME_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum PngmeStatus {
+  PNGME_STATUS_OK = 0,
+  PNGME_STATUS_NULL_POINTER = 1,
+  PNGME_STATUS_INVALID_PNG = 2,
+  PNGME_STATUS_INVALID_CHUNK_TYPE = 3,
+  PNGME_STATUS_CHUNK_NOT_FOUND = 4,
+} PngmeStatus;
+
+/**
+ * An opaque handle to a parsed PNG, owned by the caller until passed to
+ * `pngme_free`.
+ */
+typedef struct PngmePng PngmePng;
+
+/**
+ * A byte buffer allocated by pngme. Release it with `pngme_buffer_free`.
+ */
+typedef struct PngmeBuffer {
+  uint8_t *data;
+  uintptr_t len;
+} PngmeBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Parses `len` bytes at `data` and stores a new handle in `out`.
+ */
+PngmeStatus pngme_parse(const uint8_t *data, uintptr_t len, struct PngmePng **out);
+
+/**
+ * Appends a chunk of the given type holding `len` bytes from `data`.
+ */
+PngmeStatus pngme_encode(struct PngmePng *png,
+                         const char *chunk_type,
+                         const uint8_t *data,
+                         uintptr_t len);
+
+/**
+ * Copies the data of the first chunk of the given type into `out`.
+ */
+PngmeStatus pngme_decode(const struct PngmePng *png,
+                         const char *chunk_type,
+                         struct PngmeBuffer *out);
+
+/**
+ * Serializes the PNG into `out`.
+ */
+PngmeStatus pngme_to_bytes(const struct PngmePng *png, struct PngmeBuffer *out);
+
+/**
+ * Releases a handle returned by `pngme_parse`. Passing NULL is a no-op.
+ */
+void pngme_free(struct PngmePng *png);
+
+/**
+ * Releases a buffer filled in by pngme.
+ */
+void pngme_buffer_free(struct PngmeBuffer buffer);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PNGME_H */
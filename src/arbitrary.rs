@@ -0,0 +1,112 @@
+//! `arbitrary` support, enabled with the `arbitrary` feature, so fuzzers and
+//! property tests can generate structured input instead of raw bytes.
+//! Generated chunk types are always valid and generated PNGs always start
+//! with `IHDR` and end with `IEND`, with anything in between.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, png::Png};
+
+impl<'a> Arbitrary<'a> for ChunkType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut bytes = [0u8; 4];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            let letter = b'a' + u.int_in_range(0..=25)?;
+            // The third letter is reserved and must be uppercase.
+            *byte = if i == 2 || bool::arbitrary(u)? {
+                letter.to_ascii_uppercase()
+            } else {
+                letter
+            };
+        }
+        Ok(ChunkType::try_from(bytes).expect("generated chunk types are valid"))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Chunk {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let chunk_type = ChunkType::arbitrary(u)?;
+        let data = Vec::<u8>::arbitrary(u)?;
+        Ok(Chunk::new(chunk_type, data))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Png {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let header = <[u8; 13]>::arbitrary(u)?.to_vec();
+        let mut chunks = vec![Chunk::new(chunk_type("IHDR"), header)];
+        for chunk in u.arbitrary_iter::<Chunk>()? {
+            let chunk = chunk?;
+            if !matches!(&chunk.chunk_type().bytes(), b"IHDR" | b"IEND") {
+                chunks.push(chunk);
+            }
+        }
+        chunks.push(Chunk::new(chunk_type("IEND"), Vec::new()));
+        Ok(Png::from_chunks(chunks))
+    }
+}
+
+fn chunk_type(name: &str) -> ChunkType {
+    name.parse().expect("standard chunk types are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn png_from(seed: &[u8]) -> Png {
+        Png::arbitrary(&mut Unstructured::new(seed)).unwrap()
+    }
+
+    fn chunk_bytes(chunks: &[Chunk]) -> Vec<Vec<u8>> {
+        chunks.iter().map(Chunk::as_bytes).collect()
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(seed in prop::collection::vec(any::<u8>(), 0..2048)) {
+            let png = png_from(&seed);
+            let parsed = Png::try_from(&png.as_bytes()[..]).unwrap();
+            prop_assert_eq!(chunk_bytes(parsed.chunks()), chunk_bytes(png.chunks()));
+            prop_assert_eq!(parsed.as_bytes(), png.as_bytes());
+        }
+
+        #[test]
+        fn insert_preserves_other_chunks(
+            seed in prop::collection::vec(any::<u8>(), 0..2048),
+            chunk_seed in prop::collection::vec(any::<u8>(), 0..64),
+            index in any::<prop::sample::Index>(),
+        ) {
+            let png = png_from(&seed);
+            let chunk = Chunk::arbitrary(&mut Unstructured::new(&chunk_seed)).unwrap();
+            let mut edited = png.clone();
+            edited.insert_chunk(index.index(png.chunks().len() + 1), chunk.clone()).unwrap();
+
+            // Insertion may move the chunk to a legal position, so accept
+            // any position that leaves the original chunks in order.
+            let original = chunk_bytes(png.chunks());
+            let edited = chunk_bytes(edited.chunks());
+            let preserved = (0..edited.len()).any(|i| {
+                let mut remaining = edited.clone();
+                remaining.remove(i) == chunk.as_bytes() && remaining == original
+            });
+            prop_assert!(preserved);
+        }
+
+        #[test]
+        fn remove_preserves_other_chunks(
+            seed in prop::collection::vec(any::<u8>(), 0..2048),
+            index in any::<prop::sample::Index>(),
+        ) {
+            let png = png_from(&seed);
+            let index = index.index(png.chunks().len());
+            let mut edited = png.clone();
+            edited.remove_chunk_at(index).unwrap();
+
+            let mut expected = chunk_bytes(png.chunks());
+            expected.remove(index);
+            prop_assert_eq!(chunk_bytes(edited.chunks()), expected);
+        }
+    }
+}
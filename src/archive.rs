@@ -0,0 +1,459 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+use sha2::{Digest, Sha256};
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, png::Png, Error, Result};
+
+/// A file stored in a PNG by [`pack`], with the metadata needed to restore
+/// it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedFile {
+    /// A plain file name, without directories.
+    pub name: String,
+    /// Seconds since the Unix epoch.
+    pub modified: u64,
+    /// Unix permission bits.
+    pub mode: u32,
+    pub data: Vec<u8>,
+}
+
+/// What the index chunk records about one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub name: String,
+    pub size: u64,
+    pub modified: u64,
+    pub mode: u32,
+    pub sha256: [u8; 32],
+    /// The SHA-256 of each data chunk the contents are made of, in order.
+    pub shards: Vec<[u8; 32]>,
+}
+
+/// The table of contents of an archive packed into a PNG. Each file is cut
+/// where its content says to (see [`content_defined_chunks`]) and every
+/// distinct piece is stored once, in its own data chunk, so packing a
+/// slightly changed file again reuses most of the chunks byte for byte.
+/// Files refer to their pieces by hash.
+///
+/// The encoding is the magic bytes, a version byte and a big-endian `u32`
+/// entry count, then per entry a `u16` name length, the UTF-8 name, `u64`
+/// size and modification time, `u32` mode, the SHA-256 of the contents, and
+/// a `u32` piece count followed by the SHA-256 of each piece.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Archive {
+    entries: Vec<IndexEntry>,
+}
+
+impl TryFrom<&Chunk> for Archive {
+    type Error = Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        if chunk.chunk_type().bytes() != Archive::INDEX_CHUNK_TYPE {
+            return Err(Box::new(ArchiveError::WrongChunkType(
+                chunk.chunk_type().to_string(),
+            )));
+        }
+        let mut reader = Reader(chunk.data());
+        if reader.take(Archive::MAGIC.len())? != Archive::MAGIC {
+            return Err(Box::new(ArchiveError::MissingMagic));
+        }
+        let version = reader.take(1)?[0];
+        if version != Archive::VERSION {
+            return Err(Box::new(ArchiveError::UnsupportedVersion(version)));
+        }
+
+        let count = u32::from_be_bytes(reader.array()?);
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let name_length = u16::from_be_bytes(reader.array()?);
+            let name = String::from_utf8(reader.take(name_length.into())?.to_vec())?;
+            entries.push(IndexEntry {
+                name,
+                size: u64::from_be_bytes(reader.array()?),
+                modified: u64::from_be_bytes(reader.array()?),
+                mode: u32::from_be_bytes(reader.array()?),
+                sha256: reader.array()?,
+                shards: (0..u32::from_be_bytes(reader.array()?))
+                    .map(|_| reader.array())
+                    .collect::<Result<_>>()?,
+            });
+        }
+        if !reader.0.is_empty() {
+            return Err(Box::new(ArchiveError::TrailingBytes(reader.0.len())));
+        }
+        Ok(Archive { entries })
+    }
+}
+
+impl Archive {
+    pub const INDEX_CHUNK_TYPE: [u8; 4] = *b"arIx";
+    pub const DATA_CHUNK_TYPE: [u8; 4] = *b"arDt";
+    pub const MAGIC: [u8; 4] = *b"PMar";
+    pub const VERSION: u8 = 2;
+    /// The least file data one chunk holds, unless it ends a file.
+    pub const MIN_CHUNK_DATA: usize = 1 << 16;
+    /// The most file data one chunk holds.
+    pub const MAX_CHUNK_DATA: usize = 1 << 20;
+    /// Cuts are made where the top this many bits of the rolling hash are
+    /// zero, giving pieces of about 256 KiB.
+    const BOUNDARY_BITS: u32 = 18;
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = Archive::MAGIC.to_vec();
+        data.push(Archive::VERSION);
+        data.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+        for entry in &self.entries {
+            data.extend_from_slice(&(entry.name.len() as u16).to_be_bytes());
+            data.extend_from_slice(entry.name.as_bytes());
+            data.extend_from_slice(&entry.size.to_be_bytes());
+            data.extend_from_slice(&entry.modified.to_be_bytes());
+            data.extend_from_slice(&entry.mode.to_be_bytes());
+            data.extend_from_slice(&entry.sha256);
+            data.extend_from_slice(&(entry.shards.len() as u32).to_be_bytes());
+            for shard in &entry.shards {
+                data.extend_from_slice(shard);
+            }
+        }
+        Chunk::new(
+            ChunkType::from_bytes_unchecked(Archive::INDEX_CHUNK_TYPE),
+            data,
+        )
+    }
+}
+
+/// Random values for the rolling hash, one per byte value, from SplitMix64
+/// so that the cut points never change between builds.
+const GEAR: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut state: u64 = 0;
+    let mut i = 0;
+    while i < table.len() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Cuts `data` into pieces of [`Archive::MIN_CHUNK_DATA`] to
+/// [`Archive::MAX_CHUNK_DATA`] bytes at points chosen by a rolling hash of
+/// the last 64 bytes. Since the cut points depend only on nearby content,
+/// inserting or deleting bytes changes the pieces around the edit and
+/// leaves the rest as they were.
+pub fn content_defined_chunks(data: &[u8]) -> Vec<&[u8]> {
+    let boundary = !0u64 << (64 - Archive::BOUNDARY_BITS);
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut hash = 0u64;
+    for (i, &byte) in data.iter().enumerate() {
+        hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+        let length = i + 1 - start;
+        if length >= Archive::MIN_CHUNK_DATA && hash & boundary == 0
+            || length >= Archive::MAX_CHUNK_DATA
+        {
+            pieces.push(&data[start..=i]);
+            start = i + 1;
+            hash = 0;
+        }
+    }
+    if start < data.len() {
+        pieces.push(&data[start..]);
+    }
+    pieces
+}
+
+/// Stores `files` in `png` as an index chunk followed by one data chunk per
+/// distinct piece of their contents. A PNG holds at most one archive.
+pub fn pack(png: &mut Png, files: &[PackedFile]) -> Result<()> {
+    if png.archive().is_some() {
+        return Err(Box::new(ArchiveError::AlreadyPacked));
+    }
+    let mut archive = Archive::default();
+    let mut stored = HashSet::new();
+    let mut pieces = Vec::new();
+    for file in files {
+        check_name(&file.name)?;
+        if archive.entries.iter().any(|e| e.name == file.name) {
+            return Err(Box::new(ArchiveError::DuplicateName(file.name.clone())));
+        }
+        let mut shards = Vec::new();
+        for piece in content_defined_chunks(&file.data) {
+            let hash: [u8; 32] = Sha256::digest(piece).into();
+            if stored.insert(hash) {
+                pieces.push(piece);
+            }
+            shards.push(hash);
+        }
+        archive.entries.push(IndexEntry {
+            name: file.name.clone(),
+            size: file.data.len() as u64,
+            modified: file.modified,
+            mode: file.mode,
+            sha256: Sha256::digest(&file.data).into(),
+            shards,
+        });
+    }
+    let total: usize = archive.entries.iter().map(|e| e.shards.len()).sum();
+    log::debug!("Storing {} distinct pieces of {}", pieces.len(), total);
+
+    png.append_chunk(archive.to_chunk());
+    for piece in pieces {
+        png.append_chunk(Chunk::new(
+            ChunkType::from_bytes_unchecked(Archive::DATA_CHUNK_TYPE),
+            piece.to_vec(),
+        ));
+    }
+    Ok(())
+}
+
+/// Removes the archive [`pack`] stored, returning whether there was one.
+pub fn remove(png: &mut Png) -> bool {
+    let packed = png.archive().is_some();
+    png.retain_chunks(|c| {
+        let chunk_type = c.chunk_type().bytes();
+        chunk_type != Archive::INDEX_CHUNK_TYPE && chunk_type != Archive::DATA_CHUNK_TYPE
+    });
+    packed
+}
+
+/// The files [`pack`] stored in `png`, checked against the sizes and
+/// digests in the index.
+pub fn unpack(png: &Png) -> Result<Vec<PackedFile>> {
+    let archive = png.archive().ok_or(ArchiveError::NotPacked)??;
+    let pieces: HashMap<[u8; 32], &[u8]> = png
+        .chunks()
+        .iter()
+        .filter(|c| c.chunk_type().bytes() == Archive::DATA_CHUNK_TYPE)
+        .map(|c| (Sha256::digest(c.data()).into(), c.data()))
+        .collect();
+
+    let mut files = Vec::new();
+    for entry in archive.entries {
+        check_name(&entry.name)?;
+        let mut contents = Vec::new();
+        for shard in &entry.shards {
+            let piece = pieces
+                .get(shard)
+                .ok_or_else(|| ArchiveError::MissingShard(entry.name.clone()))?;
+            contents.extend_from_slice(piece);
+        }
+        if contents.len() as u64 != entry.size {
+            return Err(Box::new(ArchiveError::SizeMismatch(
+                entry.name,
+                entry.size,
+                contents.len(),
+            )));
+        }
+        if <[u8; 32]>::from(Sha256::digest(&contents)) != entry.sha256 {
+            return Err(Box::new(ArchiveError::DigestMismatch(entry.name)));
+        }
+        files.push(PackedFile {
+            name: entry.name,
+            modified: entry.modified,
+            mode: entry.mode,
+            data: contents,
+        });
+    }
+    Ok(files)
+}
+
+/// Names are plain file names so that unpacking cannot write outside the
+/// chosen directory.
+fn check_name(name: &str) -> Result<()> {
+    let unsafe_name = name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.len() > u16::MAX as usize
+        || name.contains(['/', '\\', '\0']);
+    if unsafe_name {
+        return Err(Box::new(ArchiveError::InvalidName(name.to_string())));
+    }
+    Ok(())
+}
+
+/// Reads big-endian fields from the front of a byte slice.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        if self.0.len() < length {
+            return Err(Box::new(ArchiveError::Truncated));
+        }
+        let (taken, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Ok(taken)
+    }
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into()?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ArchiveError {
+    WrongChunkType(String),
+    MissingMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    TrailingBytes(usize),
+    AlreadyPacked,
+    NotPacked,
+    DuplicateName(String),
+    InvalidName(String),
+    MissingShard(String),
+    SizeMismatch(String, u64, usize),
+    DigestMismatch(String),
+}
+impl std::error::Error for ArchiveError {}
+impl Display for ArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            ArchiveError::WrongChunkType(chunk_type) => {
+                crate::tr!("archive.wrong_chunk_type", chunk_type = chunk_type)
+            }
+            ArchiveError::MissingMagic => crate::tr!("archive.missing_magic"),
+            ArchiveError::UnsupportedVersion(version) => {
+                crate::tr!("archive.unsupported_version", version = version)
+            }
+            ArchiveError::Truncated => crate::tr!("archive.truncated"),
+            ArchiveError::TrailingBytes(count) => {
+                crate::tr!("archive.trailing_bytes", count = count)
+            }
+            ArchiveError::AlreadyPacked => crate::tr!("archive.already_packed"),
+            ArchiveError::NotPacked => crate::tr!("archive.not_packed"),
+            ArchiveError::DuplicateName(name) => {
+                crate::tr!("archive.duplicate_name", name = format!("{:?}", name))
+            }
+            ArchiveError::InvalidName(name) => {
+                crate::tr!("archive.invalid_name", name = format!("{:?}", name))
+            }
+            ArchiveError::MissingShard(name) => crate::tr!("archive.missing_shard", name = name),
+            ArchiveError::SizeMismatch(name, expected, found) => crate::tr!(
+                "archive.size_mismatch",
+                name = name,
+                expected = expected,
+                found = found
+            ),
+            ArchiveError::DigestMismatch(name) => {
+                crate::tr!("archive.digest_mismatch", name = name)
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    fn file(name: &str, data: &[u8]) -> PackedFile {
+        PackedFile {
+            name: name.to_string(),
+            modified: 1_700_000_000,
+            mode: 0o640,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_pack_round_trip() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let big = vec![7; Archive::MAX_CHUNK_DATA + 10];
+        let files = vec![
+            file("notes.txt", b"hello"),
+            file("empty", b""),
+            file("big.bin", &big),
+        ];
+        pack(&mut png, &files).unwrap();
+        assert_eq!(png.chunks_of("arDt").count(), 3);
+
+        let reparsed = Png::try_from(png.as_bytes().as_slice()).unwrap();
+        assert_eq!(unpack(&reparsed).unwrap(), files);
+        assert!(pack(&mut png, &files).is_err());
+    }
+
+    /// Deterministic bytes that look random to the rolling hash.
+    fn noise(length: usize) -> Vec<u8> {
+        let mut state = 1u32;
+        (0..length)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_content_defined_chunks() {
+        let data = noise(4 << 20);
+        let pieces = content_defined_chunks(&data);
+        assert_eq!(pieces.concat(), data);
+        assert!(pieces.len() > 4);
+        for piece in &pieces[..pieces.len() - 1] {
+            assert!((Archive::MIN_CHUNK_DATA..=Archive::MAX_CHUNK_DATA).contains(&piece.len()));
+        }
+
+        // An insertion only changes the pieces around it.
+        let mut edited = data.clone();
+        edited.insert(2 << 20, 0xff);
+        let before: HashSet<&[u8]> = pieces.into_iter().collect();
+        let after = content_defined_chunks(&edited);
+        let changed = after.iter().filter(|p| !before.contains(*p)).count();
+        assert!(
+            changed <= 2,
+            "{} of {} pieces changed",
+            changed,
+            after.len()
+        );
+    }
+
+    #[test]
+    fn test_dedup() {
+        let data = noise(1 << 20);
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        pack(&mut png, &[file("a", &data)]).unwrap();
+        let single = png.chunks_of("arDt").count();
+
+        assert!(remove(&mut png));
+        pack(&mut png, &[file("a", &data), file("copy", &data)]).unwrap();
+        assert_eq!(png.chunks_of("arDt").count(), single);
+        let files = unpack(&png).unwrap();
+        assert_eq!(files[1].data, data);
+    }
+
+    #[test]
+    fn test_pack_errors() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(unpack(&png).is_err());
+        assert!(pack(&mut png, &[file("../escape", b"x")]).is_err());
+        assert!(pack(&mut png, &[file("a", b"x"), file("a", b"y")]).is_err());
+    }
+
+    #[test]
+    fn test_tampered_data() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        pack(&mut png, &[file("a", b"abc")]).unwrap();
+        let index = png
+            .chunks()
+            .iter()
+            .position(|c| c.chunk_type().bytes() == Archive::DATA_CHUNK_TYPE)
+            .unwrap();
+        png.remove_chunk_at(index).unwrap();
+        png.insert_chunk(
+            index,
+            Chunk::new(
+                ChunkType::from_bytes_unchecked(Archive::DATA_CHUNK_TYPE),
+                b"abd".to_vec(),
+            ),
+        )
+        .unwrap();
+        assert!(unpack(&png).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{Error, Result};
+
+/// How much a file may grow when it is modified: a number of bytes such as
+/// `65536` or `64K`, or a percentage of its original size such as `5%`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GrowthBudget {
+    Bytes(usize),
+    Percent(f64),
+}
+
+impl FromStr for GrowthBudget {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || -> Error { Box::new(BudgetError::InvalidBudget(s.to_string())) };
+        match s.trim().strip_suffix('%') {
+            Some(percent) => match percent.trim().parse::<f64>() {
+                Ok(percent) if percent.is_finite() && percent >= 0.0 => {
+                    Ok(GrowthBudget::Percent(percent))
+                }
+                _ => Err(invalid()),
+            },
+            None => parse_size(s)
+                .map(GrowthBudget::Bytes)
+                .map_err(|_| invalid()),
+        }
+    }
+}
+
+impl Display for GrowthBudget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GrowthBudget::Bytes(bytes) => write!(f, "{} bytes", bytes),
+            GrowthBudget::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+impl GrowthBudget {
+    /// The most a file of `original` bytes may grow by.
+    pub fn allowed(self, original: usize) -> usize {
+        match self {
+            GrowthBudget::Bytes(bytes) => bytes,
+            GrowthBudget::Percent(percent) => (original as f64 * percent / 100.0) as usize,
+        }
+    }
+    /// Fails if going from `original` to `modified` bytes is over budget.
+    pub fn check(self, original: usize, modified: usize) -> Result<()> {
+        let growth = modified.saturating_sub(original);
+        let allowed = self.allowed(original);
+        if growth > allowed {
+            return Err(Box::new(BudgetError::Exceeded { growth, allowed }));
+        }
+        Ok(())
+    }
+}
+
+/// A byte count, optionally with a binary K, M or G suffix and a trailing
+/// B or iB, so `64K`, `64KB` and `64KiB` are all 65536.
+pub fn parse_size(s: &str) -> Result<usize> {
+    let invalid = || -> Error { Box::new(BudgetError::InvalidSize(s.to_string())) };
+    let upper = s.trim().to_ascii_uppercase();
+    let digits = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (digits, shift) = match digits.as_bytes().last() {
+        Some(b'K') => (&digits[..digits.len() - 1], 10),
+        Some(b'M') => (&digits[..digits.len() - 1], 20),
+        Some(b'G') => (&digits[..digits.len() - 1], 30),
+        _ => (digits, 0),
+    };
+    let value: usize = digits.parse().map_err(|_| invalid())?;
+    value.checked_mul(1 << shift).ok_or_else(invalid)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BudgetError {
+    InvalidSize(String),
+    InvalidBudget(String),
+    Exceeded { growth: usize, allowed: usize },
+}
+impl std::error::Error for BudgetError {}
+impl Display for BudgetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            BudgetError::InvalidSize(s) => {
+                crate::tr!("budget.invalid_size", size = format!("{:?}", s))
+            }
+            BudgetError::InvalidBudget(s) => {
+                crate::tr!("budget.invalid_budget", budget = format!("{:?}", s))
+            }
+            BudgetError::Exceeded { growth, allowed } => {
+                crate::tr!("budget.exceeded", growth = growth, allowed = allowed)
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_size("64K").unwrap(), 65536);
+        assert_eq!(parse_size("1MiB").unwrap(), 1 << 20);
+        assert!(parse_size("lots").is_err());
+        assert_eq!(
+            GrowthBudget::from_str("64K").unwrap(),
+            GrowthBudget::Bytes(65536)
+        );
+        assert_eq!(
+            GrowthBudget::from_str("2.5%").unwrap(),
+            GrowthBudget::Percent(2.5)
+        );
+        assert!(GrowthBudget::from_str("-5%").is_err());
+        assert!(GrowthBudget::from_str("%").is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let budget = GrowthBudget::Percent(10.0);
+        assert_eq!(budget.allowed(4803), 480);
+        assert!(budget.check(4803, 5283).is_ok());
+        assert!(budget.check(4803, 5284).is_err());
+        assert!(budget.check(4803, 100).is_ok());
+        assert!(GrowthBudget::Bytes(0).check(10, 11).is_err());
+    }
+}
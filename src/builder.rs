@@ -0,0 +1,153 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{
+    chunk::Chunk, chunk_type::ChunkType, ihdr::Ihdr, metadata::MetadataMap,
+    ordering::InsertionPolicy, png::Png, Result,
+};
+
+/// Assembles a [`Png`] from an image header and chunks, checking the
+/// specification's ordering rules when [`PngBuilder::build`] is called.
+///
+/// Chunks are kept in the order they were added and `IEND` is appended
+/// automatically. Text entries are written as `tEXt` (or `iTXt` for values
+/// outside Latin-1) after all other chunks.
+#[derive(Debug, Clone, Default)]
+pub struct PngBuilder {
+    header: Option<Ihdr>,
+    chunks: Vec<Chunk>,
+    text: Vec<(String, String)>,
+}
+
+impl PngBuilder {
+    pub fn new() -> PngBuilder {
+        PngBuilder::default()
+    }
+    pub fn header(mut self, ihdr: Ihdr) -> PngBuilder {
+        self.header = Some(ihdr);
+        self
+    }
+    pub fn chunk(mut self, chunk: Chunk) -> PngBuilder {
+        self.chunks.push(chunk);
+        self
+    }
+    pub fn text(mut self, keyword: &str, text: &str) -> PngBuilder {
+        self.text.push((keyword.to_string(), text.to_string()));
+        self
+    }
+    /// Fails if there is no header, if `IHDR` or `IEND` were added as plain
+    /// chunks, if a chunk is in a position the specification forbids, or if
+    /// a text keyword is invalid.
+    pub fn build(self) -> Result<Png> {
+        let header = self.header.ok_or(BuilderError::MissingHeader)?;
+        let mut png = Png::from_chunks(vec![
+            header.to_chunk(),
+            Chunk::new(ChunkType::from_str("IEND")?, Vec::new()),
+        ]);
+        png.set_insertion_policy(InsertionPolicy::Strict);
+
+        let text_chunks = self
+            .text
+            .iter()
+            .map(|(keyword, text)| MetadataMap::text_chunk(keyword, text))
+            .collect::<Result<Vec<_>>>()?;
+        for chunk in self.chunks.into_iter().chain(text_chunks) {
+            if let b"IHDR" | b"IEND" = &chunk.chunk_type().bytes() {
+                return Err(Box::new(BuilderError::ReservedChunk(
+                    chunk.chunk_type().to_string(),
+                )));
+            }
+            let before_iend = png.chunks().len() - 1;
+            png.insert_chunk(before_iend, chunk)?;
+        }
+
+        png.set_insertion_policy(InsertionPolicy::default());
+        Ok(png)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BuilderError {
+    MissingHeader,
+    ReservedChunk(String),
+}
+impl std::error::Error for BuilderError {}
+impl Display for BuilderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            BuilderError::MissingHeader => crate::tr!("builder.missing_header"),
+            BuilderError::ReservedChunk(chunk_type) => {
+                crate::tr!("builder.reserved_chunk", chunk_type = chunk_type)
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Gamma;
+
+    fn ihdr() -> Ihdr {
+        Ihdr {
+            width: 1,
+            height: 1,
+            bit_depth: 8,
+            color_type: Ihdr::TRUECOLOR,
+            compression_method: 0,
+            filter_method: 0,
+            interlace_method: 0,
+        }
+    }
+
+    fn idat() -> Chunk {
+        Chunk::new(ChunkType::from_str("IDAT").unwrap(), vec![1, 2, 3])
+    }
+
+    #[test]
+    fn test_build() {
+        let png = PngBuilder::new()
+            .header(ihdr())
+            .chunk(Gamma(0.45455).to_chunk())
+            .chunk(idat())
+            .text("Author", "me")
+            .build()
+            .unwrap();
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "gAMA", "IDAT", "tEXt", "IEND"]);
+        assert_eq!(png.ihdr().unwrap().unwrap(), ihdr());
+
+        let reparsed = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert_eq!(reparsed.as_bytes(), png.as_bytes());
+    }
+
+    #[test]
+    fn test_ordering_is_enforced() {
+        let result = PngBuilder::new()
+            .header(ihdr())
+            .chunk(idat())
+            .chunk(Gamma(0.45455).to_chunk())
+            .build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_invalid_builds() {
+        assert!(PngBuilder::new().chunk(idat()).build().is_err());
+        assert!(PngBuilder::new()
+            .header(ihdr())
+            .chunk(ihdr().to_chunk())
+            .build()
+            .is_err());
+        assert!(PngBuilder::new()
+            .header(ihdr())
+            .text(" padded", "x")
+            .build()
+            .is_err());
+    }
+}
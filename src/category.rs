@@ -0,0 +1,91 @@
+use std::fmt::Display;
+
+use crate::chunk_type::ChunkType;
+
+/// What a chunk is for, so whole families of chunks can be stripped or kept
+/// without naming every type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChunkCategory {
+    /// IHDR, PLTE, IDAT and IEND, without which there is no image.
+    Image,
+    /// Color space information: cHRM, cICP, gAMA, iCCP, mDCV, cLLI, sBIT and
+    /// sRGB.
+    Color,
+    /// tRNS and bKGD, which change how the image looks when composited.
+    Transparency,
+    /// tEXt, zTXt and iTXt.
+    Text,
+    /// tIME.
+    Time,
+    /// eXIf.
+    Exif,
+    /// Physical size and calibration: pHYs, oFFs, pCAL and sCAL.
+    Physical,
+    /// Palette statistics: hIST and sPLT.
+    Palette,
+    /// APNG frames and control: acTL, fcTL and fdAT.
+    Animation,
+    /// Anything the specification does not define.
+    Private,
+}
+
+impl ChunkCategory {
+    pub fn of(chunk_type: &ChunkType) -> ChunkCategory {
+        match &chunk_type.bytes() {
+            b"IHDR" | b"PLTE" | b"IDAT" | b"IEND" => ChunkCategory::Image,
+            b"cHRM" | b"cICP" | b"gAMA" | b"iCCP" | b"mDCV" | b"cLLI" | b"sBIT" | b"sRGB" => {
+                ChunkCategory::Color
+            }
+            b"tRNS" | b"bKGD" => ChunkCategory::Transparency,
+            b"tEXt" | b"zTXt" | b"iTXt" => ChunkCategory::Text,
+            b"tIME" => ChunkCategory::Time,
+            b"eXIf" => ChunkCategory::Exif,
+            b"pHYs" | b"oFFs" | b"pCAL" | b"sCAL" => ChunkCategory::Physical,
+            b"hIST" | b"sPLT" => ChunkCategory::Palette,
+            b"acTL" | b"fcTL" | b"fdAT" => ChunkCategory::Animation,
+            _ => ChunkCategory::Private,
+        }
+    }
+}
+
+impl Display for ChunkCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChunkCategory::Image => write!(f, "image"),
+            ChunkCategory::Color => write!(f, "color"),
+            ChunkCategory::Transparency => write!(f, "transparency"),
+            ChunkCategory::Text => write!(f, "text"),
+            ChunkCategory::Time => write!(f, "time"),
+            ChunkCategory::Exif => write!(f, "exif"),
+            ChunkCategory::Physical => write!(f, "physical"),
+            ChunkCategory::Palette => write!(f, "palette"),
+            ChunkCategory::Animation => write!(f, "animation"),
+            ChunkCategory::Private => write!(f, "private"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::STANDARD_CHUNK_TYPES;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_every_standard_type_is_categorized() {
+        for chunk_type in STANDARD_CHUNK_TYPES {
+            let category = ChunkCategory::of(&ChunkType::from_str(chunk_type).unwrap());
+            assert_ne!(category, ChunkCategory::Private, "{}", chunk_type);
+        }
+    }
+
+    #[test]
+    fn test_categories() {
+        let of = |s| ChunkCategory::of(&ChunkType::from_str(s).unwrap());
+        assert_eq!(of("IDAT"), ChunkCategory::Image);
+        assert_eq!(of("iCCP"), ChunkCategory::Color);
+        assert_eq!(of("zTXt"), ChunkCategory::Text);
+        assert_eq!(of("RuSt"), ChunkCategory::Private);
+        assert_eq!(of("ruSt").to_string(), "private");
+    }
+}
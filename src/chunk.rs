@@ -1,53 +1,93 @@
 use std::fmt::Display;
+use std::sync::OnceLock;
 
-use crate::{chunk_type::ChunkType, Error, Result};
+use crate::{chunk_type::ChunkType, parse_options::ParseOptions, Error, Result};
 
+#[derive(Debug, Clone)]
 pub struct Chunk {
     chunk_type: ChunkType,
     data: Vec<u8>,
+    crc: u32,
+    /// Whether `crc` matches the contents, computed on first use.
+    crc_valid: OnceLock<bool>,
+    /// Where the chunk's length field was in the file it was parsed from.
+    offset: Option<usize>,
 }
 
-impl TryFrom<&[u8]> for Chunk {
-    type Error = Error;
-
-    fn try_from(bytes: &[u8]) -> Result<Self> {
-        let (chunk_data_length_bytes, bytes) = bytes.split_at(Chunk::LENGTH_SIZE);
-        let chunk_data_length = u32::from_be_bytes(chunk_data_length_bytes.try_into()?) as usize;
-
-        // Check whether the input slice can provide as many bytes as we need.
-        if (chunk_data_length + (Chunk::METADATA_SIZE - Chunk::LENGTH_SIZE)) > bytes.len() {
-            return Err(Box::new(ChunkError::InputTooSmall(
-                chunk_data_length,
-                bytes.len(),
-            )));
-        }
+/// A chunk split at its boundaries but not yet validated. Borrowing the data
+/// keeps boundary scanning cheap; converting to a [`Chunk`] checks the type and
+/// CRC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawChunk<'a> {
+    chunk_type: [u8; 4],
+    data: &'a [u8],
+    crc: u32,
+}
 
-        let (chunk_type_bytes, bytes) = bytes.split_at(Chunk::CHUNK_TYPE_SIZE);
-        let chunk_type_bytes: [u8; 4] = chunk_type_bytes.try_into()?;
-        let chunk_type = ChunkType::try_from(chunk_type_bytes)?;
+impl<'a> TryFrom<&'a [u8]> for RawChunk<'a> {
+    type Error = Error;
 
-        if !chunk_type.is_valid() {
-            return Err(Box::new(ChunkError::InvalidChunkType(
-                chunk_type.to_string(),
-            )));
-        }
+    fn try_from(bytes: &'a [u8]) -> Result<Self> {
+        // Every step uses checked splitting, so that a short slice or a length
+        // field near u32::MAX is an error rather than a panic or overflow.
+        let too_small = |required: usize| ChunkError::InputTooSmall(required, bytes.len());
+        let (length_bytes, rest) = bytes
+            .split_first_chunk::<{ Chunk::LENGTH_SIZE }>()
+            .ok_or_else(|| too_small(Chunk::METADATA_SIZE))?;
+        let chunk_data_length = u32::from_be_bytes(*length_bytes) as usize;
+        let required = chunk_data_length.saturating_add(Chunk::METADATA_SIZE);
+
+        let (chunk_type, rest) = rest
+            .split_first_chunk::<{ Chunk::CHUNK_TYPE_SIZE }>()
+            .ok_or_else(|| too_small(required))?;
+        let (data, rest) = rest
+            .split_at_checked(chunk_data_length)
+            .ok_or_else(|| too_small(required))?;
+        let (crc_bytes, _) = rest
+            .split_first_chunk::<{ Chunk::CRC_SIZE }>()
+            .ok_or_else(|| too_small(required))?;
+
+        Ok(RawChunk {
+            chunk_type: *chunk_type,
+            data,
+            crc: u32::from_be_bytes(*crc_bytes),
+        })
+    }
+}
 
-        let (chunk_data, bytes) = bytes.split_at(chunk_data_length);
-        let (checksum_bytes, _) = bytes.split_at(Chunk::CRC_SIZE);
+impl RawChunk<'_> {
+    pub fn chunk_type(&self) -> [u8; 4] {
+        self.chunk_type
+    }
+    pub fn data(&self) -> &[u8] {
+        self.data
+    }
+    pub fn length(&self) -> usize {
+        self.data.len()
+    }
+    /// The CRC stored in the file, which may not match the contents.
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+    /// The number of bytes this chunk occupies in the file.
+    pub fn total_length(&self) -> usize {
+        self.data.len() + Chunk::METADATA_SIZE
+    }
+}
 
-        let new_chunk = Chunk::new(chunk_type, chunk_data.to_owned());
+impl TryFrom<&[u8]> for Chunk {
+    type Error = Error;
 
-        let checksum = u32::from_be_bytes(checksum_bytes.try_into()?);
-        let calculated_checksum = new_chunk.crc();
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        Chunk::try_from(RawChunk::try_from(bytes)?)
+    }
+}
 
-        if checksum != calculated_checksum {
-            return Err(Box::new(ChunkError::InvalidCrc(
-                calculated_checksum,
-                checksum,
-            )));
-        }
+impl TryFrom<RawChunk<'_>> for Chunk {
+    type Error = Error;
 
-        Ok(new_chunk)
+    fn try_from(raw: RawChunk<'_>) -> Result<Self> {
+        Chunk::from_raw_with_options(raw, &ParseOptions::default())
     }
 }
 
@@ -68,9 +108,51 @@ impl Chunk {
     pub const LENGTH_SIZE: usize = 4;
     pub const CRC_SIZE: usize = 4;
     pub const METADATA_SIZE: usize = Chunk::CHUNK_TYPE_SIZE + Chunk::LENGTH_SIZE + Chunk::CRC_SIZE;
+    /// The specification limits chunk data to 2^31 - 1 bytes.
+    pub const MAX_LENGTH: usize = (1 << 31) - 1;
 
     pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Chunk {
-        Chunk { chunk_type, data }
+        let crc = Chunk::checksum(&chunk_type, &data);
+        Chunk {
+            chunk_type,
+            data,
+            crc,
+            crc_valid: OnceLock::from(true),
+            offset: None,
+        }
+    }
+    /// Keeps a chunk exactly as it was read, even if its type is malformed or
+    /// its stored CRC does not match, so that it can be written back verbatim.
+    pub fn from_raw_unchecked(raw: RawChunk<'_>) -> Chunk {
+        Chunk {
+            chunk_type: ChunkType::from_bytes_unchecked(raw.chunk_type),
+            data: raw.data.to_owned(),
+            crc: raw.crc,
+            crc_valid: OnceLock::new(),
+            offset: None,
+        }
+    }
+    /// Validates a raw chunk, applying only the checks enabled in `options`.
+    pub fn from_raw_with_options(raw: RawChunk<'_>, options: &ParseOptions) -> Result<Chunk> {
+        let chunk_type = ChunkType::from_bytes_unchecked(raw.chunk_type);
+
+        let malformed = raw.chunk_type.iter().any(|b| !b.is_ascii_alphabetic());
+        if malformed && options.reject_malformed_chunk_type {
+            return Err(Box::new(ChunkError::InvalidChunkType(
+                chunk_type.to_string(),
+            )));
+        }
+        if !chunk_type.is_reserved_bit_valid() && options.reject_invalid_reserved_bit {
+            return Err(Box::new(ChunkError::InvalidChunkType(
+                chunk_type.to_string(),
+            )));
+        }
+
+        let chunk = Chunk::from_raw_unchecked(raw);
+        if options.reject_bad_crc && !options.lazy_crc {
+            chunk.verify()?;
+        }
+        Ok(chunk)
     }
     pub fn length(&self) -> usize {
         self.data.len()
@@ -82,19 +164,47 @@ impl Chunk {
         &self.data
     }
     pub fn crc(&self) -> u32 {
-        let bytes: Vec<u8> = self
-            .chunk_type
-            .bytes()
-            .iter()
-            .cloned()
-            .chain(self.data.iter().cloned())
-            .collect();
-        crc::crc32::checksum_ieee(&bytes)
+        self.crc
+    }
+    pub fn is_crc_valid(&self) -> bool {
+        *self
+            .crc_valid
+            .get_or_init(|| self.crc == Chunk::checksum(&self.chunk_type, &self.data))
+    }
+    /// The byte offset the chunk was read from, or `None` if it was created
+    /// rather than parsed from a file.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+    pub(crate) fn with_offset(mut self, offset: usize) -> Chunk {
+        self.offset = Some(offset);
+        self
+    }
+    /// Checks the stored CRC against the contents. Chunks parsed with
+    /// [`ParseOptions::lazy_crc`] are only checked here, on first use.
+    pub fn verify(&self) -> Result<()> {
+        if !self.is_crc_valid() {
+            return Err(Box::new(ChunkError::InvalidCrc(
+                Chunk::checksum(&self.chunk_type, &self.data),
+                self.crc,
+            )));
+        }
+        Ok(())
+    }
+    /// The chunk data, after verifying its CRC.
+    pub fn checked_data(&self) -> Result<&[u8]> {
+        self.verify()?;
+        Ok(&self.data)
     }
     pub fn data_as_string(&self) -> Result<String> {
         let s = std::str::from_utf8(&self.data)?;
         Ok(String::from(s))
     }
+    /// Like [`Chunk::data_as_string`], but invalid UTF-8 sequences are
+    /// replaced with U+FFFD instead of failing.
+    pub fn data_as_string_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.data).into_owned()
+    }
     pub fn as_bytes(&self) -> Vec<u8> {
         let length_bytes = u32::to_be_bytes(self.data().len() as u32);
         let type_bytes = self.chunk_type().bytes();
@@ -107,10 +217,20 @@ impl Chunk {
             .copied()
             .collect::<Vec<u8>>()
     }
+
+    fn checksum(chunk_type: &ChunkType, data: &[u8]) -> u32 {
+        let bytes: Vec<u8> = chunk_type
+            .bytes()
+            .iter()
+            .cloned()
+            .chain(data.iter().cloned())
+            .collect();
+        crc::crc32::checksum_ieee(&bytes)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-enum ChunkError {
+pub(crate) enum ChunkError {
     InputTooSmall(usize, usize),
     InvalidCrc(u32, u32),
     InvalidChunkType(String),
@@ -120,17 +240,20 @@ impl Display for ChunkError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ChunkError::InputTooSmall(required, available) => {
-                write!(
-                    f,
-                    "Input size {} too small, expected {}",
-                    available, required
-                )
+                let message = crate::tr!(
+                    "chunk.input_too_small",
+                    available = available,
+                    required = required
+                );
+                write!(f, "{}", message)
             }
             ChunkError::InvalidCrc(expected, actual) => {
-                write!(f, "Invalid CRC {}, expected {}", actual, expected)
+                let message = crate::tr!("chunk.invalid_crc", actual = actual, expected = expected);
+                write!(f, "{}", message)
             }
             ChunkError::InvalidChunkType(chunk_type) => {
-                write!(f, "Invalid chunk type {}", chunk_type)
+                let message = crate::tr!("chunk.invalid_chunk_type", chunk_type = chunk_type);
+                write!(f, "{}", message)
             }
         }
     }
@@ -191,6 +314,13 @@ mod tests {
         assert_eq!(chunk_string, expected_chunk_string);
     }
 
+    #[test]
+    fn test_chunk_string_lossy() {
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"caf\xe9".to_vec());
+        assert!(chunk.data_as_string().is_err());
+        assert_eq!(chunk.data_as_string_lossy(), "caf\u{fffd}");
+    }
+
     #[test]
     fn test_chunk_crc() {
         let chunk = testing_chunk();
@@ -287,6 +417,30 @@ mod tests {
         let _chunk_string = format!("{}", chunk);
     }
 
+    #[test]
+    fn test_raw_chunk_from_short_input() {
+        assert!(RawChunk::try_from(&[0, 0, 0][..]).is_err());
+        assert!(RawChunk::try_from(&[0, 0, 0, 9, 82, 117, 83, 116, 0, 0, 0, 0][..]).is_err());
+    }
+
+    #[test]
+    fn test_raw_chunk_preserved_verbatim() {
+        #[rustfmt::skip]
+        let bytes = [
+            0, 0, 0, 2,         // length
+            32, 117, 83, 116,   // Chunk Type (bad)
+            65, 66,             // Data
+            1, 2, 3, 4,         // CRC (bad)
+        ];
+        let raw = RawChunk::try_from(&bytes[..]).unwrap();
+        assert_eq!(raw.total_length(), bytes.len());
+        assert!(Chunk::try_from(raw).is_err());
+
+        let chunk = Chunk::from_raw_unchecked(raw);
+        assert!(!chunk.is_crc_valid());
+        assert_eq!(chunk.as_bytes(), bytes);
+    }
+
     #[test]
     fn test_chunk_as_bytes() {
         let chunk = testing_chunk();
@@ -0,0 +1,88 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{matcher::ChunkTypeMatcher, Error, Result};
+
+/// Chunk type patterns separated by `/`, addressing a chunk inside PNGs
+/// carried in the chunks of other PNGs: `ruSt/ruSt` is the ruSt chunk of
+/// the PNG stored in the outer file's ruSt chunk. Every segment but the
+/// last selects a carrier; a path of one segment is an ordinary lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkPath {
+    segments: Vec<ChunkTypeMatcher>,
+}
+
+impl FromStr for ChunkPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let segments = s
+            .split('/')
+            .map(ChunkTypeMatcher::from_str)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ChunkPath { segments })
+    }
+}
+
+impl Display for ChunkPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let segments: Vec<String> = self.segments.iter().map(|s| s.to_string()).collect();
+        write!(f, "{}", segments.join("/"))
+    }
+}
+
+impl ChunkPath {
+    pub fn segments(&self) -> &[ChunkTypeMatcher] {
+        &self.segments
+    }
+    /// Whether the path goes through at least one carried PNG.
+    pub fn is_nested(&self) -> bool {
+        self.segments.len() > 1
+    }
+    /// The pattern of the chunk the path ends at.
+    pub fn last(&self) -> &ChunkTypeMatcher {
+        self.segments
+            .last()
+            .expect("paths have at least one segment")
+    }
+    /// The first `len` segments, naming a carrier along the way.
+    pub fn prefix(&self, len: usize) -> ChunkPath {
+        ChunkPath {
+            segments: self.segments[..len].to_vec(),
+        }
+    }
+    /// Makes every segment match either case.
+    pub fn ignore_case(self, ignore_case: bool) -> ChunkPath {
+        ChunkPath {
+            segments: self
+                .segments
+                .into_iter()
+                .map(|s| s.ignore_case(ignore_case))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+
+    #[test]
+    fn test_chunk_path() {
+        let path = ChunkPath::from_str("ruSt/ru??/IHDR").unwrap();
+        assert!(path.is_nested());
+        assert_eq!(path.segments().len(), 3);
+        assert_eq!(path.to_string(), "ruSt/ru??/IHDR");
+        assert_eq!(path.prefix(2).to_string(), "ruSt/ru??");
+        assert!(path.last().matches(&ChunkType::from_str("IHDR").unwrap()));
+
+        let path = ChunkPath::from_str("ruSt").unwrap();
+        assert!(!path.is_nested());
+        let path = path.ignore_case(true);
+        assert!(path.last().matches(&ChunkType::from_str("RUST").unwrap()));
+
+        assert!(ChunkPath::from_str("ruSt/").is_err());
+        assert!(ChunkPath::from_str("ruSt//ruSt").is_err());
+    }
+}
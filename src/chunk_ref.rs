@@ -0,0 +1,113 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, png::Png, Error, Result};
+
+/// A chunk of another file, written `PATH:TYPE` for the first chunk of a
+/// type or `PATH:TYPE:INDEX` for a later one, counting from 0. The path is
+/// whatever comes before the chunk type, so it may contain colons itself,
+/// as Windows paths do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkRef {
+    pub path: PathBuf,
+    pub chunk_type: ChunkType,
+    pub index: usize,
+}
+
+impl FromStr for ChunkRef {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Box::new(ChunkRefError::InvalidRef(s.to_string()));
+        let (rest, last) = s.rsplit_once(':').ok_or_else(invalid)?;
+        let (path, chunk_type, index) = match last.parse::<usize>() {
+            Ok(index) => {
+                let (path, chunk_type) = rest.rsplit_once(':').ok_or_else(invalid)?;
+                (path, chunk_type, index)
+            }
+            Err(_) => (rest, last, 0),
+        };
+        if path.is_empty() {
+            return Err(invalid());
+        }
+        Ok(ChunkRef {
+            path: PathBuf::from(path),
+            chunk_type: ChunkType::from_str(chunk_type).map_err(|_| invalid())?,
+            index,
+        })
+    }
+}
+
+impl Display for ChunkRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.path.display(),
+            self.chunk_type,
+            self.index
+        )
+    }
+}
+
+impl ChunkRef {
+    /// The referenced chunk in `png`, the file at [`ChunkRef::path`].
+    pub fn find<'a>(&self, png: &'a Png) -> Option<&'a Chunk> {
+        png.chunks()
+            .iter()
+            .filter(|chunk| *chunk.chunk_type() == self.chunk_type)
+            .nth(self.index)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ChunkRefError {
+    InvalidRef(String),
+}
+impl std::error::Error for ChunkRefError {}
+impl Display for ChunkRefError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            ChunkRefError::InvalidRef(s) => {
+                crate::tr!("chunk_ref.invalid_ref", reference = format!("{:?}", s))
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    #[test]
+    fn test_parse() {
+        let chunk_ref = ChunkRef::from_str("other.png:tEXt:2").unwrap();
+        assert_eq!(chunk_ref.path, PathBuf::from("other.png"));
+        assert_eq!(chunk_ref.chunk_type.to_string(), "tEXt");
+        assert_eq!(chunk_ref.index, 2);
+        assert_eq!(chunk_ref.to_string(), "other.png:tEXt:2");
+
+        let chunk_ref = ChunkRef::from_str(r"C:\images\a.png:ruSt").unwrap();
+        assert_eq!(chunk_ref.path, PathBuf::from(r"C:\images\a.png"));
+        assert_eq!(chunk_ref.index, 0);
+
+        assert!(ChunkRef::from_str("other.png").is_err());
+        assert!(ChunkRef::from_str(":tEXt").is_err());
+        assert!(ChunkRef::from_str("other.png:t3Xt:0").is_err());
+    }
+
+    #[test]
+    fn test_find() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let chunk_ref = ChunkRef::from_str("dice.png:RuSt").unwrap();
+        assert_eq!(
+            chunk_ref.find(&png).unwrap().data_as_string().unwrap(),
+            "hey"
+        );
+        let chunk_ref = ChunkRef::from_str("dice.png:RuSt:1").unwrap();
+        assert!(chunk_ref.find(&png).is_none());
+    }
+}
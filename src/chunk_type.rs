@@ -2,7 +2,15 @@ use std::{fmt::Display, str::FromStr};
 
 use crate::{Error, Result};
 
-#[derive(Debug, PartialEq, Eq)]
+/// Chunk types defined by the PNG specification and its registered
+/// extensions. Anything else is a private or unknown chunk.
+pub const STANDARD_CHUNK_TYPES: [&str; 28] = [
+    "IHDR", "PLTE", "IDAT", "IEND", "cHRM", "cICP", "gAMA", "iCCP", "mDCV", "cLLI", "sBIT", "sRGB",
+    "bKGD", "hIST", "tRNS", "eXIf", "pHYs", "sPLT", "tIME", "iTXt", "tEXt", "zTXt", "acTL", "fcTL",
+    "fdAT", "oFFs", "pCAL", "sCAL",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ChunkType([u8; 4]);
 
 impl TryFrom<[u8; 4]> for ChunkType {
@@ -34,12 +42,89 @@ impl FromStr for ChunkType {
 
 impl Display for ChunkType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = std::str::from_utf8(&self.0).map_err(|_e| std::fmt::Error)?;
-        std::fmt::Display::fmt(s, f)
+        // Chunks kept verbatim in raw mode may have non-alphabetic types.
+        let s = self.0.escape_ascii().to_string();
+        std::fmt::Display::fmt(&s, f)
+    }
+}
+
+/// The chunk type a lookup such as [`Png::chunk`](crate::png::Png::chunk)
+/// selects, given as a [`ChunkType`] or by name. A name that is not a valid
+/// chunk type selects nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkTypeQuery(Option<ChunkType>);
+
+impl ChunkTypeQuery {
+    pub fn chunk_type(&self) -> Option<&ChunkType> {
+        self.0.as_ref()
     }
 }
 
+impl From<ChunkType> for ChunkTypeQuery {
+    fn from(chunk_type: ChunkType) -> Self {
+        ChunkTypeQuery(Some(chunk_type))
+    }
+}
+
+impl From<&ChunkType> for ChunkTypeQuery {
+    fn from(chunk_type: &ChunkType) -> Self {
+        ChunkTypeQuery(Some(chunk_type.clone()))
+    }
+}
+
+impl From<&str> for ChunkTypeQuery {
+    fn from(name: &str) -> Self {
+        ChunkTypeQuery(ChunkType::from_str(name).ok())
+    }
+}
+
+impl From<&String> for ChunkTypeQuery {
+    fn from(name: &String) -> Self {
+        ChunkTypeQuery::from(name.as_str())
+    }
+}
+
+/// Where a chunk type stands according to its name and the case of its
+/// letters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkClass {
+    /// A critical chunk defined by the specification, such as `IDAT`.
+    CriticalStandard,
+    /// An ancillary chunk defined by the specification, such as `tEXt`.
+    AncillaryStandard,
+    /// Any other type with the safe-to-copy bit set.
+    PrivateSafe,
+    /// Any other type without the safe-to-copy bit, which editors drop once
+    /// the image changes.
+    PrivateUnsafe,
+    /// A type with a lowercase reserved (third) letter.
+    Invalid,
+}
+
 impl ChunkType {
+    /// A private ancillary type from any four letters: the first two are
+    /// made lowercase and the reserved third uppercase, so `RUST` becomes
+    /// `ruST`. The case of the last letter, the safe-to-copy bit, is kept.
+    pub fn new_private(name: &str) -> Result<ChunkType> {
+        let mut bytes = ChunkType::from_str(name)?.bytes();
+        bytes[0].make_ascii_lowercase();
+        bytes[1].make_ascii_lowercase();
+        bytes[2].make_ascii_uppercase();
+        Ok(ChunkType(bytes))
+    }
+    pub fn classify(&self) -> ChunkClass {
+        let standard = STANDARD_CHUNK_TYPES.iter().any(|t| t.as_bytes() == self.0);
+        match self {
+            _ if !self.is_valid() => ChunkClass::Invalid,
+            _ if standard && self.is_critical() => ChunkClass::CriticalStandard,
+            _ if standard => ChunkClass::AncillaryStandard,
+            _ if self.is_safe_to_copy() => ChunkClass::PrivateSafe,
+            _ => ChunkClass::PrivateUnsafe,
+        }
+    }
+    pub(crate) fn from_bytes_unchecked(bytes: [u8; 4]) -> ChunkType {
+        ChunkType(bytes)
+    }
     pub fn bytes(&self) -> [u8; 4] {
         self.0
     }
@@ -58,6 +143,20 @@ impl ChunkType {
     pub fn is_safe_to_copy(&self) -> bool {
         self.0[3].is_ascii_lowercase()
     }
+    /// The number of letters that have to be inserted, removed or changed
+    /// to turn one type into the other. A change of case counts as a change.
+    pub fn edit_distance(&self, other: &ChunkType) -> usize {
+        let mut previous: Vec<usize> = (0..=other.0.len()).collect();
+        for (i, a) in self.0.iter().enumerate() {
+            let mut current = vec![i + 1];
+            for (j, b) in other.0.iter().enumerate() {
+                let substitution = previous[j] + (a != b) as usize;
+                current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+            }
+            previous = current;
+        }
+        previous[other.0.len()]
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -69,8 +168,12 @@ impl std::error::Error for ChunkTypeError {}
 impl Display for ChunkTypeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ChunkTypeError::InvalidByteArray => write!(f, "Invalid byte array"),
-            ChunkTypeError::InvalidString => write!(f, "Invalid string"),
+            ChunkTypeError::InvalidByteArray => {
+                write!(f, "{}", crate::tr!("chunk_type.invalid_byte_array"))
+            }
+            ChunkTypeError::InvalidString => {
+                write!(f, "{}", crate::tr!("chunk_type.invalid_string"))
+            }
         }
     }
 }
@@ -108,6 +211,27 @@ mod tests {
         assert!(!chunk.is_critical());
     }
 
+    #[test]
+    pub fn test_new_private() {
+        assert_eq!(ChunkType::new_private("RUST").unwrap().to_string(), "ruST");
+        assert_eq!(ChunkType::new_private("IDAt").unwrap().to_string(), "idAt");
+        assert!(ChunkType::new_private("ru5t").is_err());
+        assert_eq!(
+            ChunkType::new_private("RuSt").unwrap().classify(),
+            ChunkClass::PrivateSafe
+        );
+    }
+
+    #[test]
+    pub fn test_classify() {
+        let classify = |s| ChunkType::from_str(s).unwrap().classify();
+        assert_eq!(classify("IDAT"), ChunkClass::CriticalStandard);
+        assert_eq!(classify("tEXt"), ChunkClass::AncillaryStandard);
+        assert_eq!(classify("ruSt"), ChunkClass::PrivateSafe);
+        assert_eq!(classify("ruST"), ChunkClass::PrivateUnsafe);
+        assert_eq!(classify("Rust"), ChunkClass::Invalid);
+    }
+
     #[test]
     pub fn test_chunk_type_is_public() {
         let chunk = ChunkType::from_str("RUSt").unwrap();
@@ -165,6 +289,16 @@ mod tests {
         assert_eq!(&chunk.to_string(), "RuSt");
     }
 
+    #[test]
+    pub fn test_edit_distance() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        let distance = |s| chunk.edit_distance(&ChunkType::from_str(s).unwrap());
+        assert_eq!(distance("RuSt"), 0);
+        assert_eq!(distance("ruSt"), 1);
+        assert_eq!(distance("uStR"), 2);
+        assert_eq!(distance("tEXt"), 3);
+    }
+
     #[test]
     pub fn test_chunk_type_trait_impls() {
         let chunk_type_1: ChunkType = TryFrom::try_from([82, 117, 83, 116]).unwrap();
@@ -0,0 +1,238 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, Error, Result};
+
+/// PNG stores gamma and chromaticity values as integers scaled by this factor.
+const SCALE: f64 = 100_000.0;
+
+/// The image gamma stored in a `gAMA` chunk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gamma(pub f64);
+
+/// The CIE 1931 x,y chromaticities of the reference white point and the
+/// primaries, stored in a `cHRM` chunk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chromaticities {
+    pub white_point: (f64, f64),
+    pub red: (f64, f64),
+    pub green: (f64, f64),
+    pub blue: (f64, f64),
+}
+
+/// The rendering intent stored in an `sRGB` chunk, which marks the image as
+/// being in the sRGB color space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderingIntent {
+    Perceptual = 0,
+    RelativeColorimetric = 1,
+    Saturation = 2,
+    AbsoluteColorimetric = 3,
+}
+
+impl TryFrom<&Chunk> for Gamma {
+    type Error = Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        let data = expect_chunk(chunk, Gamma::CHUNK_TYPE, 4)?;
+        Ok(Gamma(read_scaled(data, 0)))
+    }
+}
+
+impl TryFrom<&Chunk> for Chromaticities {
+    type Error = Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        let data = expect_chunk(chunk, Chromaticities::CHUNK_TYPE, 32)?;
+        let point = |i: usize| (read_scaled(data, i * 8), read_scaled(data, i * 8 + 4));
+        Ok(Chromaticities {
+            white_point: point(0),
+            red: point(1),
+            green: point(2),
+            blue: point(3),
+        })
+    }
+}
+
+impl TryFrom<&Chunk> for RenderingIntent {
+    type Error = Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        let data = expect_chunk(chunk, RenderingIntent::CHUNK_TYPE, 1)?;
+        match data[0] {
+            0 => Ok(RenderingIntent::Perceptual),
+            1 => Ok(RenderingIntent::RelativeColorimetric),
+            2 => Ok(RenderingIntent::Saturation),
+            3 => Ok(RenderingIntent::AbsoluteColorimetric),
+            intent => Err(Box::new(ColorError::UnknownRenderingIntent(intent))),
+        }
+    }
+}
+
+impl Display for Gamma {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.5}", self.0)
+    }
+}
+
+impl Display for Chromaticities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let points = [
+            ("white", self.white_point),
+            ("red", self.red),
+            ("green", self.green),
+            ("blue", self.blue),
+        ];
+        for (i, (name, (x, y))) in points.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} ({:.5}, {:.5})", name, x, y)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for RenderingIntent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            RenderingIntent::Perceptual => "perceptual",
+            RenderingIntent::RelativeColorimetric => "relative colorimetric",
+            RenderingIntent::Saturation => "saturation",
+            RenderingIntent::AbsoluteColorimetric => "absolute colorimetric",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Gamma {
+    pub const CHUNK_TYPE: [u8; 4] = *b"gAMA";
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(
+            ChunkType::from_str("gAMA").unwrap(),
+            scaled_bytes(self.0).to_vec(),
+        )
+    }
+}
+
+impl Chromaticities {
+    pub const CHUNK_TYPE: [u8; 4] = *b"cHRM";
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [self.white_point, self.red, self.green, self.blue]
+            .iter()
+            .flat_map(|(x, y)| scaled_bytes(*x).into_iter().chain(scaled_bytes(*y)))
+            .collect();
+        Chunk::new(ChunkType::from_str("cHRM").unwrap(), data)
+    }
+}
+
+impl RenderingIntent {
+    pub const CHUNK_TYPE: [u8; 4] = *b"sRGB";
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::from_str("sRGB").unwrap(), vec![*self as u8])
+    }
+}
+
+fn expect_chunk(chunk: &Chunk, chunk_type: [u8; 4], length: usize) -> Result<&[u8]> {
+    if chunk.chunk_type().bytes() != chunk_type {
+        return Err(Box::new(ColorError::WrongChunkType(
+            chunk_type,
+            chunk.chunk_type().to_string(),
+        )));
+    }
+    if chunk.length() != length {
+        return Err(Box::new(ColorError::InvalidLength(
+            chunk_type,
+            chunk.length(),
+        )));
+    }
+    Ok(chunk.data())
+}
+
+fn read_scaled(data: &[u8], offset: usize) -> f64 {
+    let bytes: [u8; 4] = data[offset..offset + 4].try_into().unwrap();
+    u32::from_be_bytes(bytes) as f64 / SCALE
+}
+
+fn scaled_bytes(value: f64) -> [u8; 4] {
+    ((value * SCALE).round() as u32).to_be_bytes()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ColorError {
+    WrongChunkType([u8; 4], String),
+    InvalidLength([u8; 4], usize),
+    UnknownRenderingIntent(u8),
+}
+impl std::error::Error for ColorError {}
+impl Display for ColorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            ColorError::WrongChunkType(expected, found) => crate::tr!(
+                "color.wrong_chunk_type",
+                expected = expected.escape_ascii(),
+                found = found
+            ),
+            ColorError::InvalidLength(chunk_type, length) => crate::tr!(
+                "color.invalid_length",
+                chunk_type = chunk_type.escape_ascii(),
+                length = length
+            ),
+            ColorError::UnknownRenderingIntent(intent) => {
+                crate::tr!("color.unknown_rendering_intent", intent = intent)
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gamma_fixed_point() {
+        let chunk = Chunk::new(
+            ChunkType::from_str("gAMA").unwrap(),
+            45455u32.to_be_bytes().to_vec(),
+        );
+        let gamma = Gamma::try_from(&chunk).unwrap();
+        assert_eq!(gamma, Gamma(0.45455));
+        assert_eq!(gamma.to_chunk().data(), chunk.data());
+    }
+
+    #[test]
+    fn test_chromaticities_round_trip() {
+        // The sRGB primaries and D65 white point.
+        let chromaticities = Chromaticities {
+            white_point: (0.3127, 0.329),
+            red: (0.64, 0.33),
+            green: (0.3, 0.6),
+            blue: (0.15, 0.06),
+        };
+        let parsed = Chromaticities::try_from(&chromaticities.to_chunk()).unwrap();
+        assert_eq!(parsed, chromaticities);
+    }
+
+    #[test]
+    fn test_rendering_intent() {
+        let chunk = RenderingIntent::Saturation.to_chunk();
+        assert_eq!(chunk.data(), [2]);
+        assert_eq!(
+            RenderingIntent::try_from(&chunk).unwrap(),
+            RenderingIntent::Saturation
+        );
+
+        let chunk = Chunk::new(ChunkType::from_str("sRGB").unwrap(), vec![7]);
+        assert!(RenderingIntent::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_wrong_length() {
+        let chunk = Chunk::new(ChunkType::from_str("gAMA").unwrap(), vec![0; 3]);
+        assert!(Gamma::try_from(&chunk).is_err());
+    }
+}
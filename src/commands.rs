@@ -1,67 +1,2521 @@
+use std::cell::RefCell;
+use std::fmt::Display;
 use std::fs::OpenOptions;
-use std::io::{Read, Write};
+use std::io::{BufReader, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
 
+use clap::ValueEnum;
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use pngme::archive::{self, Archive, PackedFile};
+use pngme::budget::GrowthBudget;
+use pngme::category::ChunkCategory;
 use pngme::chunk::Chunk;
-use pngme::chunk_type::ChunkType;
-use pngme::png::Png;
+use pngme::chunk_path::ChunkPath;
+use pngme::chunk_ref::ChunkRef;
+use pngme::chunk_type::{ChunkClass, ChunkType};
+#[cfg(feature = "cloud")]
+use pngme::cloud::{S3Client, S3Location};
+use pngme::compact::CompactOptions;
+use pngme::corpus::Corpus;
+use pngme::diff::ChunkDiff;
+use pngme::discovery;
+use pngme::envelope::Envelope;
+use pngme::error::ErrorKind;
+use pngme::fec::{self, FecSpec};
+use pngme::history::{History, HistoryEntry};
+use pngme::iccp::IccProfile;
+use pngme::l10n::{self, Locale};
+use pngme::labels;
+use pngme::matcher::ChunkTypeMatcher;
+use pngme::merge::MergePolicy;
+use pngme::metadata::{self, MetadataMap};
+use pngme::journal::{Edit, Journal};
+use pngme::lsb::{self, LsbOptions};
+use pngme::ordering_channel;
+use pngme::parse_options::ParseOptions;
+use pngme::payload_schema::PayloadValidator;
+use pngme::phys::PhysicalDimensions;
+use pngme::pixel_diff;
+use pngme::png::{self, EditSession, Png, RemovePolicy, WritePolicy};
+use pngme::policy::{self, Policy, Severity};
+use pngme::profile::Profile;
+use pngme::registry::Registry;
+use pngme::seal;
+use pngme::search::{self, Pattern};
+use pngme::shard::{self, Shard};
+use pngme::signing;
+use pngme::sniff::{self, PayloadType};
+use pngme::summary::{ChunkStats, SizeSummary};
+use pngme::table::{ChunkRecord, Format, Table};
+use pngme::text_encoding::TextEncoding;
+use pngme::thumbnail::{Style, Thumbnail};
+use pngme::tr;
+use pngme::transparency::{Background, Transparency};
+use pngme::validate::{json_string, ValidationProfile};
+use pngme::verify::ExpectedHash;
+use sha2::{Digest, Sha256};
 
-pub fn encode(file_path: &Path, chunk_type: &str, message: &str, output_path: &Option<PathBuf>) {
-    let mut png = read_png(file_path);
-    let chunk_type = ChunkType::from_str(chunk_type).expect("Failed to creat chunk type");
-    let chunk = Chunk::new(chunk_type, message.as_bytes().to_vec());
-    png.append_chunk(chunk);
+use crate::progress_bar::ProgressBar;
+use crate::shell::Shell;
+use crate::term::{self, Role};
+use crate::watch::DirWatcher;
+use crate::EnvelopeArgs;
+
+static PARSE_OPTIONS: OnceLock<ParseOptions> = OnceLock::new();
+static SHOW_PROGRESS: AtomicBool = AtomicBool::new(false);
+pub(crate) static QUIET: AtomicBool = AtomicBool::new(false);
+static BACKUP_SUFFIX: OnceLock<String> = OnceLock::new();
+static FORCE_OUTPUT: OnceLock<PathBuf> = OnceLock::new();
+static MAX_GROWTH: OnceLock<GrowthBudget> = OnceLock::new();
+static JOURNAL: AtomicBool = AtomicBool::new(false);
+static DETERMINISTIC: AtomicBool = AtomicBool::new(false);
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+static HISTORY: OnceLock<String> = OnceLock::new();
+
+thread_local! {
+    /// The PNG this thread read most recently, which history entries for new
+    /// files are worked out against.
+    static LAST_READ: RefCell<Option<Png>> = const { RefCell::new(None) };
+}
+
+/// Like `println!`, but silenced by `--quiet`.
+macro_rules! outln {
+    ($($arg:tt)*) => {
+        if !$crate::commands::QUIET.load(std::sync::atomic::Ordering::Relaxed) {
+            println!($($arg)*);
+        }
+    };
+}
+pub(crate) use outln;
+
+/// Like `print!`, but silenced by `--quiet`.
+macro_rules! out {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            print!($($arg)*);
+        }
+    };
+}
+
+/// Failures detected by the commands themselves rather than the library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    ChunkNotFound(String),
+    /// A chunk type the user asked for is missing, with the types in the
+    /// file that are close enough to be what they meant.
+    ChunkNotFoundSuggest(String, Vec<String>),
+    KeyNotFound(String),
+    LabelNotFound(String),
+    NoChunkAtOffset(usize),
+    Expired,
+    VerificationFailed(ErrorKind),
+    /// The catalog key of a hint on how to avoid the current time.
+    Nondeterministic(&'static str),
+    BatchFailed(usize),
+    /// A chunk type that would change how readers treat the image, the
+    /// catalog key of why, and a private type to use instead.
+    RiskyChunkType(String, &'static str, Option<String>),
+    MissingEnv(String),
+    PixelsDiffer(usize),
+    ProtectedChunk(String),
+    /// Fewer edits are journaled than were asked to be undone.
+    NothingToUndo(usize),
+    /// What already holds a payload, and the flags that allow writing anyway.
+    AlreadyEncoded(String, &'static str),
+    /// How many problems `check --explain` found, and the most specific kind.
+    CheckFailed(usize, ErrorKind),
+    /// A file that would be overwritten but cannot be written.
+    ReadOnly(String),
+    /// The chunk path of a payload that was to be read as a PNG.
+    NotAPng(String),
+    /// A `grep` pattern found in none of the files.
+    NoMatch(String),
+    /// Why `grep` could not search everything it was asked to.
+    SearchFailed(String),
+}
+
+impl std::error::Error for CommandError {}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            CommandError::ChunkNotFound(chunk_type) => {
+                tr!("error.chunk_not_found", chunk_type = chunk_type)
+            }
+            CommandError::ChunkNotFoundSuggest(chunk_type, suggestions) => {
+                let mut message = tr!("error.chunk_not_found", chunk_type = chunk_type);
+                if !suggestions.is_empty() {
+                    let suggestions = suggestions.join(", ");
+                    message.push_str(&tr!("error.did_you_mean", suggestions = suggestions));
+                }
+                message
+            }
+            CommandError::KeyNotFound(key) => {
+                tr!("error.key_not_found", key = format!("{:?}", key))
+            }
+            CommandError::LabelNotFound(label) => {
+                tr!("error.label_not_found", label = format!("{:?}", label))
+            }
+            CommandError::NoChunkAtOffset(offset) => {
+                tr!("error.no_chunk_at_offset", offset = format!("{:#x}", offset))
+            }
+            CommandError::Expired => tr!("error.expired"),
+            CommandError::VerificationFailed(_) => tr!("error.verification_failed"),
+            CommandError::Nondeterministic(hint) => tr!("error.nondeterministic", hint = tr!(hint)),
+            CommandError::BatchFailed(count) => tr!("error.batch_failed", count = count),
+            CommandError::RiskyChunkType(chunk_type, problem, suggestion) => {
+                let mut message =
+                    tr!("error.risky_chunk_type", chunk_type = chunk_type, problem = tr!(problem));
+                if let Some(suggestion) = suggestion {
+                    message.push_str(&tr!("error.private_suggestion", suggestion = suggestion));
+                }
+                message.push_str(&tr!("error.or_force"));
+                message
+            }
+            CommandError::PixelsDiffer(count) => tr!("error.pixels_differ", count = count),
+            CommandError::CheckFailed(count, _) => tr!("error.check_failed", count = count),
+            CommandError::AlreadyEncoded(name, flags) => {
+                tr!("error.already_encoded", name = name, flags = flags)
+            }
+            CommandError::NothingToUndo(available) => {
+                tr!("error.nothing_to_undo", available = available)
+            }
+            CommandError::ProtectedChunk(chunk_type) => {
+                tr!("error.protected_chunk", chunk_type = chunk_type)
+            }
+            CommandError::MissingEnv(name) => tr!("error.missing_env", name = name),
+            CommandError::ReadOnly(path) => tr!("error.read_only", path = path),
+            CommandError::NotAPng(path) => tr!("error.not_a_png", path = path),
+            CommandError::NoMatch(pattern) => tr!("error.no_match", pattern = pattern),
+            CommandError::SearchFailed(reason) => reason.clone(),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+/// The process exit code for an error: 1 generic error, 2 chunk not found,
+/// 3 invalid PNG, 4 CRC mismatch. `grep` follows grep(1) instead: 1 when
+/// nothing matched and 2 when something could not be searched.
+pub fn exit_code(error: &pngme::Error) -> u8 {
+    let kind = match error.downcast_ref::<CommandError>() {
+        Some(CommandError::NoMatch(_)) => return 1,
+        Some(CommandError::SearchFailed(_)) => return 2,
+        Some(CommandError::ChunkNotFound(_))
+        | Some(CommandError::ChunkNotFoundSuggest(..))
+        | Some(CommandError::KeyNotFound(_))
+        | Some(CommandError::LabelNotFound(_))
+        | Some(CommandError::NoChunkAtOffset(_)) => ErrorKind::ChunkNotFound,
+        Some(CommandError::Expired)
+        | Some(CommandError::Nondeterministic(_))
+        | Some(CommandError::BatchFailed(_))
+        | Some(CommandError::RiskyChunkType(..))
+        | Some(CommandError::MissingEnv(_))
+        | Some(CommandError::PixelsDiffer(_))
+        | Some(CommandError::ProtectedChunk(_))
+        | Some(CommandError::NothingToUndo(_))
+        | Some(CommandError::ReadOnly(_))
+        | Some(CommandError::AlreadyEncoded(..)) => ErrorKind::Other,
+        Some(CommandError::NotAPng(_)) => ErrorKind::InvalidPng,
+        Some(CommandError::VerificationFailed(kind))
+        | Some(CommandError::CheckFailed(_, kind)) => *kind,
+        None => ErrorKind::of(error),
+    };
+    match kind {
+        ErrorKind::ChunkNotFound => 2,
+        ErrorKind::InvalidPng => 3,
+        ErrorKind::CrcMismatch => 4,
+        ErrorKind::Other => 1,
+    }
+}
+
+pub fn set_parse_options(options: ParseOptions) {
+    PARSE_OPTIONS
+        .set(options)
+        .expect("Parse options are already set");
+}
+
+pub fn set_show_progress(show: bool) {
+    SHOW_PROGRESS.store(show, Ordering::Relaxed);
+}
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Makes commands fail rather than use a source of nondeterminism, so that
+/// the same inputs always produce byte-for-byte identical files.
+pub fn set_deterministic(deterministic: bool) {
+    DETERMINISTIC.store(deterministic, Ordering::Relaxed);
+}
+
+/// Makes every command record how to undo its changes to a file in the
+/// file's journal before overwriting it.
+pub fn set_journal(journal: bool) {
+    JOURNAL.store(journal, Ordering::Relaxed);
+}
+
+/// Makes every write add an entry for `command` to the file's history
+/// chunk.
+pub fn set_history(command: &str) {
+    HISTORY
+        .set(command.to_owned())
+        .expect("History command is already set");
+}
+
+/// Makes every command copy a file to `<file><suffix>` before overwriting it.
+pub fn set_backup_suffix(suffix: &str) {
+    BACKUP_SUFFIX
+        .set(suffix.to_owned())
+        .expect("Backup suffix is already set");
+}
+
+/// Writes files that turn out to be read-only into `dir` instead, under
+/// the same name.
+pub fn set_force_output(dir: &Path) {
+    FORCE_OUTPUT
+        .set(dir.to_owned())
+        .expect("Forced output directory is already set");
+}
+
+/// Makes writes fail, before anything is written, if the new file would be
+/// larger than the original by more than `budget`.
+pub fn set_max_growth(budget: GrowthBudget) {
+    MAX_GROWTH
+        .set(budget)
+        .expect("Growth budget is already set");
+}
+
+/// Fails if `output_path` is an existing file that cannot be written and
+/// there is no `--force-output` directory to write to instead. Called before
+/// the input is parsed, so that a read-only file fails fast.
+pub fn check_writable(output_path: &Path) -> pngme::Result<()> {
+    if FORCE_OUTPUT.get().is_none() && is_read_only(output_path) {
+        let path = output_path.display().to_string();
+        return Err(Box::new(CommandError::ReadOnly(path)));
+    }
+    Ok(())
+}
+
+/// Loads the chunk registry that `list` and `print` describe chunks from.
+pub fn set_registry(path: &Path) -> pngme::Result<()> {
+    let registry = Registry::load(path)?;
+    REGISTRY.set(registry).expect("Registry is already set");
+    Ok(())
+}
+
+/// Shows what a chunk type is registered as, or nothing if it is not.
+fn registered_as(chunk_type: &ChunkType) -> String {
+    REGISTRY
+        .get()
+        .and_then(|registry| registry.get(chunk_type))
+        .map(|entry| format!(" [{}]", entry.name))
+        .unwrap_or_default()
+}
+
+/// The chunk `encode` writes to, and what to do if it already holds a
+/// payload.
+pub enum ChunkChoice<'a> {
+    Type(ChunkType, WritePolicy),
+    /// A private type derived from this seed that the file does not use yet;
+    /// the message is always enveloped so `decode --auto` can find it.
+    Auto(&'a str),
+    /// The chunk recorded for this label in the label index, or a new one.
+    /// The message is always enveloped.
+    Label(&'a str, WritePolicy),
+}
+
+pub fn encode(
+    file_path: &Path,
+    chunk_type: ChunkChoice,
+    message: &[u8],
+    envelope: &EnvelopeArgs,
+    fec: &Option<String>,
+    output_path: &Option<PathBuf>,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_png(file_path)?;
+    let mut png = original.clone();
+    let existing = match &chunk_type {
+        ChunkChoice::Type(chunk_type, WritePolicy::Fail) => png
+            .chunk(chunk_type)
+            .map(|_| (chunk_type.to_string(), "--overwrite or --append")),
+        ChunkChoice::Label(label, WritePolicy::Fail) => {
+            let index = png.label_index().transpose()?.unwrap_or_default();
+            index
+                .resolve(&png, label)
+                .map(|_| (format!("Label {:?}", label), "--overwrite"))
+        }
+        _ => None,
+    };
+    if let Some((name, flags)) = existing {
+        return Err(Box::new(CommandError::AlreadyEncoded(name, flags)));
+    }
+    let wrap = !matches!(chunk_type, ChunkChoice::Type(..)) || !envelope.is_empty();
+    let mut data = if wrap {
+        build_envelope(message, envelope)?
+    } else {
+        message.to_vec()
+    };
+    if let Some(spec) = fec {
+        data = FecSpec::from_str(spec)?.encode(&data)?;
+    }
+    match chunk_type {
+        ChunkChoice::Type(chunk_type, policy) => {
+            png.write_chunk(Chunk::new(chunk_type, data), policy)?
+        }
+        ChunkChoice::Auto(seed) => {
+            let chunk_type = discovery::auto_chunk_type(&png, seed);
+            outln!("{}", tr!("output.using_chunk_type", chunk_type = chunk_type));
+            png.append_chunk(Chunk::new(chunk_type, data));
+        }
+        ChunkChoice::Label(label, policy) => {
+            let chunk_type = labels::write(&mut png, label, data, policy)?;
+            log::info!("Storing {:?} in a {} chunk", label, chunk_type);
+        }
+    }
 
     let output_path = match output_path {
         Some(path) => path.to_owned(),
         None => file_path.to_owned(),
     };
 
-    write_png(&output_path, &png);
+    write_or_preview(&output_path, &original, &png, dry_run)
+}
+
+/// Where `encode` takes the message from. Reading it from standard input or
+/// the environment keeps secrets out of shell history and `ps`.
+pub enum MessageSource<'a> {
+    Arg(&'a str),
+    Stdin,
+    Env(&'a str),
+    /// The contents of a file, as they are.
+    File(&'a Path),
+    /// The data of a chunk in another PNG, as they are, so that metadata
+    /// can be copied between files without a temporary file.
+    Chunk(&'a ChunkRef),
+}
+
+impl MessageSource<'_> {
+    /// The message as bytes. Text sources go through `parse`, which applies
+    /// `--input-format`; files and chunks are taken byte for byte. A single
+    /// trailing line break is dropped from standard input so that
+    /// `echo secret | pngme encode ...` stores just `secret`.
+    pub fn read(&self, parse: impl Fn(&str) -> pngme::Result<Vec<u8>>) -> pngme::Result<Vec<u8>> {
+        match self {
+            MessageSource::Arg(message) => parse(message),
+            MessageSource::Stdin => {
+                let mut message = String::new();
+                std::io::stdin().read_to_string(&mut message)?;
+                let trimmed = message.strip_suffix('\n').unwrap_or(&message);
+                let trimmed = trimmed.strip_suffix('\r').unwrap_or(trimmed);
+                parse(trimmed)
+            }
+            MessageSource::Env(name) => match std::env::var(name) {
+                Ok(message) => parse(&message),
+                Err(_) => Err(CommandError::MissingEnv(name.to_string()).into()),
+            },
+            MessageSource::File(path) => Ok(std::fs::read(path)?),
+            MessageSource::Chunk(chunk_ref) => {
+                // Not read_png, which would take the place of the carrier as
+                // the file the history is worked out against.
+                let options = PARSE_OPTIONS.get().copied().unwrap_or_default();
+                let png = Png::parse_with_options(&std::fs::read(&chunk_ref.path)?, &options)?;
+                let chunk = chunk_ref
+                    .find(&png)
+                    .ok_or_else(|| CommandError::ChunkNotFound(chunk_ref.to_string()))?;
+                log::info!("Copying {} bytes from {}", chunk.length(), chunk_ref);
+                Ok(chunk.checked_data()?.to_vec())
+            }
+        }
+    }
+}
+
+/// Messages belong in private ancillary chunks; anything else changes how
+/// readers treat the image, so it is refused unless `force` is set.
+pub fn check_chunk_type(chunk_type: &str, force: bool) -> pngme::Result<ChunkType> {
+    let chunk_type = ChunkType::from_str(chunk_type)?;
+    let problem = match chunk_type.classify() {
+        ChunkClass::CriticalStandard | ChunkClass::AncillaryStandard => "risk.standard",
+        ChunkClass::Invalid => "risk.reserved",
+        _ if chunk_type.is_critical() => "risk.critical",
+        ChunkClass::PrivateSafe | ChunkClass::PrivateUnsafe => return Ok(chunk_type),
+    };
+    // Suggest the private, safe-to-copy spelling of the same letters.
+    let name = chunk_type.to_string().to_ascii_lowercase();
+    let suggestion = ChunkType::new_private(&name).ok().map(|t| t.to_string());
+    if force {
+        log::warn!("{} {}", chunk_type, l10n::message(Locale::English, problem, &[]));
+        return Ok(chunk_type);
+    }
+    Err(Box::new(CommandError::RiskyChunkType(
+        chunk_type.to_string(),
+        problem,
+        suggestion,
+    )))
+}
+
+/// Fails unless the chunk type is in the `--registry` file.
+pub fn check_registered(chunk_type: ChunkType) -> pngme::Result<ChunkType> {
+    let registry = REGISTRY.get().ok_or_else(|| tr!("error.registry_required"))?;
+    let entry = registry.require(&chunk_type)?;
+    log::info!("{} is registered as {}", chunk_type, entry);
+    Ok(chunk_type)
+}
+
+pub fn encode_trailer(
+    file_path: &Path,
+    message: &[u8],
+    output_path: &Option<PathBuf>,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_png(file_path)?;
+    let mut png = original.clone();
+    png.set_trailer(message.to_vec());
+    let output_path = output_path.as_deref().unwrap_or(file_path);
+    write_or_preview(output_path, &original, &png, dry_run)
 }
 
-pub fn decode(file_path: &Path, chunk_type: &str) {
-    let png = read_png(file_path);
-    let chunk = png.chunk_by_type(chunk_type).expect("Failed to find chunk");
-    let decoded_chunk = chunk.data_as_string().expect("Failed to decode chunk");
-    println!("{}", decoded_chunk);
+/// How `decode` shows a payload.
+pub struct PayloadFormat {
+    /// Detected from the payload when not given.
+    pub encoding: Option<TextEncoding>,
+    pub lossy: bool,
+    /// Checked before the payload is shown.
+    pub validator: Option<Box<dyn PayloadValidator>>,
+    /// How text output ends its lines. Raw binary output is left alone.
+    pub newline: Newline,
+    /// Print what the payload appears to be instead of the payload.
+    pub sniff: bool,
 }
 
-pub fn remove(file_path: &Path, chunk_type: &str) {
-    let mut png = read_png(file_path);
-    png.remove_chunk(chunk_type).expect("Failed to remove chunk");
-    write_png(file_path, &png);
+/// The line endings of text that `decode` prints.
+#[derive(Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Newline {
+    /// End the message with \n and keep its own line breaks as they are
+    #[default]
+    Lf,
+    /// End the message with \r\n and turn its line breaks into \r\n, as
+    /// Windows tools expect
+    Crlf,
+    /// Print the message exactly, with nothing after it
+    None,
 }
 
-pub fn print(file_path: &Path) {
-    let png = read_png(file_path);
-    println!("{}", png);
+impl Newline {
+    /// `text` with its line breaks in this style, followed by the
+    /// terminator.
+    fn apply(self, text: &str) -> String {
+        match self {
+            Newline::Lf => format!("{}\n", text),
+            Newline::Crlf => {
+                let text = text.replace("\r\n", "\n").replace('\n', "\r\n");
+                format!("{}\r\n", text)
+            }
+            Newline::None => text.to_string(),
+        }
+    }
+}
+
+/// Writes `bytes` to stdout as they are, never translating line endings.
+/// Binary data is refused on a terminal, which it would garble and which on
+/// Windows cannot show bytes that are not UTF-8 at all.
+fn write_stdout(bytes: &[u8]) -> pngme::Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    if stdout.is_terminal() && std::str::from_utf8(bytes).is_err() {
+        return Err(tr!("error.binary_to_terminal").into());
+    }
+    stdout.write_all(bytes)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Whether an output path of `-` asks for stdout.
+fn is_stdout(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Prints `data` as text. Binary data that cannot be shown as text in the
+/// detected encoding is written raw when stdout is not a terminal, and as
+/// hex when it is.
+fn print_payload(data: &[u8], format: &PayloadFormat) -> pngme::Result<()> {
+    if let Some(validator) = &format.validator {
+        validator.validate(data)?;
+    }
+    let payload_type = sniff::sniff(data);
+    log::info!("The payload looks like {}", payload_type);
+    if format.sniff {
+        outln!("{}", payload_type);
+        return Ok(());
+    }
+    let encoding = match format.encoding {
+        Some(encoding) => encoding,
+        None if format.lossy => TextEncoding::Utf8,
+        None => match TextEncoding::detect(data) {
+            Some(encoding) => encoding,
+            None if std::io::stdout().is_terminal() => TextEncoding::Hex,
+            None if QUIET.load(Ordering::Relaxed) => return Ok(()),
+            None => return write_stdout(data),
+        },
+    };
+    let text = encoding.decode(data, format.lossy)?;
+    out!("{}", format.newline.apply(&text));
+    Ok(())
+}
+
+pub fn decode(
+    file_path: &Path,
+    matcher: &ChunkTypeMatcher,
+    ignore_expiry: bool,
+    format: &PayloadFormat,
+) -> pngme::Result<()> {
+    // Reading stops at the requested chunk. A damaged FEC-protected chunk is
+    // repaired rather than rejected, so CRCs are checked here instead.
+    let options = ParseOptions {
+        lazy_crc: true,
+        ..PARSE_OPTIONS.get().copied().unwrap_or_default()
+    };
+    let mut present = Vec::new();
+    for chunk in png::scan_with_options(open_input(file_path)?, &options) {
+        let chunk = chunk?;
+        if matcher.matches(chunk.chunk_type()) {
+            return print_message(&chunk, ignore_expiry, format);
+        }
+        present.push(chunk.chunk_type().clone());
+    }
+    Err(Box::new(chunk_not_found(matcher, &present)))
+}
+
+/// Opens a file, or with the net feature an http or https URL, for reading
+/// as a stream.
+fn open_input(file_path: &Path) -> pngme::Result<Box<dyn Read>> {
+    let input = file_path.to_string_lossy();
+    if !input.starts_with("http://") && !input.starts_with("https://") {
+        return Ok(Box::new(BufReader::new(std::fs::File::open(file_path)?)));
+    }
+    #[cfg(feature = "net")]
+    return Ok(Box::new(pngme::net::open(&input)?));
+    #[cfg(not(feature = "net"))]
+    Err(tr!("error.net_disabled").into())
+}
+
+pub fn decode_label(
+    file_path: &Path,
+    label: &str,
+    ignore_expiry: bool,
+    format: &PayloadFormat,
+) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let index = png.label_index().transpose()?.unwrap_or_default();
+    let chunk = index
+        .resolve(&png, label)
+        .ok_or_else(|| CommandError::LabelNotFound(label.to_string()))?;
+    print_message(chunk, ignore_expiry, format)
+}
+
+/// Prints a message written by `encode`, repairing it if it has parity and
+/// showing its envelope if it has one.
+fn print_message(chunk: &Chunk, ignore_expiry: bool, format: &PayloadFormat) -> pngme::Result<()> {
+    let (payload, envelope) = message_payload(chunk, ignore_expiry)?;
+    if let Some(envelope) = envelope {
+        let header = envelope.to_string();
+        match format.newline {
+            _ if format.sniff => {}
+            Newline::Crlf => out!("{}", header.replace('\n', "\r\n")),
+            _ => out!("{}", header),
+        }
+    }
+    print_payload(&payload, format)
+}
+
+/// The payload of a message written by `encode`, repaired if it has parity
+/// and taken out of its envelope if it has one.
+fn message_payload(
+    chunk: &Chunk,
+    ignore_expiry: bool,
+) -> pngme::Result<(Vec<u8>, Option<Envelope>)> {
+    let data = if fec::is_protected(chunk.data()) {
+        fec::decode(chunk.data())?
+    } else if parse_options().reject_bad_crc {
+        chunk.checked_data()?.to_vec()
+    } else {
+        chunk.data().to_vec()
+    };
+    if !Envelope::is_envelope(&data) {
+        return Ok((data, None));
+    }
+
+    let envelope = Envelope::try_from(data.as_ref())?;
+    if envelope.is_expired() && !ignore_expiry {
+        return Err(Box::new(CommandError::Expired));
+    }
+    Ok((envelope.payload().to_vec(), Some(envelope)))
+}
+
+/// Decodes the chunk at the end of `path`, descending into the PNG carried
+/// by the chunk each earlier segment selects.
+pub fn decode_nested(
+    file_path: &Path,
+    path: &ChunkPath,
+    ignore_expiry: bool,
+    format: &PayloadFormat,
+) -> pngme::Result<()> {
+    let options = PARSE_OPTIONS.get().copied().unwrap_or_default();
+    let mut png = read_png(file_path)?;
+    for depth in 1..path.segments().len() {
+        let chunk = find_chunk(&png, &path.segments()[depth - 1])?;
+        let carrier = path.prefix(depth);
+        let (payload, _) = message_payload(chunk, ignore_expiry)?;
+        if sniff::sniff(&payload) != PayloadType::Png {
+            return Err(Box::new(CommandError::NotAPng(carrier.to_string())));
+        }
+        log::info!("Reading the PNG carried in {}", carrier);
+        png = Png::parse_with_options(&payload, &options)?;
+    }
+    print_message(find_chunk(&png, path.last())?, ignore_expiry, format)
+}
+
+/// The first chunk matching `matcher`, or an error suggesting close types.
+fn find_chunk<'a>(png: &'a Png, matcher: &ChunkTypeMatcher) -> pngme::Result<&'a Chunk> {
+    png.chunk_matching(matcher).ok_or_else(|| {
+        let present = png.chunks().iter().map(Chunk::chunk_type);
+        Box::new(chunk_not_found(matcher, present)) as pngme::Error
+    })
+}
+
+/// Lists the pngme payloads in private chunks of `file_path`.
+pub fn decode_auto(file_path: &Path, ignore_expiry: bool) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let payloads = discovery::discover(&png);
+    if payloads.is_empty() {
+        return Err(Box::new(CommandError::ChunkNotFound("pngme payload".to_string())));
+    }
+    for payload in payloads {
+        let envelope = &payload.envelope;
+        if envelope.is_expired() && !ignore_expiry {
+            continue;
+        }
+        let created = match envelope.created() {
+            Some(created) => format!("\tcreated {}", created),
+            None => String::new(),
+        };
+        outln!(
+            "{}\t{}\t{} bytes{}{}{}",
+            payload.index,
+            payload.chunk_type,
+            envelope.payload().len(),
+            created,
+            if payload.protected { "\tfec" } else { "" },
+            if envelope.is_expired() { "\texpired" } else { "" },
+        );
+    }
+    Ok(())
+}
+
+pub fn encode_split(
+    file_paths: &[PathBuf],
+    input_file: &Path,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let payload = std::fs::read(input_file)?;
+    let shards = shard::split(&payload, file_paths.len())?;
+    for (file_path, shard) in file_paths.iter().zip(shards) {
+        let original = read_png(file_path)?;
+        let mut png = original.clone();
+        let chunk_type = ChunkType::from_str(shard::CHUNK_TYPE)?;
+        png.append_chunk(Chunk::new(chunk_type, shard.to_bytes()));
+        write_or_preview(file_path, &original, &png, dry_run)?;
+    }
+    Ok(())
+}
+
+pub fn encode_ordering(
+    file_path: &Path,
+    input_file: &Path,
+    output_path: &Option<PathBuf>,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_png(file_path)?;
+    let message = std::fs::read(input_file)?;
+    let png = ordering_channel::embed(&original, &message)?;
+
+    let output_path = match output_path {
+        Some(path) => path.to_owned(),
+        None => file_path.to_owned(),
+    };
+
+    write_or_preview(&output_path, &original, &png, dry_run)
+}
+
+pub fn encode_lsb(
+    file_path: &Path,
+    input_file: &Path,
+    options: &LsbOptions,
+    output_path: &Option<PathBuf>,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_png(file_path)?;
+    let message = std::fs::read(input_file)?;
+    let png = lsb::embed(&original, &message, options)?;
+
+    let output_path = match output_path {
+        Some(path) => path.to_owned(),
+        None => file_path.to_owned(),
+    };
+
+    write_or_preview(&output_path, &original, &png, dry_run)
+}
+
+pub fn decode_lsb(file_path: &Path, options: &LsbOptions, newline: Newline) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let message = lsb::extract(&png, options)?;
+    out!("{}", newline.apply(&String::from_utf8_lossy(&message)));
+    Ok(())
+}
+
+/// Reverts the last `count` journaled edits to `file_path`.
+pub fn undo(file_path: &Path, count: usize, dry_run: bool) -> pngme::Result<()> {
+    let journal_path = Journal::sidecar_path(file_path);
+    let mut journal = Journal::load(&journal_path)?;
+    if journal.edits().len() < count {
+        return Err(Box::new(CommandError::NothingToUndo(journal.edits().len())));
+    }
+    let current = read_png(file_path)?;
+    let mut png = current.clone();
+    for _ in 0..count {
+        png = journal.pop().expect("checked above").undo(&png)?;
+    }
+    if dry_run {
+        return write_or_preview(file_path, &current, &png, true);
+    }
+    write_png_unjournaled(file_path, &png)?;
+    journal.save(&journal_path)?;
+    outln!("{}", tr!("output.undid", count = count, left = journal.edits().len()));
+    Ok(())
+}
+
+pub fn decode_ordering(file_path: &Path, newline: Newline) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let message = ordering_channel::extract(&png)?;
+    out!("{}", newline.apply(&String::from_utf8_lossy(&message)));
+    Ok(())
+}
+
+/// Where a command that processes many files writes its results.
+pub enum OutputLayout {
+    InPlace,
+    /// Every output goes directly into the directory, named after its input.
+    Flatten(PathBuf),
+    /// Outputs mirror the relative paths of their inputs under the directory.
+    PreserveTree(PathBuf),
+}
+
+impl OutputLayout {
+    fn output_path(&self, input: &Path) -> PathBuf {
+        match self {
+            OutputLayout::InPlace => input.to_owned(),
+            OutputLayout::Flatten(dir) => match input.file_name() {
+                Some(name) => dir.join(name),
+                None => dir.join(input),
+            },
+            // Roots, prefixes and `..` are dropped so nothing lands outside
+            // the directory; inputs that then clash are caught as collisions.
+            // The tree of an S3 object is its key.
+            OutputLayout::PreserveTree(dir) => dir.join(
+                s3_key(input)
+                    .unwrap_or(input)
+                    .components()
+                    .filter(|c| matches!(c, std::path::Component::Normal(_)))
+                    .collect::<PathBuf>(),
+            ),
+        }
+    }
+}
+
+#[derive(Default)]
+struct BatchSummary {
+    processed: usize,
+    skipped: usize,
+    failed: usize,
+    /// Files left untouched because `--fail-fast` stopped the batch.
+    not_run: usize,
+    /// What happened to each input, in the order they were given.
+    results: Vec<BatchResult>,
+}
+
+struct BatchResult {
+    input: PathBuf,
+    output: PathBuf,
+    status: BatchStatus,
+    error: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum BatchStatus {
+    Processed,
+    Skipped,
+    Failed,
+    NotRun,
+}
+
+impl Display for BatchStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            BatchStatus::Processed => "processed",
+            BatchStatus::Skipped => "skipped",
+            BatchStatus::Failed => "failed",
+            BatchStatus::NotRun => "not run",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+impl BatchSummary {
+    fn record(&mut self, input: &Path, output: &Path, status: BatchStatus, error: Option<String>) {
+        match status {
+            BatchStatus::Processed => self.processed += 1,
+            BatchStatus::Skipped => self.skipped += 1,
+            BatchStatus::Failed => self.failed += 1,
+            BatchStatus::NotRun => self.not_run += 1,
+        }
+        self.results.push(BatchResult {
+            input: input.to_owned(),
+            output: output.to_owned(),
+            status,
+            error,
+        });
+    }
+    fn to_json(&self) -> String {
+        let results: Vec<String> = self
+            .results
+            .iter()
+            .map(|r| {
+                let error = match &r.error {
+                    Some(error) => json_string(error),
+                    None => "null".to_string(),
+                };
+                format!(
+                    "{{\"input\":{},\"output\":{},\"status\":{},\"error\":{}}}",
+                    json_string(&r.input.to_string_lossy()),
+                    json_string(&r.output.to_string_lossy()),
+                    json_string(&r.status.to_string()),
+                    error
+                )
+            })
+            .collect();
+        format!(
+            "{{\"processed\":{},\"skipped\":{},\"failed\":{},\"not_run\":{},\"files\":[{}]}}",
+            self.processed,
+            self.skipped,
+            self.failed,
+            self.not_run,
+            results.join(",")
+        )
+    }
+}
+
+impl Display for BatchSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} processed, {} skipped, {} failed",
+            self.processed, self.skipped, self.failed
+        )?;
+        if self.not_run > 0 {
+            write!(f, ", {} not run", self.not_run)?;
+        }
+        Ok(())
+    }
+}
+
+/// By default keeps going when a file fails so one bad input does not stop
+/// the batch; the failures are logged and make the command fail at the end.
+/// With `fail_fast` no new files are started after the first failure. A
+/// panic while processing a file is caught and counted as that file failing.
+/// Inputs that would be written to the same output as an earlier input are
+/// skipped. Inputs and the output directory may be `s3://bucket/prefix`
+/// locations with the cloud feature, and up to `jobs` files are processed at
+/// once, each worker taking the next file as soon as it is free.
+pub fn apply(
+    file_paths: &[PathBuf],
+    profile_path: &Path,
+    layout: &OutputLayout,
+    jobs: usize,
+    fail_fast: bool,
+    json: bool,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let profile = Profile::load(profile_path)?;
+    let inputs = expand_inputs(file_paths)?;
+    let mut outputs = std::collections::HashSet::new();
+    let mut outcomes: Vec<Option<(BatchStatus, Option<String>)>> = Vec::new();
+    let mut work = Vec::new();
+    for (index, file_path) in inputs.iter().enumerate() {
+        let output_path = layout.output_path(file_path);
+        if !outputs.insert(output_path.clone()) {
+            let reason = tr!("output.duplicate_output", path = output_path.display());
+            log::warn!("Skipping {}: {}", file_path.display(), reason);
+            outcomes.push(Some((BatchStatus::Skipped, Some(reason))));
+        } else {
+            outcomes.push(None);
+            work.push((index, file_path, output_path));
+        }
+    }
+
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let outcomes = Mutex::new(outcomes);
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, work.len().max(1)) {
+            scope.spawn(|| {
+                let next_work = || match stop.load(Ordering::Relaxed) {
+                    true => None,
+                    false => work.get(next.fetch_add(1, Ordering::Relaxed)),
+                };
+                while let Some((index, file_path, output_path)) = next_work() {
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        apply_profile(&profile, file_path, output_path, dry_run)
+                    }));
+                    let outcome = match result {
+                        Ok(Ok(())) => (BatchStatus::Processed, None),
+                        Ok(Err(e)) => (BatchStatus::Failed, Some(e.to_string())),
+                        Err(panic) => (BatchStatus::Failed, Some(panic_message(panic.as_ref()))),
+                    };
+                    if let Some(error) = &outcome.1 {
+                        log::error!("{}: {}", file_path.display(), error);
+                        stop.store(fail_fast, Ordering::Relaxed);
+                    }
+                    outcomes.lock().expect("no worker panics holding it")[*index] = Some(outcome);
+                }
+            });
+        }
+    });
+
+    let mut summary = BatchSummary::default();
+    let outcomes = outcomes.into_inner().expect("the workers have finished");
+    for (file_path, outcome) in inputs.iter().zip(outcomes) {
+        let (status, error) = outcome.unwrap_or((BatchStatus::NotRun, None));
+        summary.record(file_path, &layout.output_path(file_path), status, error);
+    }
+    if json {
+        outln!("{}", summary.to_json());
+    } else if !matches!(layout, OutputLayout::InPlace) || summary.not_run > 0 {
+        outln!("{}", summary);
+    }
+    if summary.failed > 0 {
+        return Err(Box::new(CommandError::BatchFailed(summary.failed)));
+    }
+    Ok(())
+}
+
+/// The message a panic was started with, if it was a string.
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => format!("panicked: {}", message),
+        (_, Some(message)) => format!("panicked: {}", message),
+        _ => "panicked".to_string(),
+    }
+}
+
+fn apply_profile(
+    profile: &Profile,
+    file_path: &Path,
+    output_path: &Path,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_location(file_path)?;
+    let mut png = original.clone();
+    profile.apply(&mut png)?;
+    if dry_run || s3_key(output_path).is_none() {
+        if let (false, Some(parent)) = (dry_run, output_path.parent()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        return write_or_preview(output_path, &original, &png, dry_run);
+    }
+    #[cfg(feature = "cloud")]
+    return {
+        let object = S3Location::from_str(&output_path.to_string_lossy())?;
+        let bytes = finalize(output_path, Some(&original), &png)?.as_bytes();
+        s3_client()?.put(&object, &bytes)?;
+        log::info!("Uploaded {} bytes to {}", bytes.len(), object);
+        Ok(())
+    };
+    #[cfg(not(feature = "cloud"))]
+    Err(tr!("error.cloud_disabled").into())
+}
+
+/// The key of an `s3://bucket/key` location, which batch commands accept in
+/// place of paths.
+fn s3_key(path: &Path) -> Option<&Path> {
+    let (_, key) = path.to_str()?.strip_prefix("s3://")?.split_once('/')?;
+    Some(Path::new(key))
+}
+
+/// Replaces each `s3://bucket/prefix` input with the PNG objects under it.
+fn expand_inputs(file_paths: &[PathBuf]) -> pngme::Result<Vec<PathBuf>> {
+    let mut inputs = Vec::new();
+    for file_path in file_paths {
+        if !file_path.to_string_lossy().starts_with("s3://") {
+            inputs.push(file_path.clone());
+            continue;
+        }
+        #[cfg(feature = "cloud")]
+        {
+            let prefix = S3Location::from_str(&file_path.to_string_lossy())?;
+            let objects = s3_client()?.list(&prefix)?;
+            let pngs: Vec<PathBuf> = objects
+                .iter()
+                .filter(|object| object.key.to_ascii_lowercase().ends_with(".png"))
+                .map(|object| PathBuf::from(object.to_string()))
+                .collect();
+            log::info!("{} PNG objects under {}", pngs.len(), prefix);
+            inputs.extend(pngs);
+        }
+        #[cfg(not(feature = "cloud"))]
+        return Err(tr!("error.cloud_disabled").into());
+    }
+    Ok(inputs)
+}
+
+/// Reads a PNG from a file or, with the cloud feature, an S3 object.
+fn read_location(path: &Path) -> pngme::Result<Png> {
+    if s3_key(path).is_none() {
+        return read_png(path);
+    }
+    #[cfg(feature = "cloud")]
+    return {
+        let object = S3Location::from_str(&path.to_string_lossy())?;
+        let bytes = s3_client()?.get(&object)?;
+        Png::parse_with_options(&bytes, &PARSE_OPTIONS.get().copied().unwrap_or_default())
+    };
+    #[cfg(not(feature = "cloud"))]
+    Err(tr!("error.cloud_disabled").into())
+}
+
+/// The client for S3 locations, configured from the environment on first
+/// use and shared by all workers.
+#[cfg(feature = "cloud")]
+fn s3_client() -> pngme::Result<&'static S3Client> {
+    static CLIENT: OnceLock<S3Client> = OnceLock::new();
+    if let Some(client) = CLIENT.get() {
+        return Ok(client);
+    }
+    let client = S3Client::from_env()?;
+    Ok(CLIENT.get_or_init(|| client))
+}
+
+pub fn sign(file_path: &Path, key_path: &Path) -> pngme::Result<()> {
+    let key = signing::parse_key(&std::fs::read(key_path)?)?;
+    let key = SigningKey::from_bytes(&key);
+    let mut png = read_png(file_path)?;
+    signing::sign(&mut png, &key);
+    write_png(file_path, &png)?;
+
+    let public_key: String = key
+        .verifying_key()
+        .as_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    outln!("{}", tr!("output.public_key", key = public_key));
+    Ok(())
+}
+
+pub fn verify_sig(file_path: &Path, pubkey_path: &Path) -> pngme::Result<()> {
+    let key = signing::parse_key(&std::fs::read(pubkey_path)?)?;
+    let png = read_png(file_path)?;
+    signing::verify(&png, &VerifyingKey::from_bytes(&key)?)?;
+    outln!("{}", tr!("output.signature_ok", file = file_path.display()));
+    Ok(())
+}
+
+pub fn seal(file_path: &Path) -> pngme::Result<()> {
+    let mut png = read_png(file_path)?;
+    let sealed = seal::seal(&mut png);
+    write_png(file_path, &png)?;
+    outln!("{}", tr!("output.sealed", file = file_path.display(), count = sealed.len()));
+    Ok(())
+}
+
+pub fn verify_seal(file_path: &Path) -> pngme::Result<()> {
+    // Any chunk whose boundaries can be found is read, so that damaged or
+    // unfamiliar chunks are reported as changes rather than parse errors.
+    let png = Png::parse_with_options(&std::fs::read(file_path)?, &lenient_options())?;
+    let mismatches = seal::verify(&png)?;
+    if mismatches.is_empty() {
+        outln!("{}", tr!("output.seal_ok", file = file_path.display()));
+        return Ok(());
+    }
+    for mismatch in &mismatches {
+        outln!("{}", mismatch);
+    }
+    Err(Box::new(CommandError::VerificationFailed(ErrorKind::Other)))
+}
+
+pub fn capacity(file_path: &Path, lsb_options: &[LsbOptions]) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let ordering = ordering_channel::capacity(&png);
+    outln!("{}", tr!("output.capacity_ordering", capacity = ordering));
+    for options in lsb_options {
+        let mode = match options.bits {
+            1 => tr!("output.lsb_mode_one_bit", channels = options.channels),
+            bits => tr!("output.lsb_mode", channels = options.channels, bits = bits),
+        };
+        match lsb::capacity(&png, options) {
+            Ok(bytes) => outln!("{}", tr!("output.capacity_bytes", mode = mode, bytes = bytes)),
+            Err(e) => outln!("{}", tr!("output.capacity_unavailable", mode = mode, error = e)),
+        }
+    }
+    Ok(())
+}
+
+pub fn decode_join(
+    file_paths: &[PathBuf],
+    output: &Option<PathBuf>,
+    auto_extension: bool,
+) -> pngme::Result<()> {
+    let mut shards = Vec::new();
+    for file_path in file_paths {
+        let png = read_png(file_path)?;
+        for chunk in png.chunks_of(shard::CHUNK_TYPE) {
+            shards.push(Shard::try_from(chunk.checked_data()?)?);
+        }
+    }
+    if shards.is_empty() {
+        return Err(CommandError::ChunkNotFound(shard::CHUNK_TYPE.to_string()).into());
+    }
+
+    let payload = shard::join(shards)?;
+    write_payload(&payload, output, auto_extension)
+}
+
+/// Writes a decoded or extracted payload to `output`, or to stdout. With
+/// `auto_extension`, the extension for what the payload appears to be is
+/// added to the file name, and the name actually written is printed.
+fn write_payload(
+    payload: &[u8],
+    output: &Option<PathBuf>,
+    auto_extension: bool,
+) -> pngme::Result<()> {
+    let Some(path) = output else {
+        return write_stdout(payload);
+    };
+    if !auto_extension {
+        std::fs::write(path, payload)?;
+        return Ok(());
+    }
+    let payload_type = sniff::sniff(payload);
+    let path = payload_type.with_extension(path);
+    std::fs::write(&path, payload)?;
+    outln!("{}: {}", path.display(), payload_type);
+    Ok(())
+}
+
+fn build_envelope(message: &[u8], args: &EnvelopeArgs) -> pngme::Result<Vec<u8>> {
+    let mut envelope = Envelope::new(message.to_vec());
+    // An expiry is relative to the creation time, so it needs one too.
+    match args.created {
+        Some(created) => envelope.set_created(created),
+        None if !args.timestamp && args.expires_in.is_none() => {}
+        None if DETERMINISTIC.load(Ordering::Relaxed) => {
+            return Err(CommandError::Nondeterministic("hint.created").into());
+        }
+        None => envelope.stamp(),
+    }
+    for entry in &args.metadata {
+        let (key, value) = entry
+            .split_once('=')
+            .ok_or_else(|| tr!("error.expected_key_value", entry = format!("{:?}", entry)))?;
+        envelope.insert_metadata(key, value)?;
+    }
+    if let Some(mime) = &args.mime {
+        envelope.set_mime_type(mime)?;
+    }
+    if let (Some(expires_in), Some(created)) = (args.expires_in, envelope.created()) {
+        envelope.set_expires(created + expires_in);
+    }
+    envelope.to_bytes()
+}
+
+pub fn decode_trailer(file_path: &Path, format: &PayloadFormat) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    print_payload(png.trailer(), format)
+}
+
+/// Removes the first chunk matching `matcher`, or all of them, printing a
+/// line for each and, with `print`, its payload.
+pub fn remove(
+    file_path: &Path,
+    matcher: &ChunkTypeMatcher,
+    all: bool,
+    policy: RemovePolicy,
+    print: bool,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_png(file_path)?;
+    let mut png = original.clone();
+    let Some(index) = png.index_matching(matcher) else {
+        return Err(Box::new(chunk_not_found(matcher, png.chunk_types())));
+    };
+    for chunk in png.chunks_matching(matcher).take(if all { usize::MAX } else { 1 }) {
+        if policy.check(chunk.chunk_type()).is_err() {
+            return Err(Box::new(CommandError::ProtectedChunk(chunk.chunk_type().to_string())));
+        }
+    }
+    let removed = match all {
+        true => png.remove_chunks_matching(matcher, policy)?,
+        false => vec![png.remove_chunk_at(index)?],
+    };
+
+    let format = PayloadFormat {
+        encoding: None,
+        lossy: false,
+        validator: None,
+        newline: Newline::default(),
+        sniff: false,
+    };
+    for chunk in &removed {
+        if print {
+            print_payload(chunk.data(), &format)?;
+        }
+        if !dry_run {
+            let message = tr!(
+                "output.removed",
+                chunk_type = chunk.chunk_type(),
+                length = chunk.length(),
+                crc = format!("{:#010x}", chunk.crc())
+            );
+            outln!("{}", message);
+        }
+    }
+    write_or_preview(file_path, &original, &png, dry_run)
+}
+
+/// Removes the chunk found at `offset` in the file, as another tool would
+/// report it.
+pub fn remove_at(
+    file_path: &Path,
+    offset: usize,
+    policy: RemovePolicy,
+    print: bool,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_png(file_path)?;
+    let mut png = original.clone();
+    let index = chunk_at_offset(&png, offset)?;
+    let chunk_type = png.chunks()[index].chunk_type();
+    if policy.check(chunk_type).is_err() {
+        return Err(Box::new(CommandError::ProtectedChunk(chunk_type.to_string())));
+    }
+    let chunk = png.remove_chunk_at(index)?;
+
+    if print {
+        let format = PayloadFormat {
+            encoding: None,
+            lossy: false,
+            validator: None,
+            newline: Newline::default(),
+            sniff: false,
+        };
+        print_payload(chunk.data(), &format)?;
+    }
+    if !dry_run {
+        let message = tr!(
+            "output.removed_at",
+            chunk_type = chunk.chunk_type(),
+            offset = format!("{:#x}", chunk.offset().unwrap_or(offset)),
+            length = chunk.length(),
+            crc = format!("{:#010x}", chunk.crc())
+        );
+        outln!("{}", message);
+    }
+    write_or_preview(file_path, &original, &png, dry_run)
 }
 
-fn read_png(file_path: &Path) -> Png {
-    let f = std::fs::File::open(file_path).expect("Failed to open file");
-    let mut reader = std::io::BufReader::new(f);
+/// Writes the data of the chunk found at `offset`, or with `raw` its length,
+/// type and CRC too, to `output` or stdout.
+pub fn extract(
+    file_path: &Path,
+    offset: usize,
+    raw: bool,
+    output: &Option<PathBuf>,
+    sniff: bool,
+    auto_extension: bool,
+) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let chunk = &png.chunks()[chunk_at_offset(&png, offset)?];
+    log::info!(
+        "Extracting {} chunk at offset {:#x}",
+        chunk.chunk_type(),
+        chunk.offset().unwrap_or(offset)
+    );
+    let bytes = match raw {
+        true => chunk.as_bytes(),
+        false => chunk.data().to_vec(),
+    };
+    if sniff {
+        outln!("{}", sniff::sniff(&bytes));
+        return Ok(());
+    }
+    write_payload(&bytes, output, auto_extension)
+}
+
+/// The index of the chunk whose bytes spanned `offset` when the file was read.
+fn chunk_at_offset(png: &Png, offset: usize) -> pngme::Result<usize> {
+    png.chunk_index_at_offset(offset)
+        .ok_or_else(|| CommandError::NoChunkAtOffset(offset).into())
+}
+
+pub fn print(file_path: &Path, preview: Option<usize>) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    outln!("Png {{");
+    for chunk in png.chunks() {
+        let crc = match chunk.is_crc_valid() {
+            true => String::new(),
+            false => term::paint(tr!("output.bad_crc"), Role::Error).to_string(),
+        };
+        let chunk_type = term::chunk_type(chunk.chunk_type());
+        let registered = registered_as(chunk.chunk_type());
+        match preview.and_then(|max_len| metadata::preview(chunk, max_len)) {
+            Some(text) => {
+                let text = term::paint(text, Role::Dim);
+                outln!("  {}{}{} {}", chunk_type, crc, registered, text)
+            }
+            None => outln!("  {}{}{}", chunk_type, crc, registered),
+        }
+    }
+    outln!("}}\n");
+    out!("{}", SizeSummary::new(&png));
+    Ok(())
+}
+
+/// With `shards`, the SHA-256 of each archive data chunk is shown, which is
+/// how `pack` tells its pieces apart.
+pub fn list(
+    file_path: &Path,
+    filter: &Option<ChunkTypeMatcher>,
+    preview: Option<usize>,
+    shards: bool,
+    format: Format,
+) -> pngme::Result<()> {
+    let reader = BufReader::new(std::fs::File::open(file_path)?);
+    let options = PARSE_OPTIONS.get().copied().unwrap_or_default();
+    let mut table = Table::new(&ChunkRecord::COLUMNS);
+    for (index, chunk) in png::scan_with_options(reader, &options).enumerate() {
+        let chunk = chunk?;
+        if filter
+            .as_ref()
+            .is_some_and(|f| !f.matches(chunk.chunk_type()))
+        {
+            continue;
+        }
+        if format != Format::Text {
+            let file = file_path.display().to_string();
+            table.push(ChunkRecord::new(&file, index, &chunk).to_row());
+            continue;
+        }
+        let mut annotation = registered_as(chunk.chunk_type());
+        if shards && chunk.chunk_type().bytes() == Archive::DATA_CHUNK_TYPE {
+            let digest: String = Sha256::digest(chunk.data())
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            annotation.push_str(&format!(" sha256:{}", digest));
+        }
+        let chunk_type = term::chunk_type(chunk.chunk_type());
+        match preview.and_then(|max_len| metadata::preview(&chunk, max_len)) {
+            Some(text) => {
+                let text = term::paint(text, Role::Dim);
+                outln!("{} {}{} {}", chunk_type, chunk.length(), annotation, text)
+            }
+            None => outln!("{} {}{}", chunk_type, chunk.length(), annotation),
+        }
+    }
+    if format != Format::Text {
+        print_table(&table, format);
+    }
+    Ok(())
+}
+
+/// Files that cannot be read are reported and left out of the totals.
+pub fn stats(file_paths: &[PathBuf], format: Format) -> pngme::Result<()> {
+    let mut stats = ChunkStats::default();
+    let mut table = Table::new(&ChunkRecord::COLUMNS);
+    for file_path in file_paths {
+        let png = match read_png(file_path) {
+            Ok(png) => png,
+            Err(e) => {
+                log::warn!("{}: {}", file_path.display(), e);
+                continue;
+            }
+        };
+        stats.add(&png);
+        let file = file_path.display().to_string();
+        for (index, chunk) in png.chunks().iter().enumerate() {
+            table.push(ChunkRecord::new(&file, index, chunk).to_row());
+        }
+    }
+    match format {
+        Format::Text => out!("{}", stats),
+        Format::Json => outln!("{}", stats.to_json()),
+        Format::Csv | Format::Tsv => print_table(&table, format),
+    }
+    Ok(())
+}
+
+/// Files are read however damaged their chunks, and those that still cannot
+/// be read are reported and left out.
+pub fn corpus_scan(dir: &Path, format: Format, min_text_size: usize) -> pngme::Result<()> {
+    let mut corpus = Corpus::default();
+    for file_path in png_files_under(dir)? {
+        let png = std::fs::read(&file_path)
+            .map_err(Into::into)
+            .and_then(|bytes| Png::parse_with_options(&bytes, &lenient_options()));
+        match png {
+            Ok(png) => corpus.add(&file_path.display().to_string(), &png),
+            Err(e) => log::warn!("{}: {}", file_path.display(), e),
+        }
+    }
+    let anomalies = corpus.anomalies(min_text_size);
+    match format {
+        Format::Text => {
+            out!("{}", corpus.stats());
+            outln!("{}", tr!("output.anomalies", count = anomalies.len()));
+            for anomaly in &anomalies {
+                outln!("  {}", anomaly);
+            }
+        }
+        Format::Json => outln!("{}", corpus.to_json(&anomalies)),
+        Format::Csv | Format::Tsv => print_table(&corpus.to_table(&anomalies), format),
+    }
+    Ok(())
+}
+
+/// Prints a table in any format, JSON on a line of its own.
+fn print_table(table: &Table, format: Format) {
+    match format {
+        Format::Json => outln!("{}", table.render(format)),
+        _ => out!("{}", table.render(format)),
+    }
+}
+
+/// Every `.png` file under `dir`, in sorted order. Symbolic links to
+/// directories are not followed.
+fn png_files_under(dir: &Path) -> pngme::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+pub fn palette(file_path: &Path, swatches: bool) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let palette = png
+        .palette()
+        .ok_or_else(|| CommandError::ChunkNotFound("PLTE".to_string()))??;
+    if !swatches {
+        out!("{}", palette);
+        return Ok(());
+    }
+    for (i, [r, g, b]) in palette.entries().iter().enumerate() {
+        outln!(
+            "{:3}: \x1b[48;2;{};{};{}m    \x1b[0m #{:02x}{:02x}{:02x}",
+            i, r, g, b, r, g, b
+        );
+    }
+    Ok(())
+}
+
+pub fn info(file_path: &Path) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    if let Some(ihdr) = png.ihdr() {
+        let ihdr = ihdr?;
+        outln!("{}", tr!("info.image", value = ihdr));
+        outln!("{}", tr!("info.interlace", value = ihdr.interlace_method_name()));
+    }
+    if let Some(gamma) = png.gamma() {
+        outln!("{}", tr!("info.gamma", value = gamma?));
+    }
+    if let Some(chromaticities) = png.chromaticities() {
+        outln!("{}", tr!("info.chromaticities", value = chromaticities?));
+    }
+    if let Some(intent) = png.srgb() {
+        outln!("{}", tr!("info.srgb", value = intent?));
+    }
+    if let Some(dimensions) = png.physical_dimensions() {
+        outln!("{}", tr!("info.density", value = dimensions?));
+    }
+    if let Some(transparency) = png.transparency() {
+        outln!("{}", tr!("info.transparency", value = transparency?));
+    }
+    if let Some(background) = png.background() {
+        outln!("{}", tr!("info.background", value = background?));
+    }
+    Ok(())
+}
+
+pub fn scrub(
+    file_path: &Path,
+    output_path: &Option<PathBuf>,
+    strip_trailer: bool,
+    keep_transparency: bool,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_png(file_path)?;
+    let mut png = original.clone();
+    png.retain_chunks(|c| {
+        let chunk_type = c.chunk_type().bytes();
+        c.chunk_type().is_critical()
+            || keep_transparency
+                && (chunk_type == Transparency::CHUNK_TYPE || chunk_type == Background::CHUNK_TYPE)
+    });
+    if strip_trailer {
+        png.set_trailer(Vec::new());
+    }
+
+    let output_path = match output_path {
+        Some(path) => path.to_owned(),
+        None => file_path.to_owned(),
+    };
+
+    write_or_preview(&output_path, &original, &png, dry_run)
+}
+
+pub fn strip(
+    file_path: &Path,
+    output_path: &Option<PathBuf>,
+    categories: &[ChunkCategory],
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_png(file_path)?;
+    let mut png = original.clone();
+    png.retain_chunks(|c| !categories.contains(&ChunkCategory::of(c.chunk_type())));
+    let output_path = output_path.as_deref().unwrap_or(file_path);
+    write_or_preview(output_path, &original, &png, dry_run)
+}
+
+/// Critical chunks left off the list are dropped like any other, but with a
+/// warning since the result is unlikely to be a readable image.
+pub fn keep_only(
+    file_path: &Path,
+    output_path: &Option<PathBuf>,
+    keep: &[ChunkTypeMatcher],
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_png(file_path)?;
+    let mut png = original.clone();
+    png.retain_chunks(|c| {
+        let kept = keep.iter().any(|m| m.matches(c.chunk_type()));
+        if !kept && c.chunk_type().is_critical() {
+            log::warn!("Removing critical chunk {}", c.chunk_type());
+        }
+        kept
+    });
+    let output_path = output_path.as_deref().unwrap_or(file_path);
+    write_or_preview(output_path, &original, &png, dry_run)
+}
+
+pub fn compact(
+    file_path: &Path,
+    output_path: &Option<PathBuf>,
+    options: &CompactOptions,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_png(file_path)?;
+    let mut png = original.clone();
+    let report = png.compact(options)?;
+    let output_path = output_path.as_deref().unwrap_or(file_path);
+    write_or_preview(output_path, &original, &png, dry_run)?;
+    outln!("{}", report);
+    Ok(())
+}
+
+pub fn rechunk(
+    file_path: &Path,
+    output_path: &Option<PathBuf>,
+    idat_size: usize,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_png(file_path)?;
+    let mut png = original.clone();
+    png.rechunk_idat(idat_size)?;
+    let output_path = output_path.as_deref().unwrap_or(file_path);
+    write_or_preview(output_path, &original, &png, dry_run)
+}
+
+/// Moves the chunks into canonical order. A file already in that order is
+/// left alone unless it is being written somewhere else.
+pub fn reorder_canonical(
+    file_path: &Path,
+    output_path: &Option<PathBuf>,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_png(file_path)?;
+    let mut png = original.clone();
+    if !png.reorder_canonical() {
+        outln!("{}", tr!("output.canonical", file = file_path.display()));
+        if output_path.is_none() {
+            return Ok(());
+        }
+    }
+    let output_path = output_path.as_deref().unwrap_or(file_path);
+    write_or_preview(output_path, &original, &png, dry_run)?;
+    if dry_run {
+        // Moving a chunk changes no data, so the preview shows nothing else.
+        let order: Vec<String> = png.chunk_types().iter().map(|t| t.to_string()).collect();
+        outln!("{}", tr!("output.new_order", order = order.join(" ")));
+    }
+    Ok(())
+}
+
+pub fn copy_chunks(
+    source_path: &Path,
+    destination_path: &Path,
+    output_path: &Option<PathBuf>,
+    policy: &MergePolicy,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let source = read_png(source_path)?;
+    let original = read_png(destination_path)?;
+    let mut png = original.clone();
+    let report = png.merge_ancillary_from(&source, policy);
+    for chunk_type in &report.kept_existing {
+        outln!("{}", tr!("output.kept_existing", chunk_type = chunk_type));
+    }
+    for chunk_type in &report.unsafe_to_copy {
+        outln!("{}", tr!("output.skipped_unsafe", chunk_type = chunk_type));
+    }
+    if report.copied.is_empty() {
+        outln!("{}", tr!("output.nothing_to_copy"));
+        return Ok(());
+    }
+    let output_path = output_path.as_deref().unwrap_or(destination_path);
+    write_or_preview(output_path, &original, &png, dry_run)
+}
+
+pub fn repair_truncated(
+    file_path: &Path,
+    output_path: &Option<PathBuf>,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let bytes = std::fs::read(file_path)?;
+    let options = PARSE_OPTIONS.get().copied().unwrap_or_default();
+    let (original, truncation) = Png::parse_partial(&bytes, &options)?;
+    let Some(truncation) = truncation else {
+        outln!("{}", tr!("output.not_truncated", file = file_path.display()));
+        return Ok(());
+    };
+    outln!("{}", truncation);
+    // Without a whole IDAT there is no image left to save.
+    for required in ["IHDR", "IDAT"] {
+        if original.chunk(required).is_none() {
+            return Err(Box::new(CommandError::ChunkNotFound(required.to_string())));
+        }
+    }
+
+    let mut png = original.clone();
+    png.append_chunk(Chunk::new(ChunkType::from_str("IEND")?, Vec::new()));
+    // The damaged file is kept, since it may hold more than could be read.
+    let output_path = match output_path {
+        Some(path) => path.to_owned(),
+        None => file_path.with_extension("repaired.png"),
+    };
+    write_or_preview(&output_path, &original, &png, dry_run)
+}
+
+pub fn check(file_path: &Path) -> pngme::Result<()> {
+    let bytes = std::fs::read(file_path)?;
+    #[cfg(feature = "parallel")]
+    let png = Png::try_from_parallel(&bytes)?;
+    #[cfg(not(feature = "parallel"))]
+    let png = Png::try_from(&bytes[..])?;
+
+    if let Some(palette) = png.palette() {
+        palette?;
+    }
+
+    let ok = term::paint(tr!("output.ok"), Role::Ok);
+    let count = png.chunks().len();
+    outln!("{}", tr!("output.check_ok", file = file_path.display(), ok = ok, count = count));
+    Ok(())
+}
+
+/// Like [`check`], but reports every problem against the rules in
+/// [`pngme::validate::RULES`] that `profile` enforces rather than failing on
+/// the first. `explain` adds the rule, the bytes around the problem and a
+/// suggested fix.
+pub fn check_rules(
+    file_path: &Path,
+    profile: ValidationProfile,
+    explain: bool,
+    json: bool,
+) -> pngme::Result<()> {
+    let bytes = std::fs::read(file_path)?;
+    let violations = pngme::validate::validate_for(&bytes, profile);
+    if json {
+        outln!("{}", pngme::validate::to_json(&bytes, &violations));
+    } else if violations.is_empty() {
+        let ok = term::paint(tr!("output.ok"), Role::Ok);
+        outln!("{}", tr!("output.rules_ok", file = file_path.display(), ok = ok));
+    }
+    for violation in violations.iter().filter(|_| !json) {
+        outln!("{}", term::paint(violation, Role::Error));
+        if !explain {
+            continue;
+        }
+        let rule = violation.rule;
+        let section = rule.section;
+        outln!("  {}", tr!("output.rule", requirement = rule.requirement, section = section));
+        for line in violation.hexdump(&bytes) {
+            outln!("  {}", term::paint(line, Role::Dim));
+        }
+        outln!("  {}", tr!("output.fix", fix = rule.fix));
+        outln!();
+    }
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let kind = match violations.iter().all(|v| matches!(v.rule.id, "crc" | "ancillary-crc")) {
+        true => ErrorKind::CrcMismatch,
+        false => ErrorKind::InvalidPng,
+    };
+    Err(Box::new(CommandError::CheckFailed(violations.len(), kind)))
+}
+
+/// Reports where a file breaks `policy`, failing if any of the violations
+/// is an error rather than a warning or note.
+pub fn lint(file_path: &Path, policy: &Policy, json: bool) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let violations = policy.lint(&png);
+    if json {
+        outln!("{}", policy::to_json(&violations));
+    } else if violations.is_empty() {
+        let ok = term::paint(tr!("output.ok"), Role::Ok);
+        outln!("{}", tr!("output.rules_ok", file = file_path.display(), ok = ok));
+    }
+    for violation in violations.iter().filter(|_| !json) {
+        let role = match violation.severity {
+            Severity::Error => Role::Error,
+            Severity::Warning => Role::Warning,
+            Severity::Info => Role::Dim,
+        };
+        outln!("{}", term::paint(violation, role));
+    }
+    let errors = violations.iter().filter(|v| v.severity == Severity::Error).count();
+    if errors > 0 {
+        return Err(Box::new(CommandError::CheckFailed(errors, ErrorKind::Other)));
+    }
+    Ok(())
+}
+
+pub fn verify(
+    file_path: &Path,
+    chunk_type: &str,
+    expect_hash: &Option<String>,
+) -> pngme::Result<()> {
+    let expected = match expect_hash {
+        Some(hash) => Some(ExpectedHash::from_str(hash)?),
+        None => None,
+    };
+    // CRCs are checked as part of the report rather than while parsing.
+    let bytes = std::fs::read(file_path)?;
+    let options = ParseOptions {
+        lazy_crc: true,
+        ..PARSE_OPTIONS.get().copied().unwrap_or_default()
+    };
+    let png = Png::parse_with_options(&bytes, &options)?;
+
+    let report = pngme::verify::verify(&png, chunk_type, expected.as_ref());
+    out!("{}", report);
+    let kind = match report.failure() {
+        None => return Ok(()),
+        Some(check) if check.name == "present" => ErrorKind::ChunkNotFound,
+        Some(check) if check.name == "crc" => ErrorKind::CrcMismatch,
+        Some(_) => ErrorKind::Other,
+    };
+    Err(Box::new(CommandError::VerificationFailed(kind)))
+}
+
+pub fn detect(file_path: &Path) -> pngme::Result<()> {
+    let bytes = std::fs::read(file_path)?;
+    let report = match progress_bar(file_path) {
+        Some(mut bar) => pngme::detect::detect_with_progress(&bytes, &mut |e| bar.update(e)),
+        None => pngme::detect::detect(&bytes),
+    }?;
+    out!("{}", report);
+    Ok(())
+}
+
+/// Prints one line per match. Files that cannot be read are reported and
+/// skipped so one bad asset does not stop a search across many.
+pub fn grep(
+    pattern: &str,
+    file_paths: &[PathBuf],
+    hex: bool,
+    decompress: bool,
+) -> pngme::Result<()> {
+    let compiled = if hex {
+        Pattern::hex(pattern)
+    } else {
+        Pattern::regex(pattern)
+    };
+    let compiled = compiled.map_err(|e| CommandError::SearchFailed(e.to_string()))?;
+    let (mut matched, mut unreadable) = (false, 0);
+    for file_path in file_paths {
+        let png = match read_png(file_path) {
+            Ok(png) => png,
+            Err(e) => {
+                log::warn!("{}: {}", file_path.display(), e);
+                unreadable += 1;
+                continue;
+            }
+        };
+        for found in search::search(&png, &compiled, decompress) {
+            matched = true;
+            outln!(
+                "{}: {} #{} at {}{}",
+                file_path.display(),
+                found.chunk_type,
+                found.chunk_index,
+                found.offset,
+                if found.decompressed { " (decompressed)" } else { "" }
+            );
+        }
+    }
+    if unreadable > 0 {
+        let reason = tr!("error.not_searched", count = unreadable);
+        return Err(Box::new(CommandError::SearchFailed(reason)));
+    }
+    if !matched {
+        return Err(Box::new(CommandError::NoMatch(pattern.to_string())));
+    }
+    Ok(())
+}
+
+pub fn diff(old_path: &Path, new_path: &Path, hexdiff: bool) -> pngme::Result<()> {
+    let old = read_png(old_path)?;
+    let new = read_png(new_path)?;
+    let diffs = pngme::diff::diff(&old, &new);
+
+    if diffs.is_empty() {
+        outln!("{}", tr!("output.no_differences"));
+    }
+
+    for chunk_diff in diffs {
+        outln!("{}", chunk_diff);
+        if let (true, ChunkDiff::Modified { chunk_type, index, new_index, .. }) =
+            (hexdiff, &chunk_diff)
+        {
+            let old_chunk = old.chunks_of(chunk_type).nth(*index).unwrap();
+            let new_chunk = new.chunks_of(chunk_type).nth(*new_index).unwrap();
+            for line in pngme::diff::hexdiff(old_chunk.data(), new_chunk.data()) {
+                outln!("    {}", line);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn view(file_path: &Path, width: usize, style: Style) -> pngme::Result<()> {
+    let pixels = read_png(file_path)?.decode_pixels()?;
+    out!("{}", Thumbnail::new(&pixels, width, style).render());
+    Ok(())
+}
+
+pub fn pixdiff(
+    old_path: &Path,
+    new_path: &Path,
+    output: &Option<PathBuf>,
+) -> pngme::Result<()> {
+    let old = read_png(old_path)?.decode_pixels()?;
+    let new = read_png(new_path)?.decode_pixels()?;
+    let diff = pixel_diff::compare(&old, &new)?;
+    outln!("{}", diff);
+    if let Some(output) = output {
+        write_png(output, &diff.to_png(&old)?)?;
+    }
+    match diff.is_identical() {
+        true => Ok(()),
+        false => Err(Box::new(CommandError::PixelsDiffer(diff.differing_pixels()))),
+    }
+}
+
+pub fn shell(file_path: &Path) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    Shell::new(file_path, png).run();
+    Ok(())
+}
+
+/// Stores the files in a copy of `carrier` written to `output_path`. With
+/// `replace`, an archive already in the carrier is dropped first; pieces of
+/// files that did not change are written back unchanged.
+pub fn pack(
+    carrier: &Path,
+    file_paths: &[PathBuf],
+    output_path: &Path,
+    replace: bool,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    let original = read_png(carrier)?;
+    let mut png = original.clone();
+    if replace && archive::remove(&mut png) {
+        log::info!("Replacing the archive in {}", carrier.display());
+    }
+    let mut files = Vec::new();
+    for file_path in file_paths {
+        let metadata = std::fs::metadata(file_path)?;
+        let name = file_path
+            .file_name()
+            .ok_or_else(|| format!("{} is not a file", file_path.display()))?;
+        files.push(PackedFile {
+            name: name.to_string_lossy().into_owned(),
+            modified: metadata
+                .modified()?
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            mode: file_mode(&metadata),
+            data: std::fs::read(file_path)?,
+        });
+    }
+    archive::pack(&mut png, &files)?;
+    log::info!("Packed {} files", files.len());
+    write_or_preview(output_path, &original, &png, dry_run)
+}
+
+/// Writes the files stored by `pack` into `dir`, restoring their
+/// modification times and permissions.
+pub fn unpack(file_path: &Path, dir: &Path, overwrite: bool) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let files = archive::unpack(&png)?;
+    // Checked up front so a clash does not leave the directory half written.
+    if let Some(path) = files
+        .iter()
+        .map(|file| dir.join(&file.name))
+        .find(|path| !overwrite && path.exists())
+    {
+        let message = tr!("error.file_exists", path = path.display());
+        return Err(message.into());
+    }
+    std::fs::create_dir_all(dir)?;
+    for file in &files {
+        let path = dir.join(&file.name);
+        let mut output = std::fs::File::create(&path)?;
+        output.write_all(&file.data)?;
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(file.modified);
+        output.set_modified(modified)?;
+        set_file_mode(&path, file.mode)?;
+        outln!("{} ({} bytes)", path.display(), file.data.len());
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o777
+}
+
+#[cfg(not(unix))]
+fn file_mode(metadata: &std::fs::Metadata) -> u32 {
+    match metadata.permissions().readonly() {
+        true => 0o444,
+        false => 0o644,
+    }
+}
+
+#[cfg(unix)]
+fn set_file_mode(path: &Path, mode: u32) -> pngme::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?)
+}
+
+#[cfg(not(unix))]
+fn set_file_mode(path: &Path, mode: u32) -> pngme::Result<()> {
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode & 0o222 == 0);
+    Ok(std::fs::set_permissions(path, permissions)?)
+}
+
+pub fn watch(dir: &Path, on_add: &str, recursive: bool, debounce_ms: u64) -> pngme::Result<()> {
+    if !dir.is_dir() {
+        return Err(tr!("error.not_a_directory", path = dir.display()).into());
+    }
+    let debounce = std::time::Duration::from_millis(debounce_ms);
+    DirWatcher::new(dir, on_add, recursive, debounce)?.run()
+}
+
+pub fn icc_extract(file_path: &Path, output: &Path) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let chunk = png
+        .chunk("iCCP")
+        .ok_or_else(|| CommandError::ChunkNotFound("iCCP".to_string()))?;
+    let profile = IccProfile::from_chunk_with_options(chunk, &parse_options())?;
+    std::fs::write(output, profile.profile())?;
+    Ok(())
+}
+
+pub fn icc_embed(
+    file_path: &Path,
+    profile_path: &Path,
+    name: &str,
+    output_path: &Option<PathBuf>,
+) -> pngme::Result<()> {
+    let mut png = read_png(file_path)?;
+    let profile_bytes = std::fs::read(profile_path)?;
+    let profile = IccProfile::new(name, profile_bytes)?;
+    png.set_icc_profile(&profile)?;
+
+    let output_path = match output_path {
+        Some(path) => path.to_owned(),
+        None => file_path.to_owned(),
+    };
+
+    write_png(&output_path, &png)
+}
+
+pub fn exif_dump(file_path: &Path) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let exif = png
+        .exif()
+        .ok_or_else(|| CommandError::ChunkNotFound("eXIf".to_string()))??;
+    out!("{}", exif);
+    Ok(())
+}
+
+pub fn exif_strip(file_path: &Path, output_path: &Option<PathBuf>) -> pngme::Result<()> {
+    let mut png = read_png(file_path)?;
+    while png.remove_first("eXIf", RemovePolicy::Protect).is_ok() {}
+
+    let output_path = match output_path {
+        Some(path) => path.to_owned(),
+        None => file_path.to_owned(),
+    };
+
+    write_png(&output_path, &png)
+}
+
+pub fn create(output_path: &Path, width: u32, height: u32, color: &str) -> pngme::Result<()> {
+    let rgb = parse_hex_color(color)?;
+    let png = pngme::encoder::solid_color(width, height, rgb)?;
+    write_png(output_path, &png)
+}
+
+pub fn set_dpi(file_path: &Path, dpi: u32, dry_run: bool) -> pngme::Result<()> {
+    let original = read_png(file_path)?;
+    let mut png = original.clone();
+    png.set_physical_dimensions(PhysicalDimensions::from_dpi(dpi));
+    write_or_preview(file_path, &original, &png, dry_run)
+}
+
+pub fn set(file_path: &Path, key: &str, value: &str) -> pngme::Result<()> {
+    let mut png = read_png(file_path)?;
+    let mut metadata = MetadataMap::from_png_with_options(&png, &parse_options())?;
+    metadata.set(key, value)?;
+    metadata.write_to(&mut png)?;
+    write_png(file_path, &png)
+}
+
+pub fn get(file_path: &Path, key: &str) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let metadata = MetadataMap::from_png_with_options(&png, &parse_options())?;
+    let value = metadata
+        .get(key)
+        .ok_or_else(|| CommandError::KeyNotFound(key.to_string()))?;
+    outln!("{}", value);
+    Ok(())
+}
+
+pub fn del(file_path: &Path, key: &str) -> pngme::Result<()> {
+    let mut png = read_png(file_path)?;
+    let mut metadata = MetadataMap::from_png_with_options(&png, &parse_options())?;
+    metadata
+        .remove(key)
+        .ok_or_else(|| CommandError::KeyNotFound(key.to_string()))?;
+    metadata.write_to(&mut png)?;
+    write_png(file_path, &png)
+}
+
+pub fn labels(file_path: &Path) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let Some(index) = png.label_index().transpose()? else {
+        return Ok(());
+    };
+    for entry in index.entries() {
+        let size = match index.resolve(&png, &entry.label) {
+            Some(chunk) => format!("{} bytes", chunk.length()),
+            None => "missing".to_string(),
+        };
+        outln!(
+            "{}\t{}[{}]\t{}",
+            entry.label,
+            entry.chunk_type,
+            entry.occurrence,
+            size
+        );
+    }
+    Ok(())
+}
+
+pub fn keys(file_path: &Path) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let metadata = MetadataMap::from_png_with_options(&png, &parse_options())?;
+    for key in metadata.keys() {
+        outln!("{}", key);
+    }
+    Ok(())
+}
+
+fn parse_hex_color(color: &str) -> pngme::Result<[u8; 3]> {
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(tr!("error.bad_hex_color", color = format!("{:?}", color)).into());
+    }
+    let mut rgb = [0u8; 3];
+    for (i, channel) in rgb.iter_mut().enumerate() {
+        *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)?;
+    }
+    Ok(rgb)
+}
+
+/// How many letters a present chunk type may differ by to be suggested.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// A not-found error suggesting the closest of the `present` chunk types,
+/// unless a pattern was given.
+fn chunk_not_found<'a>(
+    matcher: &ChunkTypeMatcher,
+    present: impl IntoIterator<Item = &'a ChunkType>,
+) -> CommandError {
+    let chunk_type = matcher.to_string();
+    let Ok(wanted) = ChunkType::from_str(&chunk_type) else {
+        return CommandError::ChunkNotFound(chunk_type);
+    };
+    let mut close: Vec<(usize, String)> = Vec::new();
+    for candidate in present {
+        let distance = wanted.edit_distance(candidate);
+        let name = candidate.to_string();
+        if distance <= MAX_SUGGESTION_DISTANCE && !close.iter().any(|(_, n)| *n == name) {
+            close.push((distance, name));
+        }
+    }
+    close.sort_by_key(|(distance, _)| *distance);
+    let suggestions = close.into_iter().map(|(_, name)| name).collect();
+    CommandError::ChunkNotFoundSuggest(chunk_type, suggestions)
+}
+
+/// The parse options set from the command line.
+fn parse_options() -> ParseOptions {
+    PARSE_OPTIONS.get().copied().unwrap_or_default()
+}
+
+/// The configured parse options, but accepting any chunk whose boundaries
+/// can be found.
+fn lenient_options() -> ParseOptions {
+    ParseOptions {
+        reject_invalid_reserved_bit: false,
+        reject_bad_crc: false,
+        reject_malformed_chunk_type: false,
+        ..PARSE_OPTIONS.get().copied().unwrap_or_default()
+    }
+}
+
+fn read_png(file_path: &Path) -> pngme::Result<Png> {
+    let options = PARSE_OPTIONS.get().copied().unwrap_or_default();
+    let f = std::fs::File::open(file_path)?;
+    // Read at most one byte past the size limit, enough for parsing to
+    // report it without loading an arbitrarily large file.
+    let limit = options.max_total_size.map_or(u64::MAX, |max| max + 1);
+    let mut reader = std::io::BufReader::new(f).take(limit);
     let mut bytes = Vec::new();
 
-    reader
-        .read_to_end(&mut bytes)
-        .expect("Failed to read PNG data");
+    reader.read_to_end(&mut bytes)?;
+
+    let png = match progress_bar(file_path) {
+        Some(mut bar) => Png::parse_with_progress(&bytes, &options, &mut |e| bar.update(e))?,
+        None => Png::parse_with_options(&bytes, &options)?,
+    };
+    if HISTORY.get().is_some() {
+        LAST_READ.with(|last| *last.borrow_mut() = Some(png.clone()));
+    }
+    Ok(png)
+}
+
+fn progress_bar(file_path: &Path) -> Option<ProgressBar> {
+    if SHOW_PROGRESS.load(Ordering::Relaxed) && !QUIET.load(Ordering::Relaxed) {
+        Some(ProgressBar::new(&file_path.display().to_string()))
+    } else {
+        None
+    }
+}
+
+/// Writes `after` to `output_path`, or with `dry_run` only prints the chunks
+/// that would change relative to `before`. The edit is saved through an
+/// [`EditSession`], so stale unsafe-to-copy chunks are dropped.
+fn write_or_preview(
+    output_path: &Path,
+    before: &Png,
+    after: &Png,
+    dry_run: bool,
+) -> pngme::Result<()> {
+    if !dry_run {
+        return write_png_from(output_path, Some(before), after);
+    }
+    let after = &without_stale_chunks(before, after);
+    check_growth(output_path, before, after)?;
+
+    outln!("{}", tr!("output.dry_run", file = output_path.display()));
+    for chunk_diff in pngme::diff::diff(before, after) {
+        let offset = match &chunk_diff {
+            ChunkDiff::Removed {
+                chunk_type, index, ..
+            }
+            | ChunkDiff::Modified {
+                chunk_type, index, ..
+            } => chunk_offset(before, chunk_type, *index),
+            ChunkDiff::Added {
+                chunk_type, index, ..
+            } => chunk_offset(after, chunk_type, *index),
+        };
+        outln!("{} at offset {}", chunk_diff, offset);
+    }
+    if before.trailer() != after.trailer() {
+        outln!(
+            "~ trailer {} -> {} bytes",
+            before.trailer().len(),
+            after.trailer().len()
+        );
+    }
+    Ok(())
+}
+
+/// Byte offset within the file of the n-th chunk of a type.
+fn chunk_offset(png: &Png, chunk_type: &str, index: usize) -> usize {
+    let mut offset = png.header().len();
+    let mut seen = 0;
+    for chunk in png.chunks() {
+        if chunk.chunk_type().to_string() == chunk_type {
+            if seen == index {
+                break;
+            }
+            seen += 1;
+        }
+        offset += chunk.length() + Chunk::METADATA_SIZE;
+    }
+    offset
+}
+
+pub(crate) fn write_png(output_path: &Path, png: &Png) -> pngme::Result<()> {
+    write_png_from(output_path, None, png)
+}
+
+/// Like [`write_png`], first checking the `--max-growth` budget against
+/// `original` on what is actually written, history chunk included.
+fn write_png_from(output_path: &Path, original: Option<&Png>, png: &Png) -> pngme::Result<()> {
+    let output_path = &output_target(output_path)?;
+    let png = &finalize(output_path, original, png)?;
+    if JOURNAL.load(Ordering::Relaxed) && !is_stdout(output_path) && output_path.exists() {
+        record_edit(output_path, png)?;
+    }
+    write_png_unjournaled(output_path, png)
+}
+
+/// What is actually written to `output_path` when `png` replaces `original`:
+/// without unsafe-to-copy chunks that outlived a change to the critical
+/// chunks, with the command recorded under `--history`, and within the
+/// `--max-growth` budget.
+fn finalize(output_path: &Path, original: Option<&Png>, png: &Png) -> pngme::Result<Png> {
+    let mut png = match original {
+        Some(original) => without_stale_chunks(original, png),
+        None => png.clone(),
+    };
+    if let Some(command) = HISTORY.get() {
+        png = with_history(output_path, original, &png, command)?;
+    }
+    if let Some(original) = original {
+        check_growth(output_path, original, &png)?;
+    }
+    Ok(png)
+}
 
-    let png = Png::try_from(&bytes[..]).expect("Failed to read PNG");
+/// `after` without the unsafe-to-copy chunks that must not outlive a change
+/// to the critical chunks of `before`.
+fn without_stale_chunks(before: &Png, after: &Png) -> Png {
+    let mut session = EditSession::new(before.clone());
+    *session.png_mut() = after.clone();
+    session.save()
+}
 
-    png
+/// Fails if going from `original` to `modified` is over the `--max-growth`
+/// budget.
+fn check_growth(output_path: &Path, original: &Png, modified: &Png) -> pngme::Result<()> {
+    if let Some(budget) = MAX_GROWTH.get() {
+        let (original, modified) = (original.as_bytes().len(), modified.as_bytes().len());
+        budget.check(original, modified)?;
+        let growth = modified.saturating_sub(original);
+        log::info!("{} grows by {} bytes, within {}", output_path.display(), growth, budget);
+    }
+    Ok(())
 }
 
-fn write_png(output_path: &Path, png: &Png) {
+/// Adds the edit from the current contents of `path` to `png` to its
+/// journal. Files that are not PNGs are overwritten without a record.
+fn record_edit(path: &Path, png: &Png) -> pngme::Result<()> {
+    let before = match std::fs::read(path).map(|bytes| Png::try_from(bytes.as_ref())) {
+        Ok(Ok(before)) => before,
+        _ => {
+            log::warn!("{} is not a readable PNG, not journaling", path.display());
+            return Ok(());
+        }
+    };
+    let edit = Edit::between(&before, png);
+    if edit.is_empty() {
+        return Ok(());
+    }
+    let journal_path = Journal::sidecar_path(path);
+    let mut journal = Journal::load(&journal_path)?;
+    journal.push(edit);
+    journal.save(&journal_path)?;
+    log::info!("Recorded the edit in {}", journal_path.display());
+    Ok(())
+}
+
+/// `png` with an entry for `command` at the end of its history. The changes
+/// are worked out against the file being overwritten or, for a new file, the
+/// `original` it was made from or else the PNG this thread last read. A
+/// history the command dropped is carried over so that entries are never
+/// lost.
+fn with_history(
+    output_path: &Path,
+    original: Option<&Png>,
+    png: &Png,
+    command: &str,
+) -> pngme::Result<Png> {
+    if DETERMINISTIC.load(Ordering::Relaxed) {
+        return Err(CommandError::Nondeterministic("hint.no_history").into());
+    }
+    let before = std::fs::read(output_path)
+        .ok()
+        .and_then(|bytes| Png::try_from(bytes.as_ref()).ok())
+        .or_else(|| original.cloned())
+        .or_else(|| LAST_READ.with(|last| last.borrow().clone()));
+    let mut history = match (png.history(), before.as_ref().and_then(Png::history)) {
+        (Some(history), _) | (None, Some(history)) => history?,
+        (None, None) => History::default(),
+    };
+
+    let changes: Vec<(char, String)> = match &before {
+        Some(before) => pngme::diff::diff(before, png)
+            .into_iter()
+            .map(|change| match change {
+                ChunkDiff::Added { chunk_type, .. } => ('+', chunk_type),
+                ChunkDiff::Removed { chunk_type, .. } => ('-', chunk_type),
+                ChunkDiff::Modified { chunk_type, .. } => ('~', chunk_type),
+            })
+            .collect(),
+        None => png
+            .chunks()
+            .iter()
+            .map(|c| ('+', c.chunk_type().to_string()))
+            .collect(),
+    };
+    let changes = changes
+        .into_iter()
+        .filter(|(_, chunk_type)| chunk_type.as_bytes() != History::CHUNK_TYPE)
+        .map(|(sign, chunk_type)| format!("{}{}", sign, chunk_type))
+        .collect();
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    history.push(HistoryEntry {
+        timestamp,
+        command: command.to_owned(),
+        changes,
+    });
+    let mut png = png.clone();
+    png.set_history(&history);
+    Ok(png)
+}
+
+/// Prints the modifications recorded in the file's history chunk.
+pub fn history(file_path: &Path) -> pngme::Result<()> {
+    let png = read_png(file_path)?;
+    let history = png
+        .history()
+        .ok_or_else(|| CommandError::ChunkNotFound("hiSt".to_string()))??;
+    for entry in history.entries() {
+        outln!("{}", entry);
+    }
+    Ok(())
+}
+
+/// Whether `path` is an existing file that cannot be opened for writing.
+fn is_read_only(path: &Path) -> bool {
+    let opened = OpenOptions::new().append(true).open(path);
+    matches!(opened, Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied)
+}
+
+/// Where `output_path` is written: the path itself or, if it is read-only,
+/// the file of the same name in the `--force-output` directory.
+fn output_target(output_path: &Path) -> pngme::Result<PathBuf> {
+    if is_stdout(output_path) || !is_read_only(output_path) {
+        return Ok(output_path.to_owned());
+    }
+    match (FORCE_OUTPUT.get(), output_path.file_name()) {
+        (Some(dir), Some(name)) => {
+            std::fs::create_dir_all(dir)?;
+            let target = dir.join(name);
+            log::warn!(
+                "{} is read-only, writing {} instead",
+                output_path.display(),
+                target.display()
+            );
+            Ok(target)
+        }
+        _ => Err(Box::new(CommandError::ReadOnly(output_path.display().to_string()))),
+    }
+}
+
+fn write_png_unjournaled(output_path: &Path, png: &Png) -> pngme::Result<()> {
+    let output_path = &output_target(output_path)?;
+    if is_stdout(output_path) {
+        let bytes = png.as_bytes();
+        write_stdout(&bytes)?;
+        log::info!("Wrote {} bytes to stdout", bytes.len());
+        return Ok(());
+    }
+    if let Some(suffix) = BACKUP_SUFFIX.get() {
+        if output_path.exists() {
+            let mut backup_path = output_path.as_os_str().to_owned();
+            backup_path.push(suffix);
+            std::fs::copy(output_path, &backup_path)?;
+            log::info!("Backed up {} to {:?}", output_path.display(), backup_path);
+        }
+    }
+
     let mut output_file = OpenOptions::new()
         .write(true)
         .create(true)
         .truncate(true)
-        .open(output_path)
-        .expect("Failed to open output file");
+        .open(output_path)?;
 
-    output_file
-        .write(png.as_bytes().as_slice())
-        .expect("Failed to write output file");
+    let bytes = png.as_bytes();
+    output_file.write_all(&bytes)?;
+    log::info!("Wrote {} bytes to {}", bytes.len(), output_path.display());
+    Ok(())
 }
@@ -0,0 +1,55 @@
+use std::fmt::Display;
+
+/// What [`Png::compact`](crate::png::Png::compact) does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactOptions {
+    /// The largest IDAT chunk to write when consolidating image data.
+    pub max_idat_size: usize,
+}
+
+impl Default for CompactOptions {
+    fn default() -> Self {
+        CompactOptions {
+            max_idat_size: CompactOptions::DEFAULT_MAX_IDAT_SIZE,
+        }
+    }
+}
+
+impl CompactOptions {
+    pub const DEFAULT_MAX_IDAT_SIZE: usize = 1 << 20;
+}
+
+/// What a compaction pass changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactReport {
+    /// The keyword of each `tEXt` chunk dropped because an earlier one had
+    /// exactly the same keyword and text.
+    pub duplicate_text: Vec<String>,
+    /// Ancillary chunks dropped because they held no data.
+    pub empty_chunks: usize,
+    pub idat_before: usize,
+    pub idat_after: usize,
+    /// Negative when splitting image data into smaller chunks grew the file.
+    pub bytes_saved: i64,
+}
+
+impl Display for CompactReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Duplicate tEXt chunks removed: {}",
+            self.duplicate_text.len()
+        )?;
+        if !self.duplicate_text.is_empty() {
+            write!(f, " ({})", self.duplicate_text.join(", "))?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Empty chunks removed: {}", self.empty_chunks)?;
+        writeln!(
+            f,
+            "IDAT chunks: {} -> {}",
+            self.idat_before, self.idat_after
+        )?;
+        write!(f, "Bytes saved: {}", self.bytes_saved)
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use toml::{Table, Value};
+
+use crate::{Error, Result};
+
+/// Per-user settings, written in TOML:
+///
+/// ```toml
+/// history = true
+/// ```
+///
+/// `history` records every modification pngme makes in the file's history
+/// chunk (see [`crate::history::History`]); it is off by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    pub history: bool,
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let table: Table = s.parse()?;
+        let mut config = Config::default();
+        for (key, value) in &table {
+            match (key.as_str(), value) {
+                ("history", Value::Boolean(history)) => config.history = *history,
+                ("history", value) => {
+                    return Err(Box::new(ConfigError::InvalidValue(
+                        key.clone(),
+                        value.to_string(),
+                    )))
+                }
+                _ => return Err(Box::new(ConfigError::UnknownKey(key.clone()))),
+            }
+        }
+        Ok(config)
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config> {
+        Config::from_str(&std::fs::read_to_string(path)?)
+    }
+    /// Where the config is read from: `$PNGME_CONFIG`, or `pngme/config.toml`
+    /// under `$XDG_CONFIG_HOME` or `~/.config`.
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("PNGME_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        let base = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("pngme").join("config.toml"))
+    }
+    /// The config at [`Config::default_path`], or the defaults if there is no
+    /// file there.
+    pub fn load_default() -> Result<Config> {
+        match Config::default_path() {
+            Some(path) if path.exists() => Config::load(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ConfigError {
+    UnknownKey(String),
+    InvalidValue(String, String),
+}
+impl std::error::Error for ConfigError {}
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            ConfigError::UnknownKey(key) => {
+                crate::tr!("config.unknown_key", key = format!("{:?}", key))
+            }
+            ConfigError::InvalidValue(key, value) => {
+                crate::tr!("config.invalid_value", key = key, value = value)
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        assert_eq!(Config::from_str("").unwrap(), Config::default());
+        assert!(Config::from_str("history = true").unwrap().history);
+        assert!(Config::from_str("history = \"yes\"").is_err());
+        assert!(Config::from_str("color = true").is_err());
+    }
+}
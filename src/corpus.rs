@@ -0,0 +1,236 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+use crate::{
+    category::ChunkCategory,
+    png::Png,
+    summary::ChunkStats,
+    table::{ChunkRecord, Table},
+    validate::json_string,
+};
+
+/// Why a chunk stands out from the rest of a corpus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyKind {
+    /// A private chunk type that no other file in the corpus has.
+    UniquePrivateChunk,
+    /// A text chunk far larger than the corpus's other text chunks, holding
+    /// more than `threshold` bytes.
+    LargeText { threshold: usize },
+}
+
+/// A chunk flagged by [`Corpus::anomalies`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anomaly {
+    pub file: String,
+    /// The position of the chunk in the file, counting from 0.
+    pub index: usize,
+    pub chunk_type: String,
+    pub size: usize,
+    pub kind: AnomalyKind,
+}
+
+impl Anomaly {
+    /// A short machine-readable name for the kind of anomaly.
+    pub fn reason(&self) -> &'static str {
+        match self.kind {
+            AnomalyKind::UniquePrivateChunk => "unique-private-chunk",
+            AnomalyKind::LargeText { .. } => "large-text",
+        }
+    }
+    pub fn to_json(&self) -> String {
+        let threshold = match self.kind {
+            AnomalyKind::LargeText { threshold } => format!(",\"threshold\":{}", threshold),
+            AnomalyKind::UniquePrivateChunk => String::new(),
+        };
+        format!(
+            "{{\"file\":{},\"type\":{},\"size\":{},\"reason\":{}{}}}",
+            json_string(&self.file),
+            json_string(&self.chunk_type),
+            self.size,
+            json_string(self.reason()),
+            threshold
+        )
+    }
+}
+
+impl Display for Anomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} ({} bytes) ",
+            self.file, self.chunk_type, self.size
+        )?;
+        match self.kind {
+            AnomalyKind::UniquePrivateChunk => write!(f, "is a private chunk no other file has"),
+            AnomalyKind::LargeText { threshold } => {
+                write!(
+                    f,
+                    "is larger than the corpus's usual text ({} bytes)",
+                    threshold
+                )
+            }
+        }
+    }
+}
+
+/// Chunk statistics across many files, kept per file so that files which
+/// differ from the rest can be picked out, such as ones carrying data out
+/// through their metadata.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Corpus {
+    stats: ChunkStats,
+    chunks: Vec<ChunkRecord>,
+}
+
+impl Corpus {
+    pub fn add(&mut self, file: &str, png: &Png) {
+        self.stats.add(png);
+        let records = png.chunks().iter().enumerate();
+        self.chunks
+            .extend(records.map(|(index, chunk)| ChunkRecord::new(file, index, chunk)));
+    }
+    pub fn stats(&self) -> &ChunkStats {
+        &self.stats
+    }
+    /// Chunks that stand out, in file order. Text chunks are only flagged
+    /// above `min_text_size` bytes, however small the corpus's others are.
+    pub fn anomalies(&self, min_text_size: usize) -> Vec<Anomaly> {
+        let mut files_with_type: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for record in &self.chunks {
+            files_with_type
+                .entry(&record.chunk_type)
+                .or_default()
+                .insert(&record.file);
+        }
+        let text_threshold = min_text_size.max(self.text_size_fence());
+
+        let mut anomalies = Vec::new();
+        for record in &self.chunks {
+            let kind = if record.has_flag("private")
+                && files_with_type[record.chunk_type.as_str()].len() == 1
+            {
+                AnomalyKind::UniquePrivateChunk
+            } else if is_text(&record.chunk_type) && record.length > text_threshold {
+                AnomalyKind::LargeText {
+                    threshold: text_threshold,
+                }
+            } else {
+                continue;
+            };
+            anomalies.push(Anomaly {
+                file: record.file.clone(),
+                index: record.index,
+                chunk_type: record.chunk_type.clone(),
+                size: record.length,
+                kind,
+            });
+        }
+        anomalies
+    }
+    /// The upper Tukey fence of the text chunk sizes, three interquartile
+    /// ranges above the third quartile, so only far outliers exceed it.
+    fn text_size_fence(&self) -> usize {
+        let mut sizes: Vec<usize> = self
+            .chunks
+            .iter()
+            .filter(|c| is_text(&c.chunk_type))
+            .map(|c| c.length)
+            .collect();
+        if sizes.is_empty() {
+            return 0;
+        }
+        sizes.sort_unstable();
+        let quartile = |q: usize| sizes[(sizes.len() - 1) * q / 4];
+        let (q1, q3) = (quartile(1), quartile(3));
+        q3 + 3 * (q3 - q1)
+    }
+    /// Every chunk in the corpus as a [`ChunkRecord`], with the reasons for
+    /// any anomalies added to its flags.
+    pub fn to_table(&self, anomalies: &[Anomaly]) -> Table {
+        let mut table = Table::new(&ChunkRecord::COLUMNS);
+        for record in &self.chunks {
+            let mut record = record.clone();
+            record.flags.extend(
+                anomalies
+                    .iter()
+                    .filter(|a| a.file == record.file && a.index == record.index)
+                    .map(Anomaly::reason),
+            );
+            table.push(record.to_row());
+        }
+        table
+    }
+    pub fn to_json(&self, anomalies: &[Anomaly]) -> String {
+        let anomalies: Vec<String> = anomalies.iter().map(Anomaly::to_json).collect();
+        format!(
+            "{{\"stats\":{},\"anomalies\":[{}]}}",
+            self.stats.to_json(),
+            anomalies.join(",")
+        )
+    }
+}
+
+fn is_text(chunk_type: &str) -> bool {
+    chunk_type
+        .parse()
+        .is_ok_and(|t| ChunkCategory::of(&t) == ChunkCategory::Text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn png(chunks: &[(&str, usize)]) -> Png {
+        let chunks = chunks
+            .iter()
+            .map(|(t, size)| Chunk::new(ChunkType::from_str(t).unwrap(), vec![b'a'; *size]))
+            .collect();
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_anomalies() {
+        let mut corpus = Corpus::default();
+        for i in 0..8 {
+            corpus.add(
+                &format!("{}.png", i),
+                &png(&[("IHDR", 13), ("tEXt", 20 + i), ("buIl", 8), ("IEND", 0)]),
+            );
+        }
+        corpus.add(
+            "odd.png",
+            &png(&[("IHDR", 13), ("tEXt", 5000), ("exFl", 64), ("IEND", 0)]),
+        );
+
+        let anomalies = corpus.anomalies(1024);
+        assert_eq!(anomalies.len(), 2);
+        assert!(anomalies.iter().all(|a| a.file == "odd.png"));
+        assert_eq!(
+            anomalies[0].kind,
+            AnomalyKind::LargeText { threshold: 1024 }
+        );
+        assert_eq!(anomalies[1].chunk_type, "exFl");
+        assert_eq!(anomalies[1].kind, AnomalyKind::UniquePrivateChunk);
+        assert_eq!(corpus.stats().files, 9);
+
+        assert!(corpus
+            .anomalies(10_000)
+            .iter()
+            .all(|a| a.reason() != "large-text"));
+        let json: serde_json::Value = serde_json::from_str(&corpus.to_json(&anomalies)).unwrap();
+        assert_eq!(json["anomalies"][1]["reason"], "unique-private-chunk");
+        assert_eq!(json["stats"]["files"], 9);
+
+        let table = corpus.to_table(&anomalies);
+        let odd = &table.rows()[table.rows().len() - 3..];
+        assert_eq!(odd[0][6].to_string(), "safe-to-copy large-text");
+        assert_eq!(
+            odd[1][6].to_string(),
+            "private safe-to-copy unique-private-chunk"
+        );
+    }
+}
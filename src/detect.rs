@@ -0,0 +1,284 @@
+use std::fmt::Display;
+
+use crate::{
+    chunk::Chunk, chunk_type::STANDARD_CHUNK_TYPES, ihdr::Ihdr, image_data,
+    parse_options::ParseOptions, png::Png, progress::ProgressEvent, Result,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    NonStandardChunks,
+    TrailingData,
+    LargeTextChunks,
+    LsbStatistics,
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Check::NonStandardChunks => write!(f, "non-standard-chunks"),
+            Check::TrailingData => write!(f, "trailing-data"),
+            Check::LargeTextChunks => write!(f, "large-text-chunks"),
+            Check::LsbStatistics => write!(f, "lsb-statistics"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub check: Check,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// The outcome of running every heuristic against a file. Checks that could
+/// not be run (e.g. LSB analysis on palette images) are listed as skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectionReport {
+    findings: Vec<Finding>,
+    skipped: Vec<(Check, String)>,
+}
+
+impl Display for DetectionReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for check in DetectionReport::CHECKS {
+            if let Some((_, reason)) = self.skipped.iter().find(|(c, _)| *c == check) {
+                writeln!(f, "{}: skipped ({})", check, reason)?;
+                continue;
+            }
+            let findings: Vec<&Finding> =
+                self.findings.iter().filter(|x| x.check == check).collect();
+            if findings.is_empty() {
+                writeln!(f, "{}: ok", check)?;
+            }
+            for finding in findings {
+                writeln!(f, "{}: [{}] {}", check, finding.severity, finding.message)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DetectionReport {
+    const CHECKS: [Check; 4] = [
+        Check::NonStandardChunks,
+        Check::TrailingData,
+        Check::LargeTextChunks,
+        Check::LsbStatistics,
+    ];
+
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|f| f.severity).max()
+    }
+}
+
+/// Text chunks larger than this are unusual for genuine metadata.
+const LARGE_TEXT_CHUNK_SIZE: usize = 16 * 1024;
+
+/// A chi-square p-value above this suggests sample LSBs have been equalised
+/// by embedding.
+const LSB_P_VALUE_THRESHOLD: f64 = 0.95;
+
+/// Runs every detection heuristic over the raw bytes of a PNG file.
+pub fn detect(bytes: &[u8]) -> Result<DetectionReport> {
+    detect_with_progress(bytes, &mut |_| {})
+}
+
+/// Like [`detect`], reporting progress while the file is parsed.
+pub fn detect_with_progress(
+    bytes: &[u8],
+    progress: &mut dyn FnMut(ProgressEvent),
+) -> Result<DetectionReport> {
+    let png = Png::parse_with_progress(bytes, &ParseOptions::default(), progress)?;
+
+    let mut findings = Vec::new();
+    let mut skipped = Vec::new();
+
+    for chunk in png.chunks() {
+        let chunk_type = chunk.chunk_type().to_string();
+        if !STANDARD_CHUNK_TYPES.contains(&chunk_type.as_str()) {
+            findings.push(Finding {
+                check: Check::NonStandardChunks,
+                severity: Severity::Medium,
+                message: format!("{} chunk ({} bytes)", chunk_type, chunk.length()),
+            });
+        }
+    }
+
+    // Well-formed chunks after IEND are parsed as chunks, but decoders stop
+    // at IEND just the same.
+    let after_end = png
+        .chunks()
+        .iter()
+        .skip_while(|c| &c.chunk_type().bytes() != b"IEND")
+        .skip(1);
+    for chunk in after_end {
+        findings.push(Finding {
+            check: Check::TrailingData,
+            severity: Severity::High,
+            message: format!(
+                "{} chunk ({} bytes) after IEND",
+                chunk.chunk_type(),
+                chunk.length()
+            ),
+        });
+    }
+    if !png.trailer().is_empty() {
+        findings.push(Finding {
+            check: Check::TrailingData,
+            severity: Severity::High,
+            message: format!("{} bytes after IEND", png.trailer().len()),
+        });
+    }
+
+    for chunk in png.chunks().iter().filter(|c| is_text_chunk(c)) {
+        if chunk.length() > LARGE_TEXT_CHUNK_SIZE {
+            findings.push(Finding {
+                check: Check::LargeTextChunks,
+                severity: Severity::Medium,
+                message: format!("{} chunk of {} bytes", chunk.chunk_type(), chunk.length()),
+            });
+        }
+    }
+
+    match lsb_p_value(&png) {
+        Ok(p_value) if p_value > LSB_P_VALUE_THRESHOLD => findings.push(Finding {
+            check: Check::LsbStatistics,
+            severity: Severity::High,
+            message: format!("chi-square p-value {:.3} indicates LSB embedding", p_value),
+        }),
+        Ok(_) => {}
+        Err(e) => skipped.push((Check::LsbStatistics, e.to_string())),
+    }
+
+    Ok(DetectionReport { findings, skipped })
+}
+
+fn is_text_chunk(chunk: &Chunk) -> bool {
+    matches!(&chunk.chunk_type().bytes(), b"tEXt" | b"zTXt" | b"iTXt")
+}
+
+/// Runs the Westfeld–Pfitzmann chi-square attack over all 8-bit samples and
+/// returns the probability that sample LSBs were overwritten.
+fn lsb_p_value(png: &Png) -> Result<f64> {
+    let ihdr = png.ihdr().ok_or("missing IHDR")??;
+    if ihdr.bit_depth != 8 || ihdr.color_type == Ihdr::INDEXED {
+        return Err("requires 8-bit non-palette samples".into());
+    }
+    let samples = image_data::unfiltered_rows(png, &ihdr)?;
+
+    let mut histogram = [0u64; 256];
+    for sample in samples {
+        histogram[sample as usize] += 1;
+    }
+
+    let mut chi_square = 0.0;
+    let mut degrees_of_freedom = 0;
+    for pair in histogram.chunks(2) {
+        let expected = (pair[0] + pair[1]) as f64 / 2.0;
+        if expected > 0.0 {
+            chi_square += (pair[0] as f64 - expected).powi(2) / expected;
+            degrees_of_freedom += 1;
+        }
+    }
+    if degrees_of_freedom < 2 {
+        return Err("too few distinct sample values".into());
+    }
+
+    Ok(1.0 - chi_square_cdf(chi_square, (degrees_of_freedom - 1) as f64))
+}
+
+/// Approximates the chi-square CDF using the Wilson–Hilferty transform.
+fn chi_square_cdf(x: f64, k: f64) -> f64 {
+    let z = ((x / k).cbrt() - (1.0 - 2.0 / (9.0 * k))) / (2.0 / (9.0 * k)).sqrt();
+    normal_cdf(z)
+}
+
+/// Standard normal CDF via the Abramowitz–Stegun erf approximation.
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        (1.0 + erf) / 2.0
+    } else {
+        (1.0 - erf) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+    use crate::{chunk::Chunk, chunk_type::ChunkType};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_non_standard_chunk() {
+        let report = detect(&PNG_FILE).unwrap();
+        let finding = &report.findings()[0];
+        assert_eq!(finding.check, Check::NonStandardChunks);
+        assert!(finding.message.starts_with("RuSt"));
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend_from_slice(b"hidden");
+        let report = detect(&bytes).unwrap();
+        assert!(report
+            .findings()
+            .iter()
+            .any(|f| f.check == Check::TrailingData && f.message == "6 bytes after IEND"));
+        assert_eq!(report.max_severity(), Some(Severity::High));
+
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend_from_slice(
+            &Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![1]).as_bytes(),
+        );
+        let report = detect(&bytes).unwrap();
+        assert!(report
+            .findings()
+            .iter()
+            .any(|f| f.check == Check::TrailingData
+                && f.message == "ruSt chunk (1 bytes) after IEND"));
+    }
+
+    #[test]
+    fn test_chi_square_cdf() {
+        assert!((chi_square_cdf(1.0, 1.0) - 0.6827).abs() < 0.02);
+        assert!((normal_cdf(0.0) - 0.5).abs() < 1e-6);
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_report_display() {
+        let report = detect(&PNG_FILE).unwrap();
+        let report = report.to_string();
+        assert!(report.contains("trailing-data: ok"));
+        assert!(report.contains("non-standard-chunks: [medium] RuSt"));
+    }
+}
@@ -0,0 +1,321 @@
+use std::fmt::Display;
+
+use crate::{chunk::Chunk, png::Png};
+
+/// A difference between two PNGs at the chunk level. Unchanged chunks are
+/// lined up first, so inserting a `tEXt` chunk shows up as one addition rather
+/// than as every later chunk shifting. `index` counts chunks of the same type,
+/// in `new` for additions and in `old` otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkDiff {
+    Added {
+        chunk_type: String,
+        index: usize,
+        length: usize,
+        crc: u32,
+    },
+    Removed {
+        chunk_type: String,
+        index: usize,
+        length: usize,
+        crc: u32,
+    },
+    Modified {
+        chunk_type: String,
+        index: usize,
+        /// The index among chunks of the same type in `new`.
+        new_index: usize,
+        old_length: usize,
+        new_length: usize,
+        old_crc: u32,
+        new_crc: u32,
+    },
+}
+
+impl Display for ChunkDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChunkDiff::Added {
+                chunk_type,
+                index,
+                length,
+                crc,
+            } => write!(
+                f,
+                "+ {}[{}] {} bytes, crc {:#010x}",
+                chunk_type, index, length, crc
+            ),
+            ChunkDiff::Removed {
+                chunk_type,
+                index,
+                length,
+                crc,
+            } => write!(
+                f,
+                "- {}[{}] {} bytes, crc {:#010x}",
+                chunk_type, index, length, crc
+            ),
+            ChunkDiff::Modified {
+                chunk_type,
+                index,
+                new_index: _,
+                old_length,
+                new_length,
+                old_crc,
+                new_crc,
+            } => write!(
+                f,
+                "~ {}[{}] {} -> {} bytes ({:+}), crc {:#010x} -> {:#010x}",
+                chunk_type,
+                index,
+                old_length,
+                new_length,
+                *new_length as i64 - *old_length as i64,
+                old_crc,
+                new_crc
+            ),
+        }
+    }
+}
+
+/// Compares the chunks of two PNGs, listing removals and modifications in the
+/// order they appear in `old`, followed by additions in the order of `new`.
+pub fn diff(old: &Png, new: &Png) -> Vec<ChunkDiff> {
+    let old_chunks = indexed_chunks(old);
+    let new_chunks = indexed_chunks(new);
+    let mut diffs = Vec::new();
+    let mut added = Vec::new();
+
+    // Chunks left over between two unchanged ones are paired up by type as
+    // modifications; the rest were removed or added.
+    let mut common = common_chunks(old.chunks(), new.chunks());
+    common.push((old_chunks.len(), new_chunks.len()));
+    let (mut old_start, mut new_start) = (0, 0);
+    for (old_end, new_end) in common {
+        let mut unpaired: Vec<usize> = (new_start..new_end).collect();
+        for (chunk_type, index, old_chunk) in &old_chunks[old_start..old_end] {
+            let counterpart = unpaired
+                .iter()
+                .position(|n| new_chunks[*n].0 == *chunk_type)
+                .map(|position| &new_chunks[unpaired.remove(position)]);
+            diffs.push(match counterpart {
+                Some((_, new_index, new_chunk)) => ChunkDiff::Modified {
+                    chunk_type: chunk_type.clone(),
+                    index: *index,
+                    new_index: *new_index,
+                    old_length: old_chunk.length(),
+                    new_length: new_chunk.length(),
+                    old_crc: old_chunk.crc(),
+                    new_crc: new_chunk.crc(),
+                },
+                None => ChunkDiff::Removed {
+                    chunk_type: chunk_type.clone(),
+                    index: *index,
+                    length: old_chunk.length(),
+                    crc: old_chunk.crc(),
+                },
+            });
+        }
+        added.extend(unpaired.into_iter().map(|n| {
+            let (chunk_type, index, new_chunk) = &new_chunks[n];
+            ChunkDiff::Added {
+                chunk_type: chunk_type.clone(),
+                index: *index,
+                length: new_chunk.length(),
+                crc: new_chunk.crc(),
+            }
+        }));
+        (old_start, new_start) = (old_end + 1, new_end + 1);
+    }
+
+    diffs.extend(added);
+    diffs
+}
+
+/// Past this many pairs of chunks to compare, the middle of two files that
+/// differ is reported as all changed rather than aligned.
+const MAX_ALIGNMENT_CELLS: usize = 1 << 24;
+
+/// The positions in `old` and `new` of a longest common subsequence of
+/// identical chunks, so that inserting or removing a chunk does not make
+/// every later one look changed.
+fn common_chunks(old: &[Chunk], new: &[Chunk]) -> Vec<(usize, usize)> {
+    let same = |a: &Chunk, b: &Chunk| a.chunk_type() == b.chunk_type() && a.data() == b.data();
+    let prefix = old.iter().zip(new).take_while(|(a, b)| same(a, b)).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut common: Vec<(usize, usize)> = (0..prefix).map(|i| (i, i)).collect();
+    let (rows, columns) = (old_middle.len(), new_middle.len() + 1);
+    if rows.saturating_mul(columns) <= MAX_ALIGNMENT_CELLS {
+        // lengths[i * columns + j] is the length of the longest common
+        // subsequence of old_middle[i..] and new_middle[j..].
+        let mut lengths = vec![0u32; (rows + 1) * columns];
+        for i in (0..rows).rev() {
+            for j in (0..columns - 1).rev() {
+                lengths[i * columns + j] = if same(&old_middle[i], &new_middle[j]) {
+                    lengths[(i + 1) * columns + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * columns + j].max(lengths[i * columns + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < rows && j < columns - 1 {
+            if same(&old_middle[i], &new_middle[j]) {
+                common.push((prefix + i, prefix + j));
+                (i, j) = (i + 1, j + 1);
+            } else if lengths[(i + 1) * columns + j] >= lengths[i * columns + j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+    common.extend(
+        (0..suffix)
+            .rev()
+            .map(|k| (old.len() - 1 - k, new.len() - 1 - k)),
+    );
+    common
+}
+
+/// Renders a line-oriented hex diff of two payloads, 16 bytes per row,
+/// including only the rows that differ.
+pub fn hexdiff(old: &[u8], new: &[u8]) -> Vec<String> {
+    const WIDTH: usize = 16;
+    let rows = old.len().max(new.len()).div_ceil(WIDTH);
+    let row = |bytes: &[u8], r: usize| -> Option<String> {
+        let start = r * WIDTH;
+        if start >= bytes.len() {
+            return None;
+        }
+        let end = (start + WIDTH).min(bytes.len());
+        let hex: Vec<String> = bytes[start..end]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Some(format!("{:08x}  {}", start, hex.join(" ")))
+    };
+
+    let mut lines = Vec::new();
+    for r in 0..rows {
+        let (old_row, new_row) = (row(old, r), row(new, r));
+        if old_row != new_row {
+            lines.extend(old_row.map(|l| format!("- {}", l)));
+            lines.extend(new_row.map(|l| format!("+ {}", l)));
+        }
+    }
+    lines
+}
+
+fn indexed_chunks(png: &Png) -> Vec<(String, usize, &Chunk)> {
+    let mut indexed: Vec<(String, usize, &Chunk)> = Vec::new();
+    for chunk in png.chunks() {
+        let chunk_type = chunk.chunk_type().to_string();
+        let index = indexed.iter().filter(|(t, _, _)| *t == chunk_type).count();
+        indexed.push((chunk_type, index, chunk));
+    }
+    indexed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use crate::png::tests::PNG_FILE;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &str) -> Chunk {
+        Chunk::new(
+            ChunkType::from_str(chunk_type).unwrap(),
+            data.as_bytes().to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_identical() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(diff(&png, &png).is_empty());
+    }
+
+    #[test]
+    fn test_added_removed_modified() {
+        let old = Png::from_chunks(vec![chunk("tEXt", "a"), chunk("ruSt", "old")]);
+        let new = Png::from_chunks(vec![
+            chunk("tEXt", "a"),
+            chunk("tEXt", "b"),
+            chunk("ruSt", "newer"),
+        ]);
+
+        let diffs = diff(&old, &new);
+        assert_eq!(diffs.len(), 2);
+        assert!(matches!(
+            &diffs[0],
+            ChunkDiff::Modified { chunk_type, old_length: 3, new_length: 5, .. } if chunk_type == "ruSt"
+        ));
+        assert!(matches!(
+            &diffs[1],
+            ChunkDiff::Added { chunk_type, index: 1, .. } if chunk_type == "tEXt"
+        ));
+        assert!(diffs[0]
+            .to_string()
+            .starts_with("~ ruSt[0] 3 -> 5 bytes (+2)"));
+
+        let diffs = diff(&new, &old);
+        assert!(matches!(&diffs[0], ChunkDiff::Removed { index: 1, .. }));
+    }
+
+    #[test]
+    fn test_removal_in_a_run() {
+        let old = Png::from_chunks(vec![
+            chunk("tEXt", "a"),
+            chunk("tEXt", "b"),
+            chunk("tEXt", "c"),
+        ]);
+        let new = Png::from_chunks(vec![chunk("tEXt", "b"), chunk("tEXt", "c")]);
+        assert!(matches!(
+            diff(&old, &new)[..],
+            [ChunkDiff::Removed { index: 0, .. }]
+        ));
+        assert!(matches!(
+            diff(&new, &old)[..],
+            [ChunkDiff::Added { index: 0, .. }]
+        ));
+
+        let new = Png::from_chunks(vec![
+            chunk("tEXt", "z"),
+            chunk("tEXt", "a"),
+            chunk("tEXt", "b"),
+            chunk("tEXt", "d"),
+        ]);
+        assert!(matches!(
+            diff(&old, &new)[..],
+            [
+                ChunkDiff::Modified {
+                    index: 2,
+                    new_index: 3,
+                    ..
+                },
+                ChunkDiff::Added { index: 0, .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_hexdiff() {
+        let old = [0u8; 20];
+        let mut new = [0u8; 20];
+        new[18] = 0xff;
+        let lines = hexdiff(&old, &new);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "- 00000010  00 00 00 00");
+        assert_eq!(lines[1], "+ 00000010  00 00 ff 00");
+    }
+}
@@ -0,0 +1,128 @@
+//! Conventions for finding payloads without knowing their chunk type.
+//! `encode --auto` derives a private chunk type from a seed and always
+//! wraps the message in an [`Envelope`], whose magic bytes let
+//! [`discover`] tell pngme payloads apart from other private chunks.
+
+use sha2::{Digest, Sha256};
+
+use crate::{chunk_type::ChunkType, envelope::Envelope, fec, png::Png};
+
+/// The seed used when none is given.
+pub const DEFAULT_SEED: &str = "pngme";
+
+/// A payload found by [`discover`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredPayload {
+    /// The index of the chunk in the PNG.
+    pub index: usize,
+    pub chunk_type: ChunkType,
+    /// Whether the payload is protected by Reed-Solomon parity.
+    pub protected: bool,
+    pub envelope: Envelope,
+}
+
+/// The `attempt`th chunk type derived from `seed`. Every derived type is
+/// ancillary, private and safe to copy, so it never clashes with a type
+/// defined by the specification.
+pub fn derive_chunk_type(seed: &str, attempt: u32) -> ChunkType {
+    let digest = Sha256::new()
+        .chain_update(seed.as_bytes())
+        .chain_update(attempt.to_be_bytes())
+        .finalize();
+    let mut bytes = [0u8; 4];
+    for (byte, value) in bytes.iter_mut().zip(digest.iter()) {
+        *byte = b'a' + value % 26;
+    }
+    bytes[2].make_ascii_uppercase();
+    ChunkType::from_bytes_unchecked(bytes)
+}
+
+/// The first type derived from `seed` that `png` does not use yet, so the
+/// same seed picks the same type for the same image.
+pub fn auto_chunk_type(png: &Png, seed: &str) -> ChunkType {
+    (0..)
+        .map(|attempt| derive_chunk_type(seed, attempt))
+        .find(|chunk_type| png.chunks().iter().all(|c| c.chunk_type() != chunk_type))
+        .expect("there are more chunk types than chunks")
+}
+
+/// Every private ancillary chunk holding an envelope, in file order. Chunks
+/// with parity are repaired first; ones that cannot be are skipped.
+pub fn discover(png: &Png) -> Vec<DiscoveredPayload> {
+    png.chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| {
+            let chunk_type = chunk.chunk_type();
+            !chunk_type.is_critical() && !chunk_type.is_public()
+        })
+        .filter_map(|(index, chunk)| {
+            let protected = fec::is_protected(chunk.data());
+            let data = match protected {
+                true => fec::decode(chunk.data()).ok()?,
+                false => chunk.data().to_vec(),
+            };
+            let envelope = Envelope::try_from(data.as_ref()).ok()?;
+            Some(DiscoveredPayload {
+                index,
+                chunk_type: chunk.chunk_type().clone(),
+                protected,
+                envelope,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::png::tests::PNG_FILE;
+    use std::str::FromStr;
+
+    fn testing_png() -> Png {
+        Png::try_from(&PNG_FILE[..]).unwrap()
+    }
+
+    #[test]
+    fn test_derived_types_are_private_and_deterministic() {
+        for attempt in 0..100 {
+            let chunk_type = derive_chunk_type("build-info", attempt);
+            assert!(chunk_type.is_valid());
+            assert!(!chunk_type.is_critical());
+            assert!(!chunk_type.is_public());
+            assert!(chunk_type.is_safe_to_copy());
+            assert_eq!(chunk_type, derive_chunk_type("build-info", attempt));
+        }
+        assert_ne!(derive_chunk_type("a", 0), derive_chunk_type("b", 0));
+    }
+
+    #[test]
+    fn test_auto_chunk_type_skips_used_types() {
+        let mut png = testing_png();
+        let first = auto_chunk_type(&png, DEFAULT_SEED);
+        assert_eq!(first, derive_chunk_type(DEFAULT_SEED, 0));
+        png.append_chunk(Chunk::new(first.clone(), Vec::new()));
+        let second = auto_chunk_type(&png, DEFAULT_SEED);
+        assert_eq!(second, derive_chunk_type(DEFAULT_SEED, 1));
+    }
+
+    #[test]
+    fn test_discover_finds_only_envelopes() {
+        let mut png = testing_png();
+        let envelope = Envelope::new(b"secret".to_vec());
+        let chunk_type = auto_chunk_type(&png, DEFAULT_SEED);
+        png.append_chunk(Chunk::new(chunk_type.clone(), envelope.to_bytes().unwrap()));
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"plain".to_vec(),
+        ));
+
+        let found = discover(&png);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].chunk_type, chunk_type);
+        assert_eq!(found[0].envelope, envelope);
+        assert!(!found[0].protected);
+        assert_eq!(png.chunks()[found[0].index].chunk_type(), &chunk_type);
+    }
+}
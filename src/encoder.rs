@@ -0,0 +1,138 @@
+use std::fmt::Display;
+use std::io::Write;
+use std::str::FromStr;
+
+use flate2::{write::ZlibEncoder, Compression};
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, ihdr::Ihdr, png::Png, Result};
+
+/// The largest width or height the specification allows.
+pub const MAX_DIMENSION: u32 = (1 << 31) - 1;
+
+/// Synthesizes an 8-bit truecolor PNG filled with a single color, made of
+/// just `IHDR`, one `IDAT` and `IEND`. Handy as a carrier for payloads when
+/// no real image is available.
+pub fn solid_color(width: u32, height: u32, rgb: [u8; 3]) -> Result<Png> {
+    if !(1..=MAX_DIMENSION).contains(&width) || !(1..=MAX_DIMENSION).contains(&height) {
+        return Err(Box::new(EncoderError::InvalidDimensions(width, height)));
+    }
+
+    let ihdr = Ihdr {
+        width,
+        height,
+        bit_depth: 8,
+        color_type: Ihdr::TRUECOLOR,
+        compression_method: 0,
+        filter_method: 0,
+        interlace_method: 0,
+    };
+
+    // Every row uses the Sub filter: the first pixel is stored as is and the
+    // rest are zero differences, which deflate squeezes down to almost nothing.
+    let mut row = vec![0u8; 1 + ihdr.row_bytes(width)];
+    row[0] = 1;
+    row[1..4].copy_from_slice(&rgb);
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    for _ in 0..height {
+        encoder.write_all(&row)?;
+    }
+    let idat = encoder.finish()?;
+
+    Ok(Png::from_chunks(vec![
+        ihdr.to_chunk(),
+        Chunk::new(ChunkType::from_str("IDAT")?, idat),
+        Chunk::new(ChunkType::from_str("IEND")?, Vec::new()),
+    ]))
+}
+
+/// Encodes 8-bit truecolor pixels, three bytes per pixel row by row, as a
+/// minimal PNG with unfiltered scanlines.
+pub fn rgb(width: u32, height: u32, pixels: &[u8]) -> Result<Png> {
+    if !(1..=MAX_DIMENSION).contains(&width) || !(1..=MAX_DIMENSION).contains(&height) {
+        return Err(Box::new(EncoderError::InvalidDimensions(width, height)));
+    }
+    let row_bytes = width as usize * 3;
+    if pixels.len() != row_bytes * height as usize {
+        return Err(Box::new(EncoderError::InvalidDataLength(
+            pixels.len(),
+            row_bytes * height as usize,
+        )));
+    }
+
+    let ihdr = Ihdr {
+        width,
+        height,
+        bit_depth: 8,
+        color_type: Ihdr::TRUECOLOR,
+        compression_method: 0,
+        filter_method: 0,
+        interlace_method: 0,
+    };
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in pixels.chunks(row_bytes) {
+        encoder.write_all(&[0])?;
+        encoder.write_all(row)?;
+    }
+    let idat = encoder.finish()?;
+
+    Ok(Png::from_chunks(vec![
+        ihdr.to_chunk(),
+        Chunk::new(ChunkType::from_str("IDAT")?, idat),
+        Chunk::new(ChunkType::from_str("IEND")?, Vec::new()),
+    ]))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum EncoderError {
+    InvalidDimensions(u32, u32),
+    InvalidDataLength(usize, usize),
+}
+impl std::error::Error for EncoderError {}
+impl Display for EncoderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            EncoderError::InvalidDimensions(width, height) => {
+                crate::tr!("encoder.invalid_dimensions", width = width, height = height)
+            }
+            EncoderError::InvalidDataLength(actual, expected) => crate::tr!(
+                "encoder.invalid_data_length",
+                actual = actual,
+                expected = expected
+            ),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_data;
+
+    #[test]
+    fn test_solid_color_round_trip() {
+        let png = solid_color(3, 2, [0x12, 0x34, 0x56]).unwrap();
+        let bytes = png.as_bytes();
+        let png = Png::try_from(bytes.as_ref()).unwrap();
+
+        let ihdr = png.ihdr().unwrap().unwrap();
+        assert_eq!((ihdr.width, ihdr.height), (3, 2));
+        let pixels = image_data::unfiltered_rows(&png, &ihdr).unwrap();
+        assert_eq!(pixels, [0x12, 0x34, 0x56].repeat(6));
+    }
+
+    #[test]
+    fn test_rgb_round_trip() {
+        let pixels: Vec<u8> = (0..2 * 3 * 3).collect();
+        let png = rgb(3, 2, &pixels).unwrap();
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert_eq!(png.decode_pixels().unwrap().data(), pixels);
+        assert!(rgb(3, 2, &pixels[1..]).is_err());
+    }
+
+    #[test]
+    fn test_zero_size_rejected() {
+        assert!(solid_color(0, 1, [0, 0, 0]).is_err());
+    }
+}
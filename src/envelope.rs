@@ -0,0 +1,303 @@
+use std::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{Error, Result};
+
+/// A payload wrapped with a small versioned header carrying an optional
+/// creation time, MIME type and expiry, and free-form `key=value` metadata
+/// such as an author.
+///
+/// The encoding is the magic bytes, a version byte, a big-endian `u16`
+/// header length, the header as UTF-8 `key=value` lines, then the payload.
+/// The header lines always come in the same order, creation time, MIME type
+/// and expiry, then the metadata in insertion order, so equal envelopes
+/// encode to the same bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    created: Option<u64>,
+    mime_type: Option<String>,
+    expires: Option<u64>,
+    metadata: Vec<(String, String)>,
+    payload: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for Envelope {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        if !Envelope::is_envelope(bytes) {
+            return Err(Box::new(EnvelopeError::MissingMagic));
+        }
+        let version = bytes[Envelope::MAGIC.len()];
+        if version != Envelope::VERSION {
+            return Err(Box::new(EnvelopeError::UnsupportedVersion(version)));
+        }
+
+        let rest = &bytes[Envelope::MAGIC.len() + 1..];
+        if rest.len() < 2 {
+            return Err(Box::new(EnvelopeError::Truncated));
+        }
+        let header_length = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+        let rest = &rest[2..];
+        if rest.len() < header_length {
+            return Err(Box::new(EnvelopeError::Truncated));
+        }
+        let (header, payload) = rest.split_at(header_length);
+
+        let mut envelope = Envelope {
+            created: None,
+            mime_type: None,
+            expires: None,
+            metadata: Vec::new(),
+            payload: payload.to_vec(),
+        };
+        for line in std::str::from_utf8(header)?.lines() {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| EnvelopeError::InvalidHeaderLine(line.to_string()))?;
+            match key {
+                "created" => envelope.created = Some(value.parse()?),
+                "expires" => envelope.expires = Some(value.parse()?),
+                "mime" => envelope.mime_type = Some(value.to_string()),
+                _ => envelope.metadata.push((key.to_string(), value.to_string())),
+            }
+        }
+        Ok(envelope)
+    }
+}
+
+impl Display for Envelope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(created) = self.created {
+            writeln!(f, "created: {}", created)?;
+        }
+        if let Some(mime_type) = &self.mime_type {
+            writeln!(f, "mime: {}", mime_type)?;
+        }
+        if let Some(expires) = self.expires {
+            writeln!(f, "expires: {}", expires)?;
+        }
+        for (key, value) in &self.metadata {
+            writeln!(f, "{}: {}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl Envelope {
+    pub const MAGIC: [u8; 4] = *b"PMEv";
+    pub const VERSION: u8 = 1;
+    const RESERVED_KEYS: [&'static str; 3] = ["created", "expires", "mime"];
+
+    /// Wraps `payload` without a creation time, which is only recorded when
+    /// asked for with [`Envelope::set_created`] or [`Envelope::stamp`].
+    pub fn new(payload: Vec<u8>) -> Envelope {
+        Envelope {
+            created: None,
+            mime_type: None,
+            expires: None,
+            metadata: Vec::new(),
+            payload,
+        }
+    }
+    /// Whether `bytes` start like an encoded envelope.
+    pub fn is_envelope(bytes: &[u8]) -> bool {
+        bytes.len() > Envelope::MAGIC.len() && bytes.starts_with(&Envelope::MAGIC)
+    }
+    /// Seconds since the Unix epoch at which the envelope was created.
+    pub fn created(&self) -> Option<u64> {
+        self.created
+    }
+    /// Overrides the creation time, e.g. to make the encoding reproducible.
+    pub fn set_created(&mut self, created: u64) {
+        self.created = Some(created);
+    }
+    /// Records the current time as the creation time.
+    pub fn stamp(&mut self) {
+        self.set_created(unix_now());
+    }
+    pub fn mime_type(&self) -> Option<&str> {
+        self.mime_type.as_deref()
+    }
+    pub fn set_mime_type(&mut self, mime_type: &str) -> Result<()> {
+        Envelope::validate_value(mime_type)?;
+        self.mime_type = Some(mime_type.to_string());
+        Ok(())
+    }
+    /// Seconds since the Unix epoch after which the payload is stale.
+    pub fn expires(&self) -> Option<u64> {
+        self.expires
+    }
+    pub fn set_expires(&mut self, expires: u64) {
+        self.expires = Some(expires);
+    }
+    /// The metadata, in the order it was inserted.
+    pub fn metadata(&self) -> &[(String, String)] {
+        &self.metadata
+    }
+    pub fn insert_metadata(&mut self, key: &str, value: &str) -> Result<()> {
+        if key.is_empty() || key.contains('=') || Envelope::RESERVED_KEYS.contains(&key) {
+            return Err(Box::new(EnvelopeError::InvalidKey(key.to_string())));
+        }
+        Envelope::validate_value(key)?;
+        Envelope::validate_value(value)?;
+        self.metadata.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(unix_now())
+    }
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        self.expires.is_some_and(|expires| now >= expires)
+    }
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut header = String::new();
+        if let Some(created) = self.created {
+            header.push_str(&format!("created={}\n", created));
+        }
+        if let Some(mime_type) = &self.mime_type {
+            header.push_str(&format!("mime={}\n", mime_type));
+        }
+        if let Some(expires) = self.expires {
+            header.push_str(&format!("expires={}\n", expires));
+        }
+        for (key, value) in &self.metadata {
+            header.push_str(&format!("{}={}\n", key, value));
+        }
+        let header_length: u16 = header
+            .len()
+            .try_into()
+            .map_err(|_| EnvelopeError::HeaderTooLarge(header.len()))?;
+
+        let mut bytes = Envelope::MAGIC.to_vec();
+        bytes.push(Envelope::VERSION);
+        bytes.extend_from_slice(&header_length.to_be_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(&self.payload);
+        Ok(bytes)
+    }
+
+    fn validate_value(value: &str) -> Result<()> {
+        if value.contains(['\n', '\r']) {
+            return Err(Box::new(EnvelopeError::InvalidValue(value.to_string())));
+        }
+        Ok(())
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum EnvelopeError {
+    MissingMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    InvalidHeaderLine(String),
+    InvalidKey(String),
+    InvalidValue(String),
+    HeaderTooLarge(usize),
+}
+impl std::error::Error for EnvelopeError {}
+impl Display for EnvelopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            EnvelopeError::MissingMagic => crate::tr!("envelope.missing_magic"),
+            EnvelopeError::UnsupportedVersion(version) => {
+                crate::tr!("envelope.unsupported_version", version = version)
+            }
+            EnvelopeError::Truncated => crate::tr!("envelope.truncated"),
+            EnvelopeError::InvalidHeaderLine(line) => {
+                crate::tr!("envelope.invalid_header_line", line = format!("{:?}", line))
+            }
+            EnvelopeError::InvalidKey(key) => {
+                crate::tr!("envelope.invalid_key", key = format!("{:?}", key))
+            }
+            EnvelopeError::InvalidValue(value) => {
+                crate::tr!("envelope.invalid_value", value = format!("{:?}", value))
+            }
+            EnvelopeError::HeaderTooLarge(length) => {
+                crate::tr!("envelope.header_too_large", length = length)
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut envelope = Envelope::new(b"secret".to_vec());
+        envelope.set_mime_type("text/plain").unwrap();
+        envelope.set_expires(2_000_000_000);
+        envelope.insert_metadata("author", "sj").unwrap();
+
+        let bytes = envelope.to_bytes().unwrap();
+        assert!(Envelope::is_envelope(&bytes));
+        let parsed = Envelope::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(parsed, envelope);
+        assert_eq!(parsed.payload(), b"secret");
+        assert_eq!(
+            parsed.metadata(),
+            [("author".to_string(), "sj".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_encoding_is_reproducible() {
+        let encode = |created: Option<u64>| {
+            let mut envelope = Envelope::new(b"secret".to_vec());
+            if let Some(created) = created {
+                envelope.set_created(created);
+            }
+            envelope.insert_metadata("b", "2").unwrap();
+            envelope.insert_metadata("a", "1").unwrap();
+            envelope.to_bytes().unwrap()
+        };
+        assert_eq!(encode(None), encode(None));
+        let parsed = Envelope::try_from(encode(None).as_ref()).unwrap();
+        assert_eq!(parsed.created(), None);
+        let keys: Vec<&str> = parsed.metadata().iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["b", "a"]);
+
+        assert_eq!(encode(Some(1_700_000_000)), encode(Some(1_700_000_000)));
+        let parsed = Envelope::try_from(encode(Some(1_700_000_000)).as_ref()).unwrap();
+        assert_eq!(parsed.created(), Some(1_700_000_000));
+
+        let mut stamped = Envelope::new(Vec::new());
+        stamped.stamp();
+        assert!(stamped.created().is_some());
+    }
+
+    #[test]
+    fn test_expiry() {
+        let mut envelope = Envelope::new(Vec::new());
+        assert!(!envelope.is_expired());
+        envelope.set_expires(100);
+        assert!(!envelope.is_expired_at(99));
+        assert!(envelope.is_expired_at(100));
+    }
+
+    #[test]
+    fn test_invalid_metadata() {
+        let mut envelope = Envelope::new(Vec::new());
+        assert!(envelope.insert_metadata("created", "0").is_err());
+        assert!(envelope.insert_metadata("a=b", "c").is_err());
+        assert!(envelope.insert_metadata("author", "two\nlines").is_err());
+    }
+
+    #[test]
+    fn test_plain_data_is_not_envelope() {
+        assert!(!Envelope::is_envelope(b"hello"));
+        assert!(Envelope::try_from(&b"hello"[..]).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{chunk::ChunkError, png::PngError, Error};
+
+/// A coarse classification of the errors returned by this crate, for callers
+/// such as the CLI that map failures to exit codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A requested chunk does not exist.
+    ChunkNotFound,
+    /// The input is not a well-formed PNG.
+    InvalidPng,
+    /// A chunk's stored CRC does not match its contents.
+    CrcMismatch,
+    /// Anything else, such as I/O errors or invalid arguments.
+    Other,
+}
+
+impl ErrorKind {
+    pub fn of(error: &Error) -> ErrorKind {
+        if let Some(error) = error.downcast_ref::<ChunkError>() {
+            return match error {
+                ChunkError::InvalidCrc(..) => ErrorKind::CrcMismatch,
+                _ => ErrorKind::InvalidPng,
+            };
+        }
+        match error.downcast_ref::<PngError>() {
+            Some(PngError::ChunkNotFound) => ErrorKind::ChunkNotFound,
+            Some(PngError::BadSignature { .. }) => ErrorKind::InvalidPng,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::{tests::PNG_FILE, Png, RemovePolicy};
+
+    #[test]
+    fn test_error_kinds() {
+        let error = Png::try_from(&b"not a png"[..]).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::InvalidPng);
+
+        let mut bytes = PNG_FILE.to_vec();
+        let crc_index = bytes.len() - 12 - 1;
+        bytes[crc_index] ^= 0xff;
+        let error = Png::try_from(bytes.as_ref()).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::CrcMismatch);
+
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let error = png.remove_first("teSt", RemovePolicy::Protect).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::ChunkNotFound);
+
+        let error: Error = "something else".into();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::Other);
+    }
+}
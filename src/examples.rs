@@ -0,0 +1,308 @@
+//! Usage examples shown at the end of each subcommand's `--help`.
+
+pub const ENCODE: &str = "\
+Examples:
+  pngme encode dice.png ruSt 'hello'             Hide a message in a new ruSt chunk
+  pngme encode dice.png ruSt 'hello' out.png     Write the result to another file
+  pngme encode dice.png ruSt 'hello' - > out.png  Write the PNG to stdout
+  pngme --force-output out encode ro.png ruSt 'hi'  Put read-only files in out/
+  pngme encode dice.png --trailer 'hello'        Hide the message after IEND
+  pngme encode dice.png ruSt 'hi' --fec rs:20%   Add parity to survive damage
+  pngme encode dice.png ruSt --message-file a.bin --max-growth 64K  Stay under a size limit
+  pngme encode dice.png tEXt 'raw' --force       Allow a standard or critical type
+  pngme encode dice.png ruSt 89504e47 --input-format hex
+  pngme encode dice.png ruSt --message-env SECRET
+  pngme encode dice.png ruSt --message-stdin < msg.txt
+  pngme encode dice.png ruSt --message-file payload.bin
+  pngme encode dice.png tEXt --from-chunk other.png:tEXt:1 --force  Copy the second tEXt
+  pngme encode dice.png --auto 'hello'           Pick an unused private chunk type
+  pngme encode dice.png --label build-info 'v1.2'
+  pngme encode dice.png ruSt 'new' --overwrite   Replace the existing ruSt chunk in place
+  pngme encode dice.png ruSt 'more' --append     Add a second ruSt chunk
+  pngme encode dice.png teAm 'ui' --registry team-chunks.toml --require-registered
+  pngme encode dice.png --mode ordering --input-file msg.txt
+  pngme encode dice.png --mode lsb --input-file msg.txt --channels rgb --bits 2
+  pngme encode --split-across a.png b.png --input-file secret.bin";
+
+pub const DECODE: &str = "\
+Examples:
+  pngme decode dice.png ruSt
+  pngme decode dice.png 'ru??'                   First chunk matching the pattern
+  pngme decode dice.png rust --ignore-case
+  pngme decode dice.png ruSt/ruSt --recurse      The ruSt chunk of the PNG in the ruSt chunk
+  pngme decode dice.png --trailer
+  pngme decode dice.png --auto                   List the payloads in private chunks
+  pngme decode dice.png --label build-info
+  pngme decode dice.png maNi --validate-json manifest.schema.json
+  pngme decode dice.png ruSt --output-format base64
+  pngme decode dice.png ruSt --lossy             Replace invalid UTF-8
+  pngme decode dice.png ruSt --newline crlf      Windows line endings
+  pngme decode dice.png ruSt --newline none      The message byte for byte
+  pngme decode dice.png ruSt > payload.bin       Binary payloads are written raw
+  pngme decode https://example.com/dice.png ruSt  Needs the net feature
+  pngme decode dice.png --mode ordering
+  pngme decode dice.png --mode lsb --channels rgb --bits 2
+  pngme decode dice.png ruSt --sniff             What the payload is, e.g. application/json
+  pngme decode --join a.png b.png -o secret.bin
+  pngme decode --join a.png b.png -o secret --auto-extension";
+
+pub const APPLY: &str = "\
+Examples:
+  pngme apply --profile release.toml *.png
+  pngme apply --profile release.toml dice.png --dry-run
+  pngme apply --profile release.toml src/**/*.png --output-dir out/
+  pngme apply --profile release.toml src/*.png --output-dir out/ --flatten
+  pngme apply --profile release.toml s3://assets/icons/ --output-dir s3://assets/out/ -j 8
+  pngme apply --profile release.toml *.png -j 4 --fail-fast --json > summary.json";
+
+pub const SIGN: &str = "\
+Examples:
+  pngme sign dice.png --key private.key    Prints the public key to verify with";
+
+pub const VERIFY_SIG: &str = "\
+Examples:
+  pngme verify-sig dice.png --pubkey public.key";
+
+pub const SEAL: &str = "\
+Examples:
+  pngme seal dice.png    Sealing again replaces the previous seal";
+
+pub const VERIFY_SEAL: &str = "\
+Examples:
+  pngme verify-seal dice.png    Lists every added, removed or modified chunk";
+
+pub const CAPACITY: &str = "\
+Examples:
+  pngme capacity dice.png
+  pngme capacity dice.png --channels rgba --bits 2";
+
+pub const REMOVE: &str = "\
+Examples:
+  pngme remove dice.png ruSt
+  pngme remove dice.png tEXt --dry-run
+  pngme remove dice.png 'z*'                     First chunk whose type starts with z
+  pngme remove dice.png tEXt --all               Every tEXt chunk
+  pngme remove dice.png ruSt --print             Show the payload being removed
+  pngme remove dice.png PLTE --force-critical    Remove a chunk the image needs";
+
+pub const REMOVE_AT: &str = "\
+Examples:
+  pngme remove-at dice.png --offset 0x1A2B
+  pngme remove-at dice.png --offset 6699 --dry-run
+  pngme remove-at dice.png --offset 0x21 --print      Show the payload being removed";
+
+pub const EXTRACT: &str = "\
+Examples:
+  pngme extract dice.png --offset 0x1A2B > payload.bin
+  pngme extract dice.png --offset 0x1A2B -o payload.bin
+  pngme extract dice.png --offset 0x1A2B --raw -o chunk.bin    Length, type and CRC too
+  pngme extract dice.png --offset 0x1A2B --sniff               What the data is, e.g. image/jpeg
+  pngme extract dice.png --offset 0x1A2B -o payload --auto-extension  Writes payload.jpg etc.";
+
+pub const UNDO: &str = "\
+Examples:
+  pngme --journal remove dice.png ruSt           Record the edit so it can be undone
+  pngme undo dice.png
+  pngme undo dice.png -n 3 --dry-run             Preview reverting the last three edits";
+
+pub const PRINT: &str = "\
+Examples:
+  pngme print dice.png
+  pngme print dice.png --preview 40
+  pngme print dice.png --registry team-chunks.toml   Name the registered private chunks
+  pngme print dice.png --color always | less -R";
+
+pub const LIST: &str = "\
+Examples:
+  pngme list dice.png
+  pngme list dice.png --filter 't*'              Only tEXt, tIME, tRNS, ...
+  pngme list dice.png --preview 40               Show the start of text-like chunks
+  pngme list dice.png --registry team-chunks.toml
+  pngme list archive.png --shards                Hash each piece stored by `pack`
+  pngme list dice.png --format csv > chunks.csv";
+
+pub const STATS: &str = "\
+Examples:
+  pngme stats assets/*.png
+  pngme stats assets/*.png --json
+  pngme stats assets/*.png --format tsv          Every chunk, for a spreadsheet";
+
+pub const CORPUS_SCAN: &str = "\
+Examples:
+  pngme corpus-scan assets/    Statistics per chunk type, then the files that stand out
+  pngme corpus-scan assets/ --format csv > chunks.csv
+  pngme corpus-scan assets/ --json --min-text-size 4096
+
+Flags private chunk types found in only one file, and text chunks far larger
+than the rest of the corpus's.";
+
+pub const PALETTE: &str = "\
+Examples:
+  pngme palette indexed.png --swatches";
+
+pub const INFO: &str = "\
+Examples:
+  pngme info dice.png";
+
+pub const SCRUB: &str = "\
+Examples:
+  pngme scrub photo.png clean.png --strip-trailer
+  pngme scrub photo.png --keep-transparency --dry-run";
+
+pub const STRIP: &str = "\
+Examples:
+  pngme strip photo.png --text --time
+  pngme strip photo.png clean.png --color-info --exif --dry-run";
+
+pub const KEEP_ONLY: &str = "\
+Examples:
+  pngme keep-only photo.png IHDR,PLTE,IDAT,IEND
+  pngme keep-only photo.png IHDR,PLTE,IDAT,IEND,tRNS -o clean.png";
+
+pub const COMPACT: &str = "\
+Examples:
+  pngme compact photo.png
+  pngme compact photo.png -o small.png --max-idat-size 64K
+  pngme compact photo.png --dry-run";
+
+pub const RECHUNK: &str = "\
+Examples:
+  pngme rechunk photo.png --idat-size 64K
+  pngme rechunk photo.png --idat-size 8192 -o streamed.png";
+
+pub const REORDER: &str = "\
+Examples:
+  pngme reorder scan.png --canonical
+  pngme reorder a.png --canonical -o a.sorted.png   Normalize before diffing
+  pngme reorder scan.png --canonical --dry-run";
+
+pub const COPY_CHUNKS: &str = "\
+Examples:
+  pngme copy-chunks original.png edited.png      Restore metadata an editor dropped
+  pngme copy-chunks original.png edited.png --types tEXt,iCCP
+  pngme copy-chunks a.png b.png --types 't*' --on-conflict replace -o c.png";
+
+pub const CHECK: &str = "\
+Examples:
+  pngme check dice.png
+  pngme check broken.png --explain           Every problem, the rule and a suggested fix
+  pngme check broken.png --explain --json
+  pngme check site.png --profile browser     Only what stops browsers showing it
+  pngme check site.png --profile libpng --explain
+  pngme --lang es check dice.png             Messages in Spanish, as LANG=es_ES.UTF-8 does";
+
+pub const LINT: &str = "\
+Examples:
+  pngme lint dice.png --policy policy.toml
+  pngme lint dice.png --policy policy.toml --json
+
+A policy lists what the pipeline accepts; all of it is optional:
+  allowed = [\"sRGB\", \"gAMA\", \"pHYs\", \"tEXt\", \"te??\"]  Critical chunks always are
+  required = [\"sRGB\"]
+  forbidden_keywords = [\"Comment\"]          Keywords of tEXt, zTXt and iTXt chunks
+  max_ancillary = \"16K\"                     All ancillary chunk data together
+  [max_size]
+  iCCP = \"64K\"
+  [severity]                                  error (the default), warning or info
+  max-ancillary = \"warning\"";
+
+pub const REPAIR: &str = "\
+Examples:
+  pngme repair cut.png --truncate                Writes cut.repaired.png
+  pngme repair cut.png fixed.png --truncate
+  pngme repair cut.png --truncate --dry-run";
+
+pub const VERIFY: &str = "\
+Examples:
+  pngme verify dice.png ruSt
+  pngme verify dice.png ruSt --expect-hash sha256:<hex>";
+
+pub const DETECT: &str = "\
+Examples:
+  pngme detect suspicious.png";
+
+pub const DIFF: &str = "\
+Examples:
+  pngme diff before.png after.png --hexdiff";
+
+pub const VIEW: &str = "\
+Examples:
+  pngme view dice.png
+  pngme view dice.png --width 40 --style block
+  pngme view dice.png --style ascii > preview.txt";
+
+pub const PIXDIFF: &str = "\
+Examples:
+  pngme pixdiff before.png after.png             Check an edit left the pixels alone
+  pngme pixdiff a.png b.png -o changes.png       Mark differing pixels in red";
+
+pub const GREP: &str = "\
+Examples:
+  pngme grep 'token-[0-9a-f]{8}' assets/*.png
+  pngme grep --hex 'deadbeef' dice.png
+  pngme grep --decompress Copyright photo.png   Includes compressed text chunks
+
+Like grep(1), exits with 0 if anything matched, 1 if nothing did and 2 if a
+file could not be searched.";
+
+pub const SHELL: &str = "\
+Examples:
+  pngme shell dice.png    Then type `help` for the shell's commands";
+
+pub const HISTORY: &str = "\
+Examples:
+  pngme encode dice.png ruSt 'hello' --history   Record the change in dice.png
+  pngme history dice.png";
+
+pub const PACK: &str = "\
+Examples:
+  pngme pack dice.png notes.txt build.log -o archive.png
+  pngme pack archive.png notes.txt build.log -o archive.png --replace";
+
+pub const UNPACK: &str = "\
+Examples:
+  pngme unpack archive.png -d restored/
+  pngme unpack archive.png -d restored/ --overwrite";
+
+pub const WATCH: &str = "\
+Examples:
+  pngme watch incoming --on-add 'encode {} ruSt \"tag\"'
+  pngme watch incoming -r --on-add 'scrub {}' --debounce 1000";
+
+pub const ICC: &str = "\
+Examples:
+  pngme icc extract photo.png -o profile.icc
+  pngme icc embed dice.png profile.icc --name 'Display P3'";
+
+pub const EXIF: &str = "\
+Examples:
+  pngme exif dump photo.png
+  pngme exif strip photo.png clean.png";
+
+pub const CREATE: &str = "\
+Examples:
+  pngme create carrier.png --width 16 --height 16 --fill ff8800";
+
+pub const SET_DPI: &str = "\
+Examples:
+  pngme set-dpi scan.png 300";
+
+pub const SET: &str = "\
+Examples:
+  pngme set dice.png Author 'Jane Doe'";
+
+pub const GET: &str = "\
+Examples:
+  pngme get dice.png Author";
+
+pub const DEL: &str = "\
+Examples:
+  pngme del dice.png Author";
+
+pub const LABELS: &str = "\
+Examples:
+  pngme labels dice.png";
+
+pub const KEYS: &str = "\
+Examples:
+  pngme keys dice.png";
@@ -0,0 +1,303 @@
+use std::fmt::Display;
+
+use crate::{chunk::Chunk, Error, Result};
+
+/// The raw TIFF/EXIF payload of an `eXIf` chunk, with accessors for a few
+/// commonly inspected tags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exif {
+    data: Vec<u8>,
+    little_endian: bool,
+}
+
+impl TryFrom<&Chunk> for Exif {
+    type Error = Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        if chunk.chunk_type().bytes() != Exif::CHUNK_TYPE {
+            return Err(Box::new(ExifError::WrongChunkType(
+                chunk.chunk_type().to_string(),
+            )));
+        }
+
+        let data = chunk.data();
+        let little_endian = match data.get(..4) {
+            Some([b'I', b'I', 42, 0]) => true,
+            Some([b'M', b'M', 0, 42]) => false,
+            _ => return Err(Box::new(ExifError::InvalidTiffHeader)),
+        };
+
+        Ok(Exif {
+            data: data.to_vec(),
+            little_endian,
+        })
+    }
+}
+
+impl Display for Exif {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let byte_order = if self.little_endian {
+            "little-endian"
+        } else {
+            "big-endian"
+        };
+        writeln!(f, "Exif {{")?;
+        writeln!(f, "  Size: {} bytes ({})", self.data.len(), byte_order)?;
+        if let Some(orientation) = self.orientation() {
+            writeln!(f, "  Orientation: {}", orientation)?;
+        }
+        if let Some(date_time) = self.date_time() {
+            writeln!(f, "  DateTime: {}", date_time)?;
+        }
+        if let Some(date_time) = self.date_time_original() {
+            writeln!(f, "  DateTimeOriginal: {}", date_time)?;
+        }
+        if let Some((latitude, longitude)) = self.gps_coordinates() {
+            writeln!(f, "  GPS: {:.6}, {:.6}", latitude, longitude)?;
+        }
+        writeln!(f, "}}")?;
+        Ok(())
+    }
+}
+
+impl Exif {
+    pub const CHUNK_TYPE: [u8; 4] = *b"eXIf";
+
+    const TAG_ORIENTATION: u16 = 0x0112;
+    const TAG_DATE_TIME: u16 = 0x0132;
+    const TAG_EXIF_IFD: u16 = 0x8769;
+    const TAG_GPS_IFD: u16 = 0x8825;
+    const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+    const TAG_GPS_LATITUDE_REF: u16 = 0x0001;
+    const TAG_GPS_LATITUDE: u16 = 0x0002;
+    const TAG_GPS_LONGITUDE_REF: u16 = 0x0003;
+    const TAG_GPS_LONGITUDE: u16 = 0x0004;
+
+    const TYPE_ASCII: u16 = 2;
+    const TYPE_SHORT: u16 = 3;
+    const TYPE_LONG: u16 = 4;
+    const TYPE_RATIONAL: u16 = 5;
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    pub fn orientation(&self) -> Option<u16> {
+        let entry = self.find_entry(self.ifd0_offset()?, Exif::TAG_ORIENTATION)?;
+        match entry.field_type {
+            Exif::TYPE_SHORT => self.read_u16(entry.value_offset),
+            _ => None,
+        }
+    }
+    pub fn date_time(&self) -> Option<String> {
+        let entry = self.find_entry(self.ifd0_offset()?, Exif::TAG_DATE_TIME)?;
+        self.read_ascii(&entry)
+    }
+    pub fn date_time_original(&self) -> Option<String> {
+        let exif_ifd = self.sub_ifd_offset(Exif::TAG_EXIF_IFD)?;
+        let entry = self.find_entry(exif_ifd, Exif::TAG_DATE_TIME_ORIGINAL)?;
+        self.read_ascii(&entry)
+    }
+    /// Returns the (latitude, longitude) recorded in the GPS IFD, in signed
+    /// decimal degrees.
+    pub fn gps_coordinates(&self) -> Option<(f64, f64)> {
+        let gps_ifd = self.sub_ifd_offset(Exif::TAG_GPS_IFD)?;
+        let latitude = self.read_degrees(gps_ifd, Exif::TAG_GPS_LATITUDE)?;
+        let longitude = self.read_degrees(gps_ifd, Exif::TAG_GPS_LONGITUDE)?;
+        let latitude_ref = self.find_entry(gps_ifd, Exif::TAG_GPS_LATITUDE_REF);
+        let longitude_ref = self.find_entry(gps_ifd, Exif::TAG_GPS_LONGITUDE_REF);
+
+        let sign = |entry: Option<IfdEntry>, negative: &str| match entry {
+            Some(entry) if self.read_ascii(&entry).as_deref() == Some(negative) => -1.0,
+            _ => 1.0,
+        };
+
+        Some((
+            latitude * sign(latitude_ref, "S"),
+            longitude * sign(longitude_ref, "W"),
+        ))
+    }
+
+    fn ifd0_offset(&self) -> Option<usize> {
+        self.read_u32(4).map(|offset| offset as usize)
+    }
+    fn sub_ifd_offset(&self, tag: u16) -> Option<usize> {
+        let entry = self.find_entry(self.ifd0_offset()?, tag)?;
+        match entry.field_type {
+            Exif::TYPE_LONG => self.read_u32(entry.value_offset).map(|o| o as usize),
+            _ => None,
+        }
+    }
+    fn find_entry(&self, ifd_offset: usize, tag: u16) -> Option<IfdEntry> {
+        let count = self.read_u16(ifd_offset)? as usize;
+        (0..count)
+            .map(|i| ifd_offset + 2 + i * 12)
+            .filter(|offset| self.read_u16(*offset) == Some(tag))
+            .find_map(|offset| {
+                let field_type = self.read_u16(offset + 2)?;
+                let count = self.read_u32(offset + 4)? as usize;
+                let size = count.checked_mul(Exif::type_size(field_type))?;
+                // Values of four bytes or fewer are stored inline in the entry.
+                let value_offset = if size <= 4 {
+                    offset + 8
+                } else {
+                    self.read_u32(offset + 8)? as usize
+                };
+                Some(IfdEntry {
+                    field_type,
+                    count,
+                    value_offset,
+                })
+            })
+    }
+    fn read_ascii(&self, entry: &IfdEntry) -> Option<String> {
+        if entry.field_type != Exif::TYPE_ASCII {
+            return None;
+        }
+        let bytes = self
+            .data
+            .get(entry.value_offset..entry.value_offset.checked_add(entry.count)?)?;
+        let bytes = bytes.split(|b| *b == 0).next()?;
+        Some(String::from_utf8_lossy(bytes).into_owned())
+    }
+    fn read_degrees(&self, ifd_offset: usize, tag: u16) -> Option<f64> {
+        let entry = self.find_entry(ifd_offset, tag)?;
+        if entry.field_type != Exif::TYPE_RATIONAL || entry.count != 3 {
+            return None;
+        }
+        let degrees = self.read_rational(entry.value_offset)?;
+        let minutes = self.read_rational(entry.value_offset + 8)?;
+        let seconds = self.read_rational(entry.value_offset + 16)?;
+        Some(degrees + minutes / 60.0 + seconds / 3600.0)
+    }
+    fn read_rational(&self, offset: usize) -> Option<f64> {
+        let numerator = self.read_u32(offset)?;
+        let denominator = self.read_u32(offset + 4)?;
+        if denominator == 0 {
+            return None;
+        }
+        Some(f64::from(numerator) / f64::from(denominator))
+    }
+    fn read_u16(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+    fn read_u32(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+    fn type_size(field_type: u16) -> usize {
+        match field_type {
+            Exif::TYPE_SHORT => 2,
+            Exif::TYPE_LONG | 9 => 4,
+            Exif::TYPE_RATIONAL | 10 | 12 => 8,
+            _ => 1,
+        }
+    }
+}
+
+struct IfdEntry {
+    field_type: u16,
+    count: usize,
+    value_offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ExifError {
+    WrongChunkType(String),
+    InvalidTiffHeader,
+}
+impl std::error::Error for ExifError {}
+impl Display for ExifError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            ExifError::WrongChunkType(chunk_type) => {
+                crate::tr!("exif.wrong_chunk_type", chunk_type = chunk_type)
+            }
+            ExifError::InvalidTiffHeader => crate::tr!("exif.invalid_tiff_header"),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    // A little-endian TIFF structure with Orientation, DateTime and a GPS IFD
+    // holding 0°7'30" N, 0°7'30" W.
+    #[rustfmt::skip]
+    const EXIF_DATA: [u8; 148] = [
+        b'I', b'I', 42, 0, 8, 0, 0, 0,
+        // IFD0: 3 entries
+        3, 0,
+        0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0,     // Orientation = 6
+        0x32, 0x01, 2, 0, 20, 0, 0, 0, 50, 0, 0, 0,   // DateTime at 50
+        0x25, 0x88, 4, 0, 1, 0, 0, 0, 70, 0, 0, 0,    // GPS IFD at 70
+        0, 0, 0, 0,
+        // DateTime (offset 50)
+        b'2', b'0', b'2', b'4', b':', b'0', b'1', b':', b'0', b'2', b' ',
+        b'0', b'3', b':', b'0', b'4', b':', b'0', b'5', 0,
+        // GPS IFD (offset 70): 4 entries
+        4, 0,
+        1, 0, 2, 0, 2, 0, 0, 0, b'N', 0, 0, 0,
+        2, 0, 5, 0, 3, 0, 0, 0, 124, 0, 0, 0,
+        3, 0, 2, 0, 2, 0, 0, 0, b'W', 0, 0, 0,
+        4, 0, 5, 0, 3, 0, 0, 0, 124, 0, 0, 0,
+        0, 0, 0, 0,
+        // Rationals (offset 124) shared by both coordinates: 0/1, 7/1, 30/1
+        0, 0, 0, 0, 1, 0, 0, 0,
+        7, 0, 0, 0, 1, 0, 0, 0,
+        30, 0, 0, 0, 1, 0, 0, 0,
+    ];
+
+    fn exif_chunk(data: Vec<u8>) -> Chunk {
+        Chunk::new(ChunkType::from_str("eXIf").unwrap(), data)
+    }
+
+    fn testing_exif() -> Exif {
+        Exif::try_from(&exif_chunk(EXIF_DATA.to_vec())).unwrap()
+    }
+
+    #[test]
+    fn test_orientation() {
+        assert_eq!(testing_exif().orientation(), Some(6));
+    }
+
+    #[test]
+    fn test_date_time() {
+        assert_eq!(
+            testing_exif().date_time().as_deref(),
+            Some("2024:01:02 03:04:05")
+        );
+        assert_eq!(testing_exif().date_time_original(), None);
+    }
+
+    #[test]
+    fn test_gps_coordinates() {
+        let (latitude, longitude) = testing_exif().gps_coordinates().unwrap();
+        assert!((latitude - 0.125).abs() < 1e-9);
+        assert!((longitude + 0.125).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_truncated_data_has_no_tags() {
+        let exif = Exif::try_from(&exif_chunk(b"MM\0\x2a\0\0\0\x08".to_vec())).unwrap();
+        assert_eq!(exif.orientation(), None);
+        assert_eq!(exif.gps_coordinates(), None);
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        assert!(Exif::try_from(&exif_chunk(b"not tiff".to_vec())).is_err());
+    }
+}
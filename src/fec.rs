@@ -0,0 +1,208 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crc::crc32::checksum_ieee;
+use reed_solomon_erasure::galois_8::ReedSolomon;
+
+use crate::{Error, Result};
+
+/// How much Reed-Solomon parity to add, written as `rs:<percent>%`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FecSpec {
+    pub parity_percent: u8,
+}
+
+impl FromStr for FecSpec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parity_percent = s
+            .strip_prefix("rs:")
+            .and_then(|p| p.strip_suffix('%'))
+            .and_then(|p| p.parse::<u8>().ok())
+            .filter(|p| (1..=100).contains(p))
+            .ok_or_else(|| FecError::InvalidSpec(s.to_string()))?;
+        Ok(FecSpec { parity_percent })
+    }
+}
+
+impl FecSpec {
+    /// The payload is cut into at most this many data shards.
+    const MAX_DATA_SHARDS: usize = 64;
+
+    /// Protects `payload` with Reed-Solomon parity.
+    ///
+    /// The payload is cut into equally sized data shards and parity shards
+    /// are added in proportion. Each shard is stored with its own CRC so that
+    /// damaged shards can be told apart from intact ones and rebuilt. The
+    /// encoding is the magic bytes, a version byte, the data and parity shard
+    /// counts as bytes, big-endian `u32` shard size and payload length, then
+    /// each shard prefixed with its CRC. The header itself is not protected.
+    pub fn encode(&self, payload: &[u8]) -> Result<Vec<u8>> {
+        let data_shards = payload.len().clamp(1, FecSpec::MAX_DATA_SHARDS);
+        let parity_shards = (data_shards * self.parity_percent as usize).div_ceil(100);
+        let shard_size = payload.len().div_ceil(data_shards).max(1);
+        let payload_length: u32 = payload
+            .len()
+            .try_into()
+            .map_err(|_| FecError::PayloadTooLarge(payload.len()))?;
+
+        let mut shards: Vec<Vec<u8>> = (0..data_shards + parity_shards)
+            .map(|i| {
+                let start = (i * shard_size).min(payload.len());
+                let end = (start + shard_size).min(payload.len());
+                let mut shard = payload[start..end].to_vec();
+                shard.resize(shard_size, 0);
+                shard
+            })
+            .collect();
+        ReedSolomon::new(data_shards, parity_shards)?.encode(&mut shards)?;
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.push(data_shards as u8);
+        bytes.push(parity_shards as u8);
+        bytes.extend_from_slice(&(shard_size as u32).to_be_bytes());
+        bytes.extend_from_slice(&payload_length.to_be_bytes());
+        for shard in &shards {
+            bytes.extend_from_slice(&checksum_ieee(shard).to_be_bytes());
+            bytes.extend_from_slice(shard);
+        }
+        Ok(bytes)
+    }
+}
+
+const MAGIC: [u8; 4] = *b"PMfc";
+const VERSION: u8 = 1;
+const HEADER_LENGTH: usize = 4 + 1 + 1 + 1 + 4 + 4;
+
+/// Whether `bytes` start like a payload protected by [`FecSpec::encode`].
+pub fn is_protected(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// Recovers the payload from bytes produced by [`FecSpec::encode`], even if
+/// some shards were corrupted or cut off, as long as no more shards are lost
+/// than there are parity shards.
+pub fn decode(bytes: &[u8]) -> Result<Vec<u8>> {
+    if !is_protected(bytes) {
+        return Err(Box::new(FecError::MissingMagic));
+    }
+    if bytes.len() < HEADER_LENGTH {
+        return Err(Box::new(FecError::Truncated));
+    }
+    if bytes[4] != VERSION {
+        return Err(Box::new(FecError::UnsupportedVersion(bytes[4])));
+    }
+    let data_shards = bytes[5] as usize;
+    let parity_shards = bytes[6] as usize;
+    let shard_size = u32::from_be_bytes(bytes[7..11].try_into()?) as usize;
+    let payload_length = u32::from_be_bytes(bytes[11..15].try_into()?) as usize;
+    if payload_length > data_shards * shard_size {
+        return Err(Box::new(FecError::Truncated));
+    }
+
+    let body = &bytes[HEADER_LENGTH..];
+    let stride = 4 + shard_size;
+    let mut shards: Vec<Option<Vec<u8>>> = (0..data_shards + parity_shards)
+        .map(|i| {
+            let record = body.get(i * stride..(i + 1) * stride)?;
+            let (crc, shard) = record.split_at(4);
+            (checksum_ieee(shard).to_be_bytes() == crc).then(|| shard.to_vec())
+        })
+        .collect();
+    let lost = shards.iter().filter(|s| s.is_none()).count();
+    if lost > parity_shards {
+        return Err(Box::new(FecError::TooManyLostShards(lost, parity_shards)));
+    }
+    if lost > 0 {
+        ReedSolomon::new(data_shards, parity_shards)?.reconstruct_data(&mut shards)?;
+    }
+
+    let mut payload: Vec<u8> = shards
+        .into_iter()
+        .take(data_shards)
+        .flat_map(|s| s.expect("data shards were reconstructed"))
+        .collect();
+    payload.truncate(payload_length);
+    Ok(payload)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FecError {
+    InvalidSpec(String),
+    PayloadTooLarge(usize),
+    MissingMagic,
+    Truncated,
+    UnsupportedVersion(u8),
+    TooManyLostShards(usize, usize),
+}
+impl std::error::Error for FecError {}
+impl Display for FecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            FecError::InvalidSpec(spec) => {
+                crate::tr!("fec.invalid_spec", spec = format!("{:?}", spec))
+            }
+            FecError::PayloadTooLarge(length) => {
+                crate::tr!("fec.payload_too_large", length = length)
+            }
+            FecError::MissingMagic => crate::tr!("fec.missing_magic"),
+            FecError::Truncated => crate::tr!("fec.truncated"),
+            FecError::UnsupportedVersion(version) => {
+                crate::tr!("fec.unsupported_version", version = version)
+            }
+            FecError::TooManyLostShards(lost, parity) => {
+                crate::tr!("fec.too_many_lost_shards", lost = lost, parity = parity)
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYLOAD: &[u8] = b"Social media re-encoders sometimes damage ancillary data.";
+
+    #[test]
+    fn test_spec() {
+        assert_eq!(
+            FecSpec::from_str("rs:10%").unwrap(),
+            FecSpec { parity_percent: 10 }
+        );
+        assert!(FecSpec::from_str("rs:0%").is_err());
+        assert!(FecSpec::from_str("rs:10").is_err());
+        assert!(FecSpec::from_str("ldpc:10%").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let encoded = FecSpec { parity_percent: 10 }.encode(PAYLOAD).unwrap();
+        assert!(is_protected(&encoded));
+        assert_eq!(decode(&encoded).unwrap(), PAYLOAD);
+
+        let empty = FecSpec { parity_percent: 10 }.encode(b"").unwrap();
+        assert_eq!(decode(&empty).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_recovers_corruption_and_truncation() {
+        let mut encoded = FecSpec { parity_percent: 20 }.encode(PAYLOAD).unwrap();
+        encoded[HEADER_LENGTH + 6] ^= 0xff;
+        assert_eq!(decode(&encoded).unwrap(), PAYLOAD);
+
+        encoded.truncate(encoded.len() - 3);
+        assert_eq!(decode(&encoded).unwrap(), PAYLOAD);
+    }
+
+    #[test]
+    fn test_too_much_damage() {
+        let mut encoded = FecSpec { parity_percent: 10 }.encode(PAYLOAD).unwrap();
+        for i in 0..20 {
+            encoded[HEADER_LENGTH + i * 5 + 4] ^= 0xff;
+        }
+        assert!(decode(&encoded).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+//! C ABI for embedding pngme in other languages. The matching declarations
+//! live in `include/pngme.h`, generated with `cbindgen --config cbindgen.toml`.
+
+use std::ffi::{c_char, CStr};
+use std::str::FromStr;
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, png::Png};
+
+/// An opaque handle to a parsed PNG, owned by the caller until passed to
+/// `pngme_free`.
+pub struct PngmePng {
+    png: Png,
+}
+
+/// A byte buffer allocated by pngme. Release it with `pngme_buffer_free`.
+#[repr(C)]
+pub struct PngmeBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PngmeStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidPng = 2,
+    InvalidChunkType = 3,
+    ChunkNotFound = 4,
+}
+
+impl PngmeBuffer {
+    fn from_vec(bytes: Vec<u8>) -> PngmeBuffer {
+        let bytes = bytes.into_boxed_slice();
+        let len = bytes.len();
+        let data = Box::into_raw(bytes) as *mut u8;
+        PngmeBuffer { data, len }
+    }
+}
+
+unsafe fn chunk_type_from_ptr(chunk_type: *const c_char) -> Option<ChunkType> {
+    let chunk_type = CStr::from_ptr(chunk_type).to_str().ok()?;
+    ChunkType::from_str(chunk_type).ok()
+}
+
+/// Parses `len` bytes at `data` and stores a new handle in `out`.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes and `out` must be a valid pointer
+/// to write to.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_parse(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut PngmePng,
+) -> PngmeStatus {
+    if data.is_null() || out.is_null() {
+        return PngmeStatus::NullPointer;
+    }
+    let bytes = std::slice::from_raw_parts(data, len);
+    match Png::try_from(bytes) {
+        Ok(png) => {
+            *out = Box::into_raw(Box::new(PngmePng { png }));
+            PngmeStatus::Ok
+        }
+        Err(_) => PngmeStatus::InvalidPng,
+    }
+}
+
+/// Appends a chunk of the given type holding `len` bytes from `data`.
+///
+/// # Safety
+///
+/// `png` must come from `pngme_parse`, `chunk_type` must be a NUL-terminated
+/// string and `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_encode(
+    png: *mut PngmePng,
+    chunk_type: *const c_char,
+    data: *const u8,
+    len: usize,
+) -> PngmeStatus {
+    if png.is_null() || chunk_type.is_null() || (data.is_null() && len > 0) {
+        return PngmeStatus::NullPointer;
+    }
+    let chunk_type = match chunk_type_from_ptr(chunk_type) {
+        Some(chunk_type) => chunk_type,
+        None => return PngmeStatus::InvalidChunkType,
+    };
+    let data = if len == 0 {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(data, len).to_vec()
+    };
+    (*png).png.append_chunk(Chunk::new(chunk_type, data));
+    PngmeStatus::Ok
+}
+
+/// Copies the data of the first chunk of the given type into `out`.
+///
+/// # Safety
+///
+/// `png` must come from `pngme_parse`, `chunk_type` must be a NUL-terminated
+/// string and `out` must be a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_decode(
+    png: *const PngmePng,
+    chunk_type: *const c_char,
+    out: *mut PngmeBuffer,
+) -> PngmeStatus {
+    if png.is_null() || chunk_type.is_null() || out.is_null() {
+        return PngmeStatus::NullPointer;
+    }
+    let chunk_type = match chunk_type_from_ptr(chunk_type) {
+        Some(chunk_type) => chunk_type,
+        None => return PngmeStatus::InvalidChunkType,
+    };
+    match (*png).png.chunk(&chunk_type) {
+        Some(chunk) => {
+            *out = PngmeBuffer::from_vec(chunk.data().to_vec());
+            PngmeStatus::Ok
+        }
+        None => PngmeStatus::ChunkNotFound,
+    }
+}
+
+/// Serializes the PNG into `out`.
+///
+/// # Safety
+///
+/// `png` must come from `pngme_parse` and `out` must be a valid pointer to
+/// write to.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_to_bytes(
+    png: *const PngmePng,
+    out: *mut PngmeBuffer,
+) -> PngmeStatus {
+    if png.is_null() || out.is_null() {
+        return PngmeStatus::NullPointer;
+    }
+    *out = PngmeBuffer::from_vec((*png).png.as_bytes());
+    PngmeStatus::Ok
+}
+
+/// Releases a handle returned by `pngme_parse`. Passing NULL is a no-op.
+///
+/// # Safety
+///
+/// `png` must come from `pngme_parse` and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_free(png: *mut PngmePng) {
+    if !png.is_null() {
+        drop(Box::from_raw(png));
+    }
+}
+
+/// Releases a buffer filled in by pngme.
+///
+/// # Safety
+///
+/// `buffer` must have been filled in by pngme and not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn pngme_buffer_free(buffer: PngmeBuffer) {
+    if !buffer.data.is_null() {
+        let slice = std::ptr::slice_from_raw_parts_mut(buffer.data, buffer.len);
+        drop(Box::from_raw(slice));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        unsafe {
+            let mut png = std::ptr::null_mut();
+            let status = pngme_parse(PNG_FILE.as_ptr(), PNG_FILE.len(), &mut png);
+            assert_eq!(status, PngmeStatus::Ok);
+
+            let message = b"hello from C";
+            let status = pngme_encode(png, c"ruSt".as_ptr(), message.as_ptr(), message.len());
+            assert_eq!(status, PngmeStatus::Ok);
+
+            let mut buffer = PngmeBuffer {
+                data: std::ptr::null_mut(),
+                len: 0,
+            };
+            let status = pngme_decode(png, c"ruSt".as_ptr(), &mut buffer);
+            assert_eq!(status, PngmeStatus::Ok);
+            assert_eq!(std::slice::from_raw_parts(buffer.data, buffer.len), message);
+            pngme_buffer_free(buffer);
+
+            pngme_free(png);
+        }
+    }
+
+    #[test]
+    fn test_error_codes() {
+        unsafe {
+            let mut png = std::ptr::null_mut();
+            let status = pngme_parse(b"not a png".as_ptr(), 9, &mut png);
+            assert_eq!(status, PngmeStatus::InvalidPng);
+
+            pngme_parse(PNG_FILE.as_ptr(), PNG_FILE.len(), &mut png);
+            let mut buffer = PngmeBuffer {
+                data: std::ptr::null_mut(),
+                len: 0,
+            };
+            let status = pngme_decode(png, c"teSt".as_ptr(), &mut buffer);
+            assert_eq!(status, PngmeStatus::ChunkNotFound);
+            let status = pngme_decode(png, c"1234".as_ptr(), &mut buffer);
+            assert_eq!(status, PngmeStatus::InvalidChunkType);
+            pngme_free(png);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt::Display;
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, Error, Result};
+
+/// One modification of a file by pngme.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The subcommand that made the change, such as `encode`.
+    pub command: String,
+    /// The chunk types affected, each prefixed with `+` if added, `-` if
+    /// removed or `~` if modified.
+    pub changes: Vec<String>,
+}
+
+impl Display for HistoryEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day) = civil_date(self.timestamp);
+        let time = self.timestamp % 86400;
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC  {}",
+            year,
+            month,
+            day,
+            time / 3600,
+            time / 60 % 60,
+            time % 60,
+            self.command
+        )?;
+        if !self.changes.is_empty() {
+            write!(f, "  {}", self.changes.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// An audit trail of the pngme commands that modified a file, stored in its
+/// own private chunk. Entries are only ever added.
+///
+/// The encoding is the magic bytes, a version byte, then one UTF-8
+/// `timestamp<TAB>command<TAB>changes` line per entry, with the changes
+/// separated by spaces.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+}
+
+impl TryFrom<&Chunk> for History {
+    type Error = Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        if chunk.chunk_type().bytes() != History::CHUNK_TYPE {
+            return Err(Box::new(HistoryError::WrongChunkType(
+                chunk.chunk_type().to_string(),
+            )));
+        }
+        let data = chunk.data();
+        if !data.starts_with(&History::MAGIC) || data.len() <= History::MAGIC.len() {
+            return Err(Box::new(HistoryError::MissingMagic));
+        }
+        let version = data[History::MAGIC.len()];
+        if version != History::VERSION {
+            return Err(Box::new(HistoryError::UnsupportedVersion(version)));
+        }
+
+        let mut history = History::default();
+        for line in std::str::from_utf8(&data[History::MAGIC.len() + 1..])?.lines() {
+            let invalid = || HistoryError::InvalidEntry(line.to_string());
+            let mut fields = line.split('\t');
+            let (Some(timestamp), Some(command), Some(changes), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(Box::new(invalid()));
+            };
+            history.entries.push(HistoryEntry {
+                timestamp: timestamp.parse().map_err(|_| invalid())?,
+                command: command.to_string(),
+                changes: changes.split_whitespace().map(str::to_string).collect(),
+            });
+        }
+        Ok(history)
+    }
+}
+
+impl History {
+    pub const CHUNK_TYPE: [u8; 4] = *b"hiSt";
+    pub const MAGIC: [u8; 4] = *b"PMhs";
+    pub const VERSION: u8 = 1;
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+    /// Adds an entry at the end. Tabs and line breaks in the command are
+    /// replaced with spaces so they cannot break the encoding.
+    pub fn push(&mut self, mut entry: HistoryEntry) {
+        entry.command = entry.command.replace(['\t', '\n', '\r'], " ");
+        self.entries.push(entry);
+    }
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = History::MAGIC.to_vec();
+        data.push(History::VERSION);
+        for entry in &self.entries {
+            let line = format!(
+                "{}\t{}\t{}\n",
+                entry.timestamp,
+                entry.command,
+                entry.changes.join(" ")
+            );
+            data.extend_from_slice(line.as_bytes());
+        }
+        Chunk::new(ChunkType::from_bytes_unchecked(History::CHUNK_TYPE), data)
+    }
+}
+
+/// The UTC year, month and day of a time in seconds since the Unix epoch,
+/// after Howard Hinnant's `civil_from_days`.
+pub(crate) fn civil_date(secs: u64) -> (i64, i64, i64) {
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HistoryError {
+    WrongChunkType(String),
+    MissingMagic,
+    UnsupportedVersion(u8),
+    InvalidEntry(String),
+}
+impl std::error::Error for HistoryError {}
+impl Display for HistoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            HistoryError::WrongChunkType(chunk_type) => {
+                crate::tr!("history.wrong_chunk_type", chunk_type = chunk_type)
+            }
+            HistoryError::MissingMagic => crate::tr!("history.missing_magic"),
+            HistoryError::UnsupportedVersion(version) => {
+                crate::tr!("history.unsupported_version", version = version)
+            }
+            HistoryError::InvalidEntry(line) => {
+                crate::tr!("history.invalid_entry", entry = format!("{:?}", line))
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_round_trip() {
+        let mut history = History::default();
+        history.push(HistoryEntry {
+            timestamp: 1_700_000_000,
+            command: "encode".to_string(),
+            changes: vec!["+ruSt".to_string()],
+        });
+        history.push(HistoryEntry {
+            timestamp: 1_700_000_060,
+            command: "scrub\tnow".to_string(),
+            changes: vec!["-tEXt".to_string(), "~IHDR".to_string()],
+        });
+        let parsed = History::try_from(&history.to_chunk()).unwrap();
+        assert_eq!(parsed, history);
+        assert_eq!(parsed.entries()[1].command, "scrub now");
+        assert_eq!(
+            parsed.entries()[0].to_string(),
+            "2023-11-14 22:13:20 UTC  encode  +ruSt"
+        );
+    }
+
+    #[test]
+    fn test_civil_date() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(951_782_400), (2000, 2, 29));
+    }
+}
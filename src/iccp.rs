@@ -0,0 +1,194 @@
+use std::fmt::Display;
+use std::io::Write;
+use std::str::FromStr;
+
+use flate2::{write::ZlibEncoder, Compression};
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, parse_options::ParseOptions, Error, Result};
+
+/// An embedded ICC colour profile, as stored in an `iCCP` chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IccProfile {
+    name: String,
+    profile: Vec<u8>,
+}
+
+impl TryFrom<&Chunk> for IccProfile {
+    type Error = Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        IccProfile::from_chunk_with_options(chunk, &ParseOptions::default())
+    }
+}
+
+impl Display for IccProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({} bytes)", self.name, self.profile.len())
+    }
+}
+
+impl IccProfile {
+    pub const CHUNK_TYPE: [u8; 4] = *b"iCCP";
+    const COMPRESSION_METHOD: u8 = 0;
+    const MAX_NAME_LENGTH: usize = 79;
+
+    /// Parses an `iCCP` chunk, refusing profiles that inflate past
+    /// [`ParseOptions::max_inflated_size`].
+    pub fn from_chunk_with_options(chunk: &Chunk, options: &ParseOptions) -> Result<IccProfile> {
+        if chunk.chunk_type().bytes() != IccProfile::CHUNK_TYPE {
+            return Err(Box::new(IccProfileError::WrongChunkType(
+                chunk.chunk_type().to_string(),
+            )));
+        }
+
+        let data = chunk.data();
+        let separator = data
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or(IccProfileError::MissingSeparator)?;
+        let (name_bytes, rest) = data.split_at(separator);
+        IccProfile::validate_name_bytes(name_bytes)?;
+
+        // Skip the null separator, then read the compression method.
+        let compression_method = *rest.get(1).ok_or(IccProfileError::MissingSeparator)?;
+        if compression_method != IccProfile::COMPRESSION_METHOD {
+            return Err(Box::new(IccProfileError::UnknownCompressionMethod(
+                compression_method,
+            )));
+        }
+
+        let profile = options.inflate(&rest[2..])?;
+
+        // Profile names are Latin-1, so every byte maps directly to a char.
+        let name = name_bytes.iter().map(|b| *b as char).collect();
+
+        Ok(IccProfile { name, profile })
+    }
+    pub fn new(name: &str, profile: Vec<u8>) -> Result<IccProfile> {
+        let name_bytes = name
+            .chars()
+            .map(|c| u8::try_from(u32::from(c)))
+            .collect::<std::result::Result<Vec<u8>, _>>()
+            .map_err(|_| IccProfileError::InvalidName(name.to_string()))?;
+        IccProfile::validate_name_bytes(&name_bytes)?;
+
+        Ok(IccProfile {
+            name: name.to_string(),
+            profile,
+        })
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn profile(&self) -> &[u8] {
+        &self.profile
+    }
+    pub fn to_chunk(&self) -> Result<Chunk> {
+        let mut data: Vec<u8> = self.name.chars().map(|c| c as u8).collect();
+        data.push(0);
+        data.push(IccProfile::COMPRESSION_METHOD);
+
+        let mut encoder = ZlibEncoder::new(data, Compression::default());
+        encoder.write_all(&self.profile)?;
+        let data = encoder.finish()?;
+
+        let chunk_type = ChunkType::from_str("iCCP")?;
+        Ok(Chunk::new(chunk_type, data))
+    }
+
+    fn validate_name_bytes(name: &[u8]) -> Result<()> {
+        let printable = |b: &u8| (32..=126).contains(b) || *b >= 161;
+        if name.is_empty()
+            || name.len() > IccProfile::MAX_NAME_LENGTH
+            || !name.iter().all(printable)
+            || name.starts_with(b" ")
+            || name.ends_with(b" ")
+        {
+            let name = name.iter().map(|b| *b as char).collect();
+            return Err(Box::new(IccProfileError::InvalidName(name)));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum IccProfileError {
+    WrongChunkType(String),
+    MissingSeparator,
+    InvalidName(String),
+    UnknownCompressionMethod(u8),
+}
+impl std::error::Error for IccProfileError {}
+impl Display for IccProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            IccProfileError::WrongChunkType(chunk_type) => {
+                crate::tr!("iccp.wrong_chunk_type", chunk_type = chunk_type)
+            }
+            IccProfileError::MissingSeparator => crate::tr!("iccp.missing_separator"),
+            IccProfileError::InvalidName(name) => {
+                crate::tr!("iccp.invalid_name", name = format!("{:?}", name))
+            }
+            IccProfileError::UnknownCompressionMethod(method) => {
+                crate::tr!("iccp.unknown_compression_method", method = method)
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_profile() -> IccProfile {
+        IccProfile::new("Test profile", b"not really an ICC profile".to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let profile = testing_profile();
+        let chunk = profile.to_chunk().unwrap();
+        assert_eq!(&chunk.chunk_type().to_string(), "iCCP");
+
+        let parsed = IccProfile::try_from(&chunk).unwrap();
+        assert_eq!(parsed, profile);
+        assert_eq!(parsed.name(), "Test profile");
+        assert_eq!(parsed.profile(), b"not really an ICC profile");
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        assert!(IccProfile::new("", Vec::new()).is_err());
+        assert!(IccProfile::new(" leading space", Vec::new()).is_err());
+        assert!(IccProfile::new(&"x".repeat(80), Vec::new()).is_err());
+        assert!(IccProfile::new("snowman \u{2603}", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_inflate_limit() {
+        let profile = IccProfile::new("Bomb", vec![0; 1 << 16]).unwrap();
+        let chunk = profile.to_chunk().unwrap();
+        let options = ParseOptions {
+            max_inflated_size: 1 << 10,
+            ..ParseOptions::default()
+        };
+        assert!(IccProfile::from_chunk_with_options(&chunk, &options).is_err());
+        assert!(IccProfile::try_from(&chunk).is_ok());
+    }
+
+    #[test]
+    fn test_wrong_chunk_type() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), Vec::new());
+        assert!(IccProfile::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_unknown_compression_method() {
+        let chunk = Chunk::new(
+            ChunkType::from_str("iCCP").unwrap(),
+            b"Test profile\0\x01".to_vec(),
+        );
+        assert!(IccProfile::try_from(&chunk).is_err());
+    }
+}
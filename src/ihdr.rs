@@ -0,0 +1,218 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, Error, Result};
+
+/// The image header stored in the `IHDR` chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ihdr {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    pub color_type: u8,
+    pub compression_method: u8,
+    pub filter_method: u8,
+    pub interlace_method: u8,
+}
+
+impl TryFrom<&Chunk> for Ihdr {
+    type Error = Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        if chunk.chunk_type().bytes() != Ihdr::CHUNK_TYPE {
+            return Err(Box::new(IhdrError::WrongChunkType(
+                chunk.chunk_type().to_string(),
+            )));
+        }
+
+        let data = chunk.data();
+        if data.len() != Ihdr::LENGTH {
+            return Err(Box::new(IhdrError::InvalidLength(data.len())));
+        }
+
+        let ihdr = Ihdr {
+            width: u32::from_be_bytes(data[0..4].try_into()?),
+            height: u32::from_be_bytes(data[4..8].try_into()?),
+            bit_depth: data[8],
+            color_type: data[9],
+            compression_method: data[10],
+            filter_method: data[11],
+            interlace_method: data[12],
+        };
+
+        if ihdr.width == 0 || ihdr.height == 0 {
+            return Err(Box::new(IhdrError::ZeroDimension));
+        }
+        if !ihdr.is_bit_depth_valid() {
+            return Err(Box::new(IhdrError::InvalidBitDepth(
+                ihdr.color_type,
+                ihdr.bit_depth,
+            )));
+        }
+
+        Ok(ihdr)
+    }
+}
+
+impl Display for Ihdr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{}, {}-bit {}",
+            self.width,
+            self.height,
+            self.bit_depth,
+            self.color_type_name()
+        )
+    }
+}
+
+impl Ihdr {
+    pub const CHUNK_TYPE: [u8; 4] = *b"IHDR";
+    const LENGTH: usize = 13;
+
+    pub const GRAYSCALE: u8 = 0;
+    pub const TRUECOLOR: u8 = 2;
+    pub const INDEXED: u8 = 3;
+    pub const GRAYSCALE_ALPHA: u8 = 4;
+    pub const TRUECOLOR_ALPHA: u8 = 6;
+
+    pub fn channels(&self) -> usize {
+        match self.color_type {
+            Ihdr::TRUECOLOR => 3,
+            Ihdr::GRAYSCALE_ALPHA => 2,
+            Ihdr::TRUECOLOR_ALPHA => 4,
+            _ => 1,
+        }
+    }
+    pub fn bits_per_pixel(&self) -> usize {
+        self.channels() * self.bit_depth as usize
+    }
+    /// The number of bytes in one unfiltered scanline of the given width.
+    pub fn row_bytes(&self, width: u32) -> usize {
+        (width as usize * self.bits_per_pixel()).div_ceil(8)
+    }
+    pub fn color_type_name(&self) -> &'static str {
+        match self.color_type {
+            Ihdr::GRAYSCALE => "grayscale",
+            Ihdr::TRUECOLOR => "truecolor",
+            Ihdr::INDEXED => "indexed",
+            Ihdr::GRAYSCALE_ALPHA => "grayscale+alpha",
+            Ihdr::TRUECOLOR_ALPHA => "truecolor+alpha",
+            _ => "unknown",
+        }
+    }
+    pub fn interlace_method_name(&self) -> &'static str {
+        match self.interlace_method {
+            0 => "none",
+            1 => "Adam7",
+            _ => "unknown",
+        }
+    }
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = Vec::with_capacity(Ihdr::LENGTH);
+        data.extend_from_slice(&self.width.to_be_bytes());
+        data.extend_from_slice(&self.height.to_be_bytes());
+        data.extend_from_slice(&[
+            self.bit_depth,
+            self.color_type,
+            self.compression_method,
+            self.filter_method,
+            self.interlace_method,
+        ]);
+        Chunk::new(ChunkType::from_str("IHDR").unwrap(), data)
+    }
+
+    fn is_bit_depth_valid(&self) -> bool {
+        match self.color_type {
+            Ihdr::GRAYSCALE => [1, 2, 4, 8, 16].contains(&self.bit_depth),
+            Ihdr::INDEXED => [1, 2, 4, 8].contains(&self.bit_depth),
+            Ihdr::TRUECOLOR | Ihdr::GRAYSCALE_ALPHA | Ihdr::TRUECOLOR_ALPHA => {
+                [8, 16].contains(&self.bit_depth)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum IhdrError {
+    WrongChunkType(String),
+    InvalidLength(usize),
+    ZeroDimension,
+    InvalidBitDepth(u8, u8),
+}
+impl std::error::Error for IhdrError {}
+impl Display for IhdrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            IhdrError::WrongChunkType(chunk_type) => {
+                crate::tr!("ihdr.wrong_chunk_type", chunk_type = chunk_type)
+            }
+            IhdrError::InvalidLength(length) => crate::tr!("ihdr.invalid_length", length = length),
+            IhdrError::ZeroDimension => crate::tr!("ihdr.zero_dimension"),
+            IhdrError::InvalidBitDepth(color_type, bit_depth) => crate::tr!(
+                "ihdr.invalid_bit_depth",
+                bit_depth = bit_depth,
+                color_type = color_type
+            ),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_ihdr() -> Ihdr {
+        Ihdr {
+            width: 50,
+            height: 50,
+            bit_depth: 8,
+            color_type: Ihdr::TRUECOLOR_ALPHA,
+            compression_method: 0,
+            filter_method: 0,
+            interlace_method: 0,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let ihdr = testing_ihdr();
+        let parsed = Ihdr::try_from(&ihdr.to_chunk()).unwrap();
+        assert_eq!(parsed, ihdr);
+    }
+
+    #[test]
+    fn test_row_bytes() {
+        let ihdr = testing_ihdr();
+        assert_eq!(ihdr.channels(), 4);
+        assert_eq!(ihdr.row_bytes(50), 200);
+
+        let ihdr = Ihdr {
+            bit_depth: 1,
+            color_type: Ihdr::GRAYSCALE,
+            ..testing_ihdr()
+        };
+        assert_eq!(ihdr.row_bytes(50), 7);
+    }
+
+    #[test]
+    fn test_invalid_bit_depth() {
+        let ihdr = Ihdr {
+            bit_depth: 4,
+            ..testing_ihdr()
+        };
+        assert!(Ihdr::try_from(&ihdr.to_chunk()).is_err());
+    }
+
+    #[test]
+    fn test_zero_dimension() {
+        let ihdr = Ihdr {
+            width: 0,
+            ..testing_ihdr()
+        };
+        assert!(Ihdr::try_from(&ihdr.to_chunk()).is_err());
+    }
+}
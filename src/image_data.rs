@@ -0,0 +1,563 @@
+use std::fmt::Display;
+use std::io::{Read, Write};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use crate::{ihdr::Ihdr, palette::Palette, png::Png, transparency::Transparency, Result};
+
+/// The decoded pixels of an image: its scanlines after inflating and
+/// unfiltering, back to back and without filter bytes. Samples keep the
+/// image's bit depth, so sub-byte samples are packed and 16-bit ones are
+/// big-endian; indexed images hold palette indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PixelBuffer {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    data: Vec<u8>,
+    palette: Option<Palette>,
+    transparency: Option<Transparency>,
+}
+
+impl PixelBuffer {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn bit_depth(&self) -> u8 {
+        self.bit_depth
+    }
+    /// One of the color type constants on [`Ihdr`].
+    pub fn color_type(&self) -> u8 {
+        self.color_type
+    }
+    pub fn channels(&self) -> usize {
+        self.ihdr().channels()
+    }
+    /// The number of bytes in one row.
+    pub fn row_bytes(&self) -> usize {
+        self.ihdr().row_bytes(self.width)
+    }
+    /// Panics if `y` is not less than the height.
+    pub fn row(&self, y: u32) -> &[u8] {
+        let row_bytes = self.row_bytes();
+        let start = y as usize * row_bytes;
+        &self.data[start..start + row_bytes]
+    }
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.data.chunks(self.row_bytes().max(1))
+    }
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+    /// The value of one channel of the pixel at (`x`, `y`), whatever the bit
+    /// depth. Panics if any coordinate is out of range.
+    pub fn sample(&self, x: u32, y: u32, channel: usize) -> u16 {
+        assert!(x < self.width && channel < self.channels());
+        let row = self.row(y);
+        let bits = self.bit_depth as usize;
+        let bit_offset = (x as usize * self.channels() + channel) * bits;
+        let byte = bit_offset / 8;
+        match bits {
+            16 => u16::from_be_bytes([row[byte], row[byte + 1]]),
+            8 => row[byte] as u16,
+            _ => {
+                let shift = 8 - bits - bit_offset % 8;
+                ((row[byte] >> shift) & ((1 << bits) - 1)) as u16
+            }
+        }
+    }
+    /// Overwrites one channel of the pixel at (`x`, `y`). Bits of `value`
+    /// beyond the bit depth are ignored. Panics if any coordinate is out of
+    /// range.
+    pub fn set_sample(&mut self, x: u32, y: u32, channel: usize, value: u16) {
+        assert!(x < self.width && y < self.height && channel < self.channels());
+        let bits = self.bit_depth as usize;
+        let bit_offset = (x as usize * self.channels() + channel) * bits;
+        let byte = bit_offset / 8;
+        let row_bytes = self.row_bytes();
+        let row = &mut self.data[y as usize * row_bytes..][..row_bytes];
+        match bits {
+            16 => row[byte..byte + 2].copy_from_slice(&value.to_be_bytes()),
+            8 => row[byte] = value as u8,
+            _ => {
+                let mask = ((1u16 << bits) - 1) as u8;
+                let shift = 8 - bits - bit_offset % 8;
+                row[byte] = (row[byte] & !(mask << shift)) | ((value as u8 & mask) << shift);
+            }
+        }
+    }
+
+    /// The pixel at (`x`, `y`) as 16-bit RGBA, looking up palette entries
+    /// and applying `tRNS`, so images stored differently can be compared.
+    pub fn rgba(&self, x: u32, y: u32) -> [u16; 4] {
+        let scale = u16::MAX / ((1u32 << self.bit_depth) - 1) as u16;
+        let sample = |channel| self.sample(x, y, channel);
+        let opaque = u16::MAX;
+        match (self.color_type, &self.transparency) {
+            (Ihdr::INDEXED, transparency) => {
+                let index = sample(0) as usize;
+                let [r, g, b] = self
+                    .palette
+                    .as_ref()
+                    .and_then(|p| p.entries().get(index).copied())
+                    .unwrap_or_default();
+                let alpha = match transparency {
+                    Some(Transparency::Palette(alphas)) => alphas.get(index).copied(),
+                    _ => None,
+                };
+                [r, g, b, alpha.unwrap_or(u8::MAX)].map(|v| v as u16 * 257)
+            }
+            (Ihdr::GRAYSCALE, transparency) => {
+                let gray = sample(0);
+                let alpha = match transparency {
+                    Some(Transparency::Gray(key)) if *key == gray => 0,
+                    _ => opaque,
+                };
+                [gray * scale, gray * scale, gray * scale, alpha]
+            }
+            (Ihdr::TRUECOLOR, transparency) => {
+                let (r, g, b) = (sample(0), sample(1), sample(2));
+                let alpha = match transparency {
+                    Some(Transparency::Rgb(kr, kg, kb)) if (*kr, *kg, *kb) == (r, g, b) => 0,
+                    _ => opaque,
+                };
+                [r * scale, g * scale, b * scale, alpha]
+            }
+            (Ihdr::GRAYSCALE_ALPHA, _) => {
+                let gray = sample(0) * scale;
+                [gray, gray, gray, sample(1) * scale]
+            }
+            _ => [0, 1, 2, 3].map(|channel| sample(channel) * scale),
+        }
+    }
+
+    fn ihdr(&self) -> Ihdr {
+        Ihdr {
+            width: self.width,
+            height: self.height,
+            bit_depth: self.bit_depth,
+            color_type: self.color_type,
+            compression_method: 0,
+            filter_method: 0,
+            interlace_method: 0,
+        }
+    }
+}
+
+/// Compresses `pixels` into a zlib stream of unfiltered, non-interlaced
+/// scanlines, as stored across the `IDAT` chunks.
+pub(crate) fn encode(pixels: &PixelBuffer) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in pixels.rows() {
+        encoder.write_all(&[0])?;
+        encoder.write_all(row)?;
+    }
+    Ok(encoder.finish()?)
+}
+
+/// Decodes the image data of `png`, as described by its `IHDR` chunk.
+pub fn decode(png: &Png) -> Result<PixelBuffer> {
+    let ihdr = png.ihdr().ok_or(ImageDataError::MissingHeader)??;
+    Ok(PixelBuffer {
+        width: ihdr.width,
+        height: ihdr.height,
+        bit_depth: ihdr.bit_depth,
+        color_type: ihdr.color_type,
+        data: unfiltered_rows(png, &ihdr)?,
+        palette: png.palette().transpose()?,
+        transparency: png.transparency().transpose()?,
+    })
+}
+
+/// The starting column and row, then the column and row steps, of each of
+/// the seven Adam7 passes.
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// Concatenates and inflates the `IDAT` stream, then reverses the scanline
+/// filters, de-interlacing Adam7 images. Returns the unfiltered rows back to
+/// back, without filter bytes.
+pub(crate) fn unfiltered_rows(png: &Png, ihdr: &Ihdr) -> Result<Vec<u8>> {
+    let (width, height) = (ihdr.width as usize, ihdr.height as usize);
+    let too_large = || ImageDataError::TooLarge(ihdr.width, ihdr.height);
+    let passes: Vec<(usize, usize)> = match ihdr.interlace_method {
+        0 => vec![(width, height)],
+        1 => ADAM7
+            .iter()
+            .map(|&(x0, y0, dx, dy)| {
+                let columns = (width + dx - 1 - x0) / dx;
+                let rows = (height + dy - 1 - y0) / dy;
+                (columns, rows)
+            })
+            .collect(),
+        method => return Err(Box::new(ImageDataError::UnknownInterlace(method))),
+    };
+    // Passes with no pixels have no scanlines, not even filter bytes.
+    let mut expected = 0usize;
+    for &(columns, rows) in passes.iter().filter(|(c, r)| *c > 0 && *r > 0) {
+        let size = (ihdr.row_bytes(columns as u32) + 1)
+            .checked_mul(rows)
+            .ok_or_else(too_large)?;
+        expected = expected.checked_add(size).ok_or_else(too_large)?;
+    }
+
+    let compressed: Vec<u8> = png
+        .chunks()
+        .iter()
+        .filter(|c| &c.chunk_type().bytes() == b"IDAT")
+        .flat_map(|c| c.data().iter().copied())
+        .collect();
+
+    // Anything past the declared image size is ignored, which also stops a
+    // small stream from inflating without bound.
+    let mut filtered = Vec::new();
+    ZlibDecoder::new(&compressed[..])
+        .take(expected as u64)
+        .read_to_end(&mut filtered)?;
+    if filtered.len() < expected {
+        return Err(Box::new(ImageDataError::TooShort(filtered.len(), expected)));
+    }
+
+    if ihdr.interlace_method == 0 {
+        return unfilter(&filtered, ihdr, width, height);
+    }
+
+    let bits = ihdr.bits_per_pixel();
+    let row_bytes = ihdr.row_bytes(ihdr.width);
+    let mut rows = vec![0u8; row_bytes.checked_mul(height).ok_or_else(too_large)?];
+    let mut offset = 0;
+    for (&(x0, y0, dx, dy), &(columns, pass_rows)) in ADAM7.iter().zip(&passes) {
+        if columns == 0 || pass_rows == 0 {
+            continue;
+        }
+        let pass_row_bytes = ihdr.row_bytes(columns as u32);
+        let size = (pass_row_bytes + 1) * pass_rows;
+        let pass = unfilter(&filtered[offset..offset + size], ihdr, columns, pass_rows)?;
+        offset += size;
+
+        for (py, line) in pass.chunks(pass_row_bytes).enumerate() {
+            let row = &mut rows[(y0 + py * dy) * row_bytes..][..row_bytes];
+            for px in 0..columns {
+                copy_bits(line, px * bits, row, (x0 + px * dx) * bits, bits);
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// Reverses the filters on `height` scanlines of `width` pixels, each
+/// prefixed with its filter type byte.
+fn unfilter(filtered: &[u8], ihdr: &Ihdr, width: usize, height: usize) -> Result<Vec<u8>> {
+    // Filters operate on bytes, looking back one whole pixel (or one byte for
+    // sub-byte bit depths).
+    let bpp = ihdr.bits_per_pixel().div_ceil(8);
+    let row_bytes = ihdr.row_bytes(width as u32);
+    let mut rows = vec![0u8; row_bytes * height];
+    for y in 0..height {
+        let filter_type = filtered[y * (row_bytes + 1)];
+        let line = &filtered[y * (row_bytes + 1) + 1..(y + 1) * (row_bytes + 1)];
+        let (previous, current) = rows.split_at_mut(y * row_bytes);
+        let previous = if y == 0 {
+            None
+        } else {
+            Some(&previous[(y - 1) * row_bytes..])
+        };
+        unfilter_row(filter_type, bpp, line, previous, &mut current[..row_bytes])?;
+    }
+    Ok(rows)
+}
+
+/// Copies one pixel of `bits` bits between packed rows. Pixels of a byte or
+/// more are always byte aligned; smaller ones never straddle a byte.
+fn copy_bits(from: &[u8], from_bit: usize, to: &mut [u8], to_bit: usize, bits: usize) {
+    if bits >= 8 {
+        let len = bits / 8;
+        to[to_bit / 8..][..len].copy_from_slice(&from[from_bit / 8..][..len]);
+        return;
+    }
+    let mask = ((1u16 << bits) - 1) as u8;
+    let value = (from[from_bit / 8] >> (8 - bits - from_bit % 8)) & mask;
+    let shift = 8 - bits - to_bit % 8;
+    to[to_bit / 8] = (to[to_bit / 8] & !(mask << shift)) | (value << shift);
+}
+
+fn unfilter_row(
+    filter_type: u8,
+    bpp: usize,
+    line: &[u8],
+    previous: Option<&[u8]>,
+    out: &mut [u8],
+) -> Result<()> {
+    for i in 0..line.len() {
+        let a = if i >= bpp { out[i - bpp] } else { 0 };
+        let b = previous.map_or(0, |p| p[i]);
+        let c = match previous {
+            Some(p) if i >= bpp => p[i - bpp],
+            _ => 0,
+        };
+        let predictor = match filter_type {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            4 => paeth(a, b, c),
+            _ => return Err(Box::new(ImageDataError::UnknownFilter(filter_type))),
+        };
+        out[i] = line[i].wrapping_add(predictor);
+    }
+    Ok(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ImageDataError {
+    MissingHeader,
+    UnknownInterlace(u8),
+    TooLarge(u32, u32),
+    TooShort(usize, usize),
+    UnknownFilter(u8),
+}
+impl std::error::Error for ImageDataError {}
+impl Display for ImageDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            ImageDataError::MissingHeader => crate::tr!("image_data.missing_header"),
+            ImageDataError::UnknownInterlace(method) => {
+                crate::tr!("image_data.unknown_interlace", method = method)
+            }
+            ImageDataError::TooLarge(width, height) => {
+                crate::tr!("image_data.too_large", width = width, height = height)
+            }
+            ImageDataError::TooShort(available, required) => crate::tr!(
+                "image_data.too_short",
+                available = available,
+                required = required
+            ),
+            ImageDataError::UnknownFilter(filter_type) => {
+                crate::tr!("image_data.unknown_filter", filter_type = filter_type)
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chunk::Chunk, chunk_type::ChunkType};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_paeth() {
+        assert_eq!(paeth(10, 20, 10), 20);
+        assert_eq!(paeth(20, 10, 10), 20);
+        assert_eq!(paeth(10, 10, 20), 10);
+    }
+
+    #[test]
+    fn test_unfilter_row() {
+        let previous = [10, 20, 30, 40];
+        let mut out = [0u8; 4];
+
+        unfilter_row(1, 1, &[1, 1, 1, 1], Some(&previous), &mut out).unwrap();
+        assert_eq!(out, [1, 2, 3, 4]);
+
+        unfilter_row(2, 1, &[1, 1, 1, 1], Some(&previous), &mut out).unwrap();
+        assert_eq!(out, [11, 21, 31, 41]);
+
+        unfilter_row(3, 1, &[0, 0, 0, 0], None, &mut out).unwrap();
+        assert_eq!(out, [0, 0, 0, 0]);
+
+        assert!(unfilter_row(5, 1, &[0], None, &mut out[..1]).is_err());
+    }
+
+    #[test]
+    fn test_unfiltered_rows() {
+        let png = Png::try_from(&crate::png::tests::PNG_FILE[..]).unwrap();
+        let ihdr = png.ihdr().unwrap().unwrap();
+        let rows = unfiltered_rows(&png, &ihdr).unwrap();
+        assert_eq!(rows.len(), 50 * 50 * 4);
+    }
+
+    #[test]
+    fn test_decode_pixels() {
+        let png = Png::try_from(&crate::png::tests::PNG_FILE[..]).unwrap();
+        let pixels = png.decode_pixels().unwrap();
+        assert_eq!((pixels.width(), pixels.height()), (50, 50));
+        assert_eq!((pixels.channels(), pixels.row_bytes()), (4, 200));
+        assert_eq!(pixels.rows().count(), 50);
+        assert_eq!(pixels.row(49), &pixels.data()[49 * 200..]);
+        assert_eq!(pixels.sample(1, 2, 3), pixels.row(2)[7] as u16);
+    }
+
+    /// Re-encodes `pixels` with Adam7 interlacing and unfiltered scanlines.
+    fn interlace(pixels: &PixelBuffer) -> Png {
+        let ihdr = Ihdr {
+            interlace_method: 1,
+            ..pixels.ihdr()
+        };
+        let bits = ihdr.bits_per_pixel();
+        let (width, height) = (pixels.width as usize, pixels.height as usize);
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        for (x0, y0, dx, dy) in ADAM7 {
+            let columns: Vec<usize> = (x0..width).step_by(dx).collect();
+            if columns.is_empty() {
+                continue;
+            }
+            for y in (y0..height).step_by(dy) {
+                let mut line = vec![0u8; ihdr.row_bytes(columns.len() as u32)];
+                for (px, x) in columns.iter().enumerate() {
+                    copy_bits(pixels.row(y as u32), x * bits, &mut line, px * bits, bits);
+                }
+                encoder.write_all(&[0]).unwrap();
+                encoder.write_all(&line).unwrap();
+            }
+        }
+        Png::from_chunks(vec![
+            ihdr.to_chunk(),
+            Chunk::new(
+                ChunkType::from_str("IDAT").unwrap(),
+                encoder.finish().unwrap(),
+            ),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ])
+    }
+
+    #[test]
+    fn test_decode_interlaced() {
+        let png = Png::try_from(&crate::png::tests::PNG_FILE[..]).unwrap();
+        let pixels = png.decode_pixels().unwrap();
+        assert_eq!(interlace(&pixels).decode_pixels().unwrap(), pixels);
+
+        // Narrower than a pass's first column, so some passes are empty.
+        let rgb: Vec<u8> = (0..3 * 3 * 2).collect();
+        let pixels = crate::encoder::rgb(3, 2, &rgb)
+            .unwrap()
+            .decode_pixels()
+            .unwrap();
+        assert_eq!(interlace(&pixels).decode_pixels().unwrap(), pixels);
+    }
+
+    #[test]
+    fn test_decode_interlaced_sub_byte() {
+        let pixels = PixelBuffer {
+            width: 11,
+            height: 9,
+            bit_depth: 2,
+            color_type: Ihdr::GRAYSCALE,
+            // The last two bits of each row are padding, which stays zero.
+            data: (0..27u8)
+                .map(|i| i.wrapping_mul(37) & if i % 3 == 2 { 0xfc } else { 0xff })
+                .collect(),
+            palette: None,
+            transparency: None,
+        };
+        assert_eq!(interlace(&pixels).decode_pixels().unwrap(), pixels);
+    }
+
+    #[test]
+    fn test_unknown_interlace_method() {
+        let png = Png::try_from(&crate::png::tests::PNG_FILE[..]).unwrap();
+        let ihdr = Ihdr {
+            interlace_method: 2,
+            ..png.ihdr().unwrap().unwrap()
+        };
+        assert!(unfiltered_rows(&png, &ihdr).is_err());
+    }
+
+    #[test]
+    fn test_sample_bit_depths() {
+        let pixels = |bit_depth, data: Vec<u8>| PixelBuffer {
+            width: 4,
+            height: 1,
+            bit_depth,
+            color_type: Ihdr::GRAYSCALE,
+            data,
+            palette: None,
+            transparency: None,
+        };
+        let two_bit = pixels(2, vec![0b00_01_10_11]);
+        let samples: Vec<u16> = (0..4).map(|x| two_bit.sample(x, 0, 0)).collect();
+        assert_eq!(samples, [0, 1, 2, 3]);
+
+        let sixteen_bit = pixels(16, vec![0, 1, 2, 3, 0xff, 0xfe, 0, 0]);
+        assert_eq!(sixteen_bit.sample(1, 0, 0), 0x0203);
+        assert_eq!(sixteen_bit.sample(2, 0, 0), 0xfffe);
+    }
+
+    #[test]
+    fn test_set_sample() {
+        let mut pixels = PixelBuffer {
+            width: 4,
+            height: 1,
+            bit_depth: 2,
+            color_type: Ihdr::GRAYSCALE,
+            data: vec![0b00_01_10_11],
+            palette: None,
+            transparency: None,
+        };
+        pixels.set_sample(1, 0, 0, 3);
+        pixels.set_sample(3, 0, 0, 0b100);
+        assert_eq!(pixels.data(), [0b00_11_10_00]);
+
+        let mut png = Png::try_from(&crate::png::tests::PNG_FILE[..]).unwrap();
+        let mut pixels = png.decode_pixels().unwrap();
+        pixels.set_sample(5, 6, 2, 0x42);
+        png.set_pixels(&pixels).unwrap();
+        assert_eq!(png.decode_pixels().unwrap(), pixels);
+    }
+
+    #[test]
+    fn test_rgba() {
+        let gray = PixelBuffer {
+            width: 2,
+            height: 1,
+            bit_depth: 2,
+            color_type: Ihdr::GRAYSCALE,
+            data: vec![0b11_01_00_00],
+            palette: None,
+            transparency: Some(Transparency::Gray(1)),
+        };
+        assert_eq!(gray.rgba(0, 0), [u16::MAX, u16::MAX, u16::MAX, u16::MAX]);
+        assert_eq!(gray.rgba(1, 0), [21845, 21845, 21845, 0]);
+
+        let indexed = PixelBuffer {
+            width: 2,
+            height: 1,
+            bit_depth: 8,
+            color_type: Ihdr::INDEXED,
+            data: vec![1, 0],
+            palette: Some(Palette::new(vec![[0, 0, 0], [255, 128, 0]]).unwrap()),
+            transparency: Some(Transparency::Palette(vec![0])),
+        };
+        assert_eq!(indexed.rgba(0, 0), [65535, 128 * 257, 0, 65535]);
+        assert_eq!(indexed.rgba(1, 0), [0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,349 @@
+//! A log of how to undo edits to a file, kept in a sidecar next to it.
+//!
+//! Each [`Edit`] holds the operations that turn the edited file back into
+//! the original, plus a SHA-256 digest of the edited file so an edit is
+//! never undone on a file that has changed since. The sidecar is text: a
+//! header line, then an `edit <digest>` line per edit followed by its
+//! operations, one per line, with chunks in base64.
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use sha2::{Digest, Sha256};
+
+use crate::{chunk::Chunk, png::Png, text_encoding::TextEncoding, Error, Result};
+
+const HEADER: &str = "pngme journal 1";
+
+/// Chunk lists longer than this on both sides of an edit are not compared
+/// chunk by chunk; everything between the unchanged ends is recorded.
+const MAX_COMPARISONS: usize = 1 << 22;
+
+/// One step of undoing an edit, applied to the chunk list in order.
+#[derive(Debug, Clone)]
+pub enum Operation {
+    /// Remove the chunk at this index, which the edit added.
+    Remove(usize),
+    /// Put back a chunk the edit removed or changed.
+    Insert(usize, Chunk),
+    /// Restore the data after IEND.
+    Trailer(Vec<u8>),
+}
+
+/// How to undo one edit.
+#[derive(Debug, Clone)]
+pub struct Edit {
+    digest: [u8; 32],
+    operations: Vec<Operation>,
+}
+
+impl Edit {
+    /// The operations that turn `after` back into `before`. Only chunks
+    /// that differ are recorded.
+    pub fn between(before: &Png, after: &Png) -> Edit {
+        let same = |a: &Chunk, b: &Chunk| a.chunk_type() == b.chunk_type() && a.data() == b.data();
+        let (old, new) = (before.chunks(), after.chunks());
+        let prefix = old.iter().zip(new).take_while(|(a, b)| same(a, b)).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| same(a, b))
+            .count();
+        let old_middle = &old[prefix..old.len() - suffix];
+        let new_middle = &new[prefix..new.len() - suffix];
+        let (kept_old, kept_new) = common_chunks(old_middle, new_middle, same);
+
+        let mut operations = Vec::new();
+        for index in (0..new_middle.len()).rev().filter(|i| !kept_new[*i]) {
+            operations.push(Operation::Remove(prefix + index));
+        }
+        for (index, chunk) in old_middle.iter().enumerate() {
+            if !kept_old[index] {
+                operations.push(Operation::Insert(prefix + index, chunk.clone()));
+            }
+        }
+        if before.trailer() != after.trailer() {
+            operations.push(Operation::Trailer(before.trailer().to_vec()));
+        }
+        Edit {
+            digest: digest(after),
+            operations,
+        }
+    }
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+    /// Undoes the edit on `png`, which must be exactly the file it produced.
+    pub fn undo(&self, png: &Png) -> Result<Png> {
+        if digest(png) != self.digest {
+            return Err(Box::new(JournalError::Changed));
+        }
+        let mut chunks = png.chunks().to_vec();
+        let mut trailer = png.trailer().to_vec();
+        for operation in &self.operations {
+            match operation {
+                Operation::Remove(index) if *index < chunks.len() => {
+                    chunks.remove(*index);
+                }
+                Operation::Insert(index, chunk) if *index <= chunks.len() => {
+                    chunks.insert(*index, chunk.clone());
+                }
+                Operation::Trailer(data) => trailer = data.clone(),
+                _ => return Err(Box::new(JournalError::OutOfRange)),
+            }
+        }
+        let mut png = Png::from_chunks(chunks);
+        png.set_trailer(trailer);
+        Ok(png)
+    }
+}
+
+/// The edits recorded for one file, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct Journal {
+    edits: Vec<Edit>,
+}
+
+impl Journal {
+    /// Where the journal for `png_path` is kept.
+    pub fn sidecar_path(png_path: &Path) -> PathBuf {
+        let mut path = png_path.as_os_str().to_owned();
+        path.push(".pngme-journal");
+        PathBuf::from(path)
+    }
+    /// Reads a journal, or returns an empty one if the file does not exist.
+    pub fn load(path: &Path) -> Result<Journal> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Journal::from_str(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Journal::default()),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+    /// Writes the journal, deleting the file once there is nothing left to
+    /// undo.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if self.edits.is_empty() {
+            return match std::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Box::new(e)),
+                _ => Ok(()),
+            };
+        }
+        std::fs::write(path, self.to_string())?;
+        Ok(())
+    }
+    pub fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+    pub fn push(&mut self, edit: Edit) {
+        self.edits.push(edit);
+    }
+    /// Removes and returns the most recent edit.
+    pub fn pop(&mut self) -> Option<Edit> {
+        self.edits.pop()
+    }
+}
+
+impl FromStr for Journal {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut lines = s.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(Box::new(JournalError::MissingHeader));
+        }
+        let mut journal = Journal::default();
+        for line in lines {
+            let invalid = || JournalError::InvalidLine(line.to_string());
+            let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+            if keyword == "edit" {
+                let digest = TextEncoding::Hex.encode(rest)?;
+                journal.edits.push(Edit {
+                    digest: digest.try_into().map_err(|_| invalid())?,
+                    operations: Vec::new(),
+                });
+                continue;
+            }
+            let edit = journal.edits.last_mut().ok_or_else(invalid)?;
+            let index = || -> Result<usize> {
+                let index = rest.split(' ').next().unwrap_or_default();
+                index.parse().map_err(|_| invalid().into())
+            };
+            let operation = match keyword {
+                "remove" => Operation::Remove(index()?),
+                "insert" => {
+                    let (_, data) = rest.split_once(' ').ok_or_else(invalid)?;
+                    let bytes = TextEncoding::Base64.encode(data)?;
+                    Operation::Insert(index()?, Chunk::try_from(bytes.as_ref())?)
+                }
+                "trailer" => Operation::Trailer(TextEncoding::Base64.encode(rest)?),
+                _ => return Err(Box::new(invalid())),
+            };
+            edit.operations.push(operation);
+        }
+        Ok(journal)
+    }
+}
+
+impl Display for Journal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = |data: &[u8]| {
+            TextEncoding::Base64
+                .decode(data, false)
+                .expect("base64 cannot fail")
+        };
+        writeln!(f, "{}", HEADER)?;
+        for edit in &self.edits {
+            let digest: String = edit.digest.iter().map(|b| format!("{:02x}", b)).collect();
+            writeln!(f, "edit {}", digest)?;
+            for operation in &edit.operations {
+                match operation {
+                    Operation::Remove(index) => writeln!(f, "remove {}", index)?,
+                    Operation::Insert(index, chunk) => {
+                        writeln!(f, "insert {} {}", index, text(&chunk.as_bytes()))?
+                    }
+                    Operation::Trailer(data) => writeln!(f, "trailer {}", text(data))?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn digest(png: &Png) -> [u8; 32] {
+    Sha256::digest(png.as_bytes()).into()
+}
+
+/// Which chunks of `old` and `new` belong to a longest common subsequence.
+/// Lists too long to compare pairwise are treated as sharing nothing.
+fn common_chunks(
+    old: &[Chunk],
+    new: &[Chunk],
+    same: impl Fn(&Chunk, &Chunk) -> bool,
+) -> (Vec<bool>, Vec<bool>) {
+    let mut kept_old = vec![false; old.len()];
+    let mut kept_new = vec![false; new.len()];
+    if old.len().saturating_mul(new.len()) > MAX_COMPARISONS {
+        return (kept_old, kept_new);
+    }
+
+    // lengths[i][j] is the length of the longest common subsequence of
+    // old[i..] and new[j..].
+    let width = new.len() + 1;
+    let mut lengths = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = match same(&old[i], &new[j]) {
+                true => lengths[(i + 1) * width + j + 1] + 1,
+                false => lengths[(i + 1) * width + j].max(lengths[i * width + j + 1]),
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if same(&old[i], &new[j]) {
+            kept_old[i] = true;
+            kept_new[j] = true;
+            (i, j) = (i + 1, j + 1);
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    (kept_old, kept_new)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum JournalError {
+    MissingHeader,
+    InvalidLine(String),
+    Changed,
+    OutOfRange,
+}
+impl std::error::Error for JournalError {}
+impl Display for JournalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            JournalError::MissingHeader => crate::tr!("journal.missing_header"),
+            JournalError::InvalidLine(line) => {
+                crate::tr!("journal.invalid_line", line = format!("{:?}", line))
+            }
+            JournalError::Changed => crate::tr!("journal.changed"),
+            JournalError::OutOfRange => crate::tr!("journal.out_of_range"),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use crate::png::tests::PNG_FILE;
+    use crate::png::RemovePolicy;
+
+    fn testing_png() -> Png {
+        Png::try_from(&PNG_FILE[..]).unwrap()
+    }
+
+    fn chunk(chunk_type: &str, data: &str) -> Chunk {
+        Chunk::new(
+            ChunkType::from_str(chunk_type).unwrap(),
+            data.as_bytes().to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_undo_scattered_edits() {
+        let before = testing_png();
+        let mut after = before.clone();
+        after.remove_first("gAMA", RemovePolicy::Protect).unwrap();
+        after.append_chunk(chunk("ruSt", "added"));
+        after.set_trailer(b"tail".to_vec());
+
+        let edit = Edit::between(&before, &after);
+        assert!(!edit
+            .operations()
+            .iter()
+            .any(|op| matches!(op, Operation::Insert(_, c) if &c.chunk_type().bytes() == b"IDAT")));
+        assert_eq!(edit.undo(&after).unwrap().as_bytes(), before.as_bytes());
+    }
+
+    #[test]
+    fn test_undo_refuses_changed_file() {
+        let before = testing_png();
+        let mut after = before.clone();
+        after.append_chunk(chunk("ruSt", "added"));
+        let edit = Edit::between(&before, &after);
+
+        after.append_chunk(chunk("ruSt", "again"));
+        assert!(edit.undo(&after).is_err());
+    }
+
+    #[test]
+    fn test_journal_round_trip() {
+        let original = testing_png();
+        let mut first = original.clone();
+        first.append_chunk(chunk("ruSt", "one"));
+        let mut second = first.clone();
+        second.remove_first("pHYs", RemovePolicy::Protect).unwrap();
+        second.set_trailer(b"tail".to_vec());
+
+        let mut journal = Journal::default();
+        journal.push(Edit::between(&original, &first));
+        journal.push(Edit::between(&first, &second));
+        let mut journal = Journal::from_str(&journal.to_string()).unwrap();
+        assert_eq!(journal.edits().len(), 2);
+
+        let undone = journal.pop().unwrap().undo(&second).unwrap();
+        assert_eq!(undone.as_bytes(), first.as_bytes());
+        let undone = journal.pop().unwrap().undo(&undone).unwrap();
+        assert_eq!(undone.as_bytes(), original.as_bytes());
+        assert!(Journal::from_str("not a journal").is_err());
+    }
+}
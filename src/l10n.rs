@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+use toml::{Table, Value};
+
+use crate::{Error, Result};
+
+static LOCALE: AtomicU8 = AtomicU8::new(Locale::English as u8);
+
+/// A language pngme's messages are available in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    English = 0,
+    Spanish = 1,
+}
+
+impl FromStr for Locale {
+    type Err = Error;
+
+    /// Accepts a language code such as `es`, or a POSIX locale name such as
+    /// `es_ES.UTF-8`, `C` or `POSIX`.
+    fn from_str(s: &str) -> Result<Self> {
+        let language = s
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Ok(Locale::English),
+            "es" => Ok(Locale::Spanish),
+            _ => {
+                let supported: Vec<&str> = Locale::ALL.iter().map(|l| l.code()).collect();
+                Err(crate::tr!(
+                    "error.unsupported_language",
+                    language = format!("{:?}", s),
+                    supported = supported.join(", ")
+                )
+                .into())
+            }
+        }
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::English, Locale::Spanish];
+
+    /// The ISO 639-1 code of the language.
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::Spanish => "es",
+        }
+    }
+    /// The language asked for by `LC_ALL`, `LC_MESSAGES` or `LANG`, in that
+    /// order, or English if the first of them that is set is not available.
+    pub fn from_env() -> Locale {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Locale::from_str(&value).ok())
+            .unwrap_or(Locale::English)
+    }
+    pub fn catalog(self) -> &'static Catalog {
+        static CATALOGS: [OnceLock<Catalog>; 2] = [OnceLock::new(), OnceLock::new()];
+        CATALOGS[self as usize].get_or_init(|| {
+            let source = match self {
+                Locale::English => include_str!("../locales/en.toml"),
+                Locale::Spanish => include_str!("../locales/es.toml"),
+            };
+            Catalog::from_str(source).expect("built-in catalogs are valid")
+        })
+    }
+}
+
+/// Makes [`tr!`](crate::tr) and the library's error messages use `locale`.
+pub fn set_locale(locale: Locale) {
+    LOCALE.store(locale as u8, Ordering::Relaxed);
+}
+
+pub fn locale() -> Locale {
+    match LOCALE.load(Ordering::Relaxed) {
+        1 => Locale::Spanish,
+        _ => Locale::English,
+    }
+}
+
+/// The messages of one language, keyed by section and name such as
+/// `error.chunk_not_found`. Catalogs are TOML files with a table per section
+/// and `{name}` placeholders in the messages; `{{` and `}}` stand for literal
+/// braces.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl FromStr for Catalog {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let table: Table = s.parse()?;
+        let mut messages = HashMap::new();
+        for (section, value) in &table {
+            let Value::Table(entries) = value else {
+                return Err(Box::new(CatalogError::NotASection(section.clone())));
+            };
+            for (name, message) in entries {
+                let key = format!("{}.{}", section, name);
+                let Value::String(message) = message else {
+                    return Err(Box::new(CatalogError::NotAString(key)));
+                };
+                messages.insert(key, message.clone());
+            }
+        }
+        Ok(Catalog { messages })
+    }
+}
+
+impl Catalog {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(String::as_str)
+    }
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.messages.keys().map(String::as_str)
+    }
+}
+
+/// The message `key` in `locale` with its placeholders filled in from
+/// `args`. Messages missing from the catalog are taken from the English one,
+/// and unknown keys are shown as they are.
+pub fn message(locale: Locale, key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let template = locale
+        .catalog()
+        .get(key)
+        .or_else(|| Locale::English.catalog().get(key))
+        .unwrap_or(key);
+    fill(template, args)
+}
+
+/// Replaces each `{name}` in `template` with the matching argument, leaving
+/// placeholders without one as they are.
+fn fill(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        filled.push_str(&rest[..start]);
+        let brace = &rest[start..start + 1];
+        rest = &rest[start + 1..];
+        if rest.starts_with(brace) {
+            filled.push_str(brace);
+            rest = &rest[1..];
+            continue;
+        }
+        let name = match (brace, rest.find('}')) {
+            ("{", Some(end)) => &rest[..end],
+            _ => {
+                filled.push_str(brace);
+                continue;
+            }
+        };
+        match args.iter().find(|(arg, _)| *arg == name) {
+            Some((_, value)) => filled.push_str(&value.to_string()),
+            None => filled.push_str(&format!("{{{}}}", name)),
+        }
+        rest = &rest[name.len() + 1..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// A message from the catalog of the current [`locale`], with its
+/// placeholders filled in by name:
+///
+/// ```
+/// let message = pngme::tr!("error.chunk_not_found", chunk_type = "ruSt");
+/// assert_eq!(message, "No ruSt chunk found");
+/// ```
+#[macro_export]
+macro_rules! tr {
+    ($key:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::l10n::message(
+            $crate::l10n::locale(),
+            $key,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),*],
+        )
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CatalogError {
+    NotASection(String),
+    NotAString(String),
+}
+impl std::error::Error for CatalogError {}
+impl Display for CatalogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CatalogError::NotASection(name) => {
+                write!(f, "Catalog entry {} is not a section", name)
+            }
+            CatalogError::NotAString(key) => write!(f, "Catalog message {} is not a string", key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The names of the placeholders in `template`, in order.
+    fn placeholders(template: &str) -> Vec<String> {
+        let escaped = template.replace("{{", "").replace("}}", "");
+        escaped
+            .split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}'))
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_catalogs_match_english() {
+        let english = Locale::English.catalog();
+        for locale in Locale::ALL {
+            let catalog = locale.catalog();
+            for key in catalog.keys() {
+                let template = english.get(key);
+                assert!(template.is_some(), "{} has unknown key {}", locale, key);
+                let (mut expected, mut actual) = (
+                    placeholders(template.unwrap()),
+                    placeholders(catalog.get(key).unwrap()),
+                );
+                expected.sort();
+                actual.sort();
+                assert_eq!(actual, expected, "{} placeholders of {}", locale, key);
+            }
+            assert!(english.keys().all(|key| catalog.get(key).is_some()));
+        }
+    }
+
+    #[test]
+    fn test_message() {
+        let args: [(&str, &dyn Display); 1] = [("chunk_type", &"ruSt")];
+        assert_eq!(
+            message(Locale::Spanish, "error.chunk_not_found", &args),
+            "No se encontró ningún fragmento ruSt"
+        );
+        assert_eq!(
+            message(Locale::Spanish, "no.such_key", &args),
+            "no.such_key"
+        );
+        assert_eq!(fill("{a} {{b}} {c}", &[("a", &1)]), "1 {b} {c}");
+        assert_eq!(fill("x } y {", &[]), "x } y {");
+    }
+
+    #[test]
+    fn test_locale() {
+        assert_eq!(Locale::from_str("es_ES.UTF-8").unwrap(), Locale::Spanish);
+        assert_eq!(Locale::from_str("C").unwrap(), Locale::English);
+        assert_eq!(Locale::from_str("en-GB").unwrap(), Locale::English);
+        assert!(Locale::from_str("fr_FR").is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    discovery,
+    png::{Png, WritePolicy},
+    Error, Result,
+};
+
+/// Where one labelled payload is stored: the `occurrence`th chunk of its
+/// type, counting from zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelEntry {
+    pub label: String,
+    pub chunk_type: ChunkType,
+    pub occurrence: usize,
+}
+
+/// Maps human-readable labels to the chunks holding their payloads, so
+/// several independent messages can live in one image without anyone
+/// remembering chunk types. It is stored in its own private chunk.
+///
+/// The encoding is the magic bytes, a version byte, then one UTF-8
+/// `label<TAB>type<TAB>occurrence` line per entry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelIndex {
+    entries: Vec<LabelEntry>,
+}
+
+impl TryFrom<&Chunk> for LabelIndex {
+    type Error = Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        if chunk.chunk_type().bytes() != LabelIndex::CHUNK_TYPE {
+            return Err(Box::new(LabelError::WrongChunkType(
+                chunk.chunk_type().to_string(),
+            )));
+        }
+        let data = chunk.data();
+        if !data.starts_with(&LabelIndex::MAGIC) || data.len() <= LabelIndex::MAGIC.len() {
+            return Err(Box::new(LabelError::MissingMagic));
+        }
+        let version = data[LabelIndex::MAGIC.len()];
+        if version != LabelIndex::VERSION {
+            return Err(Box::new(LabelError::UnsupportedVersion(version)));
+        }
+
+        let mut index = LabelIndex::default();
+        for line in std::str::from_utf8(&data[LabelIndex::MAGIC.len() + 1..])?.lines() {
+            let invalid = || LabelError::InvalidEntry(line.to_string());
+            let mut fields = line.split('\t');
+            let (Some(label), Some(chunk_type), Some(occurrence), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(Box::new(invalid()));
+            };
+            index.entries.push(LabelEntry {
+                label: label.to_string(),
+                chunk_type: ChunkType::from_str(chunk_type).map_err(|_| invalid())?,
+                occurrence: occurrence.parse().map_err(|_| invalid())?,
+            });
+        }
+        Ok(index)
+    }
+}
+
+impl LabelIndex {
+    pub const CHUNK_TYPE: [u8; 4] = *b"lbIx";
+    pub const MAGIC: [u8; 4] = *b"PMlx";
+    pub const VERSION: u8 = 1;
+
+    pub fn entries(&self) -> &[LabelEntry] {
+        &self.entries
+    }
+    pub fn get(&self, label: &str) -> Option<&LabelEntry> {
+        self.entries.iter().find(|e| e.label == label)
+    }
+    /// Points `label` at a chunk, replacing any previous entry for it.
+    pub fn insert(&mut self, label: &str, chunk_type: ChunkType, occurrence: usize) -> Result<()> {
+        if label.is_empty() || label.contains(['\t', '\n', '\r']) {
+            return Err(Box::new(LabelError::InvalidLabel(label.to_string())));
+        }
+        let entry = LabelEntry {
+            label: label.to_string(),
+            chunk_type,
+            occurrence,
+        };
+        match self.entries.iter_mut().find(|e| e.label == label) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+        Ok(())
+    }
+    pub fn remove(&mut self, label: &str) -> Option<LabelEntry> {
+        let position = self.entries.iter().position(|e| e.label == label)?;
+        Some(self.entries.remove(position))
+    }
+    /// The chunk `label` points at, if both exist.
+    pub fn resolve<'a>(&self, png: &'a Png, label: &str) -> Option<&'a Chunk> {
+        let entry = self.get(label)?;
+        png.chunks()
+            .iter()
+            .filter(|c| c.chunk_type() == &entry.chunk_type)
+            .nth(entry.occurrence)
+    }
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = LabelIndex::MAGIC.to_vec();
+        data.push(LabelIndex::VERSION);
+        for entry in &self.entries {
+            let line = format!(
+                "{}\t{}\t{}\n",
+                entry.label, entry.chunk_type, entry.occurrence
+            );
+            data.extend_from_slice(line.as_bytes());
+        }
+        Chunk::new(
+            ChunkType::from_bytes_unchecked(LabelIndex::CHUNK_TYPE),
+            data,
+        )
+    }
+}
+
+/// Stores `data` under `label` in a new chunk of a type derived from the
+/// label. If the label already has a payload, [`WritePolicy::Overwrite`]
+/// replaces it in place and anything else fails, since a label names a
+/// single payload. Returns the chunk type used.
+pub fn write(png: &mut Png, label: &str, data: Vec<u8>, policy: WritePolicy) -> Result<ChunkType> {
+    let mut index = png.label_index().transpose()?.unwrap_or_default();
+    let existing = index.resolve(png, label).map(|chunk| {
+        let position = png
+            .chunks()
+            .iter()
+            .position(|c| std::ptr::eq(c, chunk))
+            .expect("the chunk is in the PNG");
+        (position, chunk.chunk_type().clone())
+    });
+    let chunk_type = match existing {
+        Some(_) if policy != WritePolicy::Overwrite => {
+            return Err(Box::new(LabelError::AlreadyExists(label.to_string())));
+        }
+        Some((position, chunk_type)) => {
+            png.remove_chunk_at(position)?;
+            png.insert_chunk(position, Chunk::new(chunk_type.clone(), data))?;
+            chunk_type
+        }
+        None => {
+            let chunk_type = discovery::auto_chunk_type(png, label);
+            png.append_chunk(Chunk::new(chunk_type.clone(), data));
+            index.insert(label, chunk_type.clone(), 0)?;
+            png.set_label_index(&index);
+            chunk_type
+        }
+    };
+    Ok(chunk_type)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LabelError {
+    WrongChunkType(String),
+    MissingMagic,
+    UnsupportedVersion(u8),
+    InvalidEntry(String),
+    InvalidLabel(String),
+    AlreadyExists(String),
+}
+impl std::error::Error for LabelError {}
+impl Display for LabelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            LabelError::WrongChunkType(chunk_type) => {
+                crate::tr!("labels.wrong_chunk_type", chunk_type = chunk_type)
+            }
+            LabelError::MissingMagic => crate::tr!("labels.missing_magic"),
+            LabelError::UnsupportedVersion(version) => {
+                crate::tr!("labels.unsupported_version", version = version)
+            }
+            LabelError::InvalidEntry(line) => {
+                crate::tr!("labels.invalid_entry", entry = format!("{:?}", line))
+            }
+            LabelError::AlreadyExists(label) => {
+                crate::tr!("labels.already_exists", label = format!("{:?}", label))
+            }
+            LabelError::InvalidLabel(label) => {
+                crate::tr!("labels.invalid_label", label = format!("{:?}", label))
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    fn testing_png() -> Png {
+        Png::try_from(&PNG_FILE[..]).unwrap()
+    }
+
+    #[test]
+    fn test_index_round_trip() {
+        let mut index = LabelIndex::default();
+        index
+            .insert("build-info", ChunkType::from_str("buIl").unwrap(), 0)
+            .unwrap();
+        index
+            .insert("notes", ChunkType::from_str("noTe").unwrap(), 2)
+            .unwrap();
+        let parsed = LabelIndex::try_from(&index.to_chunk()).unwrap();
+        assert_eq!(parsed, index);
+        assert_eq!(parsed.get("notes").unwrap().occurrence, 2);
+    }
+
+    #[test]
+    fn test_invalid_labels() {
+        let mut index = LabelIndex::default();
+        let chunk_type = ChunkType::from_str("buIl").unwrap();
+        assert!(index.insert("", chunk_type.clone(), 0).is_err());
+        assert!(index.insert("a\tb", chunk_type.clone(), 0).is_err());
+        assert!(index.insert("a\nb", chunk_type, 0).is_err());
+    }
+
+    #[test]
+    fn test_write_and_resolve_labels() {
+        let mut png = testing_png();
+        let build = write(&mut png, "build-info", b"v1".to_vec(), WritePolicy::Fail).unwrap();
+        let notes = write(&mut png, "notes", b"hello".to_vec(), WritePolicy::Fail).unwrap();
+        assert_ne!(build, notes);
+
+        let index = png.label_index().unwrap().unwrap();
+        assert_eq!(index.entries().len(), 2);
+        assert_eq!(index.resolve(&png, "build-info").unwrap().data(), b"v1");
+        assert_eq!(index.resolve(&png, "notes").unwrap().data(), b"hello");
+        assert!(index.resolve(&png, "missing").is_none());
+    }
+
+    #[test]
+    fn test_write_overwrites_existing_label() {
+        let mut png = testing_png();
+        write(&mut png, "build-info", b"v1".to_vec(), WritePolicy::Fail).unwrap();
+        let chunk_count = png.chunks().len();
+        assert!(write(&mut png, "build-info", b"v2".to_vec(), WritePolicy::Fail).is_err());
+        assert!(write(&mut png, "build-info", b"v2".to_vec(), WritePolicy::Append).is_err());
+        write(
+            &mut png,
+            "build-info",
+            b"v2".to_vec(),
+            WritePolicy::Overwrite,
+        )
+        .unwrap();
+
+        assert_eq!(png.chunks().len(), chunk_count);
+        let index = png.label_index().unwrap().unwrap();
+        assert_eq!(index.resolve(&png, "build-info").unwrap().data(), b"v2");
+    }
+}
@@ -1,6 +1,72 @@
 pub type Error = Box<dyn std::error::Error>;
 pub type Result<T> = std::result::Result<T, Error>;
 
+#[cfg(any(test, feature = "arbitrary"))]
+mod arbitrary;
+pub mod archive;
+pub mod budget;
+pub mod builder;
+pub mod category;
 pub mod chunk;
+pub mod chunk_path;
+pub mod chunk_ref;
 pub mod chunk_type;
+#[cfg(feature = "cloud")]
+pub mod cloud;
+pub mod color;
+pub mod compact;
+pub mod config;
+pub mod corpus;
+pub mod detect;
+pub mod diff;
+pub mod discovery;
+pub mod encoder;
+pub mod envelope;
+pub mod error;
+pub mod exif;
+pub mod fec;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod history;
+pub mod iccp;
+pub mod ihdr;
+pub mod image_data;
+pub mod journal;
+pub mod l10n;
+pub mod labels;
+pub mod lsb;
+pub mod matcher;
+pub mod merge;
+pub mod metadata;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod ordering;
+pub mod ordering_channel;
+pub mod palette;
+pub mod parse_options;
+pub mod payload_schema;
+pub mod phys;
+pub mod pixel_diff;
 pub mod png;
+pub mod policy;
+pub mod profile;
+pub mod progress;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod registry;
+pub mod seal;
+pub mod search;
+#[cfg(feature = "serde")]
+mod serialization;
+pub mod shard;
+pub mod signing;
+pub mod sniff;
+pub mod summary;
+pub mod table;
+pub mod text_encoding;
+pub mod thumbnail;
+pub mod transparency;
+pub mod validate;
+pub mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
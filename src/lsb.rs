@@ -0,0 +1,367 @@
+//! Hides a message in the least significant bits of pixel samples.
+//!
+//! The message is framed as a 32-bit big-endian length followed by its
+//! bytes. Its bits are written most significant first, `bits` at a time,
+//! into the selected channels of each pixel in raster order. The image is
+//! written back without interlacing. Palette images are rejected, since
+//! changing an index can select an unrelated color.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{
+    ihdr::Ihdr,
+    image_data::PixelBuffer,
+    png::{EditSession, Png},
+    Error, Result,
+};
+
+const LENGTH_SIZE: usize = 4;
+
+/// Which color channels carry message bits. Grayscale images use their gray
+/// sample if any of red, green or blue is selected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Channels {
+    pub red: bool,
+    pub green: bool,
+    pub blue: bool,
+    pub alpha: bool,
+}
+
+impl Default for Channels {
+    fn default() -> Self {
+        Channels {
+            red: true,
+            green: true,
+            blue: true,
+            alpha: false,
+        }
+    }
+}
+
+/// Parses any combination of the letters `r`, `g`, `b` and `a`, e.g. `rgb`.
+impl FromStr for Channels {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut channels = Channels {
+            red: false,
+            green: false,
+            blue: false,
+            alpha: false,
+        };
+        for letter in s.chars() {
+            let channel = match letter.to_ascii_lowercase() {
+                'r' => &mut channels.red,
+                'g' => &mut channels.green,
+                'b' => &mut channels.blue,
+                'a' => &mut channels.alpha,
+                _ => return Err(Box::new(LsbError::InvalidChannels(s.to_string()))),
+            };
+            *channel = true;
+        }
+        if s.is_empty() {
+            return Err(Box::new(LsbError::InvalidChannels(s.to_string())));
+        }
+        Ok(channels)
+    }
+}
+
+impl Display for Channels {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let letters = [
+            (self.red, 'r'),
+            (self.green, 'g'),
+            (self.blue, 'b'),
+            (self.alpha, 'a'),
+        ];
+        for (_, letter) in letters.iter().filter(|(selected, _)| *selected) {
+            write!(f, "{}", letter)?;
+        }
+        Ok(())
+    }
+}
+
+impl Channels {
+    /// The indices of the selected samples within a pixel of `color_type`.
+    fn samples(&self, color_type: u8) -> Result<Vec<usize>> {
+        let color = self.red || self.green || self.blue;
+        let samples = match color_type {
+            Ihdr::INDEXED => return Err(Box::new(LsbError::Indexed)),
+            Ihdr::GRAYSCALE => [(color, 0)].to_vec(),
+            Ihdr::GRAYSCALE_ALPHA => [(color, 0), (self.alpha, 1)].to_vec(),
+            Ihdr::TRUECOLOR => [(self.red, 0), (self.green, 1), (self.blue, 2)].to_vec(),
+            _ => [
+                (self.red, 0),
+                (self.green, 1),
+                (self.blue, 2),
+                (self.alpha, 3),
+            ]
+            .to_vec(),
+        };
+        let samples: Vec<usize> = samples
+            .into_iter()
+            .filter_map(|(selected, index)| selected.then_some(index))
+            .collect();
+        if samples.is_empty() {
+            return Err(Box::new(LsbError::NoChannels(*self)));
+        }
+        Ok(samples)
+    }
+}
+
+/// Where in each pixel a message is hidden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LsbOptions {
+    pub channels: Channels,
+    /// How many low bits of each selected sample to use, from 1 to 8 and
+    /// no more than the image's bit depth.
+    pub bits: u8,
+}
+
+impl Default for LsbOptions {
+    fn default() -> Self {
+        LsbOptions {
+            channels: Channels::default(),
+            bits: 1,
+        }
+    }
+}
+
+impl LsbOptions {
+    /// The selected sample indices, checked against the image's layout.
+    fn samples(&self, ihdr: &Ihdr) -> Result<Vec<usize>> {
+        let samples = self.channels.samples(ihdr.color_type)?;
+        if !(1..=8).contains(&self.bits) || self.bits > ihdr.bit_depth {
+            return Err(Box::new(LsbError::InvalidBits(self.bits, ihdr.bit_depth)));
+        }
+        Ok(samples)
+    }
+}
+
+/// The longest message that fits in `png` with `options`, in bytes.
+pub fn capacity(png: &Png, options: &LsbOptions) -> Result<usize> {
+    let ihdr = png.ihdr().ok_or(LsbError::MissingHeader)??;
+    let samples = options.samples(&ihdr)?;
+    let bits =
+        ihdr.width as u128 * ihdr.height as u128 * samples.len() as u128 * options.bits as u128;
+    let bytes = usize::try_from(bits / 8).unwrap_or(usize::MAX);
+    Ok(bytes.saturating_sub(LENGTH_SIZE))
+}
+
+/// Returns a copy of `png` with `message` hidden in its pixel samples. The
+/// image data changes, so the copy is saved through an [`EditSession`] and
+/// loses the unsafe-to-copy chunks that described the old pixels.
+pub fn embed(png: &Png, message: &[u8], options: &LsbOptions) -> Result<Png> {
+    let capacity = capacity(png, options)?;
+    if message.len() > capacity || u32::try_from(message.len()).is_err() {
+        return Err(Box::new(LsbError::TooLong(message.len(), capacity)));
+    }
+    let mut pixels = png.decode_pixels()?;
+    let samples = options.channels.samples(pixels.color_type())?;
+
+    let mut framed = (message.len() as u32).to_be_bytes().to_vec();
+    framed.extend_from_slice(message);
+    let total_bits = framed.len() * 8;
+    let bit = |i: usize| match i < total_bits {
+        true => ((framed[i / 8] >> (7 - i % 8)) & 1) as u16,
+        false => 0,
+    };
+
+    let bits = options.bits as usize;
+    let mask = (1u16 << bits) - 1;
+    let slots: Vec<_> = slots(&pixels, &samples)
+        .take(total_bits.div_ceil(bits))
+        .collect();
+    for (slot, (x, y, channel)) in slots.into_iter().enumerate() {
+        let value = (0..bits).fold(0, |value, i| (value << 1) | bit(slot * bits + i));
+        let sample = pixels.sample(x, y, channel);
+        pixels.set_sample(x, y, channel, (sample & !mask) | value);
+    }
+
+    let mut session = EditSession::new(png.clone());
+    session.png_mut().set_pixels(&pixels)?;
+    Ok(session.save())
+}
+
+pub fn extract(png: &Png, options: &LsbOptions) -> Result<Vec<u8>> {
+    let capacity = capacity(png, options)?;
+    let pixels = png.decode_pixels()?;
+    let samples = options.channels.samples(pixels.color_type())?;
+    let bits = options.bits;
+
+    let mut stream = slots(&pixels, &samples).flat_map(|(x, y, channel)| {
+        let value = pixels.sample(x, y, channel);
+        (0..bits).rev().map(move |i| ((value >> i) & 1) as u8)
+    });
+    let mut read = |count: usize| -> Vec<u8> {
+        (0..count)
+            .map(|_| (0..8).fold(0, |byte, _| (byte << 1) | stream.next().unwrap_or(0)))
+            .collect()
+    };
+
+    let length = u32::from_be_bytes(read(LENGTH_SIZE).try_into().expect("four bytes")) as usize;
+    if length > capacity {
+        return Err(Box::new(LsbError::InvalidLength(length, capacity)));
+    }
+    Ok(read(length))
+}
+
+/// Every selected sample in raster order, as (x, y, sample index).
+fn slots<'a>(
+    pixels: &PixelBuffer,
+    samples: &'a [usize],
+) -> impl Iterator<Item = (u32, u32, usize)> + 'a {
+    let (width, height) = (pixels.width(), pixels.height());
+    (0..height).flat_map(move |y| {
+        (0..width).flat_map(move |x| samples.iter().map(move |&channel| (x, y, channel)))
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LsbError {
+    MissingHeader,
+    Indexed,
+    InvalidChannels(String),
+    NoChannels(Channels),
+    InvalidBits(u8, u8),
+    TooLong(usize, usize),
+    InvalidLength(usize, usize),
+}
+impl std::error::Error for LsbError {}
+impl Display for LsbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            LsbError::MissingHeader => crate::tr!("lsb.missing_header"),
+            LsbError::Indexed => crate::tr!("lsb.indexed"),
+            LsbError::InvalidChannels(channels) => {
+                crate::tr!("lsb.invalid_channels", channels = format!("{:?}", channels))
+            }
+            LsbError::NoChannels(channels) => crate::tr!("lsb.no_channels", channels = channels),
+            LsbError::InvalidBits(bits, bit_depth) => {
+                crate::tr!("lsb.invalid_bits", bits = bits, bit_depth = bit_depth)
+            }
+            LsbError::TooLong(length, capacity) => {
+                crate::tr!("lsb.too_long", length = length, capacity = capacity)
+            }
+            LsbError::InvalidLength(length, capacity) => {
+                crate::tr!("lsb.invalid_length", length = length, capacity = capacity)
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    fn testing_png() -> Png {
+        Png::try_from(&PNG_FILE[..]).unwrap()
+    }
+
+    #[test]
+    fn test_parse_channels() {
+        let channels = Channels::from_str("rgbA").unwrap();
+        assert!(channels.red && channels.green && channels.blue && channels.alpha);
+        assert_eq!(Channels::from_str("ba").unwrap().to_string(), "ba");
+        assert!(Channels::from_str("").is_err());
+        assert!(Channels::from_str("rgx").is_err());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let png = testing_png();
+        assert_eq!(
+            capacity(&png, &LsbOptions::default()).unwrap(),
+            50 * 50 * 3 / 8 - 4
+        );
+        let options = LsbOptions {
+            channels: Channels::from_str("rgba").unwrap(),
+            bits: 2,
+        };
+        assert_eq!(capacity(&png, &options).unwrap(), 50 * 50 * 4 * 2 / 8 - 4);
+
+        let options = LsbOptions {
+            bits: 9,
+            ..LsbOptions::default()
+        };
+        assert!(capacity(&png, &options).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let png = testing_png();
+        let message = b"hidden in plain sight";
+        for (channels, bits) in [("rgb", 1), ("rgb", 2), ("a", 3), ("rgba", 8)] {
+            let options = LsbOptions {
+                channels: Channels::from_str(channels).unwrap(),
+                bits,
+            };
+            let encoded = embed(&png, message, &options).unwrap();
+            let encoded = Png::try_from(encoded.as_bytes().as_ref()).unwrap();
+            assert_eq!(extract(&encoded, &options).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_drops_unsafe_chunks() {
+        use crate::{chunk::Chunk, chunk_type::ChunkType};
+
+        let mut png = Png::try_from(&crate::png::tests::PNG_FILE[..]).unwrap();
+        png.append_chunk(Chunk::new(ChunkType::from_str("ruST").unwrap(), vec![1]));
+        let encoded = embed(&png, b"hi", &LsbOptions::default()).unwrap();
+        assert!(encoded.chunk("ruST").is_none());
+        assert!(encoded.chunk("RuSt").is_some());
+    }
+
+    #[test]
+    fn test_grayscale_uses_gray_sample() {
+        use crate::{chunk::Chunk, chunk_type::ChunkType};
+        use flate2::{write::ZlibEncoder, Compression};
+        use std::io::Write;
+
+        let ihdr = Ihdr {
+            width: 16,
+            height: 16,
+            bit_depth: 8,
+            color_type: Ihdr::GRAYSCALE,
+            compression_method: 0,
+            filter_method: 0,
+            interlace_method: 0,
+        };
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        for y in 0..16u8 {
+            encoder.write_all(&[0]).unwrap();
+            encoder.write_all(&[y * 16; 16]).unwrap();
+        }
+        let png = Png::from_chunks(vec![
+            ihdr.to_chunk(),
+            Chunk::new(
+                ChunkType::from_str("IDAT").unwrap(),
+                encoder.finish().unwrap(),
+            ),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ]);
+
+        let options = LsbOptions::default();
+        assert_eq!(capacity(&png, &options).unwrap(), 16 * 16 / 8 - 4);
+        let encoded = embed(&png, b"gray", &options).unwrap();
+        assert_eq!(extract(&encoded, &options).unwrap(), b"gray");
+    }
+
+    #[test]
+    fn test_rejects_too_long_and_palette() {
+        let png = testing_png();
+        let capacity = capacity(&png, &LsbOptions::default()).unwrap();
+        assert!(embed(&png, &vec![0; capacity + 1], &LsbOptions::default()).is_err());
+        assert!(embed(&png, &vec![0; capacity], &LsbOptions::default()).is_ok());
+
+        let channels = Channels::default();
+        assert!(channels.samples(Ihdr::INDEXED).is_err());
+        assert_eq!(channels.samples(Ihdr::GRAYSCALE_ALPHA).unwrap(), [0]);
+        let alpha = Channels::from_str("a").unwrap();
+        assert!(alpha.samples(Ihdr::GRAYSCALE).is_err());
+    }
+}
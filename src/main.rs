@@ -1,8 +1,43 @@
 mod commands;
+mod examples;
+mod progress_bar;
+mod shell;
+mod term;
+mod watch;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
 
-use clap::{Parser, Subcommand};
+use clap::{
+    ArgAction, ArgGroup, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand,
+    ValueEnum, ValueHint,
+};
+use log::LevelFilter;
+use pngme::budget::{self, GrowthBudget};
+use pngme::category::ChunkCategory;
+use pngme::chunk_path::ChunkPath;
+use pngme::chunk_ref::ChunkRef;
+use pngme::chunk_type::ChunkType;
+use pngme::compact::CompactOptions;
+use pngme::config::Config;
+use pngme::discovery;
+use pngme::l10n::{self, Locale};
+use pngme::lsb::{Channels, LsbOptions};
+use pngme::matcher::ChunkTypeMatcher;
+use pngme::merge::{Conflict, MergePolicy};
+use pngme::parse_options::ParseOptions;
+use pngme::payload_schema::{JsonSchema, PayloadValidator};
+use pngme::png::{RemovePolicy, WritePolicy};
+use pngme::policy::Policy;
+use pngme::table::Format;
+use pngme::text_encoding::TextEncoding;
+use pngme::thumbnail::Style;
+use pngme::tr;
+use pngme::validate::ValidationProfile;
+
+use crate::commands::{ChunkChoice, MessageSource, Newline, OutputLayout, PayloadFormat};
+use crate::term::{ColorChoice, Role};
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
@@ -10,55 +45,1885 @@ use clap::{Parser, Subcommand};
 struct Cli {
     #[clap(subcommand)]
     command: Commands,
+    /// Keep chunks with bad CRCs or malformed types instead of failing
+    #[clap(long, global = true)]
+    raw: bool,
+    /// Only check the CRCs of chunks that are actually read
+    #[clap(long, global = true)]
+    lazy_crc: bool,
+    /// Accept files without the PNG signature, such as bare chunk streams
+    #[clap(long, global = true)]
+    skip_signature: bool,
+    /// Show a progress bar on stderr while reading files
+    #[clap(long, global = true)]
+    progress: bool,
+    /// Print nothing; rely on the exit code (0 ok, 1 error, 2 chunk not
+    /// found, 3 invalid PNG, 4 CRC mismatch)
+    #[clap(short, long, global = true)]
+    quiet: bool,
+    /// Copy a file to <file><SUFFIX> before overwriting it
+    #[clap(
+        long,
+        global = true,
+        value_name = "SUFFIX",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ".bak"
+    )]
+    backup: Option<String>,
+    /// Write files that are read-only into this directory instead, under
+    /// the same name
+    #[clap(long, global = true, value_name = "DIR", value_hint = ValueHint::DirPath)]
+    force_output: Option<PathBuf>,
+    /// Record how to undo each change to a file in <file>.pngme-journal,
+    /// for `pngme undo`
+    #[clap(long, global = true)]
+    journal: bool,
+    /// Fail instead of using anything that would make the output differ
+    /// between runs, such as the current time
+    #[clap(long, global = true)]
+    deterministic: bool,
+    /// A TOML registry of private chunk types, used to describe chunks in
+    /// `list` and `print` and to check `encode --require-registered`
+    #[clap(long, global = true, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    registry: Option<PathBuf>,
+    /// Record this command in the history chunk of each file it modifies,
+    /// as `history = true` in the config file does
+    #[clap(long, global = true)]
+    history: bool,
+    /// Do not record this command in the history chunk, whatever the config
+    /// file says
+    #[clap(long, global = true, conflicts_with = "history")]
+    no_history: bool,
+    /// When to color the output
+    #[clap(long, global = true, value_enum, value_name = "WHEN", default_value = "auto")]
+    color: ColorChoice,
+    /// Language of messages and errors, such as en or es [default: from
+    /// LC_ALL, LC_MESSAGES or LANG]
+    #[clap(long, global = true, value_name = "LANG", value_parser = parse_locale)]
+    lang: Option<Locale>,
+    /// Log what is being done to stderr: -v for progress, -vv for every
+    /// chunk read and placed
+    #[clap(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+    /// Refuse to read chunks holding more than this many bytes
+    #[clap(long, global = true, value_name = "BYTES", default_value_t = 1 << 28)]
+    max_chunk_size: u32,
+    /// Refuse to read files larger than this many bytes
+    #[clap(long, global = true, value_name = "BYTES", default_value_t = 1 << 30)]
+    max_total_size: u64,
+    /// Refuse to read files with more chunks than this
+    #[clap(long, global = true, value_name = "COUNT", default_value_t = 1 << 20)]
+    max_chunks: usize,
+    /// Refuse to inflate ICC profiles and compressed text to more than this
+    /// many bytes
+    #[clap(long, global = true, value_name = "BYTES", default_value_t = 16 << 20)]
+    max_inflated_size: u64,
+    /// Set the log level directly: off, error, warn, info, debug or trace
+    #[clap(long, global = true, value_name = "LEVEL", conflicts_with = "verbose")]
+    log_level: Option<LevelFilter>,
+}
+
+/// Options that wrap an encoded message in a metadata envelope.
+#[derive(Args)]
+pub struct EnvelopeArgs {
+    /// Attach metadata to the message, e.g. author=sj (repeatable)
+    #[clap(long = "meta", value_name = "KEY=VALUE")]
+    pub metadata: Vec<String>,
+    /// Record the MIME type of the message
+    #[clap(long)]
+    pub mime: Option<String>,
+    /// Make the message expire this many seconds after it was created
+    #[clap(long, value_name = "SECONDS")]
+    pub expires_in: Option<u64>,
+    /// Record this creation time, in seconds since the Unix epoch
+    #[clap(long, value_name = "SECONDS")]
+    pub created: Option<u64>,
+    /// Record the current time as the creation time
+    #[clap(long, conflicts_with = "created")]
+    pub timestamp: bool,
+}
+
+impl EnvelopeArgs {
+    pub fn is_empty(&self) -> bool {
+        self.metadata.is_empty()
+            && self.mime.is_none()
+            && self.expires_in.is_none()
+            && self.created.is_none()
+            && !self.timestamp
+    }
+}
+
+/// Which bits of the image `--mode lsb` uses.
+#[derive(Args)]
+pub struct LsbArgs {
+    /// Color channels that carry the message with --mode lsb, any of the
+    /// letters r, g, b and a [default: rgb]
+    #[clap(long, value_parser = parse_channels)]
+    pub channels: Option<Channels>,
+    /// How many low bits of each sample to use with --mode lsb [default: 1]
+    #[clap(long, value_parser = clap::value_parser!(u8).range(1..=8))]
+    pub bits: Option<u8>,
+}
+
+impl LsbArgs {
+    pub fn options(&self) -> LsbOptions {
+        let defaults = LsbOptions::default();
+        LsbOptions {
+            channels: self.channels.unwrap_or(defaults.channels),
+            bits: self.bits.unwrap_or(defaults.bits),
+        }
+    }
+}
+
+/// Where `encode` and `decode` hide the message.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    /// In the data of a chunk
+    Chunk,
+    /// In the order of ancillary chunks, adding no bytes
+    Ordering,
+    /// In the least significant bits of pixel samples, changing the image
+    /// slightly
+    Lsb,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Encoding {
+    /// UTF-8 or Latin-1 text as detected; binary data is written raw when
+    /// piped and as hex on a terminal
+    Auto,
+    Utf8,
+    Latin1,
+    Hex,
+    Base64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum InputFormat {
+    /// Use the text as given
+    Text,
+    /// Hex digits, e.g. 00ff10 for binary payloads
+    Hex,
+    Base64,
+}
+
+impl InputFormat {
+    fn parse(self, message: &str) -> pngme::Result<Vec<u8>> {
+        match self {
+            InputFormat::Text => TextEncoding::Utf8.encode(message),
+            InputFormat::Hex => TextEncoding::Hex.encode(message),
+            InputFormat::Base64 => TextEncoding::Base64.encode(message),
+        }
+    }
+}
+
+/// How `list`, `stats` and `corpus-scan` print their results.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// For reading in a terminal
+    Text,
+    Json,
+    /// One row per chunk: file, index, offset, type, length, crc, flags
+    Csv,
+    /// As csv, separated by tabs
+    Tsv,
+}
+
+impl Commands {
+    /// The existing file the command overwrites, so that a read-only one is
+    /// refused before anything is parsed. `encode`, whose output path can
+    /// be a positional argument, checks its own.
+    fn in_place_target(&self) -> Option<&Path> {
+        let target = match self {
+            Commands::Sign { file_path, .. }
+            | Commands::Seal { file_path }
+            | Commands::Set { file_path, .. }
+            | Commands::Del { file_path, .. } => file_path,
+            Commands::Remove {
+                file_path, dry_run, ..
+            }
+            | Commands::RemoveAt {
+                file_path, dry_run, ..
+            }
+            | Commands::Undo {
+                file_path, dry_run, ..
+            }
+            | Commands::SetDpi {
+                file_path, dry_run, ..
+            } => return (!dry_run).then_some(file_path.as_path()),
+            Commands::Scrub {
+                file_path,
+                output_path,
+                dry_run,
+                ..
+            }
+            | Commands::Strip {
+                file_path,
+                output_path,
+                dry_run,
+                ..
+            }
+            | Commands::KeepOnly {
+                file_path,
+                output: output_path,
+                dry_run,
+                ..
+            }
+            | Commands::Compact {
+                file_path,
+                output: output_path,
+                dry_run,
+                ..
+            }
+            | Commands::Rechunk {
+                file_path,
+                output: output_path,
+                dry_run,
+                ..
+            }
+            | Commands::Reorder {
+                file_path,
+                output: output_path,
+                dry_run,
+                ..
+            }
+            | Commands::CopyChunks {
+                destination_path: file_path,
+                output: output_path,
+                dry_run,
+                ..
+            } => return (!dry_run).then(|| output_path.as_deref().unwrap_or(file_path)),
+            // Repairs go to a new file unless told otherwise.
+            Commands::Repair {
+                output_path,
+                dry_run,
+                ..
+            } => return output_path.as_deref().filter(|_| !dry_run),
+            Commands::Icc {
+                command:
+                    IccCommands::Embed {
+                        file_path,
+                        output_path,
+                        ..
+                    },
+            }
+            | Commands::Exif {
+                command:
+                    ExifCommands::Strip {
+                        file_path,
+                        output_path,
+                    },
+            } => output_path.as_ref().unwrap_or(file_path),
+            _ => return None,
+        };
+        Some(target)
+    }
+}
+
+impl From<OutputFormat> for Format {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Text => Format::Text,
+            OutputFormat::Json => Format::Json,
+            OutputFormat::Csv => Format::Csv,
+            OutputFormat::Tsv => Format::Tsv,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ViewStyle {
+    /// Two pixels per character using 24-bit colors
+    HalfBlock,
+    /// One pixel per two colored spaces
+    Block,
+    /// Characters by brightness, without color
+    Ascii,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CheckProfile {
+    /// Every rule in the PNG specification
+    Strict,
+    /// Only what stops web browsers displaying the image
+    Browser,
+    /// Only what libpng rejects with its default settings
+    Libpng,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OnConflict {
+    /// Leave the destination's conflicting chunks alone
+    Keep,
+    /// Replace the destination's conflicting chunks
+    Replace,
+    /// Add the copied chunks alongside the destination's
+    Add,
+}
+
+// Parsed once per run, so the size of the largest variant does not matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
+    /// Hide a message in a PNG
+    #[clap(after_long_help = examples::ENCODE)]
     Encode {
-        file_path: PathBuf,
-        chunk_type: String,
-        message: String,
+        #[clap(required_unless_present = "split_across")]
+        file_path: Option<PathBuf>,
+        // Checked once the positional arguments are sorted out, since with
+        // --auto or --label this is the message.
+        #[clap(required_unless_present_any = [
+            "trailer", "split_across", "mode", "auto", "label", "message_stdin", "message_env",
+            "message_file", "from_chunk"
+        ])]
+        chunk_type: Option<String>,
+        #[clap(required_unless_present_any = [
+            "trailer", "split_across", "mode", "message_stdin", "message_env", "message_file",
+            "from_chunk", "auto", "label"
+        ])]
+        message: Option<String>,
+        #[clap(value_hint = ValueHint::FilePath)]
         output_path: Option<PathBuf>,
+        /// Write the message after IEND instead of into a chunk
+        #[clap(long)]
+        trailer: Option<String>,
+        /// Spread the contents of --input-file across these images, one
+        /// shard each
+        #[clap(
+            long,
+            num_args = 1..,
+            value_name = "PNG",
+            conflicts_with_all = ["file_path", "trailer"],
+            requires = "input_file"
+        )]
+        split_across: Vec<PathBuf>,
+        /// Read the payload from a file, for --split-across, --mode
+        /// ordering and --mode lsb
+        #[clap(
+            long,
+            required_if_eq_any([("mode", "ordering"), ("mode", "lsb")]),
+        )]
+        input_file: Option<PathBuf>,
+        /// Where to hide the message
+        #[clap(
+            long,
+            value_enum,
+            conflicts_with_all = ["trailer", "split_across", "fec"]
+        )]
+        mode: Option<Mode>,
+        #[clap(flatten)]
+        lsb: LsbArgs,
+        #[clap(flatten)]
+        envelope: EnvelopeArgs,
+        /// Add Reed-Solomon parity so the message survives some damage,
+        /// e.g. rs:10%
+        #[clap(long, value_name = "SPEC", conflicts_with_all = ["trailer", "split_across"])]
+        fec: Option<String>,
+        /// Read the message from standard input instead of the command line
+        #[clap(
+            long,
+            conflicts_with_all = [
+                "message_env", "message_file", "from_chunk", "trailer", "split_across", "input_file"
+            ]
+        )]
+        message_stdin: bool,
+        /// Read the message from an environment variable instead of the
+        /// command line
+        #[clap(
+            long,
+            value_name = "VAR_NAME",
+            conflicts_with_all = [
+                "message_file", "from_chunk", "trailer", "split_across", "input_file"
+            ]
+        )]
+        message_env: Option<String>,
+        /// Read the message from a file, byte for byte
+        #[clap(
+            long,
+            value_name = "FILE",
+            value_hint = ValueHint::FilePath,
+            conflicts_with_all = ["from_chunk", "trailer", "split_across", "input_file"]
+        )]
+        message_file: Option<PathBuf>,
+        /// Copy the message from a chunk of another PNG, given as
+        /// PNG:TYPE or PNG:TYPE:INDEX for a later chunk of the type
+        #[clap(
+            long,
+            value_name = "PNG:TYPE[:INDEX]",
+            value_parser = parse_chunk_ref,
+            conflicts_with_all = ["trailer", "split_across", "input_file"]
+        )]
+        from_chunk: Option<ChunkRef>,
+        /// Store the message in an unused private chunk type derived from
+        /// SEED, wrapped in an envelope so `decode --auto` can find it
+        #[clap(
+            long,
+            value_name = "SEED",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = discovery::DEFAULT_SEED,
+            conflicts_with_all = ["trailer", "split_across", "mode", "force"]
+        )]
+        auto: Option<String>,
+        /// Store the message under this label instead of a chunk type
+        #[clap(long, conflicts_with_all = ["trailer", "split_across", "mode", "force", "auto"])]
+        label: Option<String>,
+        /// Replace the existing chunk of the type, or the label's message,
+        /// where it is
+        #[clap(long, conflicts_with_all = ["trailer", "split_across", "mode", "auto"])]
+        overwrite: bool,
+        /// Add another chunk even if one of the type already exists
+        #[clap(
+            long,
+            conflicts_with_all = ["overwrite", "trailer", "split_across", "mode", "auto", "label"]
+        )]
+        append: bool,
+        /// How the message (or --trailer) is written on the command line
+        #[clap(long, value_enum, default_value = "text")]
+        input_format: InputFormat,
+        /// Use the chunk type even if it is critical or defined by the PNG
+        /// specification, which can make the image unreadable
+        #[clap(long, requires = "chunk_type")]
+        force: bool,
+        /// Refuse chunk types that are not in the --registry file
+        #[clap(long, requires = "registry", conflicts_with_all = ["auto", "label"])]
+        require_registered: bool,
+        /// Fail without writing if the file would grow by more than this,
+        /// e.g. 64K or 5% of its size
+        #[clap(long, value_name = "SIZE", value_parser = parse_growth_budget)]
+        max_growth: Option<GrowthBudget>,
+        /// Print the changes that would be made without writing the file
+        #[clap(long)]
+        dry_run: bool,
     },
+    /// Print a message hidden by `encode`
+    #[clap(after_long_help = examples::DECODE)]
     Decode {
+        /// The PNG; with a chunk type, builds with the net feature also
+        /// accept an http or https URL and stop downloading once it is found
+        #[clap(required_unless_present = "join")]
+        file_path: Option<PathBuf>,
+        /// The chunk type, or a pattern where ? matches any letter and * any
+        /// number of letters; the first matching chunk is decoded
+        #[clap(
+            required_unless_present_any = ["trailer", "join", "mode", "auto", "label"],
+            value_parser = parse_chunk_path
+        )]
+        chunk_type: Option<ChunkPath>,
+        /// Match the chunk type regardless of case
+        #[clap(long, requires = "chunk_type")]
+        ignore_case: bool,
+        /// Look inside PNGs carried in chunks: the chunk type becomes a path
+        /// such as ruSt/ruSt, each segment but the last selecting the chunk
+        /// that holds the next PNG
+        #[clap(long, requires = "chunk_type")]
+        recurse: bool,
+        /// Print the bytes found after IEND instead of a chunk
+        #[clap(long, conflicts_with = "chunk_type")]
+        trailer: bool,
+        /// Print enveloped messages even after they have expired
+        #[clap(long)]
+        ignore_expiry: bool,
+        /// Reassemble a payload split across these images
+        #[clap(
+            long,
+            num_args = 1..,
+            value_name = "PNG",
+            conflicts_with_all = ["file_path", "trailer"]
+        )]
+        join: Vec<PathBuf>,
+        /// Where to write the reassembled payload instead of stdout
+        #[clap(short, long, requires = "join")]
+        output: Option<PathBuf>,
+        /// Where the message is hidden
+        #[clap(long, value_enum, conflicts_with_all = ["trailer", "join"])]
+        mode: Option<Mode>,
+        #[clap(flatten)]
+        lsb: LsbArgs,
+        /// How to show the message
+        #[clap(
+            long,
+            visible_alias = "output-format",
+            value_enum,
+            default_value = "auto",
+            conflicts_with = "join"
+        )]
+        encoding: Encoding,
+        /// Replace invalid UTF-8 instead of failing
+        #[clap(long, conflicts_with = "join")]
+        lossy: bool,
+        /// How to end the lines of a text message
+        #[clap(long, value_enum, default_value = "lf", conflicts_with_all = ["join", "auto"])]
+        newline: Newline,
+        /// Print what the payload appears to be, such as image/png or
+        /// application/json, instead of the payload
+        #[clap(long, conflicts_with_all = ["join", "auto", "mode"])]
+        sniff: bool,
+        /// Add the extension for what the payload appears to be to the
+        /// --output file name
+        #[clap(long, requires = "output")]
+        auto_extension: bool,
+        /// Fail unless the payload is JSON matching this JSON Schema
+        #[clap(
+            long,
+            value_name = "SCHEMA",
+            value_hint = ValueHint::FilePath,
+            conflicts_with_all = ["trailer", "join", "mode", "auto"]
+        )]
+        validate_json: Option<PathBuf>,
+        /// List the pngme payloads in private chunks instead of decoding one
+        #[clap(long, conflicts_with_all = ["chunk_type", "trailer", "join", "mode"])]
+        auto: bool,
+        /// Decode the message stored under this label
+        #[clap(long, conflicts_with_all = ["chunk_type", "trailer", "join", "mode", "auto"])]
+        label: Option<String>,
+    },
+    /// Add, update and remove chunks as declared in a TOML profile
+    #[clap(after_long_help = examples::APPLY)]
+    Apply {
+        /// PNG files, or with the cloud feature s3://bucket/prefix for every
+        /// PNG object under the prefix
+        #[clap(required = true)]
+        file_paths: Vec<PathBuf>,
+        #[clap(long)]
+        profile: PathBuf,
+        /// Write the results to this directory, or s3://bucket/prefix,
+        /// instead of over the inputs
+        #[clap(long, value_hint = ValueHint::DirPath)]
+        output_dir: Option<PathBuf>,
+        /// Put every result directly in the output directory
+        #[clap(long, requires = "output_dir", conflicts_with = "preserve_tree")]
+        flatten: bool,
+        /// Mirror the inputs' relative paths under the output directory (the
+        /// default)
+        #[clap(long, requires = "output_dir")]
+        preserve_tree: bool,
+        /// How many files to process at once, which mostly helps when
+        /// downloading from or uploading to S3
+        #[clap(short, long, default_value_t = 1)]
+        jobs: usize,
+        /// Stop starting new files after the first failure
+        #[clap(long, conflicts_with = "keep_going")]
+        fail_fast: bool,
+        /// Process every file even after failures (the default)
+        #[clap(long)]
+        keep_going: bool,
+        /// Print a JSON summary with the outcome of every file
+        #[clap(long)]
+        json: bool,
+        /// Print the changes that would be made without writing the files
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Sign the critical chunks with an Ed25519 private key
+    #[clap(after_long_help = examples::SIGN)]
+    Sign {
+        #[clap(value_hint = ValueHint::FilePath)]
         file_path: PathBuf,
-        chunk_type: String,
+        /// File holding the 32-byte private key, raw or as hex
+        #[clap(long)]
+        key: PathBuf,
+    },
+    /// Check the signature made by `sign` with an Ed25519 public key
+    #[clap(after_long_help = examples::VERIFY_SIG)]
+    VerifySig {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// File holding the 32-byte public key, raw or as hex
+        #[clap(long)]
+        pubkey: PathBuf,
+    },
+    /// Record a SHA-256 hash of every chunk so later changes can be detected
+    #[clap(after_long_help = examples::SEAL)]
+    Seal {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+    },
+    /// Check that no chunk has changed since `seal`, including unknown ones
+    #[clap(after_long_help = examples::VERIFY_SEAL)]
+    VerifySeal {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+    },
+    /// Show how much can be hidden without adding any bytes
+    #[clap(after_long_help = examples::CAPACITY)]
+    Capacity {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        #[clap(flatten)]
+        lsb: LsbArgs,
     },
+    /// Remove the first chunk of a type, or all of them
+    #[clap(after_long_help = examples::REMOVE)]
     Remove {
+        #[clap(value_hint = ValueHint::FilePath)]
         file_path: PathBuf,
-        chunk_type: String,
+        /// The chunk type, or a pattern where ? matches any letter and * any
+        /// number of letters; the first matching chunk is removed
+        #[clap(value_parser = parse_chunk_pattern)]
+        chunk_type: ChunkTypeMatcher,
+        /// Match the chunk type regardless of case
+        #[clap(long)]
+        ignore_case: bool,
+        /// Remove every matching chunk instead of the first
+        #[clap(long)]
+        all: bool,
+        /// Allow removing IHDR, PLTE, IDAT or IEND, which breaks the image
+        #[clap(long)]
+        force_critical: bool,
+        /// Print each removed chunk's payload
+        #[clap(long)]
+        print: bool,
+        /// Print the changes that would be made without writing the file
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Remove the chunk at a byte offset, as reported by other tools
+    #[clap(after_long_help = examples::REMOVE_AT)]
+    RemoveAt {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// Any byte offset within the chunk, in decimal or with a 0x prefix
+        #[clap(long, value_parser = parse_offset)]
+        offset: usize,
+        /// Allow removing IHDR, PLTE, IDAT or IEND, which breaks the image
+        #[clap(long)]
+        force_critical: bool,
+        /// Print the removed chunk's payload
+        #[clap(long)]
+        print: bool,
+        /// Print the changes that would be made without writing the file
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Save the chunk at a byte offset, as reported by other tools
+    #[clap(after_long_help = examples::EXTRACT)]
+    Extract {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// Any byte offset within the chunk, in decimal or with a 0x prefix
+        #[clap(long, value_parser = parse_offset)]
+        offset: usize,
+        /// Include the chunk's length, type and CRC, not just its data
+        #[clap(long)]
+        raw: bool,
+        /// Write to this file instead of stdout
+        #[clap(short, long, value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+        /// Print what the chunk data appears to be, such as image/png or
+        /// application/json, instead of the data
+        #[clap(long, conflicts_with = "output")]
+        sniff: bool,
+        /// Add the extension for what the data appears to be to the
+        /// --output file name
+        #[clap(long, requires = "output")]
+        auto_extension: bool,
+    },
+    /// Revert edits recorded with --journal
+    #[clap(after_long_help = examples::UNDO)]
+    Undo {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// How many of the most recent edits to revert
+        #[clap(short = 'n', long, default_value_t = 1)]
+        count: usize,
+        /// Print the changes that would be made without writing the file
+        #[clap(long)]
+        dry_run: bool,
     },
+    /// Print the chunks and how much space each kind takes
+    #[clap(after_long_help = examples::PRINT)]
     Print {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// Show up to this many payload bytes of each text-like chunk
+        #[clap(long, value_name = "BYTES")]
+        preview: Option<usize>,
+    },
+    /// List chunk types and lengths as they are read, without loading the
+    /// whole file
+    #[clap(after_long_help = examples::LIST)]
+    List {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// Only list chunks whose type matches this pattern, e.g. "t*"
+        #[clap(long, value_name = "PATTERN", value_parser = parse_chunk_pattern)]
+        filter: Option<ChunkTypeMatcher>,
+        /// Match the filter regardless of case
+        #[clap(long, requires = "filter")]
+        ignore_case: bool,
+        /// Show up to this many payload bytes of each text-like chunk
+        #[clap(long, value_name = "BYTES")]
+        preview: Option<usize>,
+        /// Show the SHA-256 of each data chunk written by `pack`
+        #[clap(long)]
+        shards: bool,
+        /// Print the chunks as text, or as JSON, CSV or TSV records without
+        /// previews or annotations
+        #[clap(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+    },
+    /// Count chunks and their sizes per type across one or more files
+    #[clap(after_long_help = examples::STATS)]
+    Stats {
+        #[clap(required = true, value_hint = ValueHint::FilePath)]
+        file_paths: Vec<PathBuf>,
+        /// Print the totals per type as a table or JSON, or every chunk as
+        /// CSV or TSV records
+        #[clap(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        /// Same as --format json
+        #[clap(long, conflicts_with = "format")]
+        json: bool,
+    },
+    /// Gather chunk statistics across a directory of PNGs and flag outliers
+    #[clap(after_long_help = examples::CORPUS_SCAN)]
+    CorpusScan {
+        #[clap(value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
+        /// Print the statistics and anomalies as a table or JSON, or every
+        /// chunk as CSV or TSV records with anomalies among its flags
+        #[clap(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        /// Same as --format json
+        #[clap(long, conflicts_with = "format")]
+        json: bool,
+        /// Never flag text chunks of this many bytes or fewer as unusually large
+        #[clap(long, value_name = "BYTES", default_value_t = 1024)]
+        min_text_size: usize,
+    },
+    /// Print the PLTE palette
+    #[clap(after_long_help = examples::PALETTE)]
+    Palette {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// Show each entry as a colored swatch using ANSI escape codes
+        #[clap(long)]
+        swatches: bool,
+    },
+    /// Show the image header and color space information
+    #[clap(after_long_help = examples::INFO)]
+    Info {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+    },
+    /// Remove every ancillary chunk, keeping only what is needed to display
+    /// the image
+    #[clap(after_long_help = examples::SCRUB)]
+    Scrub {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        #[clap(value_hint = ValueHint::FilePath)]
+        output_path: Option<PathBuf>,
+        /// Also drop any data after IEND
+        #[clap(long)]
+        strip_trailer: bool,
+        /// Keep the tRNS and bKGD chunks, which affect how the image looks
+        #[clap(long)]
+        keep_transparency: bool,
+        /// Print the changes that would be made without writing the file
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Remove whole categories of ancillary chunks
+    #[clap(
+        after_long_help = examples::STRIP,
+        group(ArgGroup::new("categories").required(true).multiple(true))
+    )]
+    Strip {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        #[clap(value_hint = ValueHint::FilePath)]
+        output_path: Option<PathBuf>,
+        /// tEXt, zTXt and iTXt
+        #[clap(long, group = "categories")]
+        text: bool,
+        /// Color profiles and color space chunks such as iCCP, sRGB and gAMA
+        #[clap(long, group = "categories")]
+        color_info: bool,
+        /// tIME
+        #[clap(long, group = "categories")]
+        time: bool,
+        /// eXIf
+        #[clap(long, group = "categories")]
+        exif: bool,
+        /// Physical size chunks such as pHYs
+        #[clap(long, group = "categories")]
+        physical: bool,
+        /// Chunks the PNG specification does not define
+        #[clap(long, group = "categories")]
+        private: bool,
+        /// Print the changes that would be made without writing the file
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Remove every chunk except the listed types
+    #[clap(after_long_help = examples::KEEP_ONLY)]
+    KeepOnly {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// Comma-separated chunk types or patterns to keep, e.g.
+        /// IHDR,PLTE,IDAT,IEND
+        #[clap(value_delimiter = ',', required = true, value_parser = parse_chunk_pattern)]
+        chunk_types: Vec<ChunkTypeMatcher>,
+        #[clap(long, short, value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+        /// Print the changes that would be made without writing the file
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Drop repeated identical tEXt chunks and empty ancillary chunks and
+    /// merge IDAT chunks, reporting the bytes saved
+    #[clap(after_long_help = examples::COMPACT)]
+    Compact {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        #[clap(long, short, value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+        /// The largest IDAT chunk to write, e.g. 65536, 64K or 1M
+        #[clap(long, value_name = "SIZE", default_value = "1M", value_parser = parse_size)]
+        max_idat_size: usize,
+        /// Print the changes that would be made without writing the file
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Split and merge IDAT chunks to a target size without recompressing
+    #[clap(after_long_help = examples::RECHUNK)]
+    Rechunk {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// The size of every IDAT chunk but the last, e.g. 8192, 64K or 1M
+        #[clap(long, value_name = "SIZE", value_parser = parse_size)]
+        idat_size: usize,
+        #[clap(long, short, value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+        /// Print the changes that would be made without writing the file
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Move chunks into a spec-legal order without changing them
+    #[clap(after_long_help = examples::REORDER)]
+    Reorder {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// IHDR, color space chunks, PLTE, the other chunks before IDAT, the
+        /// IDAT run, the chunks after it and IEND, keeping the order within
+        /// each group
+        #[clap(long, required = true)]
+        canonical: bool,
+        #[clap(long, short, value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+        /// Print the changes that would be made without writing the file
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Copy ancillary chunks from one PNG into another
+    #[clap(after_long_help = examples::COPY_CHUNKS)]
+    CopyChunks {
+        #[clap(value_hint = ValueHint::FilePath)]
+        source_path: PathBuf,
+        #[clap(value_hint = ValueHint::FilePath)]
+        destination_path: PathBuf,
+        /// Comma-separated chunk types or patterns to copy; all ancillary
+        /// chunks by default
+        #[clap(long, value_delimiter = ',', value_parser = parse_chunk_pattern)]
+        types: Vec<ChunkTypeMatcher>,
+        /// What to do when the destination already has chunks of a type, text
+        /// with the same keyword, or sRGB or iCCP for the other
+        #[clap(long, value_enum, default_value = "keep")]
+        on_conflict: OnConflict,
+        /// Copy unsafe-to-copy chunks even though the images differ
+        #[clap(long)]
+        force_unsafe: bool,
+        /// Write the result here instead of over the destination
+        #[clap(long, short, value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+        /// Print the changes that would be made without writing the file
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Validate the signature, chunk types and CRCs of a PNG
+    #[clap(after_long_help = examples::CHECK)]
+    Check {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// Report every problem with the rule it breaks, the bytes around it
+        /// and how to fix it, instead of stopping at the first
+        #[clap(long)]
+        explain: bool,
+        /// Print the explanation as JSON
+        #[clap(long, requires = "explain")]
+        json: bool,
+        /// Check only the rules this kind of decoder enforces, reporting
+        /// every problem
+        #[clap(long, value_enum)]
+        profile: Option<CheckProfile>,
+    },
+    /// Check a PNG against an asset policy: allowed and required chunk
+    /// types, size limits and forbidden text keywords
+    #[clap(after_long_help = examples::LINT)]
+    Lint {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// The policy, a TOML file
+        #[clap(long, value_hint = ValueHint::FilePath)]
+        policy: PathBuf,
+        /// Print the violations as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Salvage what can be read from a damaged PNG
+    #[clap(after_long_help = examples::REPAIR)]
+    Repair {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// Where to write the repaired file [default: <name>.repaired.png]
+        #[clap(value_hint = ValueHint::FilePath)]
+        output_path: Option<PathBuf>,
+        /// Keep the chunks before the point where the file was cut short and
+        /// end it with a new IEND
+        #[clap(long, required = true)]
+        truncate: bool,
+        /// Print the changes that would be made without writing the file
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Check that a chunk exists, has a valid CRC and optionally carries
+    /// the expected payload
+    #[clap(after_long_help = examples::VERIFY)]
+    Verify {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        #[clap(value_parser = parse_chunk_type)]
+        chunk_type: String,
+        /// Expected digest of the payload, e.g. sha256:<hex>
+        #[clap(long, value_name = "ALGORITHM:HEX")]
+        expect_hash: Option<String>,
+    },
+    /// Look for signs that data has been hidden in a PNG
+    #[clap(after_long_help = examples::DETECT)]
+    Detect {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+    },
+    /// Compare the chunks of two PNGs
+    #[clap(after_long_help = examples::DIFF)]
+    Diff {
+        #[clap(value_hint = ValueHint::FilePath)]
+        old_path: PathBuf,
+        new_path: PathBuf,
+        /// Show a hex diff of the payload of each modified chunk
+        #[clap(long)]
+        hexdiff: bool,
+    },
+    /// Show a downscaled preview of the image in the terminal
+    #[clap(after_long_help = examples::VIEW)]
+    View {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        /// The widest the preview may be, in columns
+        #[clap(long, default_value_t = 80)]
+        width: usize,
+        #[clap(long, value_enum, default_value = "half-block")]
+        style: ViewStyle,
+    },
+    /// Compare the decoded pixels of two images, ignoring how they are
+    /// stored; exits with 1 if any pixel differs
+    #[clap(after_long_help = examples::PIXDIFF)]
+    Pixdiff {
+        #[clap(value_hint = ValueHint::FilePath)]
+        old_path: PathBuf,
+        #[clap(value_hint = ValueHint::FilePath)]
+        new_path: PathBuf,
+        /// Write an image with the differing pixels in red
+        #[clap(long, short, value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+    },
+    /// Search chunk payloads for a regular expression or byte sequence
+    #[clap(after_long_help = examples::GREP)]
+    Grep {
+        /// A regular expression, or hex bytes with --hex
+        pattern: String,
+        #[clap(required = true, value_hint = ValueHint::FilePath)]
+        file_paths: Vec<PathBuf>,
+        /// Treat the pattern as an exact byte sequence written in hex
+        #[clap(long)]
+        hex: bool,
+        /// Also search the inflated text of compressed zTXt and iTXt chunks
+        #[clap(long)]
+        decompress: bool,
+    },
+    /// Edit a PNG interactively, writing it only on `save`
+    #[clap(after_long_help = examples::SHELL)]
+    Shell {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+    },
+    /// Show the modifications recorded with --history
+    #[clap(after_long_help = examples::HISTORY)]
+    History {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+    },
+    /// Store files, with their names, times and permissions, in a PNG
+    #[clap(after_long_help = examples::PACK)]
+    Pack {
+        #[clap(value_hint = ValueHint::FilePath)]
+        carrier: PathBuf,
+        #[clap(required = true, value_hint = ValueHint::FilePath)]
+        file_paths: Vec<PathBuf>,
+        #[clap(short, long, value_hint = ValueHint::FilePath)]
+        output: PathBuf,
+        /// Replace an archive already in the carrier, keeping the chunks of
+        /// unchanged data as they were
+        #[clap(long)]
+        replace: bool,
+        /// Print the chunks that would be added without writing the file
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Restore the files stored by `pack` into a directory
+    #[clap(after_long_help = examples::UNPACK)]
+    Unpack {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        #[clap(short, long, value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
+        /// Replace files that already exist in the directory
+        #[clap(long)]
+        overwrite: bool,
+    },
+    /// Run a pngme command on each PNG added to or modified in a directory
+    #[clap(after_long_help = examples::WATCH)]
+    Watch {
+        #[clap(value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
+        /// The pngme arguments to run, with {} standing for the file
+        #[clap(long)]
+        on_add: String,
+        /// Also watch subdirectories
+        #[clap(short, long)]
+        recursive: bool,
+        /// Milliseconds a file must be unchanged before it is processed
+        #[clap(long, default_value_t = 500)]
+        debounce: u64,
+    },
+    /// Extract or embed an ICC color profile
+    #[clap(after_long_help = examples::ICC)]
+    Icc {
+        #[clap(subcommand)]
+        command: IccCommands,
+    },
+    /// Show or remove EXIF metadata
+    #[clap(after_long_help = examples::EXIF)]
+    Exif {
+        #[clap(subcommand)]
+        command: ExifCommands,
+    },
+    /// Generate a minimal single-color PNG to carry payloads
+    #[clap(after_long_help = examples::CREATE)]
+    Create {
+        #[clap(value_hint = ValueHint::FilePath)]
+        output_path: PathBuf,
+        #[clap(long, default_value_t = 1)]
+        width: u32,
+        #[clap(long, default_value_t = 1)]
+        height: u32,
+        /// Fill color as six hex digits, e.g. ff8800
+        #[clap(long, value_name = "COLOR", default_value = "000000")]
+        fill: String,
+    },
+    /// Set the pixel density, adding or updating the pHYs chunk
+    #[clap(after_long_help = examples::SET_DPI)]
+    SetDpi {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        dpi: u32,
+        /// Print the changes that would be made without writing the file
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Store a value in a tEXt/iTXt chunk under the given keyword
+    #[clap(after_long_help = examples::SET)]
+    Set {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        key: String,
+        value: String,
+    },
+    /// Print the value stored under a keyword
+    #[clap(after_long_help = examples::GET)]
+    Get {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        key: String,
+    },
+    /// Delete the value stored under a keyword
+    #[clap(after_long_help = examples::DEL)]
+    Del {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        key: String,
+    },
+    /// List the labels of messages stored with `encode --label`
+    #[clap(after_long_help = examples::LABELS)]
+    Labels {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+    },
+    /// List the keywords of all text chunks
+    #[clap(after_long_help = examples::KEYS)]
+    Keys {
+        #[clap(value_hint = ValueHint::FilePath)]
         file_path: PathBuf,
     },
 }
 
-fn main() {
-    let cli = Cli::parse();
+#[derive(Subcommand)]
+enum IccCommands {
+    /// Save the embedded profile to a file
+    Extract {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        #[clap(short, long)]
+        output: PathBuf,
+    },
+    /// Embed a profile, replacing any existing one
+    Embed {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        profile_path: PathBuf,
+        #[clap(long, default_value = "ICC profile")]
+        name: String,
+        #[clap(value_hint = ValueHint::FilePath)]
+        output_path: Option<PathBuf>,
+    },
+}
 
-    match &cli.command {
+#[derive(Subcommand)]
+enum ExifCommands {
+    /// Print the EXIF tags
+    Dump {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+    },
+    /// Remove the eXIf chunk
+    Strip {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file_path: PathBuf,
+        #[clap(value_hint = ValueHint::FilePath)]
+        output_path: Option<PathBuf>,
+    },
+}
+
+/// Rejects invalid chunk types while parsing arguments.
+fn parse_channels(s: &str) -> Result<Channels, String> {
+    Channels::from_str(s).map_err(|e| e.to_string())
+}
+
+fn parse_chunk_type(s: &str) -> Result<String, String> {
+    ChunkType::from_str(s).map(|_| s.to_string()).map_err(|_| {
+        format!(
+            "{:?} is not a chunk type, which is four ASCII letters such as ruSt",
+            s
+        )
+    })
+}
+
+/// A byte count, optionally with a binary K, M or G suffix.
+fn parse_size(s: &str) -> Result<usize, String> {
+    budget::parse_size(s).map_err(|e| e.to_string())
+}
+
+/// A byte count as for `parse_size`, or a percentage such as 5%.
+fn parse_growth_budget(s: &str) -> Result<GrowthBudget, String> {
+    GrowthBudget::from_str(s).map_err(|e| e.to_string())
+}
+
+/// A byte offset in decimal, or in hexadecimal with a 0x prefix.
+fn parse_offset(s: &str) -> Result<usize, String> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|_| format!("{:?} is not an offset such as 6699 or 0x1A2B", s))
+}
+
+/// Rejects invalid chunk type patterns while parsing arguments.
+fn parse_chunk_pattern(s: &str) -> Result<ChunkTypeMatcher, String> {
+    ChunkTypeMatcher::from_str(s).map_err(|e| e.to_string())
+}
+
+/// Rejects chunk paths with invalid segments while parsing arguments.
+fn parse_chunk_path(s: &str) -> Result<ChunkPath, String> {
+    ChunkPath::from_str(s).map_err(|e| e.to_string())
+}
+
+/// Rejects chunk references that are not PNG:TYPE[:INDEX].
+fn parse_chunk_ref(s: &str) -> Result<ChunkRef, String> {
+    ChunkRef::from_str(s).map_err(|e| e.to_string())
+}
+
+fn parse_locale(s: &str) -> Result<Locale, String> {
+    Locale::from_str(s).map_err(|e| e.to_string())
+}
+
+fn main() -> ExitCode {
+    l10n::set_locale(Locale::from_env());
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(locale) = cli.lang {
+        l10n::set_locale(locale);
+    }
+    let log_level = cli.log_level.unwrap_or(match cli.verbose {
+        _ if cli.quiet => LevelFilter::Off,
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    });
+    term::init(cli.color);
+    env_logger::Builder::new()
+        .filter_level(log_level)
+        .format_target(false)
+        .init();
+    let mut parse_options = if cli.raw {
+        ParseOptions::raw()
+    } else {
+        ParseOptions::default()
+    };
+    parse_options.lazy_crc = cli.lazy_crc;
+    parse_options.skip_signature = cli.skip_signature;
+    parse_options.max_chunk_size = Some(cli.max_chunk_size);
+    parse_options.max_total_size = Some(cli.max_total_size);
+    parse_options.max_chunks = Some(cli.max_chunks);
+    parse_options.max_inflated_size = cli.max_inflated_size;
+    commands::set_parse_options(parse_options);
+    commands::set_show_progress(cli.progress);
+    commands::set_quiet(cli.quiet);
+    commands::set_deterministic(cli.deterministic);
+    commands::set_journal(cli.journal);
+    if let Some(suffix) = &cli.backup {
+        commands::set_backup_suffix(suffix);
+    }
+    if let Some(dir) = &cli.force_output {
+        commands::set_force_output(dir);
+    }
+    if let Some(Err(e)) = cli.command.in_place_target().map(commands::check_writable) {
+        return exit_with(e, cli.quiet);
+    }
+    if let Some(Err(e)) = cli.registry.as_deref().map(commands::set_registry) {
+        return exit_with(e, cli.quiet);
+    }
+    let history = match (cli.history, cli.no_history) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => match Config::load_default() {
+            Ok(config) => config.history,
+            Err(e) => return exit_with(e, cli.quiet),
+        },
+    };
+    if history {
+        commands::set_history(&command_name(&matches));
+    }
+
+    let result = match &cli.command {
         Commands::Encode {
             file_path,
             chunk_type,
             message,
             output_path,
+            trailer,
+            split_across,
+            input_file,
+            mode,
+            lsb,
+            envelope,
+            fec,
+            message_stdin,
+            message_env,
+            message_file,
+            from_chunk,
+            auto,
+            label,
+            overwrite,
+            append,
+            input_format,
+            force,
+            require_registered,
+            max_growth,
+            dry_run,
         } => {
-            commands::encode(file_path, chunk_type, message, output_path);
+            if let Some(budget) = max_growth {
+                commands::set_max_growth(*budget);
+            }
+            let source = match (message_stdin, message_env, message_file, from_chunk) {
+                (true, ..) => Some(MessageSource::Stdin),
+                (false, Some(name), ..) => Some(MessageSource::Env(name)),
+                (false, None, Some(path), _) => Some(MessageSource::File(path)),
+                (false, None, None, Some(chunk_ref)) => Some(MessageSource::Chunk(chunk_ref)),
+                (false, None, None, None) => None,
+            };
+            // The chunk type and message can come from flags instead, so the
+            // positional arguments fill whichever of them are left, then the
+            // output path. Trailers and payloads read with --input-file take
+            // neither, so their only positional argument is the output path.
+            let takes_chunk = trailer.is_none() && input_file.is_none();
+            let mut positionals = [chunk_type.as_deref(), message.as_deref()].into_iter().flatten();
+            let chunk_type = match (auto, label) {
+                (None, None) if takes_chunk => positionals.next(),
+                _ => None,
+            };
+            let message = match takes_chunk {
+                true => source.or_else(|| positionals.next().map(MessageSource::Arg)),
+                false => source,
+            };
+            let (output_path, extra_argument) = match positionals.next() {
+                Some(path) => (
+                    Some(PathBuf::from(path)),
+                    output_path.is_some() || positionals.next().is_some(),
+                ),
+                None => (output_path.clone(), false),
+            };
+            let target = output_path.as_ref().or(file_path.as_ref()).filter(|_| !*dry_run);
+            if let Some(Err(e)) = target.map(|target| commands::check_writable(target)) {
+                return exit_with(e, cli.quiet);
+            }
+            match (file_path, trailer, message) {
+                _ if extra_argument => Err(tr!("error.too_many_arguments").into()),
+                (None, _, _) => {
+                    let input_file = input_file.as_ref().expect("clap requires --input-file");
+                    commands::encode_split(split_across, input_file, *dry_run)
+                }
+                (Some(file_path), _, _) if *mode == Some(Mode::Ordering) => {
+                    let input_file = input_file.as_ref().expect("clap requires --input-file");
+                    commands::encode_ordering(file_path, input_file, &output_path, *dry_run)
+                }
+                (Some(file_path), _, _) if *mode == Some(Mode::Lsb) => {
+                    let input_file = input_file.as_ref().expect("clap requires --input-file");
+                    let options = lsb.options();
+                    commands::encode_lsb(file_path, input_file, &options, &output_path, *dry_run)
+                }
+                (Some(file_path), Some(trailer), _) => {
+                    input_format.parse(trailer).and_then(|trailer| {
+                        commands::encode_trailer(file_path, &trailer, &output_path, *dry_run)
+                    })
+                }
+                (Some(file_path), None, Some(message)) => {
+                    let policy = match (overwrite, append) {
+                        (true, _) => WritePolicy::Overwrite,
+                        (false, true) => WritePolicy::Append,
+                        (false, false) => WritePolicy::Fail,
+                    };
+                    let choice = match (auto, label, chunk_type) {
+                        (Some(seed), _, _) => Ok(ChunkChoice::Auto(seed)),
+                        (None, Some(label), _) => Ok(ChunkChoice::Label(label, policy)),
+                        (None, None, Some(chunk_type)) => parse_chunk_type(chunk_type)
+                            .map_err(pngme::Error::from)
+                            .and_then(|chunk_type| commands::check_chunk_type(&chunk_type, *force))
+                            .and_then(|chunk_type| match require_registered {
+                                true => commands::check_registered(chunk_type),
+                                false => Ok(chunk_type),
+                            })
+                            .map(|chunk_type| ChunkChoice::Type(chunk_type, policy)),
+                        (None, None, None) => {
+                            Err(tr!("error.chunk_type_required").into())
+                        }
+                    };
+                    choice.and_then(|choice| {
+                        commands::encode(
+                            file_path,
+                            choice,
+                            &message.read(|text| input_format.parse(text))?,
+                            envelope,
+                            fec,
+                            &output_path,
+                            *dry_run,
+                        )
+                    })
+                }
+                _ => Err(tr!("error.chunk_type_and_message_required").into()),
+            }
         }
         Commands::Decode {
             file_path,
             chunk_type,
+            ignore_case,
+            recurse,
+            trailer,
+            ignore_expiry,
+            join,
+            output,
+            mode,
+            lsb,
+            encoding,
+            lossy,
+            newline,
+            sniff,
+            auto_extension,
+            validate_json,
+            auto,
+            label,
         } => {
-            commands::decode(file_path, chunk_type);
-        },
+            let validator = match validate_json {
+                Some(path) => match JsonSchema::load(path) {
+                    Ok(schema) => Some(Box::new(schema) as Box<dyn PayloadValidator>),
+                    Err(e) => return exit_with(e, cli.quiet),
+                },
+                None => None,
+            };
+            let format = PayloadFormat {
+                encoding: match encoding {
+                    Encoding::Auto => None,
+                    Encoding::Utf8 => Some(TextEncoding::Utf8),
+                    Encoding::Latin1 => Some(TextEncoding::Latin1),
+                    Encoding::Hex => Some(TextEncoding::Hex),
+                    Encoding::Base64 => Some(TextEncoding::Base64),
+                },
+                lossy: *lossy,
+                validator,
+                newline: *newline,
+                sniff: *sniff,
+            };
+            match (file_path, chunk_type) {
+                (None, _) => {
+                    commands::decode_join(join, output, *auto_extension)
+                }
+                (Some(file_path), _) if *mode == Some(Mode::Ordering) => {
+                    commands::decode_ordering(file_path, *newline)
+                }
+                (Some(file_path), _) if *mode == Some(Mode::Lsb) => {
+                    commands::decode_lsb(file_path, &lsb.options(), *newline)
+                }
+                (Some(file_path), _) if *auto => {
+                    commands::decode_auto(file_path, *ignore_expiry)
+                }
+                (Some(file_path), None) if label.is_some() => {
+                    let label = label.as_deref().expect("checked above");
+                    commands::decode_label(file_path, label, *ignore_expiry, &format)
+                }
+                (Some(file_path), Some(path)) if *recurse => {
+                    let path = path.clone().ignore_case(*ignore_case);
+                    commands::decode_nested(file_path, &path, *ignore_expiry, &format)
+                }
+                (Some(_), Some(path)) if path.is_nested() => {
+                    Err(tr!("error.recurse_required", path = path).into())
+                }
+                (Some(file_path), Some(path)) => {
+                    let matcher = path.last().clone().ignore_case(*ignore_case);
+                    commands::decode(file_path, &matcher, *ignore_expiry, &format)
+                }
+                (Some(file_path), None) if *trailer => {
+                    commands::decode_trailer(file_path, &format)
+                }
+                _ => Err(tr!("error.chunk_type_required_in_chunk_mode").into()),
+            }
+        }
+        Commands::Apply {
+            file_paths,
+            profile,
+            output_dir,
+            flatten,
+            preserve_tree: _,
+            jobs,
+            fail_fast,
+            keep_going: _,
+            json,
+            dry_run,
+        } => {
+            let layout = match output_dir {
+                None => OutputLayout::InPlace,
+                Some(dir) if *flatten => OutputLayout::Flatten(dir.clone()),
+                Some(dir) => OutputLayout::PreserveTree(dir.clone()),
+            };
+            let (jobs, fail_fast) = (*jobs, *fail_fast);
+            commands::apply(file_paths, profile, &layout, jobs, fail_fast, *json, *dry_run)
+        }
+        Commands::Sign { file_path, key } => {
+            commands::sign(file_path, key)
+        }
+        Commands::VerifySig { file_path, pubkey } => {
+            commands::verify_sig(file_path, pubkey)
+        }
+        Commands::Seal { file_path } => {
+            commands::seal(file_path)
+        }
+        Commands::VerifySeal { file_path } => {
+            commands::verify_seal(file_path)
+        }
+        Commands::Capacity { file_path, lsb } => {
+            let bits = match lsb.bits {
+                Some(bits) => vec![bits],
+                None => vec![1, 2, 4],
+            };
+            let options: Vec<LsbOptions> = bits
+                .into_iter()
+                .map(|bits| LsbOptions { bits, ..lsb.options() })
+                .collect();
+            commands::capacity(file_path, &options)
+        }
         Commands::Remove {
             file_path,
             chunk_type,
+            ignore_case,
+            all,
+            force_critical,
+            print,
+            dry_run,
         } => {
-            commands::remove(file_path, chunk_type);
+            let matcher = chunk_type.clone().ignore_case(*ignore_case);
+            let policy = match force_critical {
+                true => RemovePolicy::Force,
+                false => RemovePolicy::Protect,
+            };
+            commands::remove(file_path, &matcher, *all, policy, *print, *dry_run)
         },
-        Commands::Print { file_path } => {
-            commands::print(file_path);
+        Commands::RemoveAt {
+            file_path,
+            offset,
+            force_critical,
+            print,
+            dry_run,
+        } => {
+            let policy = match force_critical {
+                true => RemovePolicy::Force,
+                false => RemovePolicy::Protect,
+            };
+            commands::remove_at(file_path, *offset, policy, *print, *dry_run)
+        }
+        Commands::Extract {
+            file_path,
+            offset,
+            raw,
+            output,
+            sniff,
+            auto_extension,
+        } => {
+            commands::extract(file_path, *offset, *raw, output, *sniff, *auto_extension)
+        }
+        Commands::Undo {
+            file_path,
+            count,
+            dry_run,
+        } => {
+            commands::undo(file_path, *count, *dry_run)
+        }
+        Commands::Print { file_path, preview } => {
+            commands::print(file_path, *preview)
+        }
+        Commands::List {
+            file_path,
+            filter,
+            ignore_case,
+            preview,
+            shards,
+            format,
+        } => {
+            let filter = filter.clone().map(|f| f.ignore_case(*ignore_case));
+            commands::list(file_path, &filter, *preview, *shards, (*format).into())
         }
+        Commands::Stats { file_paths, format, json } => {
+            let format = if *json { Format::Json } else { (*format).into() };
+            commands::stats(file_paths, format)
+        }
+        Commands::CorpusScan { dir, format, json, min_text_size } => {
+            let format = if *json { Format::Json } else { (*format).into() };
+            commands::corpus_scan(dir, format, *min_text_size)
+        }
+        Commands::Palette {
+            file_path,
+            swatches,
+        } => {
+            commands::palette(file_path, *swatches)
+        }
+        Commands::Info { file_path } => {
+            commands::info(file_path)
+        }
+        Commands::Scrub {
+            file_path,
+            output_path,
+            strip_trailer,
+            keep_transparency,
+            dry_run,
+        } => {
+            commands::scrub(
+                file_path,
+                output_path,
+                *strip_trailer,
+                *keep_transparency,
+                *dry_run,
+            )
+        }
+        Commands::Strip {
+            file_path,
+            output_path,
+            text,
+            color_info,
+            time,
+            exif,
+            physical,
+            private,
+            dry_run,
+        } => {
+            let categories: Vec<ChunkCategory> = [
+                (*text, ChunkCategory::Text),
+                (*color_info, ChunkCategory::Color),
+                (*time, ChunkCategory::Time),
+                (*exif, ChunkCategory::Exif),
+                (*physical, ChunkCategory::Physical),
+                (*private, ChunkCategory::Private),
+            ]
+            .into_iter()
+            .filter_map(|(selected, category)| selected.then_some(category))
+            .collect();
+            commands::strip(file_path, output_path, &categories, *dry_run)
+        }
+        Commands::KeepOnly {
+            file_path,
+            chunk_types,
+            output,
+            dry_run,
+        } => {
+            commands::keep_only(file_path, output, chunk_types, *dry_run)
+        }
+        Commands::Compact {
+            file_path,
+            output,
+            max_idat_size,
+            dry_run,
+        } => {
+            let options = CompactOptions {
+                max_idat_size: *max_idat_size,
+            };
+            commands::compact(file_path, output, &options, *dry_run)
+        }
+        Commands::Rechunk {
+            file_path,
+            idat_size,
+            output,
+            dry_run,
+        } => {
+            commands::rechunk(file_path, output, *idat_size, *dry_run)
+        }
+        Commands::Reorder {
+            file_path,
+            canonical: _,
+            output,
+            dry_run,
+        } => commands::reorder_canonical(file_path, output, *dry_run),
+        Commands::CopyChunks {
+            source_path,
+            destination_path,
+            types,
+            on_conflict,
+            force_unsafe,
+            output,
+            dry_run,
+        } => {
+            let policy = MergePolicy {
+                types: types.clone(),
+                on_conflict: match on_conflict {
+                    OnConflict::Keep => Conflict::Keep,
+                    OnConflict::Replace => Conflict::Replace,
+                    OnConflict::Add => Conflict::Add,
+                },
+                force_unsafe: *force_unsafe,
+            };
+            commands::copy_chunks(source_path, destination_path, output, &policy, *dry_run)
+        }
+        Commands::Check {
+            file_path,
+            explain,
+            json,
+            profile,
+        } => {
+            let profile = profile.map(|profile| match profile {
+                CheckProfile::Strict => ValidationProfile::Strict,
+                CheckProfile::Browser => ValidationProfile::Browser,
+                CheckProfile::Libpng => ValidationProfile::Libpng,
+            });
+            match (explain, profile) {
+                (false, None) => commands::check(file_path),
+                _ => commands::check_rules(file_path, profile.unwrap_or_default(), *explain, *json),
+            }
+        }
+        Commands::Lint {
+            file_path,
+            policy,
+            json,
+        } => Policy::load(policy).and_then(|policy| commands::lint(file_path, &policy, *json)),
+        Commands::Repair {
+            file_path,
+            output_path,
+            truncate: _,
+            dry_run,
+        } => {
+            commands::repair_truncated(file_path, output_path, *dry_run)
+        }
+        Commands::Verify {
+            file_path,
+            chunk_type,
+            expect_hash,
+        } => commands::verify(file_path, chunk_type, expect_hash),
+        Commands::Detect { file_path } => {
+            commands::detect(file_path)
+        }
+        Commands::View {
+            file_path,
+            width,
+            style,
+        } => {
+            let style = match style {
+                ViewStyle::HalfBlock => Style::HalfBlock,
+                ViewStyle::Block => Style::Block,
+                ViewStyle::Ascii => Style::Ascii,
+            };
+            commands::view(file_path, *width, style)
+        }
+        Commands::Pixdiff {
+            old_path,
+            new_path,
+            output,
+        } => {
+            commands::pixdiff(old_path, new_path, output)
+        }
+        Commands::Diff {
+            old_path,
+            new_path,
+            hexdiff,
+        } => {
+            commands::diff(old_path, new_path, *hexdiff)
+        }
+        Commands::Grep {
+            pattern,
+            file_paths,
+            hex,
+            decompress,
+        } => {
+            commands::grep(pattern, file_paths, *hex, *decompress)
+        }
+        Commands::Shell { file_path } => {
+            commands::shell(file_path)
+        }
+        Commands::History { file_path } => {
+            commands::history(file_path)
+        }
+        Commands::Pack {
+            carrier,
+            file_paths,
+            output,
+            replace,
+            dry_run,
+        } => {
+            commands::pack(carrier, file_paths, output, *replace, *dry_run)
+        }
+        Commands::Unpack {
+            file_path,
+            dir,
+            overwrite,
+        } => {
+            commands::unpack(file_path, dir, *overwrite)
+        }
+        Commands::Watch {
+            dir,
+            on_add,
+            recursive,
+            debounce,
+        } => {
+            commands::watch(dir, on_add, *recursive, *debounce)
+        }
+        Commands::Icc { command } => match command {
+            IccCommands::Extract { file_path, output } => {
+                commands::icc_extract(file_path, output)
+            }
+            IccCommands::Embed {
+                file_path,
+                profile_path,
+                name,
+                output_path,
+            } => {
+                commands::icc_embed(file_path, profile_path, name, output_path)
+            }
+        },
+        Commands::Exif { command } => match command {
+            ExifCommands::Dump { file_path } => {
+                commands::exif_dump(file_path)
+            }
+            ExifCommands::Strip {
+                file_path,
+                output_path,
+            } => {
+                commands::exif_strip(file_path, output_path)
+            }
+        },
+        Commands::Create {
+            output_path,
+            width,
+            height,
+            fill,
+        } => {
+            commands::create(output_path, *width, *height, fill)
+        }
+        Commands::SetDpi {
+            file_path,
+            dpi,
+            dry_run,
+        } => {
+            commands::set_dpi(file_path, *dpi, *dry_run)
+        }
+        Commands::Set {
+            file_path,
+            key,
+            value,
+        } => {
+            commands::set(file_path, key, value)
+        }
+        Commands::Get { file_path, key } => {
+            commands::get(file_path, key)
+        }
+        Commands::Del { file_path, key } => {
+            commands::del(file_path, key)
+        }
+        Commands::Labels { file_path } => {
+            commands::labels(file_path)
+        }
+        Commands::Keys { file_path } => {
+            commands::keys(file_path)
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => exit_with(e, cli.quiet),
+    }
+}
+
+/// The subcommand that was run, including any nested one, such as
+/// `icc embed`.
+fn command_name(matches: &ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut matches = matches;
+    while let Some((name, subcommand)) = matches.subcommand() {
+        names.push(name);
+        matches = subcommand;
+    }
+    names.join(" ")
+}
+
+fn exit_with(error: pngme::Error, quiet: bool) -> ExitCode {
+    if !quiet {
+        eprintln!("{} {}", term::paint_stderr(tr!("error.prefix"), Role::Error), error);
+    }
+    ExitCode::from(commands::exit_code(&error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
     }
 }
@@ -0,0 +1,179 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{chunk_type::ChunkType, Error, Result};
+
+/// Selects chunk types by a glob-style pattern: `?` matches any one letter
+/// and `*` any number of letters, so `t*` matches `tEXt` and `tIME`, and
+/// `ru??` matches `ruSt`. A pattern without wildcards matches a single type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkTypeMatcher {
+    pattern: Vec<u8>,
+    ignore_case: bool,
+}
+
+impl FromStr for ChunkTypeMatcher {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let pattern = s.as_bytes().to_vec();
+        let valid = pattern
+            .iter()
+            .all(|b| b.is_ascii_alphabetic() || *b == b'?' || *b == b'*');
+        let literal_length = pattern.iter().filter(|b| **b != b'*').count();
+        let has_star = pattern.contains(&b'*');
+        if !valid || literal_length > 4 || (!has_star && literal_length != 4) {
+            return Err(Box::new(MatcherError::InvalidPattern(s.to_string())));
+        }
+        // Runs of * match the same as one, and would only slow matching down.
+        let mut pattern = pattern;
+        pattern.dedup_by(|a, b| *a == b'*' && *b == b'*');
+        Ok(ChunkTypeMatcher {
+            pattern,
+            ignore_case: false,
+        })
+    }
+}
+
+impl Display for ChunkTypeMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pattern.escape_ascii())
+    }
+}
+
+impl ChunkTypeMatcher {
+    /// A matcher for exactly `chunk_type`.
+    pub fn exact(chunk_type: &ChunkType) -> ChunkTypeMatcher {
+        ChunkTypeMatcher {
+            pattern: chunk_type.bytes().to_vec(),
+            ignore_case: false,
+        }
+    }
+    /// Makes letters in the pattern match either case. Note that the case
+    /// of a chunk type's letters carries its properties, so this can match
+    /// chunks that readers treat quite differently.
+    pub fn ignore_case(mut self, ignore_case: bool) -> ChunkTypeMatcher {
+        self.ignore_case = ignore_case;
+        self
+    }
+    pub fn is_exact(&self) -> bool {
+        !self.ignore_case && !self.pattern.iter().any(|b| *b == b'?' || *b == b'*')
+    }
+    /// The one chunk type an exact pattern matches.
+    pub fn exact_type(&self) -> Option<ChunkType> {
+        let bytes: [u8; 4] = self.pattern.as_slice().try_into().ok()?;
+        self.is_exact().then(|| ChunkType::try_from(bytes).ok())?
+    }
+    /// Matches in a single pass over the type, going back only to just after
+    /// the latest `*` when the letters that follow it do not fit.
+    pub fn matches(&self, chunk_type: &ChunkType) -> bool {
+        let name = chunk_type.bytes();
+        let (mut p, mut n) = (0, 0);
+        // The position of the latest * and of the letter it stopped before.
+        let mut star: Option<(usize, usize)> = None;
+        while n < name.len() {
+            match self.pattern.get(p) {
+                Some(b'*') => {
+                    star = Some((p, n));
+                    p += 1;
+                }
+                Some(&c) if c == b'?' || self.letters_equal(c, name[n]) => {
+                    p += 1;
+                    n += 1;
+                }
+                _ => match star {
+                    Some((star_p, star_n)) => {
+                        star = Some((star_p, star_n + 1));
+                        (p, n) = (star_p + 1, star_n + 1);
+                    }
+                    None => return false,
+                },
+            }
+        }
+        self.pattern[p..].iter().all(|b| *b == b'*')
+    }
+    fn letters_equal(&self, p: u8, n: u8) -> bool {
+        if self.ignore_case {
+            p.eq_ignore_ascii_case(&n)
+        } else {
+            p == n
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MatcherError {
+    InvalidPattern(String),
+}
+impl std::error::Error for MatcherError {}
+impl Display for MatcherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            MatcherError::InvalidPattern(pattern) => crate::tr!(
+                "matcher.invalid_pattern",
+                pattern = format!("{:?}", pattern)
+            ),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, chunk_type: &str) -> bool {
+        ChunkTypeMatcher::from_str(pattern)
+            .unwrap()
+            .matches(&ChunkType::from_str(chunk_type).unwrap())
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches("RuSt", "RuSt"));
+        assert!(!matches("RuSt", "ruSt"));
+        assert!(matches("t*", "tEXt"));
+        assert!(matches("t*", "tIME"));
+        assert!(!matches("t*", "zTXt"));
+        assert!(matches("ru??", "ruSt"));
+        assert!(!matches("*T", "IEND"));
+        assert!(matches("*Xt", "tEXt"));
+        assert!(matches("*", "IDAT"));
+        assert!(matches("*t*t", "tEXt"));
+        assert!(!matches("*t*t*t", "tEXt"));
+    }
+
+    #[test]
+    fn test_many_stars() {
+        assert!(!matches(&format!("{}Z", "*".repeat(1000)), "IDAT"));
+        assert!(!matches("*?*?*?*Z", "IDAT"));
+        assert!(matches("*?*?*?*T", "IDAT"));
+        assert_eq!(
+            ChunkTypeMatcher::from_str("**t***").unwrap().to_string(),
+            "*t*"
+        );
+    }
+
+    #[test]
+    fn test_ignore_case() {
+        let matcher = ChunkTypeMatcher::from_str("rust")
+            .unwrap()
+            .ignore_case(true);
+        assert!(matcher.matches(&ChunkType::from_str("RuSt").unwrap()));
+        assert!(!matcher.is_exact());
+        assert!(ChunkTypeMatcher::from_str("RuSt").unwrap().is_exact());
+        assert!(matcher.exact_type().is_none());
+        assert_eq!(
+            ChunkTypeMatcher::from_str("RuSt").unwrap().exact_type(),
+            Some(ChunkType::from_str("RuSt").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert!(ChunkTypeMatcher::from_str("ru?").is_err());
+        assert!(ChunkTypeMatcher::from_str("ruStt*").is_err());
+        assert!(ChunkTypeMatcher::from_str("r1St").is_err());
+        assert!(ChunkTypeMatcher::from_str("ru*").is_ok());
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    category::ChunkCategory, chunk::Chunk, chunk_type::ChunkType, matcher::ChunkTypeMatcher, policy,
+};
+
+/// What [`Png::merge_ancillary_from`](crate::png::Png::merge_ancillary_from)
+/// does with a chunk the destination already has an equivalent of: a chunk
+/// of the same type, a text chunk with the same keyword, or either of `sRGB`
+/// and `iCCP`, which must not appear together.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Conflict {
+    /// Leave the destination's chunks alone and copy nothing in their place.
+    #[default]
+    Keep,
+    /// Drop the destination's equivalent chunks in favour of the source's.
+    Replace,
+    /// Copy the source's chunks alongside the destination's.
+    Add,
+}
+
+/// Which ancillary chunks to copy from one PNG into another.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergePolicy {
+    /// Only chunks matching one of these are copied; every ancillary chunk
+    /// is a candidate when empty.
+    pub types: Vec<ChunkTypeMatcher>,
+    pub on_conflict: Conflict,
+    /// Copy chunks marked unsafe-to-copy even when the two images differ.
+    pub force_unsafe: bool,
+}
+
+/// The outcome of a merge, by chunk type in source order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    pub copied: Vec<ChunkType>,
+    /// Chunks left out because the destination already had an equivalent.
+    pub kept_existing: Vec<ChunkType>,
+    /// Chunks left out because they may depend on the source's image data.
+    pub unsafe_to_copy: Vec<ChunkType>,
+}
+
+impl MergePolicy {
+    pub(crate) fn selects(&self, chunk: &Chunk) -> bool {
+        !chunk.chunk_type().is_critical()
+            && (self.types.is_empty() || self.types.iter().any(|m| m.matches(chunk.chunk_type())))
+    }
+}
+
+/// What a chunk clashes with when merged into another image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ConflictKey {
+    Type(ChunkType),
+    /// Text chunks only clash with text carrying the same keyword.
+    Keyword(String),
+    /// `sRGB` and `iCCP` each say which colour space the image is in.
+    ColorSpace,
+}
+
+impl ConflictKey {
+    pub(crate) fn of(chunk: &Chunk) -> ConflictKey {
+        if let Some(keyword) = policy::keyword(chunk) {
+            return ConflictKey::Keyword(keyword);
+        }
+        match &chunk.chunk_type().bytes() {
+            b"sRGB" | b"iCCP" => ConflictKey::ColorSpace,
+            _ => ConflictKey::Type(chunk.chunk_type().clone()),
+        }
+    }
+}
+
+/// Whether `chunk` stays meaningful in another image. The specification
+/// forbids copying unrecognised unsafe-to-copy chunks once the critical
+/// chunks change, so those only go across between identical images. Known
+/// chunks that describe the image as a whole rather than its pixel layout
+/// are always fine.
+pub(crate) fn is_transplantable(chunk: &Chunk, same_image: bool) -> bool {
+    let chunk_type = chunk.chunk_type();
+    if chunk_type.is_safe_to_copy() || same_image {
+        return true;
+    }
+    let independent = matches!(
+        ChunkCategory::of(chunk_type),
+        ChunkCategory::Color
+            | ChunkCategory::Text
+            | ChunkCategory::Time
+            | ChunkCategory::Exif
+            | ChunkCategory::Physical
+    );
+    // sBIT is only meaningful for the bit depth it was written for.
+    independent && &chunk_type.bytes() != b"sBIT"
+}
@@ -0,0 +1,456 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, parse_options::ParseOptions, png::Png, Result};
+
+/// The textual metadata of a PNG as an ordered key-value map, backed by its
+/// `tEXt` and `iTXt` chunks with the chunk keyword as the key. When a keyword
+/// appears more than once, the first chunk wins, but writing the map back
+/// keeps every chunk of a keyword whose value did not change.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetadataMap {
+    entries: Vec<(String, String)>,
+    options: ParseOptions,
+}
+
+impl MetadataMap {
+    const MAX_KEY_LENGTH: usize = 79;
+
+    /// Loads every `tEXt` and `iTXt` chunk, in file order.
+    pub fn from_png(png: &Png) -> Result<MetadataMap> {
+        MetadataMap::from_png_with_options(png, &ParseOptions::default())
+    }
+    /// Like [`MetadataMap::from_png`], refusing compressed text that inflates
+    /// past [`ParseOptions::max_inflated_size`].
+    pub fn from_png_with_options(png: &Png, options: &ParseOptions) -> Result<MetadataMap> {
+        let mut map = MetadataMap {
+            options: *options,
+            ..MetadataMap::default()
+        };
+        for chunk in png.chunks() {
+            let entry = match &chunk.chunk_type().bytes() {
+                b"tEXt" => parse_text(chunk.data())?,
+                b"iTXt" => parse_international_text(chunk.data(), options)?,
+                _ => continue,
+            };
+            if map.get(&entry.0).is_none() {
+                map.entries.push(entry);
+            }
+        }
+        Ok(map)
+    }
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+    /// Updates the value of an existing key in place, or adds the key at the
+    /// end.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        MetadataMap::validate_key(key)?;
+        match self.entries.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.entries.push((key.to_string(), value.to_string())),
+        }
+        Ok(())
+    }
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        Some(self.entries.remove(index).1)
+    }
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(k, _)| k.as_str())
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Updates the `tEXt` and `iTXt` chunks of `png` to match the map,
+    /// touching only the keywords whose value changed. The chunks of a
+    /// removed keyword are dropped, and a changed keyword gets a single chunk
+    /// where its first one was, keeping the compression and language of an
+    /// `iTXt` chunk. New keys are added after the last text chunk, in map
+    /// order. Values that fit in Latin-1 are stored as `tEXt`, anything else
+    /// as uncompressed `iTXt`.
+    pub fn write_to(&self, png: &mut Png) -> Result<()> {
+        let current = MetadataMap::from_png_with_options(png, &self.options)?;
+        let mut chunks = Vec::with_capacity(png.chunks().len());
+        let mut replaced = HashSet::new();
+        let mut after_text = None;
+        for chunk in png.chunks() {
+            let Some(key) = text_keyword(chunk) else {
+                chunks.push(chunk.clone());
+                continue;
+            };
+            match (current.get(&key), self.get(&key)) {
+                (Some(old), Some(new)) if old == new => chunks.push(chunk.clone()),
+                (_, Some(new)) if replaced.insert(key.clone()) => {
+                    chunks.push(MetadataMap::replacement(chunk, &key, new)?)
+                }
+                _ => continue,
+            }
+            after_text = Some(chunks.len());
+        }
+
+        let mut updated = Png::from_chunks(chunks);
+        updated.set_trailer(png.trailer().to_vec());
+        updated.set_insertion_policy(png.insertion_policy());
+        let added = self.iter().filter(|(k, _)| current.get(k).is_none());
+        for (offset, (key, value)) in added.enumerate() {
+            let chunk = MetadataMap::to_chunk(key, value)?;
+            match after_text {
+                Some(index) => updated.insert_chunk(index + offset, chunk)?,
+                None => updated.append_chunk(chunk),
+            }
+        }
+        *png = updated;
+        Ok(())
+    }
+
+    /// The chunk [`MetadataMap::write_to`] would write for a single entry.
+    pub(crate) fn text_chunk(key: &str, value: &str) -> Result<Chunk> {
+        MetadataMap::validate_key(key)?;
+        MetadataMap::to_chunk(key, value)
+    }
+    fn to_chunk(key: &str, value: &str) -> Result<Chunk> {
+        let mut data = latin1_bytes(key).ok_or_else(|| MetadataError::InvalidKey(key.into()))?;
+        data.push(0);
+        match latin1_bytes(value) {
+            Some(value) => {
+                data.extend_from_slice(&value);
+                Ok(Chunk::new(ChunkType::from_str("tEXt")?, data))
+            }
+            None => {
+                // Uncompressed, with empty language tag and translated keyword.
+                data.extend_from_slice(&[0, 0, 0, 0]);
+                data.extend_from_slice(value.as_bytes());
+                Ok(Chunk::new(ChunkType::from_str("iTXt")?, data))
+            }
+        }
+    }
+    /// The chunk that takes the place of `original` when its value changes.
+    fn replacement(original: &Chunk, key: &str, value: &str) -> Result<Chunk> {
+        if &original.chunk_type().bytes() != b"iTXt" {
+            return MetadataMap::to_chunk(key, value);
+        }
+        let (keyword, rest) = split_at_nul(original.data())?;
+        if rest.len() < 2 {
+            return Err(Box::new(MetadataError::MissingSeparator));
+        }
+        let (flags, rest) = rest.split_at(2);
+        let (language, rest) = split_at_nul(rest)?;
+        let (translated_keyword, _) = split_at_nul(rest)?;
+
+        let mut data = keyword.to_vec();
+        data.push(0);
+        data.extend_from_slice(flags);
+        data.extend_from_slice(language);
+        data.push(0);
+        data.extend_from_slice(translated_keyword);
+        data.push(0);
+        if flags[0] == 1 {
+            let mut encoder = ZlibEncoder::new(data, Compression::default());
+            encoder.write_all(value.as_bytes())?;
+            data = encoder.finish()?;
+        } else {
+            data.extend_from_slice(value.as_bytes());
+        }
+        Ok(Chunk::new(ChunkType::from_str("iTXt")?, data))
+    }
+    fn validate_key(key: &str) -> Result<()> {
+        let printable = |b: &u8| (32..=126).contains(b) || *b >= 161;
+        let valid = latin1_bytes(key).is_some_and(|bytes| {
+            !bytes.is_empty()
+                && bytes.len() <= MetadataMap::MAX_KEY_LENGTH
+                && bytes.iter().all(printable)
+                && !key.starts_with(' ')
+                && !key.ends_with(' ')
+                && !key.contains("  ")
+        });
+        if !valid {
+            return Err(Box::new(MetadataError::InvalidKey(key.to_string())));
+        }
+        Ok(())
+    }
+}
+
+/// A one-line preview of a text-like chunk, with at most `max_len` payload
+/// bytes shown as escaped ASCII. Text chunks are shown as `keyword="value"`;
+/// other chunks qualify when their leading bytes are mostly printable, and
+/// anything else gives `None`.
+pub fn preview(chunk: &Chunk, max_len: usize) -> Option<String> {
+    let data = chunk.data();
+    match &chunk.chunk_type().bytes() {
+        b"tEXt" => {
+            let (keyword, text) = split_at_nul(data).ok()?;
+            Some(format!(
+                "{}={}",
+                keyword.escape_ascii(),
+                quoted(text, max_len)
+            ))
+        }
+        b"zTXt" => {
+            let (keyword, _) = split_at_nul(data).ok()?;
+            Some(format!("{}=(compressed)", keyword.escape_ascii()))
+        }
+        b"iTXt" => {
+            let (keyword, rest) = split_at_nul(data).ok()?;
+            if rest.first() == Some(&1) {
+                return Some(format!("{}=(compressed)", keyword.escape_ascii()));
+            }
+            let (_language, rest) = split_at_nul(rest.get(2..)?).ok()?;
+            let (_translated_keyword, text) = split_at_nul(rest).ok()?;
+            Some(format!(
+                "{}={}",
+                keyword.escape_ascii(),
+                quoted(text, max_len)
+            ))
+        }
+        _ => {
+            let shown = &data[..data.len().min(max_len)];
+            let printable = shown
+                .iter()
+                .filter(|b| b.is_ascii_graphic() || b" \t\r\n".contains(b))
+                .count();
+            let text_like = !shown.is_empty() && printable * 10 >= shown.len() * 9;
+            text_like.then(|| quoted(data, max_len))
+        }
+    }
+}
+
+/// The inflated text of a compressed `zTXt` or `iTXt` chunk, or `None` for
+/// other chunks and for text that fails to inflate. Inflating stops after
+/// [`ParseOptions::DEFAULT_MAX_INFLATED_SIZE`] bytes.
+pub(crate) fn inflate_text(chunk: &Chunk) -> Option<Vec<u8>> {
+    let data = chunk.data();
+    let compressed = match &chunk.chunk_type().bytes() {
+        b"zTXt" => split_at_nul(data).ok()?.1.get(1..)?,
+        b"iTXt" => {
+            let (_keyword, rest) = split_at_nul(data).ok()?;
+            if rest.first() != Some(&1) {
+                return None;
+            }
+            let (_language, rest) = split_at_nul(rest.get(2..)?).ok()?;
+            split_at_nul(rest).ok()?.1
+        }
+        _ => return None,
+    };
+    let mut text = Vec::new();
+    ZlibDecoder::new(compressed)
+        .take(ParseOptions::DEFAULT_MAX_INFLATED_SIZE)
+        .read_to_end(&mut text)
+        .ok()?;
+    Some(text)
+}
+
+fn quoted(bytes: &[u8], max_len: usize) -> String {
+    let shown = &bytes[..bytes.len().min(max_len)];
+    let ellipsis = if shown.len() < bytes.len() { "..." } else { "" };
+    format!("\"{}\"{}", shown.escape_ascii(), ellipsis)
+}
+
+fn latin1_bytes(s: &str) -> Option<Vec<u8>> {
+    s.chars().map(|c| u8::try_from(u32::from(c)).ok()).collect()
+}
+
+fn latin1_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| *b as char).collect()
+}
+
+fn split_at_nul(bytes: &[u8]) -> Result<(&[u8], &[u8])> {
+    let separator = bytes
+        .iter()
+        .position(|b| *b == 0)
+        .ok_or(MetadataError::MissingSeparator)?;
+    Ok((&bytes[..separator], &bytes[separator + 1..]))
+}
+
+/// The keyword of a `tEXt` or `iTXt` chunk.
+fn text_keyword(chunk: &Chunk) -> Option<String> {
+    match &chunk.chunk_type().bytes() {
+        b"tEXt" | b"iTXt" => Some(latin1_string(split_at_nul(chunk.data()).ok()?.0)),
+        _ => None,
+    }
+}
+
+fn parse_text(data: &[u8]) -> Result<(String, String)> {
+    let (keyword, text) = split_at_nul(data)?;
+    Ok((latin1_string(keyword), latin1_string(text)))
+}
+
+fn parse_international_text(data: &[u8], options: &ParseOptions) -> Result<(String, String)> {
+    let (keyword, rest) = split_at_nul(data)?;
+    if rest.len() < 2 {
+        return Err(Box::new(MetadataError::MissingSeparator));
+    }
+    let (compressed, rest) = (rest[0] == 1, &rest[2..]);
+    let (_language, rest) = split_at_nul(rest)?;
+    let (_translated_keyword, text) = split_at_nul(rest)?;
+
+    let text = if compressed {
+        String::from_utf8(options.inflate(text)?)?
+    } else {
+        std::str::from_utf8(text)?.to_string()
+    };
+    Ok((latin1_string(keyword), text))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MetadataError {
+    MissingSeparator,
+    InvalidKey(String),
+}
+impl std::error::Error for MetadataError {}
+impl Display for MetadataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            MetadataError::MissingSeparator => crate::tr!("metadata.missing_separator"),
+            MetadataError::InvalidKey(key) => {
+                crate::tr!("metadata.invalid_key", key = format!("{:?}", key))
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    #[test]
+    fn test_set_get_remove() {
+        let mut map = MetadataMap::default();
+        map.set("Author", "sj").unwrap();
+        map.set("Title", "dice").unwrap();
+        map.set("Author", "someone else").unwrap();
+
+        assert_eq!(map.get("Author"), Some("someone else"));
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["Author", "Title"]);
+        assert_eq!(map.remove("Author").as_deref(), Some("someone else"));
+        assert_eq!(map.len(), 1);
+        assert!(map.set(" bad", "x").is_err());
+    }
+
+    #[test]
+    fn test_preview() {
+        let text = MetadataMap::text_chunk("Author", "Jane \"JD\" Doe").unwrap();
+        assert_eq!(preview(&text, 40).unwrap(), r#"Author="Jane \"JD\" Doe""#);
+        assert_eq!(preview(&text, 4).unwrap(), r#"Author="Jane"..."#);
+
+        let international = MetadataMap::text_chunk("Comment", "日本").unwrap();
+        assert_eq!(
+            preview(&international, 40).unwrap(),
+            r#"Comment="\xe6\x97\xa5\xe6\x9c\xac""#
+        );
+
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let secret = png.chunk("RuSt").unwrap();
+        assert_eq!(preview(secret, 40).unwrap(), r#""hey""#);
+        let header = png.chunk("IHDR").unwrap();
+        assert_eq!(preview(header, 40), None);
+    }
+
+    #[test]
+    fn test_write_and_reload() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let mut map = MetadataMap::from_png(&png).unwrap();
+        assert!(map.is_empty());
+
+        map.set("Title", "dice").unwrap();
+        map.set("Comment", "日本語").unwrap();
+        map.set("Author", "sj").unwrap();
+        map.write_to(&mut png).unwrap();
+        assert!(png.chunk("iTXt").is_some());
+
+        let mut reloaded = MetadataMap::from_png(&png).unwrap();
+        assert_eq!(reloaded, map);
+
+        reloaded.remove("Title");
+        reloaded.set("Title", "changed").unwrap();
+        reloaded.set("Source", "camera").unwrap();
+        reloaded.write_to(&mut png).unwrap();
+        let reloaded = MetadataMap::from_png(&png).unwrap();
+        let keys: Vec<&str> = reloaded.keys().collect();
+        assert_eq!(keys, ["Title", "Comment", "Author", "Source"]);
+        assert_eq!(reloaded.get("Title"), Some("changed"));
+        assert_eq!(
+            &png.chunks().last().unwrap().chunk_type().to_string(),
+            "IEND"
+        );
+    }
+
+    #[test]
+    fn test_inflate_limit() {
+        let mut compressed = b"Comment\0\x01\x00\0\0".to_vec();
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[b'a'; 1 << 16]).unwrap();
+        compressed.extend(encoder.finish().unwrap());
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(Chunk::new(ChunkType::from_str("iTXt").unwrap(), compressed));
+
+        let options = ParseOptions {
+            max_inflated_size: 1 << 10,
+            ..ParseOptions::default()
+        };
+        assert!(MetadataMap::from_png_with_options(&png, &options).is_err());
+        let map = MetadataMap::from_png(&png).unwrap();
+        assert_eq!(map.get("Comment").map(str::len), Some(1 << 16));
+    }
+
+    #[test]
+    fn test_write_keeps_untouched_chunks() {
+        let mut compressed = b"Title\0\x01\x00fr\0Titre\0".to_vec();
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all("dés".as_bytes()).unwrap();
+        compressed.extend(encoder.finish().unwrap());
+        let international = Chunk::new(ChunkType::from_str("iTXt").unwrap(), compressed);
+
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(MetadataMap::text_chunk("Author", "first").unwrap());
+        png.append_chunk(international.clone());
+        png.append_chunk(MetadataMap::text_chunk("Author", "second").unwrap());
+        png.append_chunk(MetadataMap::text_chunk("Comment", "old").unwrap());
+        let text_chunks = |png: &Png| -> Vec<Vec<u8>> {
+            png.chunks()
+                .iter()
+                .filter(|c| text_keyword(c).is_some())
+                .map(|c| c.as_bytes())
+                .collect()
+        };
+        let before = text_chunks(&png);
+
+        let mut map = MetadataMap::from_png(&png).unwrap();
+        assert_eq!(map.get("Title"), Some("dés"));
+        map.write_to(&mut png).unwrap();
+        assert_eq!(text_chunks(&png), before);
+
+        map.set("Comment", "new").unwrap();
+        map.write_to(&mut png).unwrap();
+        let after = text_chunks(&png);
+        assert_eq!(after[..3], before[..3]);
+        assert_ne!(after[3], before[3]);
+
+        map.remove("Author");
+        map.set("Title", "dice").unwrap();
+        map.write_to(&mut png).unwrap();
+        let title = png.chunk("iTXt").unwrap();
+        assert!(title.data().starts_with(b"Title\0\x01\x00fr\0Titre\0"));
+        assert_eq!(inflate_text(title).unwrap(), b"dice");
+        assert_eq!(png.chunks_of("tEXt").count(), 1);
+        assert_eq!(
+            MetadataMap::from_png(&png).unwrap().get("Title"),
+            Some("dice")
+        );
+    }
+}
@@ -0,0 +1,13 @@
+use std::io::Read;
+
+use crate::Result;
+
+/// Requests `url` and returns a reader over the response body. The body is
+/// downloaded as it is read, so a caller that stops early, such as
+/// [`scan`](crate::png::scan) looking for one chunk, does not fetch the
+/// rest of the file.
+pub fn open(url: &str) -> Result<impl Read> {
+    log::debug!("Fetching {}", url);
+    let response = ureq::get(url).call()?;
+    Ok(response.into_body().into_reader())
+}
@@ -0,0 +1,109 @@
+use std::ops::RangeInclusive;
+
+use crate::chunk::Chunk;
+
+/// How [`Png::insert_chunk`](crate::png::Png::insert_chunk) treats an index
+/// that would put a chunk somewhere the PNG specification forbids.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InsertionPolicy {
+    /// Move the chunk to the nearest legal position.
+    #[default]
+    Lenient,
+    /// Reject the insertion with an error.
+    Strict,
+}
+
+/// Ancillary chunks that must come before both `PLTE` and `IDAT`.
+const BEFORE_PLTE: [&[u8; 4]; 8] = [
+    b"cHRM", b"cICP", b"cLLI", b"gAMA", b"iCCP", b"mDCV", b"sBIT", b"sRGB",
+];
+/// Ancillary chunks that must come after `PLTE` and before `IDAT`.
+const AFTER_PLTE: [&[u8; 4]; 3] = [b"bKGD", b"hIST", b"tRNS"];
+/// Ancillary chunks that only need to come before `IDAT`.
+const BEFORE_IDAT: [&[u8; 4]; 4] = [b"acTL", b"eXIf", b"pHYs", b"sPLT"];
+
+/// The insertion indices at which a chunk of type `chunk_type` would satisfy
+/// the ordering rules of the specification, given the existing `chunks`.
+/// Every chunk must sit between `IHDR` and `IEND`; known ancillary chunks are
+/// further constrained relative to `PLTE` and `IDAT`.
+pub(crate) fn legal_positions(chunks: &[Chunk], chunk_type: &[u8; 4]) -> RangeInclusive<usize> {
+    let first = |t: &[u8; 4]| chunks.iter().position(|c| &c.chunk_type().bytes() == t);
+
+    match chunk_type {
+        b"IHDR" => return 0..=0,
+        b"IEND" => return chunks.len()..=chunks.len(),
+        _ => {}
+    }
+
+    let mut start = first(b"IHDR").map_or(0, |i| i + 1);
+    let mut end = first(b"IEND").unwrap_or(chunks.len());
+    let plte = first(b"PLTE");
+    let idat = first(b"IDAT");
+
+    if chunk_type == b"PLTE" || BEFORE_IDAT.contains(&chunk_type) {
+        end = end.min(idat.unwrap_or(end));
+    }
+    if chunk_type == b"PLTE" {
+        let is_before = |c: &Chunk| BEFORE_PLTE.contains(&&c.chunk_type().bytes());
+        let is_after = |c: &Chunk| AFTER_PLTE.contains(&&c.chunk_type().bytes());
+        start = start.max(chunks.iter().rposition(is_before).map_or(start, |i| i + 1));
+        end = end.min(chunks.iter().position(is_after).unwrap_or(end));
+    }
+    if BEFORE_PLTE.contains(&chunk_type) {
+        end = end.min(plte.unwrap_or(end)).min(idat.unwrap_or(end));
+    }
+    if AFTER_PLTE.contains(&chunk_type) {
+        start = start.max(plte.map_or(start, |i| i + 1));
+        end = end.min(idat.unwrap_or(end));
+    }
+    if chunk_type == b"IDAT" {
+        start = start.max(plte.map_or(start, |i| i + 1));
+    }
+
+    start.min(end)..=end
+}
+
+/// The groups of the canonical chunk order, in file order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Group {
+    Header,
+    ColorSpace,
+    Palette,
+    AfterPalette,
+    BeforeImage,
+    Image,
+    AfterImage,
+    End,
+}
+
+fn group(chunk_type: &[u8; 4], before_idat: bool) -> Group {
+    match chunk_type {
+        b"IHDR" => Group::Header,
+        b"PLTE" => Group::Palette,
+        b"IDAT" => Group::Image,
+        b"IEND" => Group::End,
+        _ if BEFORE_PLTE.contains(&chunk_type) => Group::ColorSpace,
+        _ if AFTER_PLTE.contains(&chunk_type) => Group::AfterPalette,
+        _ if BEFORE_IDAT.contains(&chunk_type) || before_idat => Group::BeforeImage,
+        _ => Group::AfterImage,
+    }
+}
+
+/// `chunks` sorted into the canonical order: `IHDR`, the color space chunks,
+/// `PLTE`, the chunks that must follow it, the other chunks before the image
+/// data, the `IDAT` run, the chunks after it and `IEND`. The sort is stable,
+/// and chunks the specification does not constrain stay on the side of the
+/// first `IDAT` they were on.
+pub(crate) fn canonical_order(chunks: Vec<Chunk>) -> Vec<Chunk> {
+    let first_idat = chunks
+        .iter()
+        .position(|c| &c.chunk_type().bytes() == b"IDAT")
+        .unwrap_or(chunks.len());
+    let mut grouped: Vec<(Group, Chunk)> = chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, c)| (group(&c.chunk_type().bytes(), i < first_idat), c))
+        .collect();
+    grouped.sort_by_key(|(group, _)| *group);
+    grouped.into_iter().map(|(_, c)| c).collect()
+}
@@ -0,0 +1,310 @@
+//! Hides a short message without adding any bytes to the file.
+//!
+//! Each run of consecutive ancillary chunks with distinct types can be put in
+//! any of `n!` orders without breaking the ordering rules, which encodes
+//! `floor(log2(n!))` bits relative to the run sorted by type. Chunks are only
+//! moved, never renamed or rewritten, so chunks that are looked up by type,
+//! such as message, shard and signature chunks, still work afterwards.
+//! The animation chunks of an APNG are ancillary but their order is what
+//! sequences the frames, so they are never moved and split runs instead.
+//!
+//! The message is framed as a length byte followed by its bytes.
+
+use std::fmt::Display;
+
+use crate::{chunk::Chunk, png::Png, Result};
+
+/// Runs longer than this are only permuted in their first chunks, so that
+/// `n!` fits in a `u64`.
+const MAX_RUN_LENGTH: usize = 20;
+
+/// How many bits a PNG can carry in the ordering channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capacity {
+    pub runs: usize,
+    pub permutation_bits: usize,
+}
+
+impl Capacity {
+    pub fn bits(&self) -> usize {
+        self.permutation_bits
+    }
+    /// The longest message that fits after the length byte.
+    pub fn message_bytes(&self) -> usize {
+        (self.bits().saturating_sub(8) / 8).min(u8::MAX as usize)
+    }
+}
+
+impl Display for Capacity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} bits from the order of {} runs, {} message bytes",
+            self.bits(),
+            self.runs,
+            self.message_bytes()
+        )
+    }
+}
+
+pub fn capacity(png: &Png) -> Capacity {
+    let runs = runs(png.chunks());
+    Capacity {
+        runs: runs
+            .iter()
+            .filter(|r| permutation_bits(r.len()) > 0)
+            .count(),
+        permutation_bits: runs.iter().map(|r| permutation_bits(r.len())).sum(),
+    }
+}
+
+/// Returns a copy of `png` with `message` hidden in its chunk order.
+pub fn embed(png: &Png, message: &[u8]) -> Result<Png> {
+    let available = capacity(png).message_bytes();
+    if message.len() > available {
+        return Err(Box::new(OrderingChannelError::MessageTooLong(
+            message.len(),
+            available,
+        )));
+    }
+    let mut bits = to_bits(&[message.len() as u8]);
+    bits.extend(to_bits(message));
+    let mut bits = bits.into_iter();
+
+    let mut chunks = canonical(png.chunks());
+    let runs = runs(&chunks);
+    let mut permutations = Vec::new();
+    for run in &runs {
+        let width = permutation_bits(run.len());
+        let value = (0..width).fold(0u64, |v, _| v << 1 | bits.next().unwrap_or(false) as u64);
+        permutations.push(nth_permutation(run.len(), value));
+    }
+    for (run, permutation) in runs.iter().zip(permutations) {
+        let original: Vec<Chunk> = chunks[run.clone()].to_vec();
+        for (offset, index) in permutation.into_iter().enumerate() {
+            chunks[run.start + offset] = original[index].clone();
+        }
+    }
+
+    let mut embedded = Png::from_chunks(chunks);
+    embedded.set_trailer(png.trailer().to_vec());
+    embedded.set_insertion_policy(png.insertion_policy());
+    Ok(embedded)
+}
+
+/// Reads a message hidden by [`embed`].
+pub fn extract(png: &Png) -> Result<Vec<u8>> {
+    let mut bits = Vec::new();
+    for run in runs(png.chunks()) {
+        let observed = &png.chunks()[run];
+        let mut sorted: Vec<&Chunk> = observed.iter().collect();
+        sorted.sort_by_key(|c| sort_key(c));
+        let order: Vec<usize> = observed
+            .iter()
+            .map(|c| sorted.iter().position(|s| std::ptr::eq(*s, c)).unwrap())
+            .collect();
+        let width = permutation_bits(order.len());
+        let value = permutation_index(&order);
+        bits.extend((0..width).rev().map(|i| value >> i & 1 == 1));
+    }
+
+    let bytes: Vec<u8> = bits
+        .chunks_exact(8)
+        .map(|byte| byte.iter().fold(0u8, |b, bit| b << 1 | *bit as u8))
+        .collect();
+    let length = *bytes.first().ok_or(OrderingChannelError::NoMessage)? as usize;
+    bytes
+        .get(1..1 + length)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| OrderingChannelError::NoMessage.into())
+}
+
+/// Ancillary chunk types whose position carries meaning.
+const ORDER_SIGNIFICANT: [&[u8; 4]; 3] = [b"acTL", b"fcTL", b"fdAT"];
+
+/// Whether `chunk` may be moved within a run.
+fn is_movable(chunk: &Chunk) -> bool {
+    let chunk_type = chunk.chunk_type();
+    !chunk_type.is_critical() && !ORDER_SIGNIFICANT.contains(&&chunk_type.bytes())
+}
+
+/// The ranges of consecutive movable chunks whose types, ignoring case, are
+/// all distinct. Runs of one chunk carry no bits and are included for
+/// simplicity.
+fn runs(chunks: &[Chunk]) -> Vec<std::ops::Range<usize>> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    while start < chunks.len() {
+        let mut end = start;
+        while end < chunks.len()
+            && is_movable(&chunks[end])
+            && end - start < MAX_RUN_LENGTH
+            && !chunks[start..end]
+                .iter()
+                .any(|c| sort_key(c) == sort_key(&chunks[end]))
+        {
+            end += 1;
+        }
+        if end > start {
+            runs.push(start..end);
+            start = end;
+        } else {
+            start += 1;
+        }
+    }
+    runs
+}
+
+/// `chunks` with every run sorted, the arrangement that encodes zero.
+fn canonical(chunks: &[Chunk]) -> Vec<Chunk> {
+    let mut chunks = chunks.to_vec();
+    for run in runs(&chunks) {
+        chunks[run].sort_by_key(sort_key);
+    }
+    chunks
+}
+
+fn sort_key(chunk: &Chunk) -> [u8; 4] {
+    chunk.chunk_type().bytes().map(|b| b.to_ascii_uppercase())
+}
+
+fn factorial(n: usize) -> u64 {
+    (1..=n as u64).product()
+}
+
+fn permutation_bits(n: usize) -> usize {
+    (u64::BITS - 1 - factorial(n).leading_zeros()) as usize
+}
+
+/// The permutation of `0..n` with lexicographic rank `value`.
+fn nth_permutation(n: usize, mut value: u64) -> Vec<usize> {
+    let mut remaining: Vec<usize> = (0..n).collect();
+    (0..n)
+        .map(|i| {
+            let f = factorial(n - 1 - i);
+            let index = (value / f) as usize;
+            value %= f;
+            remaining.remove(index)
+        })
+        .collect()
+}
+
+/// The lexicographic rank of a permutation of `0..n`.
+fn permutation_index(order: &[usize]) -> u64 {
+    let n = order.len();
+    (0..n)
+        .map(|i| {
+            let smaller_later = order[i + 1..].iter().filter(|o| **o < order[i]).count();
+            smaller_later as u64 * factorial(n - 1 - i)
+        })
+        .sum()
+}
+
+fn to_bits(bytes: &[u8]) -> Vec<bool> {
+    bytes
+        .iter()
+        .flat_map(|b| (0..8).rev().map(move |i| b >> i & 1 == 1))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OrderingChannelError {
+    MessageTooLong(usize, usize),
+    NoMessage,
+}
+impl std::error::Error for OrderingChannelError {}
+impl Display for OrderingChannelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            OrderingChannelError::MessageTooLong(length, available) => crate::tr!(
+                "ordering_channel.message_too_long",
+                length = length,
+                available = available
+            ),
+            OrderingChannelError::NoMessage => crate::tr!("ordering_channel.no_message"),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn carrier() -> Png {
+        let mut chunks = vec![Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![])];
+        for i in 0..12u8 {
+            let chunk_type = ChunkType::try_from([b'a' + i, b'b', b'C', b'D']).unwrap();
+            chunks.push(Chunk::new(chunk_type, vec![i]));
+        }
+        chunks.push(Chunk::new(ChunkType::from_str("IDAT").unwrap(), vec![]));
+        chunks.push(Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]));
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_permutation_rank_round_trip() {
+        for value in 0..24 {
+            assert_eq!(permutation_index(&nth_permutation(4, value)), value);
+        }
+        assert_eq!(permutation_bits(1), 0);
+        assert_eq!(permutation_bits(3), 2);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let capacity = capacity(&carrier());
+        // 12! = 479001600 gives 28 bits.
+        assert_eq!(capacity.bits(), 28);
+        assert_eq!(capacity.message_bytes(), 2);
+    }
+
+    #[test]
+    fn test_embed_and_extract() {
+        let png = carrier();
+        let embedded = embed(&png, b"hi").unwrap();
+        assert_eq!(embedded.as_bytes().len(), png.as_bytes().len());
+        assert_eq!(extract(&embedded).unwrap(), b"hi");
+        let types = |png: &Png| {
+            let mut types: Vec<String> = png
+                .chunks()
+                .iter()
+                .map(|c| c.chunk_type().to_string())
+                .collect();
+            types.sort();
+            types
+        };
+        assert_eq!(types(&embedded), types(&png));
+
+        let reparsed = Png::try_from(embedded.as_bytes().as_ref()).unwrap();
+        assert_eq!(extract(&reparsed).unwrap(), b"hi");
+        assert!(embed(&png, b"too long").is_err());
+    }
+
+    #[test]
+    fn test_animation_chunks_stay_in_place() {
+        let chunk =
+            |name: &str, data: u8| Chunk::new(ChunkType::from_str(name).unwrap(), vec![data]);
+        let mut chunks = vec![chunk("IHDR", 0), chunk("acTL", 0)];
+        for i in 0..6u8 {
+            let chunk_type = ChunkType::try_from([b'a' + i, b'b', b'C', b'D']).unwrap();
+            chunks.push(Chunk::new(chunk_type, vec![i]));
+        }
+        chunks.extend([chunk("fcTL", 0), chunk("IDAT", 0), chunk("fcTL", 1)]);
+        chunks.extend([chunk("fdAT", 2), chunk("tEXt", 0), chunk("fcTL", 3)]);
+        chunks.extend([chunk("fdAT", 4), chunk("zTXt", 0), chunk("IEND", 0)]);
+        let png = Png::from_chunks(chunks);
+
+        // Only the six private chunks between acTL and fcTL can move.
+        assert_eq!(capacity(&png).bits(), permutation_bits(6));
+        let embedded = embed(&png, b"").unwrap();
+        for (before, after) in png.chunks().iter().zip(embedded.chunks()) {
+            if !is_movable(before) {
+                assert_eq!(before.as_bytes(), after.as_bytes());
+            }
+        }
+        assert_eq!(extract(&embedded).unwrap(), b"");
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, Error, Result};
+
+/// The color palette stored in the `PLTE` chunk, as RGB triplets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    entries: Vec<[u8; 3]>,
+}
+
+impl TryFrom<&Chunk> for Palette {
+    type Error = Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        if chunk.chunk_type().bytes() != Palette::CHUNK_TYPE {
+            return Err(Box::new(PaletteError::WrongChunkType(
+                chunk.chunk_type().to_string(),
+            )));
+        }
+
+        let data = chunk.data();
+        if !data.len().is_multiple_of(3) {
+            return Err(Box::new(PaletteError::InvalidLength(data.len())));
+        }
+        let entries = data
+            .chunks_exact(3)
+            .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+            .collect();
+        Palette::new(entries)
+    }
+}
+
+impl Display for Palette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, [r, g, b]) in self.entries.iter().enumerate() {
+            writeln!(f, "{:3}: #{:02x}{:02x}{:02x}", i, r, g, b)?;
+        }
+        Ok(())
+    }
+}
+
+impl Palette {
+    pub const CHUNK_TYPE: [u8; 4] = *b"PLTE";
+    pub const MAX_ENTRIES: usize = 256;
+
+    /// Fails unless there are between 1 and [`Palette::MAX_ENTRIES`] entries.
+    pub fn new(entries: Vec<[u8; 3]>) -> Result<Palette> {
+        if entries.is_empty() || entries.len() > Palette::MAX_ENTRIES {
+            return Err(Box::new(PaletteError::InvalidEntryCount(entries.len())));
+        }
+        Ok(Palette { entries })
+    }
+    pub fn entries(&self) -> &[[u8; 3]] {
+        &self.entries
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn to_chunk(&self) -> Chunk {
+        let data = self.entries.iter().flatten().copied().collect();
+        Chunk::new(ChunkType::from_str("PLTE").unwrap(), data)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PaletteError {
+    WrongChunkType(String),
+    InvalidLength(usize),
+    InvalidEntryCount(usize),
+}
+impl std::error::Error for PaletteError {}
+impl Display for PaletteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            PaletteError::WrongChunkType(found) => {
+                crate::tr!("palette.wrong_chunk_type", found = found)
+            }
+            PaletteError::InvalidLength(length) => {
+                crate::tr!("palette.invalid_length", length = length)
+            }
+            PaletteError::InvalidEntryCount(count) => crate::tr!(
+                "palette.invalid_entry_count",
+                count = count,
+                max = Palette::MAX_ENTRIES
+            ),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_round_trip() {
+        let palette = Palette::new(vec![[255, 0, 0], [0, 255, 0]]).unwrap();
+        let chunk = palette.to_chunk();
+        assert_eq!(chunk.data(), [255, 0, 0, 0, 255, 0]);
+        assert_eq!(Palette::try_from(&chunk).unwrap(), palette);
+        assert_eq!(palette.to_string(), "  0: #ff0000\n  1: #00ff00\n");
+    }
+
+    #[test]
+    fn test_invalid_palettes() {
+        let plte = |data: Vec<u8>| Chunk::new(ChunkType::from_str("PLTE").unwrap(), data);
+        assert!(Palette::try_from(&plte(vec![1, 2])).is_err());
+        assert!(Palette::try_from(&plte(Vec::new())).is_err());
+        assert!(Palette::try_from(&plte(vec![0; 257 * 3])).is_err());
+        assert!(Palette::try_from(&plte(vec![0; 256 * 3])).is_ok());
+    }
+}
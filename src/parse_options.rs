@@ -0,0 +1,144 @@
+use std::fmt::Display;
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+
+use crate::Result;
+
+/// Controls how strictly chunks are validated while parsing. Checks that are
+/// turned off keep the offending chunk verbatim instead of failing, so it is
+/// written back unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Reject chunk types whose third letter is lowercase. The specification
+    /// reserves this bit but asks decoders to tolerate it.
+    pub reject_invalid_reserved_bit: bool,
+    /// Reject chunks whose stored CRC does not match their contents.
+    pub reject_bad_crc: bool,
+    /// Reject chunk types containing bytes other than ASCII letters.
+    pub reject_malformed_chunk_type: bool,
+    /// Only split chunks at their boundaries while parsing and defer CRC
+    /// checks to [`Chunk::checked_data`](crate::chunk::Chunk::checked_data)
+    /// or [`Png::verify_all`](crate::png::Png::verify_all), which is much
+    /// faster for large files when only a few chunks are needed.
+    pub lazy_crc: bool,
+    /// Accept input that does not start with the PNG signature, such as a
+    /// bare stream of chunks cut from a network capture. A signature that is
+    /// present is still skipped.
+    pub skip_signature: bool,
+    /// Reject chunks with more data than this many bytes. A length field can
+    /// claim up to 2 GiB, so set this when reading untrusted streams.
+    pub max_chunk_size: Option<u32>,
+    /// Reject input longer than this many bytes.
+    pub max_total_size: Option<u64>,
+    /// Reject input with more chunks than this.
+    pub max_chunks: Option<usize>,
+    /// Reject compressed chunk contents, such as ICC profiles and text, that
+    /// inflate to more than this many bytes.
+    pub max_inflated_size: u64,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            reject_invalid_reserved_bit: true,
+            reject_bad_crc: true,
+            reject_malformed_chunk_type: true,
+            lazy_crc: false,
+            skip_signature: false,
+            max_chunk_size: None,
+            max_total_size: None,
+            max_chunks: None,
+            max_inflated_size: ParseOptions::DEFAULT_MAX_INFLATED_SIZE,
+        }
+    }
+}
+
+impl ParseOptions {
+    pub const DEFAULT_MAX_INFLATED_SIZE: u64 = 16 << 20;
+
+    /// Options that accept every chunk whose boundaries can be found.
+    pub fn raw() -> ParseOptions {
+        ParseOptions {
+            reject_invalid_reserved_bit: false,
+            reject_bad_crc: false,
+            reject_malformed_chunk_type: false,
+            lazy_crc: false,
+            skip_signature: false,
+            max_chunk_size: None,
+            max_total_size: None,
+            max_chunks: None,
+            max_inflated_size: ParseOptions::DEFAULT_MAX_INFLATED_SIZE,
+        }
+    }
+    pub(crate) fn check_chunk_size(&self, length: u32) -> Result<()> {
+        check(
+            Limit::ChunkSize,
+            self.max_chunk_size.map(u64::from),
+            length as u64,
+        )
+    }
+    pub(crate) fn check_total_size(&self, size: u64) -> Result<()> {
+        check(Limit::TotalSize, self.max_total_size, size)
+    }
+    pub(crate) fn check_chunk_count(&self, count: usize) -> Result<()> {
+        check(
+            Limit::Chunks,
+            self.max_chunks.map(|m| m as u64),
+            count as u64,
+        )
+    }
+    /// Inflates a zlib stream, failing once it grows past
+    /// [`ParseOptions::max_inflated_size`].
+    pub(crate) fn inflate(&self, compressed: &[u8]) -> Result<Vec<u8>> {
+        let mut inflated = Vec::new();
+        ZlibDecoder::new(compressed)
+            .take(self.max_inflated_size.saturating_add(1))
+            .read_to_end(&mut inflated)?;
+        check(
+            Limit::InflatedSize,
+            Some(self.max_inflated_size),
+            inflated.len() as u64,
+        )?;
+        Ok(inflated)
+    }
+}
+
+fn check(limit: Limit, max: Option<u64>, actual: u64) -> Result<()> {
+    match max {
+        Some(max) if actual > max => Err(Box::new(LimitExceeded { limit, max, actual })),
+        _ => Ok(()),
+    }
+}
+
+/// Which of the [`ParseOptions`] limits was exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    ChunkSize,
+    TotalSize,
+    Chunks,
+    InflatedSize,
+}
+
+/// Returned when the input goes over one of the limits in [`ParseOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    pub max: u64,
+    pub actual: u64,
+}
+
+impl std::error::Error for LimitExceeded {}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self.limit {
+            Limit::ChunkSize => "limit.chunk_size",
+            Limit::TotalSize => "limit.total_size",
+            Limit::Chunks => "limit.chunks",
+            Limit::InflatedSize => "limit.inflated_size",
+        };
+        let message = crate::tr!(key, actual = self.actual, max = self.max);
+        write!(f, "{}", message)
+    }
+}
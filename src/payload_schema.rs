@@ -0,0 +1,151 @@
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::{Error, Result};
+
+/// Checks a decoded payload before it is handed on, so that payloads of the
+/// wrong shape are rejected rather than shown.
+pub trait PayloadValidator {
+    fn validate(&self, payload: &[u8]) -> Result<()>;
+}
+
+/// Accepts payloads that are JSON documents matching a JSON Schema, such as
+/// a build manifest's.
+pub struct JsonSchema {
+    validator: jsonschema::Validator,
+}
+
+/// Where in the payload a schema rule was broken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// A JSON pointer such as `/files/0/name`, empty for the whole document.
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.path.as_str() {
+            "" => write!(f, "(root): {}", self.message),
+            path => write!(f, "{}: {}", path, self.message),
+        }
+    }
+}
+
+impl FromStr for JsonSchema {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let schema: serde_json::Value =
+            serde_json::from_str(s).map_err(|e| SchemaError::InvalidSchema(e.to_string()))?;
+        let validator = jsonschema::validator_for(&schema)
+            .map_err(|e| SchemaError::InvalidSchema(e.to_string()))?;
+        Ok(JsonSchema { validator })
+    }
+}
+
+impl JsonSchema {
+    pub fn load(path: &Path) -> Result<JsonSchema> {
+        JsonSchema::from_str(&std::fs::read_to_string(path)?)
+    }
+    /// Every rule the payload breaks, in the order the schema checks them.
+    pub fn violations(&self, payload: &[u8]) -> Result<Vec<SchemaViolation>> {
+        let document: serde_json::Value =
+            serde_json::from_slice(payload).map_err(|e| SchemaError::NotJson(e.to_string()))?;
+        Ok(self
+            .validator
+            .iter_errors(&document)
+            .map(|e| SchemaViolation {
+                path: e.instance_path().to_string(),
+                message: e.to_string(),
+            })
+            .collect())
+    }
+}
+
+impl PayloadValidator for JsonSchema {
+    fn validate(&self, payload: &[u8]) -> Result<()> {
+        let violations = self.violations(payload)?;
+        if violations.is_empty() {
+            return Ok(());
+        }
+        Err(Box::new(SchemaError::Violations(violations)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SchemaError {
+    InvalidSchema(String),
+    NotJson(String),
+    Violations(Vec<SchemaViolation>),
+}
+impl std::error::Error for SchemaError {}
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaError::InvalidSchema(e) => {
+                let message = crate::tr!("payload_schema.invalid_schema", error = e);
+                write!(f, "{}", message)
+            }
+            SchemaError::NotJson(e) => {
+                let message = crate::tr!("payload_schema.not_json", error = e);
+                write!(f, "{}", message)
+            }
+            SchemaError::Violations(violations) => {
+                write!(f, "{}", crate::tr!("payload_schema.violations"))?;
+                for violation in violations {
+                    write!(f, "\n  {}", violation)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "type": "object",
+        "required": ["version", "files"],
+        "properties": {
+            "version": {"type": "string"},
+            "files": {"type": "array", "items": {"type": "string"}}
+        }
+    }"#;
+
+    #[test]
+    fn test_valid_payload() {
+        let schema = JsonSchema::from_str(SCHEMA).unwrap();
+        let manifest = br#"{"version": "1.2", "files": ["a.png"]}"#;
+        assert!(schema.validate(manifest).is_ok());
+    }
+
+    #[test]
+    fn test_violations() {
+        let schema = JsonSchema::from_str(SCHEMA).unwrap();
+        let manifest = br#"{"version": 12, "files": ["a.png", 3]}"#;
+        let paths: Vec<String> = schema
+            .violations(manifest)
+            .unwrap()
+            .into_iter()
+            .map(|v| v.path)
+            .collect();
+        assert_eq!(paths, ["/files/1", "/version"]);
+        assert!(schema.validate(manifest).is_err());
+
+        let missing = schema.violations(br#"{"version": "1.2"}"#).unwrap();
+        assert_eq!(missing.len(), 1);
+        assert!(missing[0].to_string().starts_with("(root): "));
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(JsonSchema::from_str("not json").is_err());
+        assert!(JsonSchema::from_str(r#"{"type": 5}"#).is_err());
+        let schema = JsonSchema::from_str(SCHEMA).unwrap();
+        assert!(schema.validate(b"\x89PNG").is_err());
+    }
+}
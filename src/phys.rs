@@ -0,0 +1,145 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, Error, Result};
+
+const METERS_PER_INCH: f64 = 0.0254;
+
+/// The unit of the pixel density in a `pHYs` chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    /// Only the aspect ratio is defined.
+    Unknown = 0,
+    Meter = 1,
+}
+
+/// The intended pixel size or aspect ratio stored in a `pHYs` chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhysicalDimensions {
+    pub pixels_per_unit_x: u32,
+    pub pixels_per_unit_y: u32,
+    pub unit: Unit,
+}
+
+impl TryFrom<&Chunk> for PhysicalDimensions {
+    type Error = Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        if chunk.chunk_type().bytes() != PhysicalDimensions::CHUNK_TYPE {
+            return Err(Box::new(PhysError::WrongChunkType(
+                chunk.chunk_type().to_string(),
+            )));
+        }
+
+        let data = chunk.data();
+        if data.len() != PhysicalDimensions::LENGTH {
+            return Err(Box::new(PhysError::InvalidLength(data.len())));
+        }
+
+        let unit = match data[8] {
+            0 => Unit::Unknown,
+            1 => Unit::Meter,
+            unit => return Err(Box::new(PhysError::UnknownUnit(unit))),
+        };
+        Ok(PhysicalDimensions {
+            pixels_per_unit_x: u32::from_be_bytes(data[0..4].try_into()?),
+            pixels_per_unit_y: u32::from_be_bytes(data[4..8].try_into()?),
+            unit,
+        })
+    }
+}
+
+impl Display for PhysicalDimensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.dpi() {
+            Some((x, y)) if x.round() == y.round() => write!(f, "{:.0} dpi", x),
+            Some((x, y)) => write!(f, "{:.0}x{:.0} dpi", x, y),
+            None => write!(
+                f,
+                "aspect ratio {}:{}",
+                self.pixels_per_unit_x, self.pixels_per_unit_y
+            ),
+        }
+    }
+}
+
+impl PhysicalDimensions {
+    pub const CHUNK_TYPE: [u8; 4] = *b"pHYs";
+    const LENGTH: usize = 9;
+
+    /// The same density in both directions, converted from dots per inch.
+    pub fn from_dpi(dpi: u32) -> PhysicalDimensions {
+        let pixels_per_meter = (dpi as f64 / METERS_PER_INCH).round() as u32;
+        PhysicalDimensions {
+            pixels_per_unit_x: pixels_per_meter,
+            pixels_per_unit_y: pixels_per_meter,
+            unit: Unit::Meter,
+        }
+    }
+    /// The horizontal and vertical density in dots per inch, if the unit is
+    /// known.
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        match self.unit {
+            Unit::Meter => Some((
+                self.pixels_per_unit_x as f64 * METERS_PER_INCH,
+                self.pixels_per_unit_y as f64 * METERS_PER_INCH,
+            )),
+            Unit::Unknown => None,
+        }
+    }
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = Vec::with_capacity(PhysicalDimensions::LENGTH);
+        data.extend_from_slice(&self.pixels_per_unit_x.to_be_bytes());
+        data.extend_from_slice(&self.pixels_per_unit_y.to_be_bytes());
+        data.push(self.unit as u8);
+        Chunk::new(ChunkType::from_str("pHYs").unwrap(), data)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PhysError {
+    WrongChunkType(String),
+    InvalidLength(usize),
+    UnknownUnit(u8),
+}
+impl std::error::Error for PhysError {}
+impl Display for PhysError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            PhysError::WrongChunkType(found) => crate::tr!("phys.wrong_chunk_type", found = found),
+            PhysError::InvalidLength(length) => crate::tr!("phys.invalid_length", length = length),
+            PhysError::UnknownUnit(unit) => crate::tr!("phys.unknown_unit", unit = unit),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dpi_round_trip() {
+        let phys = PhysicalDimensions::from_dpi(300);
+        assert_eq!(phys.pixels_per_unit_x, 11811);
+        let parsed = PhysicalDimensions::try_from(&phys.to_chunk()).unwrap();
+        assert_eq!(parsed, phys);
+        assert_eq!(parsed.to_string(), "300 dpi");
+    }
+
+    #[test]
+    fn test_unknown_unit() {
+        let phys = PhysicalDimensions {
+            pixels_per_unit_x: 2,
+            pixels_per_unit_y: 1,
+            unit: Unit::Unknown,
+        };
+        assert!(phys.dpi().is_none());
+        assert_eq!(phys.to_string(), "aspect ratio 2:1");
+
+        let mut data = phys.to_chunk().data().to_vec();
+        data[8] = 5;
+        let chunk = Chunk::new(ChunkType::from_str("pHYs").unwrap(), data);
+        assert!(PhysicalDimensions::try_from(&chunk).is_err());
+    }
+}
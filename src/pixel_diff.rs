@@ -0,0 +1,176 @@
+use std::fmt::Display;
+
+use crate::{encoder, image_data::PixelBuffer, png::Png, Result};
+
+/// How the pixels of two images differ once decoded, regardless of how
+/// they are stored: compression, filtering, chunk layout, color type and
+/// metadata are all ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PixelDiff {
+    width: u32,
+    height: u32,
+    /// The largest difference in any RGBA channel, per pixel, at 16 bits.
+    deltas: Vec<u16>,
+}
+
+/// Compares two decoded images pixel by pixel as 16-bit RGBA.
+pub fn compare(a: &PixelBuffer, b: &PixelBuffer) -> Result<PixelDiff> {
+    if (a.width(), a.height()) != (b.width(), b.height()) {
+        return Err(Box::new(PixelDiffError::DimensionMismatch(
+            (a.width(), a.height()),
+            (b.width(), b.height()),
+        )));
+    }
+    let mut deltas = Vec::with_capacity(a.width() as usize * a.height() as usize);
+    for y in 0..a.height() {
+        for x in 0..a.width() {
+            let (pa, pb) = (a.rgba(x, y), b.rgba(x, y));
+            let delta = (0..4).map(|i| pa[i].abs_diff(pb[i])).max().unwrap_or(0);
+            deltas.push(delta);
+        }
+    }
+    Ok(PixelDiff {
+        width: a.width(),
+        height: a.height(),
+        deltas,
+    })
+}
+
+impl PixelDiff {
+    pub fn is_identical(&self) -> bool {
+        self.deltas.iter().all(|d| *d == 0)
+    }
+    pub fn total_pixels(&self) -> usize {
+        self.deltas.len()
+    }
+    pub fn differing_pixels(&self) -> usize {
+        self.deltas.iter().filter(|d| **d > 0).count()
+    }
+    /// The largest difference in any channel of any pixel, at 16 bits.
+    pub fn max_delta(&self) -> u16 {
+        self.deltas.iter().copied().max().unwrap_or(0)
+    }
+    /// The smallest rectangle holding every differing pixel, as inclusive
+    /// `(left, top, right, bottom)` coordinates.
+    pub fn bounds(&self) -> Option<(u32, u32, u32, u32)> {
+        let width = self.width as usize;
+        let mut differing = self
+            .deltas
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| **d > 0)
+            .map(|(i, _)| ((i % width) as u32, (i / width) as u32));
+        let (x, y) = differing.next()?;
+        Some(
+            differing.fold((x, y, x, y), |(left, top, right, bottom), (x, y)| {
+                (left.min(x), top.min(y), right.max(x), bottom.max(y))
+            }),
+        )
+    }
+    /// An image of the differences: differing pixels are solid red and the
+    /// rest show `base` as faded grayscale for orientation.
+    pub fn to_png(&self, base: &PixelBuffer) -> Result<Png> {
+        let mut pixels = Vec::with_capacity(self.deltas.len() * 3);
+        for (i, delta) in self.deltas.iter().enumerate() {
+            if *delta > 0 {
+                pixels.extend_from_slice(&[255, 0, 0]);
+                continue;
+            }
+            let (x, y) = (
+                (i % self.width as usize) as u32,
+                (i / self.width as usize) as u32,
+            );
+            let [r, g, b, _] = base.rgba(x, y);
+            let gray = (((r as u32 + g as u32 + b as u32) / 3) >> 8) as u8;
+            let faded = 128 + gray / 2;
+            pixels.extend_from_slice(&[faded, faded, faded]);
+        }
+        encoder::rgb(self.width, self.height, &pixels)
+    }
+}
+
+impl Display for PixelDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_identical() {
+            return write!(f, "Pixels identical ({} pixels)", self.total_pixels());
+        }
+        let differing = self.differing_pixels();
+        write!(
+            f,
+            "{} of {} pixels differ ({:.2}%), max channel difference {}",
+            differing,
+            self.total_pixels(),
+            differing as f64 * 100.0 / self.total_pixels() as f64,
+            self.max_delta()
+        )?;
+        if let Some((left, top, right, bottom)) = self.bounds() {
+            write!(f, ", within ({},{})-({},{})", left, top, right, bottom)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PixelDiffError {
+    DimensionMismatch((u32, u32), (u32, u32)),
+}
+impl std::error::Error for PixelDiffError {}
+impl Display for PixelDiffError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            PixelDiffError::DimensionMismatch((aw, ah), (bw, bh)) => crate::tr!(
+                "pixel_diff.dimension_mismatch",
+                a_width = aw,
+                a_height = ah,
+                b_width = bw,
+                b_height = bh
+            ),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::MetadataMap;
+    use crate::png::tests::PNG_FILE;
+
+    fn decode(png: &Png) -> PixelBuffer {
+        png.decode_pixels().unwrap()
+    }
+
+    #[test]
+    fn test_metadata_and_rechunking_keep_pixels() {
+        let original = Png::try_from(&PNG_FILE[..]).unwrap();
+        let mut edited = original.clone();
+        edited.append_chunk(MetadataMap::text_chunk("Author", "someone").unwrap());
+        edited.rechunk_idat(100).unwrap();
+
+        let diff = compare(&decode(&original), &decode(&edited)).unwrap();
+        assert!(diff.is_identical());
+        assert_eq!(diff.total_pixels(), 50 * 50);
+        assert_eq!(diff.bounds(), None);
+    }
+
+    #[test]
+    fn test_differences() {
+        let rgb: Vec<u8> = (0..4 * 3).collect();
+        let a = encoder::rgb(2, 2, &rgb).unwrap();
+        let b = encoder::solid_color(2, 2, [0, 1, 2]).unwrap();
+
+        let diff = compare(&decode(&a), &decode(&b)).unwrap();
+        assert_eq!(diff.differing_pixels(), 3);
+        assert_eq!(diff.bounds(), Some((0, 0, 1, 1)));
+        assert_eq!(diff.max_delta(), 9 * 257);
+        let image = diff.to_png(&decode(&a)).unwrap();
+        assert_eq!(&decode(&image).row(0)[3..6], [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_dimension_mismatch() {
+        let a = encoder::solid_color(2, 2, [0, 0, 0]).unwrap();
+        let b = encoder::solid_color(2, 3, [0, 0, 0]).unwrap();
+        assert!(compare(&decode(&a), &decode(&b)).is_err());
+    }
+}
@@ -1,32 +1,50 @@
-use std::{fmt::Display, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+    str::FromStr,
+};
 
-use crate::{chunk::Chunk, chunk_type::ChunkType, Error, Result};
+use crate::{
+    archive::Archive,
+    chunk::{Chunk, RawChunk},
+    chunk_type::{ChunkType, ChunkTypeQuery},
+    color::{Chromaticities, Gamma, RenderingIntent},
+    compact::{CompactOptions, CompactReport},
+    exif::Exif,
+    history::History,
+    iccp::IccProfile,
+    ihdr::Ihdr,
+    image_data::{self, PixelBuffer},
+    labels::LabelIndex,
+    matcher::ChunkTypeMatcher,
+    merge::{self, Conflict, ConflictKey, MergePolicy, MergeReport},
+    ordering::{self, InsertionPolicy},
+    palette::Palette,
+    parse_options::ParseOptions,
+    phys::PhysicalDimensions,
+    progress::ProgressEvent,
+    transparency::{Background, Transparency},
+    Error, Result,
+};
 
+#[derive(Debug, Clone)]
 pub struct Png {
     chunks: Vec<Chunk>,
+    /// Where each chunk type is in `chunks`, in file order. Every method
+    /// that changes `chunks` rebuilds it.
+    index: HashMap<ChunkType, Vec<usize>>,
+    trailer: Vec<u8>,
+    insertion_policy: InsertionPolicy,
 }
 
 impl TryFrom<&[u8]> for Png {
     type Error = Error;
 
     fn try_from(bytes: &[u8]) -> Result<Self> {
-        let signature_bytes = &bytes[..Png::STANDARD_HEADER.len()];
-
-        if Png::STANDARD_HEADER != signature_bytes {
-            return Err(Box::from(PngError::InvalidFileSignature));
-        }
-
-        let mut idx = Png::STANDARD_HEADER.len();
-        let mut chunks = Vec::new();
-
-        while idx < bytes.len() {
-            let chunk_bytes = &bytes[idx..];
-            let chunk = Chunk::try_from(chunk_bytes)?;
-            idx += chunk.length() + Chunk::METADATA_SIZE;
-            chunks.push(chunk);
-        }
-
-        Ok(Png { chunks })
+        Png::parse(bytes, &ParseOptions::default(), &mut |_| {})
     }
 }
 
@@ -34,7 +52,11 @@ impl Display for Png {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Png {{",)?;
         for chunk in &self.chunks {
-            writeln!(f, "  {}", chunk.chunk_type())?;
+            if chunk.is_crc_valid() {
+                writeln!(f, "  {}", chunk.chunk_type())?;
+            } else {
+                writeln!(f, "  {} (bad CRC)", chunk.chunk_type())?;
+            }
         }
         writeln!(f, "}}",)?;
         Ok(())
@@ -42,72 +64,1142 @@ impl Display for Png {
 }
 
 impl Png {
-    const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+    /// The eight bytes every PNG file starts with.
+    pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
 
+    /// Parses leniently: chunks with bad CRCs or malformed types are kept
+    /// verbatim instead of rejected, so untouched chunks round-trip exactly.
+    pub fn try_from_raw(bytes: &[u8]) -> Result<Png> {
+        Png::parse(bytes, &ParseOptions::raw(), &mut |_| {})
+    }
+    /// Parses with the chunk validation described by `options`.
+    pub fn parse_with_options(bytes: &[u8], options: &ParseOptions) -> Result<Png> {
+        Png::parse(bytes, options, &mut |_| {})
+    }
+    /// Parses as much of a truncated file as possible, returning the chunks
+    /// that were read in full and, if the file was cut short, where. Bytes
+    /// after the last complete chunk are dropped. Other errors are returned
+    /// as usual.
+    pub fn parse_partial(
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<(Png, Option<Truncation>)> {
+        Png::parse_chunks(bytes, options, &mut |_| {}, true)
+    }
+    /// Like [`Png::parse_with_options`], reporting progress after each chunk.
+    pub fn parse_with_progress(
+        bytes: &[u8],
+        options: &ParseOptions,
+        progress: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<Png> {
+        Png::parse(bytes, options, progress)
+    }
     pub fn from_chunks(chunks: Vec<Chunk>) -> Png {
-        Png { chunks }
+        let mut png = Png {
+            chunks,
+            index: HashMap::new(),
+            trailer: Vec::new(),
+            insertion_policy: InsertionPolicy::default(),
+        };
+        png.reindex();
+        png
+    }
+    pub fn insertion_policy(&self) -> InsertionPolicy {
+        self.insertion_policy
     }
+    pub fn set_insertion_policy(&mut self, policy: InsertionPolicy) {
+        self.insertion_policy = policy;
+    }
+    /// Adds a chunk at the last position the specification allows for its
+    /// type, which for most chunks is just before `IEND`. The position only
+    /// depends on the chunks already there, so chunks appended one after
+    /// another keep that order and the same edits always give the same file.
     pub fn append_chunk(&mut self, chunk: Chunk) {
-        self.chunks.push(chunk);
+        let positions = ordering::legal_positions(&self.chunks, &chunk.chunk_type().bytes());
+        log::debug!(
+            "Appending {} chunk at index {}",
+            chunk.chunk_type(),
+            positions.end()
+        );
+        self.chunks.insert(*positions.end(), chunk);
+        self.reindex();
+    }
+    /// Inserts a chunk at `index`. If that would break the specification's
+    /// ordering rules, the chunk is moved to the nearest legal position, or
+    /// rejected under [`InsertionPolicy::Strict`].
+    pub fn insert_chunk(&mut self, index: usize, chunk: Chunk) -> Result<()> {
+        if index > self.chunks.len() {
+            return Err(Box::new(PngError::ChunkNotFound));
+        }
+        let positions = ordering::legal_positions(&self.chunks, &chunk.chunk_type().bytes());
+        let index = match self.insertion_policy {
+            _ if positions.contains(&index) => index,
+            InsertionPolicy::Lenient => {
+                let legal = index.clamp(*positions.start(), *positions.end());
+                log::debug!(
+                    "Index {} is not allowed for {} chunk, inserting at {}",
+                    index,
+                    chunk.chunk_type(),
+                    legal
+                );
+                legal
+            }
+            InsertionPolicy::Strict => {
+                return Err(Box::new(PngError::IllegalPlacement(
+                    chunk.chunk_type().to_string(),
+                    index,
+                )))
+            }
+        };
+        self.chunks.insert(index, chunk);
+        self.reindex();
+        Ok(())
+    }
+    /// Adds `chunk`, deciding with `policy` what to do if the file already
+    /// has a chunk of its type.
+    pub fn write_chunk(&mut self, chunk: Chunk, policy: WritePolicy) -> Result<()> {
+        let existing = self.indices_of(chunk.chunk_type()).first();
+        match (existing, policy) {
+            (Some(_), WritePolicy::Fail) => Err(Box::new(PngError::AlreadyExists(
+                chunk.chunk_type().to_string(),
+            ))),
+            (Some(_), WritePolicy::Overwrite) => {
+                self.replace_first(chunk.chunk_type().clone(), chunk)?;
+                Ok(())
+            }
+            _ => {
+                self.append_chunk(chunk);
+                Ok(())
+            }
+        }
+    }
+    #[deprecated(note = "use Png::replace_first, which also takes a &ChunkType")]
+    pub fn replace_chunk_by_type(&mut self, chunk_type: &str, new_chunk: Chunk) -> Result<Chunk> {
+        self.replace_first(chunk_type, new_chunk)
     }
-    pub fn remove_chunk(&mut self, chunk_type: &str) -> Result<Chunk> {
+    /// Puts `new_chunk` where the first chunk of the given type is and returns
+    /// the chunk it replaced. Nothing else moves, so the byte offsets of the
+    /// chunks before it stay the same.
+    pub fn replace_first(
+        &mut self,
+        chunk_type: impl Into<ChunkTypeQuery>,
+        new_chunk: Chunk,
+    ) -> Result<Chunk> {
         let index = self
-            .chunks
-            .iter()
-            .position(|c| (*c).chunk_type().to_string() == chunk_type)
+            .first_index(chunk_type)
+            .ok_or(PngError::ChunkNotFound)?;
+        if new_chunk.chunk_type() != self.chunks[index].chunk_type() {
+            let mut others = self.chunks.clone();
+            others.remove(index);
+            let positions = ordering::legal_positions(&others, &new_chunk.chunk_type().bytes());
+            if !positions.contains(&index) {
+                return Err(Box::new(PngError::IllegalPlacement(
+                    new_chunk.chunk_type().to_string(),
+                    index,
+                )));
+            }
+        }
+        let old_chunk = std::mem::replace(&mut self.chunks[index], new_chunk);
+        self.reindex();
+        Ok(old_chunk)
+    }
+    #[deprecated(note = "use Png::remove_first, which also takes a &ChunkType")]
+    pub fn remove_chunk(&mut self, chunk_type: &str, policy: RemovePolicy) -> Result<Chunk> {
+        self.remove_first(chunk_type, policy)
+    }
+    /// Removes the first chunk of the given type, unless `policy` protects
+    /// it, and returns it.
+    pub fn remove_first(
+        &mut self,
+        chunk_type: impl Into<ChunkTypeQuery>,
+        policy: RemovePolicy,
+    ) -> Result<Chunk> {
+        let index = self
+            .first_index(chunk_type)
             .ok_or(PngError::ChunkNotFound)?;
+        policy.check(self.chunks[index].chunk_type())?;
         let chunk = self.chunks.remove(index);
+        self.reindex();
         Ok(chunk)
     }
+    /// Removes every chunk whose type matches and returns them in file
+    /// order. Nothing is removed if `policy` protects any of them.
+    pub fn remove_chunks_matching(
+        &mut self,
+        matcher: &ChunkTypeMatcher,
+        policy: RemovePolicy,
+    ) -> Result<Vec<Chunk>> {
+        for chunk in self.chunks_matching(matcher) {
+            policy.check(chunk.chunk_type())?;
+        }
+        let (removed, kept) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|c| matcher.matches(c.chunk_type()));
+        self.chunks = kept;
+        self.reindex();
+        Ok(removed)
+    }
+    pub fn remove_chunk_at(&mut self, index: usize) -> Result<Chunk> {
+        if index >= self.chunks.len() {
+            return Err(Box::new(PngError::ChunkNotFound));
+        }
+        let chunk = self.chunks.remove(index);
+        self.reindex();
+        Ok(chunk)
+    }
+    /// Keeps only the chunks for which `f` returns true, preserving order.
+    pub fn retain_chunks<F: FnMut(&Chunk) -> bool>(&mut self, f: F) {
+        self.chunks.retain(f);
+        self.reindex();
+    }
     pub fn header(&self) -> &[u8; 8] {
         &Png::STANDARD_HEADER
     }
     pub fn chunks(&self) -> &[Chunk] {
         &self.chunks
     }
+    /// Bytes found after the `IEND` chunk, which are ignored by decoders.
+    pub fn trailer(&self) -> &[u8] {
+        &self.trailer
+    }
+    pub fn set_trailer(&mut self, trailer: Vec<u8>) {
+        self.trailer = trailer;
+    }
+    #[deprecated(note = "use Png::chunk, which also takes a &ChunkType")]
     pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
-        let chunk_type = ChunkType::from_str(chunk_type).ok()?;
-        self.chunks.iter().find(|c| *c.chunk_type() == chunk_type)
+        self.chunk(chunk_type)
+    }
+    /// The first chunk of the given type.
+    pub fn chunk(&self, chunk_type: impl Into<ChunkTypeQuery>) -> Option<&Chunk> {
+        self.first_index(chunk_type).map(|i| &self.chunks[i])
+    }
+    /// The indices into [`Png::chunks`] of every chunk of `chunk_type`, in
+    /// file order, looked up without scanning the chunks.
+    pub fn indices_of(&self, chunk_type: &ChunkType) -> &[usize] {
+        self.index.get(chunk_type).map_or(&[], Vec::as_slice)
+    }
+    fn first_index(&self, chunk_type: impl Into<ChunkTypeQuery>) -> Option<usize> {
+        let query = chunk_type.into();
+        self.indices_of(query.chunk_type()?).first().copied()
+    }
+    fn reindex(&mut self) {
+        self.index.clear();
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            let indices = self.index.entry(chunk.chunk_type().clone()).or_default();
+            indices.push(i);
+        }
+    }
+    /// The index of the chunk whose bytes in the parsed file spanned
+    /// `offset`. Chunks added since parsing have no offset and never match.
+    pub fn chunk_index_at_offset(&self, offset: usize) -> Option<usize> {
+        self.chunks.iter().position(|c| {
+            c.offset().is_some_and(|start| {
+                (start..start + c.length() + Chunk::METADATA_SIZE).contains(&offset)
+            })
+        })
+    }
+    pub fn chunk_matching(&self, matcher: &ChunkTypeMatcher) -> Option<&Chunk> {
+        self.index_matching(matcher).map(|i| &self.chunks[i])
+    }
+    /// The index of the first chunk whose type matches, found through
+    /// [`Png::indices_of`] when the pattern is an exact type.
+    pub fn index_matching(&self, matcher: &ChunkTypeMatcher) -> Option<usize> {
+        match matcher.exact_type() {
+            Some(chunk_type) => self.indices_of(&chunk_type).first().copied(),
+            None => self
+                .chunks
+                .iter()
+                .position(|c| matcher.matches(c.chunk_type())),
+        }
+    }
+    pub fn chunks_matching<'a>(
+        &'a self,
+        matcher: &'a ChunkTypeMatcher,
+    ) -> impl Iterator<Item = &'a Chunk> {
+        self.chunks
+            .iter()
+            .filter(|c| matcher.matches(c.chunk_type()))
+    }
+    /// The distinct chunk types in the file, in the order they first appear.
+    pub fn chunk_types(&self) -> Vec<&ChunkType> {
+        let mut types: Vec<&ChunkType> = Vec::new();
+        for chunk in &self.chunks {
+            if !types.contains(&chunk.chunk_type()) {
+                types.push(chunk.chunk_type());
+            }
+        }
+        types
+    }
+    #[deprecated(note = "use Png::chunks_of, which also takes a &ChunkType")]
+    pub fn chunks_by_type<'a>(&'a self, chunk_type: &'a str) -> impl Iterator<Item = &'a Chunk> {
+        self.chunks_of(chunk_type)
+    }
+    /// Every chunk of the given type, in file order.
+    pub fn chunks_of(&self, chunk_type: impl Into<ChunkTypeQuery>) -> impl Iterator<Item = &Chunk> {
+        let indices = match chunk_type.into().chunk_type() {
+            Some(chunk_type) => self.indices_of(chunk_type),
+            None => &[],
+        };
+        indices.iter().map(|&i| &self.chunks[i])
+    }
+    pub fn ihdr(&self) -> Option<Result<Ihdr>> {
+        self.chunk("IHDR").map(Ihdr::try_from)
+    }
+    pub fn icc_profile(&self) -> Option<Result<IccProfile>> {
+        self.chunk("iCCP").map(IccProfile::try_from)
+    }
+    /// Replaces the existing `iCCP` chunk, or inserts one directly after
+    /// `IHDR` so that it precedes `PLTE` and `IDAT` as the spec requires.
+    pub fn set_icc_profile(&mut self, profile: &IccProfile) -> Result<()> {
+        let chunk = profile.to_chunk()?;
+        let existing = self
+            .chunks
+            .iter()
+            .position(|c| c.chunk_type().bytes() == IccProfile::CHUNK_TYPE);
+
+        match existing {
+            Some(index) => self.chunks[index] = chunk,
+            None => {
+                let index = self
+                    .chunks
+                    .iter()
+                    .position(|c| &c.chunk_type().bytes() == b"IHDR")
+                    .map_or(0, |i| i + 1);
+                self.chunks.insert(index, chunk);
+                self.reindex();
+            }
+        }
+        Ok(())
+    }
+    /// Copies ancillary chunks from `other`, each placed where the ordering
+    /// rules allow. Text chunks conflict with the destination's by keyword,
+    /// and `sRGB` and `iCCP` with each other. Unsafe-to-copy chunks that may
+    /// depend on the source's image data are left out unless both images
+    /// have the same critical chunks or the policy forces them.
+    pub fn merge_ancillary_from(&mut self, other: &Png, policy: &MergePolicy) -> MergeReport {
+        let same_image = self.critical_bytes() == other.critical_bytes();
+        let existing: Vec<ConflictKey> = self.chunks.iter().map(ConflictKey::of).collect();
+        let mut replaced: Vec<ConflictKey> = Vec::new();
+        let mut report = MergeReport::default();
+
+        for chunk in other.chunks.iter().filter(|c| policy.selects(c)) {
+            let chunk_type = chunk.chunk_type().clone();
+            if !policy.force_unsafe && !merge::is_transplantable(chunk, same_image) {
+                report.unsafe_to_copy.push(chunk_type);
+                continue;
+            }
+            let key = ConflictKey::of(chunk);
+            if existing.contains(&key) {
+                match policy.on_conflict {
+                    Conflict::Keep => {
+                        report.kept_existing.push(chunk_type);
+                        continue;
+                    }
+                    Conflict::Replace if !replaced.contains(&key) => {
+                        self.retain_chunks(|c| ConflictKey::of(c) != key);
+                        replaced.push(key);
+                    }
+                    Conflict::Replace | Conflict::Add => {}
+                }
+            }
+            self.append_chunk(chunk.clone());
+            report.copied.push(chunk_type);
+        }
+        report
+    }
+    /// Checks the CRC of every chunk, for PNGs parsed with
+    /// [`ParseOptions::lazy_crc`].
+    pub fn verify_all(&self) -> Result<()> {
+        self.chunks.iter().try_for_each(Chunk::verify)
+    }
+    /// Reads the file at `path` only as far as the first chunk of type
+    /// `chunk_type`. CRCs are not checked while reading, so use
+    /// [`Chunk::checked_data`] on the chunk that is found.
+    pub fn find_chunk_streaming(path: &Path, chunk_type: &str) -> Result<Option<Chunk>> {
+        let options = ParseOptions {
+            lazy_crc: true,
+            ..ParseOptions::default()
+        };
+        let Ok(chunk_type) = ChunkType::from_str(chunk_type) else {
+            return Ok(None);
+        };
+        let matcher = ChunkTypeMatcher::exact(&chunk_type);
+        Png::find_chunk_streaming_with_options(path, &matcher, &options)
+    }
+    /// Like [`Png::find_chunk_streaming`], for the first chunk whose type
+    /// `matcher` accepts.
+    pub fn find_chunk_streaming_with_options(
+        path: &Path,
+        matcher: &ChunkTypeMatcher,
+        options: &ParseOptions,
+    ) -> Result<Option<Chunk>> {
+        let reader = BufReader::new(File::open(path)?);
+        for chunk in scan_with_options(reader, options) {
+            let chunk = chunk?;
+            if matcher.matches(chunk.chunk_type()) {
+                return Ok(Some(chunk));
+            }
+        }
+        Ok(None)
+    }
+    /// Parses like [`Png::try_from`], but splits the file into chunks first
+    /// and then checks their CRCs across threads.
+    #[cfg(feature = "parallel")]
+    pub fn try_from_parallel(bytes: &[u8]) -> Result<Png> {
+        use rayon::prelude::*;
+
+        let options = ParseOptions {
+            lazy_crc: true,
+            ..ParseOptions::default()
+        };
+        let png = Png::parse_with_options(bytes, &options)?;
+        // Boxed errors are not Send, so find the bad chunk in parallel and
+        // build the error on this thread.
+        let invalid = png.chunks.par_iter().position_any(|c| !c.is_crc_valid());
+        if let Some(index) = invalid {
+            png.chunks[index].verify()?;
+        }
+        Ok(png)
+    }
+    pub fn exif(&self) -> Option<Result<Exif>> {
+        self.chunk("eXIf").map(Exif::try_from)
+    }
+    pub fn gamma(&self) -> Option<Result<Gamma>> {
+        self.chunk("gAMA").map(Gamma::try_from)
+    }
+    pub fn set_gamma(&mut self, gamma: Gamma) {
+        self.replace_or_append(gamma.to_chunk());
+    }
+    pub fn chromaticities(&self) -> Option<Result<Chromaticities>> {
+        self.chunk("cHRM").map(Chromaticities::try_from)
+    }
+    pub fn set_chromaticities(&mut self, chromaticities: Chromaticities) {
+        self.replace_or_append(chromaticities.to_chunk());
+    }
+    pub fn physical_dimensions(&self) -> Option<Result<PhysicalDimensions>> {
+        self.chunk("pHYs").map(PhysicalDimensions::try_from)
+    }
+    pub fn set_physical_dimensions(&mut self, dimensions: PhysicalDimensions) {
+        self.replace_or_append(dimensions.to_chunk());
+    }
+    pub fn palette(&self) -> Option<Result<Palette>> {
+        self.chunk("PLTE").map(Palette::try_from)
+    }
+    /// Replaces the `PLTE` chunk, or inserts one after the chunks that must
+    /// precede it and before `tRNS`, `bKGD` and `IDAT`.
+    pub fn set_palette(&mut self, palette: &Palette) {
+        self.replace_or_append(palette.to_chunk());
+    }
+    /// The `tRNS` chunk, interpreted according to the color type in `IHDR`.
+    pub fn transparency(&self) -> Option<Result<Transparency>> {
+        let chunk = self.chunk("tRNS")?;
+        Some(
+            self.color_type()
+                .and_then(|t| Transparency::from_chunk(chunk, t)),
+        )
+    }
+    /// The `bKGD` chunk, interpreted according to the color type in `IHDR`.
+    pub fn background(&self) -> Option<Result<Background>> {
+        let chunk = self.chunk("bKGD")?;
+        Some(
+            self.color_type()
+                .and_then(|t| Background::from_chunk(chunk, t)),
+        )
+    }
+    /// Moves the chunks into the order the specification suggests, from
+    /// `IHDR` through the color space chunks, `PLTE` and the image data to
+    /// `IEND`, without changing any of them. Returns whether any chunk moved.
+    pub fn reorder_canonical(&mut self) -> bool {
+        let before = self.chunk_types().into_iter().cloned().collect::<Vec<_>>();
+        self.chunks = ordering::canonical_order(std::mem::take(&mut self.chunks));
+        self.reindex();
+        self.chunk_types().into_iter().ne(before.iter())
+    }
+    /// Splits and merges the IDAT chunks so that each holds at most
+    /// `max_len` bytes, and all but the last exactly that many. The
+    /// compressed stream is kept byte for byte, so nothing is recompressed.
+    pub fn rechunk_idat(&mut self, max_len: usize) -> Result<()> {
+        if max_len == 0 || max_len > Chunk::MAX_LENGTH {
+            return Err(Box::new(PngError::InvalidIdatSize(max_len)));
+        }
+        let is_idat = |c: &Chunk| &c.chunk_type().bytes() == b"IDAT";
+        let Some(first) = self.chunks.iter().position(is_idat) else {
+            return Ok(());
+        };
+        let count = self.chunks[first..]
+            .iter()
+            .take_while(|c| is_idat(c))
+            .count();
+        if self.chunks[first + count..].iter().any(is_idat) {
+            return Err(Box::new(PngError::NonConsecutiveIdat));
+        }
+
+        let stream: Vec<u8> = self.chunks[first..first + count]
+            .iter()
+            .flat_map(|c| c.data().iter().copied())
+            .collect();
+        let chunk_type = ChunkType::from_bytes_unchecked(*b"IDAT");
+        let mut rechunked: Vec<Chunk> = stream
+            .chunks(max_len)
+            .map(|data| Chunk::new(chunk_type.clone(), data.to_vec()))
+            .collect();
+        if rechunked.is_empty() {
+            rechunked.push(Chunk::new(chunk_type, Vec::new()));
+        }
+        log::debug!("Rechunking {} IDAT chunks into {}", count, rechunked.len());
+        self.chunks.splice(first..first + count, rechunked);
+        self.reindex();
+        Ok(())
+    }
+    /// Drops `tEXt` chunks that repeat an earlier one byte for byte, drops
+    /// ancillary chunks with no data and consolidates the image data with
+    /// [`Png::rechunk_idat`]. Repeated keywords with different text are kept.
+    pub fn compact(&mut self, options: &CompactOptions) -> Result<CompactReport> {
+        let size_before = self.encoded_size();
+        let count_idat = |png: &Png| {
+            png.chunks
+                .iter()
+                .filter(|c| &c.chunk_type().bytes() == b"IDAT")
+                .count()
+        };
+        let mut report = CompactReport {
+            idat_before: count_idat(self),
+            ..CompactReport::default()
+        };
+
+        let mut texts = HashSet::new();
+        self.retain_chunks(|chunk| {
+            let chunk_type = chunk.chunk_type();
+            if !chunk_type.is_critical() && chunk.data().is_empty() {
+                report.empty_chunks += 1;
+                return false;
+            }
+            if &chunk_type.bytes() == b"tEXt" && !texts.insert(chunk.data().to_vec()) {
+                let keyword = chunk.data().split(|b| *b == 0).next().unwrap_or_default();
+                report
+                    .duplicate_text
+                    .push(keyword.iter().map(|b| *b as char).collect());
+                return false;
+            }
+            true
+        });
+        self.rechunk_idat(options.max_idat_size)?;
+
+        report.idat_after = count_idat(self);
+        report.bytes_saved = size_before as i64 - self.encoded_size() as i64;
+        Ok(report)
+    }
+    /// Inflates and unfilters the image data. See [`image_data::decode`].
+    pub fn decode_pixels(&self) -> Result<PixelBuffer> {
+        image_data::decode(self)
+    }
+    /// Replaces the image data with `pixels`, stored without interlacing
+    /// in a single `IDAT` chunk where the first one was.
+    pub fn set_pixels(&mut self, pixels: &PixelBuffer) -> Result<()> {
+        let ihdr = self.ihdr().ok_or(PngError::ChunkNotFound)??;
+        let is_idat = |c: &Chunk| &c.chunk_type().bytes() == b"IDAT";
+        // Keep the first IDAT chunk as the place to put the new data.
+        let mut seen = false;
+        self.retain_chunks(|c| !is_idat(c) || !std::mem::replace(&mut seen, true));
+        let first = self
+            .chunks
+            .iter()
+            .position(is_idat)
+            .ok_or(PngError::ChunkNotFound)?;
+        let idat = Chunk::new(
+            ChunkType::from_bytes_unchecked(*b"IDAT"),
+            image_data::encode(pixels)?,
+        );
+        self.replace_or_append(
+            Ihdr {
+                width: pixels.width(),
+                height: pixels.height(),
+                bit_depth: pixels.bit_depth(),
+                color_type: pixels.color_type(),
+                interlace_method: 0,
+                ..ihdr
+            }
+            .to_chunk(),
+        );
+        self.chunks[first] = idat;
+        Ok(())
+    }
+    /// The index of the files packed in by [`crate::archive::pack`].
+    pub fn archive(&self) -> Option<Result<Archive>> {
+        self.chunk("arIx").map(Archive::try_from)
+    }
+    pub fn history(&self) -> Option<Result<History>> {
+        self.chunk("hiSt").map(History::try_from)
+    }
+    pub fn set_history(&mut self, history: &History) {
+        self.replace_or_append(history.to_chunk());
+    }
+    pub fn label_index(&self) -> Option<Result<LabelIndex>> {
+        self.chunk("lbIx").map(LabelIndex::try_from)
+    }
+    pub fn set_label_index(&mut self, index: &LabelIndex) {
+        self.replace_or_append(index.to_chunk());
+    }
+    pub fn srgb(&self) -> Option<Result<RenderingIntent>> {
+        self.chunk("sRGB").map(RenderingIntent::try_from)
+    }
+    pub fn set_srgb(&mut self, intent: RenderingIntent) {
+        self.replace_or_append(intent.to_chunk());
+    }
+    /// The length of [`Png::as_bytes`], without serializing.
+    fn encoded_size(&self) -> usize {
+        let chunks: usize = self
+            .chunks
+            .iter()
+            .map(|c| c.data().len() + Chunk::METADATA_SIZE)
+            .sum();
+        Png::STANDARD_HEADER.len() + chunks + self.trailer.len()
+    }
+    /// The serialized critical chunks, which together define the image.
+    fn critical_bytes(&self) -> Vec<Vec<u8>> {
+        self.chunks
+            .iter()
+            .filter(|c| c.chunk_type().is_critical())
+            .map(Chunk::as_bytes)
+            .collect()
+    }
+    fn color_type(&self) -> Result<u8> {
+        let ihdr = self.ihdr().ok_or(PngError::ChunkNotFound)??;
+        Ok(ihdr.color_type)
+    }
+    /// Replaces the first chunk of the same type, or appends `chunk` at the
+    /// last position the ordering rules allow.
+    fn replace_or_append(&mut self, chunk: Chunk) {
+        match self.indices_of(chunk.chunk_type()).first() {
+            Some(&index) => self.chunks[index] = chunk,
+            None => self.append_chunk(chunk),
+        }
+    }
+    fn parse(
+        bytes: &[u8],
+        options: &ParseOptions,
+        progress: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<Png> {
+        let total = bytes.len() as u64;
+        progress(ProgressEvent::Started { total });
+        let png = Png::parse_chunks(
+            bytes,
+            options,
+            &mut |processed| progress(ProgressEvent::Advanced { processed, total }),
+            false,
+        );
+        progress(ProgressEvent::Finished);
+        png.map(|(png, _)| png)
+    }
+    /// With `partial`, stops at the first chunk that runs past the end of
+    /// `bytes` instead of failing.
+    fn parse_chunks(
+        bytes: &[u8],
+        options: &ParseOptions,
+        advance: &mut dyn FnMut(u64),
+        partial: bool,
+    ) -> Result<(Png, Option<Truncation>)> {
+        let mut idx = if bytes.starts_with(&Png::STANDARD_HEADER) {
+            Png::STANDARD_HEADER.len()
+        } else if options.skip_signature {
+            0
+        } else {
+            return Err(Box::new(PngError::bad_signature(bytes)));
+        };
+        options.check_total_size(bytes.len() as u64)?;
+
+        let mut chunks = Vec::new();
+        let mut seen_end = false;
+        let mut truncation = None;
+
+        while idx < bytes.len() {
+            // Older versions of pngme appended chunks after IEND, so keep
+            // reading well-formed chunks there. Anything else is the trailer.
+            if seen_end {
+                // As in ChunkScanner, a chunk over the limits is taken to be
+                // the start of the trailer, before its data is copied out.
+                let within_limits =
+                    bytes
+                        .get(idx..idx + Chunk::LENGTH_SIZE)
+                        .is_some_and(|length| {
+                            let length = u32::from_be_bytes(length.try_into().unwrap());
+                            options.check_chunk_size(length).is_ok()
+                        })
+                        && options.check_chunk_count(chunks.len() + 1).is_ok();
+                if !within_limits {
+                    break;
+                }
+                match RawChunk::try_from(&bytes[idx..]).and_then(Chunk::try_from) {
+                    Ok(chunk) => {
+                        let chunk = chunk.with_offset(idx);
+                        log::debug!(
+                            "Parsed {} chunk after IEND at offset {}, {} bytes",
+                            chunk.chunk_type(),
+                            idx,
+                            chunk.length()
+                        );
+                        idx += chunk.length() + Chunk::METADATA_SIZE;
+                        chunks.push(chunk);
+                        advance(idx as u64);
+                        continue;
+                    }
+                    Err(_) => break,
+                }
+            }
+
+            if partial {
+                truncation = Truncation::at(bytes, idx);
+                if truncation.is_some() {
+                    break;
+                }
+            }
+
+            // Check the length field before the data is copied out.
+            if let Some(length) = bytes.get(idx..idx + Chunk::LENGTH_SIZE) {
+                options.check_chunk_size(u32::from_be_bytes(length.try_into()?))?;
+            }
+            options.check_chunk_count(chunks.len() + 1)?;
+            let chunk = RawChunk::try_from(&bytes[idx..])
+                .and_then(|raw_chunk| Chunk::from_raw_with_options(raw_chunk, options))
+                .inspect_err(|e| log::debug!("Failed to parse chunk at offset {}: {}", idx, e))?
+                .with_offset(idx);
+            log::debug!(
+                "Parsed {} chunk at offset {}, {} bytes",
+                chunk.chunk_type(),
+                idx,
+                chunk.length()
+            );
+            idx += chunk.length() + Chunk::METADATA_SIZE;
+            seen_end = &chunk.chunk_type().bytes() == b"IEND";
+            chunks.push(chunk);
+            advance(idx as u64);
+        }
+
+        if partial && truncation.is_none() && !seen_end {
+            truncation = Some(Truncation::MissingEnd { offset: idx });
+        }
+        let trailer = match truncation {
+            Some(_) => Vec::new(),
+            None => bytes[idx.min(bytes.len())..].to_vec(),
+        };
+        if !trailer.is_empty() {
+            log::debug!("{} trailing bytes at offset {}", trailer.len(), idx);
+        }
+
+        let mut png = Png::from_chunks(chunks);
+        png.trailer = trailer;
+        Ok((png, truncation))
     }
     pub fn as_bytes(&self) -> Vec<u8> {
         let mut bytes = Png::STANDARD_HEADER.to_vec();
         for chunk in &self.chunks {
             bytes.append(&mut chunk.as_bytes());
         }
+        bytes.extend_from_slice(&self.trailer);
         bytes
     }
 }
 
+/// What [`EditSession::save`] does with unsafe-to-copy chunks once the
+/// critical chunks have changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnsafeChunks {
+    /// Leave them out of the saved PNG, as the specification requires.
+    #[default]
+    Drop,
+    /// Keep them, but log a warning for each.
+    Warn,
+}
+
+/// A series of edits to a PNG that remembers the chunks it started with.
+/// The specification says unsafe-to-copy ancillary chunks the editor does not
+/// understand must not survive a change to the critical chunks, since they
+/// may describe image data that is no longer there; [`EditSession::save`]
+/// enforces that.
+#[derive(Debug, Clone)]
+pub struct EditSession {
+    png: Png,
+    original: Png,
+    unsafe_chunks: UnsafeChunks,
+}
+
+impl EditSession {
+    pub fn new(png: Png) -> EditSession {
+        EditSession {
+            original: png.clone(),
+            png,
+            unsafe_chunks: UnsafeChunks::default(),
+        }
+    }
+    pub fn unsafe_chunks(mut self, unsafe_chunks: UnsafeChunks) -> EditSession {
+        self.unsafe_chunks = unsafe_chunks;
+        self
+    }
+    pub fn png(&self) -> &Png {
+        &self.png
+    }
+    pub fn png_mut(&mut self) -> &mut Png {
+        &mut self.png
+    }
+    pub fn critical_changed(&self) -> bool {
+        self.png.critical_bytes() != self.original.critical_bytes()
+    }
+    /// Chunks from the original PNG that may no longer match its image data.
+    /// Chunks added during the session are assumed to be up to date.
+    pub fn stale_chunks(&self) -> Vec<&Chunk> {
+        if !self.critical_changed() {
+            return Vec::new();
+        }
+        let original: Vec<Vec<u8>> = self
+            .original
+            .chunks
+            .iter()
+            .filter(|c| !c.chunk_type().is_critical() && !merge::is_transplantable(c, false))
+            .map(Chunk::as_bytes)
+            .collect();
+        self.png
+            .chunks
+            .iter()
+            .filter(|c| original.contains(&c.as_bytes()))
+            .collect()
+    }
+    /// The PNG to write, with stale chunks handled as configured. What is
+    /// saved becomes the starting point for later edits.
+    pub fn save(&mut self) -> Png {
+        let stale: Vec<Vec<u8>> = self.stale_chunks().iter().map(|c| c.as_bytes()).collect();
+        for chunk in self
+            .png
+            .chunks
+            .iter()
+            .filter(|c| stale.contains(&c.as_bytes()))
+        {
+            match self.unsafe_chunks {
+                UnsafeChunks::Drop => log::warn!(
+                    "Dropping unsafe-to-copy {} chunk because the critical chunks changed",
+                    chunk.chunk_type()
+                ),
+                UnsafeChunks::Warn => log::warn!(
+                    "Unsafe-to-copy {} chunk may not match the changed critical chunks",
+                    chunk.chunk_type()
+                ),
+            }
+        }
+        if self.unsafe_chunks == UnsafeChunks::Drop {
+            self.png.retain_chunks(|c| !stale.contains(&c.as_bytes()));
+        }
+        self.original = self.png.clone();
+        self.png.clone()
+    }
+}
+
+/// Where and how a file parsed with [`Png::parse_partial`] was cut short.
 #[derive(Debug, Clone, PartialEq, Eq)]
-enum PngError {
+pub enum Truncation {
+    /// The file ends part of the way through a chunk. The type is missing
+    /// if the file ends before it, and the length if it ends before that.
+    MidChunk {
+        offset: usize,
+        chunk_type: Option<ChunkType>,
+        length: Option<usize>,
+        available: usize,
+    },
+    /// The file ends between chunks, before `IEND`.
+    MissingEnd { offset: usize },
+}
+
+impl Truncation {
+    /// The truncation, if any, of the chunk starting at `offset`.
+    fn at(bytes: &[u8], offset: usize) -> Option<Truncation> {
+        let rest = &bytes[offset..];
+        let length = rest
+            .first_chunk::<{ Chunk::LENGTH_SIZE }>()
+            .map(|l| u32::from_be_bytes(*l) as usize);
+        if length.is_some_and(|l| l.saturating_add(Chunk::METADATA_SIZE) <= rest.len()) {
+            return None;
+        }
+        let chunk_type = rest
+            .get(Chunk::LENGTH_SIZE..Chunk::LENGTH_SIZE + Chunk::CHUNK_TYPE_SIZE)
+            .map(|t| ChunkType::from_bytes_unchecked(t.try_into().unwrap()));
+        Some(Truncation::MidChunk {
+            offset,
+            chunk_type,
+            length,
+            available: rest.len(),
+        })
+    }
+}
+
+impl Display for Truncation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            Truncation::MidChunk {
+                offset,
+                chunk_type: Some(chunk_type),
+                length: Some(length),
+                available,
+            } => crate::tr!(
+                "png.ends_inside_chunk",
+                chunk_type = chunk_type,
+                offset = offset,
+                available = available,
+                length = length + Chunk::METADATA_SIZE
+            ),
+            Truncation::MidChunk { offset, .. } => {
+                crate::tr!("png.ends_inside_header", offset = offset)
+            }
+            Truncation::MissingEnd { offset } => crate::tr!("png.missing_end", offset = offset),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+/// Reads chunks from `reader` one at a time without building a [`Png`], so a
+/// caller looking for a single chunk can stop as soon as it has been found.
+pub fn scan<R: Read>(reader: R) -> ChunkScanner<R> {
+    scan_with_options(reader, &ParseOptions::default())
+}
+
+pub fn scan_with_options<R: Read>(reader: R, options: &ParseOptions) -> ChunkScanner<R> {
+    ChunkScanner {
+        reader,
+        options: *options,
+        started: false,
+        pending: Vec::new(),
+        seen_end: false,
+        finished: false,
+        offset: 0,
+        chunks: 0,
+    }
+}
+
+/// The iterator returned by [`scan`]. It checks the file signature before the
+/// first chunk, and ends at the end of the input or after the first error.
+/// As when parsing a [`Png`], well-formed chunks after `IEND` are still
+/// yielded and anything else there is treated as a trailer and skipped.
+#[derive(Debug)]
+pub struct ChunkScanner<R> {
+    reader: R,
+    options: ParseOptions,
+    started: bool,
+    /// Bytes read while looking for a signature that turned out to be the
+    /// start of the first chunk.
+    pending: Vec<u8>,
+    seen_end: bool,
+    finished: bool,
+    offset: u64,
+    chunks: usize,
+}
+
+impl<R: Read> ChunkScanner<R> {
+    fn read_signature(&mut self) -> Result<()> {
+        let mut signature = Vec::new();
+        (&mut self.reader)
+            .take(Png::STANDARD_HEADER.len() as u64)
+            .read_to_end(&mut signature)?;
+        if signature == Png::STANDARD_HEADER {
+            self.offset = Png::STANDARD_HEADER.len() as u64;
+        } else if self.options.skip_signature {
+            self.pending = signature;
+        } else {
+            return Err(Box::new(PngError::bad_signature(&signature)));
+        }
+        Ok(())
+    }
+    /// Reads the bytes of the next chunk, stopping short if the input ends.
+    /// The limits are checked on the length field, before the data is read.
+    /// After `IEND`, a chunk over the limits is taken to be the start of the
+    /// trailer and nothing is returned.
+    fn read_chunk_bytes(&mut self) -> Result<Vec<u8>> {
+        let mut bytes = std::mem::take(&mut self.pending);
+        (&mut self.reader)
+            .take(8 - bytes.len() as u64)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() == 8 {
+            let length = u32::from_be_bytes(bytes[..4].try_into().unwrap());
+            let end = self.offset + length as u64 + Chunk::METADATA_SIZE as u64;
+            let limits = self
+                .options
+                .check_chunk_size(length)
+                .and_then(|()| self.options.check_total_size(end))
+                .and_then(|()| self.options.check_chunk_count(self.chunks + 1));
+            match limits {
+                Err(_) if self.seen_end => return Ok(Vec::new()),
+                Err(e) => return Err(e),
+                Ok(()) => {}
+            }
+            let length = length as u64;
+            (&mut self.reader)
+                .take(length + 4)
+                .read_to_end(&mut bytes)?;
+        }
+        Ok(bytes)
+    }
+    fn next_chunk(&mut self) -> Option<Result<Chunk>> {
+        if !self.started {
+            self.started = true;
+            if let Err(e) = self.read_signature() {
+                return Some(Err(e));
+            }
+        }
+        let bytes = match self.read_chunk_bytes() {
+            Ok(bytes) if bytes.is_empty() => return None,
+            Ok(bytes) => bytes,
+            Err(e) => return Some(Err(e)),
+        };
+        let offset = self.offset;
+        self.offset += bytes.len() as u64;
+        let chunk = if self.seen_end {
+            Chunk::try_from(bytes.as_slice())
+                .ok()?
+                .with_offset(offset as usize)
+        } else {
+            match RawChunk::try_from(bytes.as_slice())
+                .and_then(|raw_chunk| Chunk::from_raw_with_options(raw_chunk, &self.options))
+            {
+                Ok(chunk) => chunk.with_offset(offset as usize),
+                Err(e) => {
+                    log::debug!("Failed to read chunk at offset {}: {}", offset, e);
+                    return Some(Err(e));
+                }
+            }
+        };
+        self.chunks += 1;
+        log::debug!(
+            "Read {} chunk at offset {}, {} bytes",
+            chunk.chunk_type(),
+            offset,
+            chunk.length()
+        );
+        self.seen_end |= &chunk.chunk_type().bytes() == b"IEND";
+        Some(Ok(chunk))
+    }
+}
+
+impl<R: Read> Iterator for ChunkScanner<R> {
+    type Item = Result<Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let chunk = self.next_chunk();
+        self.finished = !matches!(chunk, Some(Ok(_)));
+        chunk
+    }
+}
+
+/// What [`Png::write_chunk`] does when the file already has a chunk of the
+/// same type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WritePolicy {
+    /// Refuse, so that a payload is not encoded twice by accident.
+    #[default]
+    Fail,
+    /// Replace the first chunk of that type where it is.
+    Overwrite,
+    /// Add the chunk alongside the existing ones.
+    Append,
+}
+
+/// Whether [`Png::remove_chunk`] may delete the chunks no image can do
+/// without.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RemovePolicy {
+    /// Refuse to remove the types in [`RemovePolicy::PROTECTED`].
+    #[default]
+    Protect,
+    /// Remove whatever matches.
+    Force,
+}
+
+impl RemovePolicy {
+    pub const PROTECTED: [&'static str; 4] = ["IHDR", "PLTE", "IDAT", "IEND"];
+
+    /// Fails if the policy forbids removing a chunk of this type.
+    pub fn check(&self, chunk_type: &ChunkType) -> Result<()> {
+        let protected = RemovePolicy::PROTECTED
+            .iter()
+            .any(|t| t.as_bytes() == chunk_type.bytes());
+        if *self == RemovePolicy::Protect && protected {
+            return Err(Box::new(PngError::ProtectedChunk(chunk_type.to_string())));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PngError {
     ChunkNotFound,
-    InvalidFileSignature,
+    IllegalPlacement(String, usize),
+    /// The input does not start with [`Png::STANDARD_HEADER`]. `found` holds
+    /// its first eight bytes, padded with zeros if it is shorter.
+    BadSignature {
+        found: [u8; 8],
+    },
+    InvalidIdatSize(usize),
+    /// IDAT chunks must be consecutive, so their data cannot be re-chunked
+    /// otherwise.
+    NonConsecutiveIdat,
+    /// Removing the chunk was refused by [`RemovePolicy::Protect`].
+    ProtectedChunk(String),
+    /// Writing the chunk was refused by [`WritePolicy::Fail`].
+    AlreadyExists(String),
+}
+
+impl PngError {
+    fn bad_signature(bytes: &[u8]) -> PngError {
+        let mut found = [0; 8];
+        let length = bytes.len().min(found.len());
+        found[..length].copy_from_slice(&bytes[..length]);
+        PngError::BadSignature { found }
+    }
 }
 impl std::error::Error for PngError {}
 impl Display for PngError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            PngError::ChunkNotFound => write!(f, "Chunk not found"),
-            PngError::InvalidFileSignature => write!(f, "Invalid PNG file signature"),
-        }
+        let message = match self {
+            PngError::ChunkNotFound => crate::tr!("png.chunk_not_found"),
+            PngError::IllegalPlacement(chunk_type, index) => crate::tr!(
+                "png.illegal_placement",
+                chunk_type = chunk_type,
+                index = index
+            ),
+            PngError::BadSignature { found } => {
+                crate::tr!("png.bad_signature", found = found.escape_ascii())
+            }
+            PngError::InvalidIdatSize(size) => crate::tr!(
+                "png.invalid_idat_size",
+                size = size,
+                max = Chunk::MAX_LENGTH
+            ),
+            PngError::NonConsecutiveIdat => crate::tr!("png.non_consecutive_idat"),
+            PngError::AlreadyExists(chunk_type) => {
+                crate::tr!("png.already_exists", chunk_type = chunk_type)
+            }
+            PngError::ProtectedChunk(chunk_type) => {
+                crate::tr!("png.protected_chunk", chunk_type = chunk_type)
+            }
+        };
+        write!(f, "{}", message)
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use crate::chunk::Chunk;
     use crate::chunk_type::ChunkType;
+    use crate::metadata::MetadataMap;
+    use crate::parse_options::{Limit, LimitExceeded};
+    use proptest::prelude::*;
     use std::convert::TryFrom;
 
     fn testing_chunks() -> Vec<Chunk> {
-        let mut chunks = Vec::new();
-
-        chunks.push(chunk_from_strings("FrSt", "I am the first chunk").unwrap());
-        chunks.push(chunk_from_strings("miDl", "I am another chunk").unwrap());
-        chunks.push(chunk_from_strings("LASt", "I am the last chunk").unwrap());
-
-        chunks
+        vec![
+            chunk_from_strings("FrSt", "I am the first chunk").unwrap(),
+            chunk_from_strings("miDl", "I am another chunk").unwrap(),
+            chunk_from_strings("LASt", "I am the last chunk").unwrap(),
+        ]
     }
 
     fn testing_png() -> Png {
@@ -166,6 +1258,72 @@ mod tests {
         assert!(png.is_err());
     }
 
+    #[test]
+    fn test_bad_signature() {
+        let error = Png::try_from(&b"GIF89a"[..]).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<PngError>(),
+            Some(&PngError::BadSignature {
+                found: *b"GIF89a\0\0"
+            })
+        );
+        let error = scan(&b"GIF89a"[..]).next().unwrap().unwrap_err();
+        assert!(error.downcast_ref::<PngError>().is_some());
+    }
+
+    #[test]
+    fn test_skip_signature() {
+        let options = ParseOptions {
+            skip_signature: true,
+            ..ParseOptions::default()
+        };
+        let stream = &PNG_FILE[Png::STANDARD_HEADER.len()..];
+        assert!(Png::try_from(stream).is_err());
+        let png = Png::parse_with_options(stream, &options).unwrap();
+        assert_eq!(png.as_bytes(), PNG_FILE);
+        assert_eq!(scan_with_options(stream, &options).count(), 7);
+
+        let png = Png::parse_with_options(&PNG_FILE, &options).unwrap();
+        assert_eq!(png.as_bytes(), PNG_FILE);
+        assert_eq!(scan_with_options(&PNG_FILE[..], &options).count(), 7);
+    }
+
+    #[test]
+    fn test_parse_partial() {
+        let options = ParseOptions::default();
+        let (png, truncation) = Png::parse_partial(&PNG_FILE, &options).unwrap();
+        assert_eq!(png.as_bytes(), PNG_FILE);
+        assert_eq!(truncation, None);
+
+        // Cut the file two bytes into the RuSt chunk's data.
+        let (png, truncation) = Png::parse_partial(&PNG_FILE[..4786], &options).unwrap();
+        assert_eq!(png.chunks().len(), 5);
+        assert!(png.trailer().is_empty());
+        assert_eq!(
+            truncation,
+            Some(Truncation::MidChunk {
+                offset: 4776,
+                chunk_type: Some(ChunkType::from_str("RuSt").unwrap()),
+                length: Some(3),
+                available: 10,
+            })
+        );
+
+        let (png, truncation) = Png::parse_partial(&PNG_FILE[..4791], &options).unwrap();
+        assert_eq!(png.chunks().len(), 6);
+        assert_eq!(truncation, Some(Truncation::MissingEnd { offset: 4791 }));
+
+        let (_, truncation) = Png::parse_partial(&PNG_FILE[..4778], &options).unwrap();
+        assert!(matches!(
+            truncation,
+            Some(Truncation::MidChunk {
+                chunk_type: None,
+                ..
+            })
+        ));
+        assert!(Png::try_from(&PNG_FILE[..4786]).is_err());
+    }
+
     #[test]
     fn test_invalid_chunk() {
         let mut chunk_bytes: Vec<u8> = testing_chunks()
@@ -196,6 +1354,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_chunk_by_type() {
         let png = testing_png();
         let chunk = png.chunk_by_type("FrSt").unwrap();
@@ -203,24 +1362,507 @@ mod tests {
         assert_eq!(&chunk.data_as_string().unwrap(), "I am the first chunk");
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn test_chunks_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("FrSt", "Another").unwrap());
+        let chunks: Vec<&Chunk> = png.chunks_by_type("FrSt").collect();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(&chunks[1].data_as_string().unwrap(), "Another");
+    }
+
+    #[test]
+    fn test_lookup_by_chunk_type() {
+        let mut png = testing_png();
+        let frst = ChunkType::from_str("FrSt").unwrap();
+        png.append_chunk(chunk_from_strings("FrSt", "Another").unwrap());
+        assert_eq!(
+            png.chunk(&frst).unwrap().data_as_string().unwrap(),
+            "I am the first chunk"
+        );
+        assert_eq!(png.chunks_of(&frst).count(), 2);
+        assert_eq!(png.chunks_of("FrSt").count(), 2);
+        assert!(png.chunk("Fr5t").is_none());
+        assert_eq!(png.chunks_of("Fr5t").count(), 0);
+
+        png.replace_first(&frst, chunk_from_strings("FrSt", "swapped").unwrap())
+            .unwrap();
+        assert_eq!(png.chunk(frst.clone()).unwrap().data(), b"swapped");
+        png.remove_first(&frst, RemovePolicy::Protect).unwrap();
+        assert_eq!(png.chunk(&frst).unwrap().data(), b"Another");
+    }
+
     #[test]
     fn test_append_chunk() {
         let mut png = testing_png();
         png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
-        let chunk = png.chunk_by_type("TeSt").unwrap();
+        let chunk = png.chunk("TeSt").unwrap();
         assert_eq!(&chunk.chunk_type().to_string(), "TeSt");
         assert_eq!(&chunk.data_as_string().unwrap(), "Message");
     }
 
     #[test]
+    fn test_indices_of() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let rust = ChunkType::from_str("RuSt").unwrap();
+        let idat = ChunkType::from_str("IDAT").unwrap();
+        assert_eq!(png.indices_of(&rust), [5]);
+        assert!(png
+            .indices_of(&ChunkType::from_str("tEXt").unwrap())
+            .is_empty());
+
+        png.insert_chunk(1, chunk_from_strings("RuSt", "a").unwrap())
+            .unwrap();
+        png.append_chunk(chunk_from_strings("RuSt", "b").unwrap());
+        assert_eq!(png.indices_of(&rust), [1, 6, 7]);
+        assert_eq!(png.indices_of(&idat), [5]);
+
+        png.remove_first("RuSt", RemovePolicy::default()).unwrap();
+        assert_eq!(png.indices_of(&rust), [5, 6]);
+        assert_eq!(png.chunk("RuSt").unwrap().data(), b"hey");
+        png.retain_chunks(|c| c.chunk_type() != &idat);
+        assert_eq!(png.indices_of(&rust), [4, 5]);
+        assert!(png.indices_of(&idat).is_empty());
+        for (chunk_type, indices) in &png.index {
+            for &i in indices {
+                assert_eq!(png.chunks()[i].chunk_type(), chunk_type);
+            }
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
     fn test_remove_chunk() {
         let mut png = testing_png();
         png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
-        png.remove_chunk("TeSt").unwrap();
+        png.remove_chunk("TeSt", RemovePolicy::Protect).unwrap();
         let chunk = png.chunk_by_type("TeSt");
         assert!(chunk.is_none());
     }
 
+    #[test]
+    fn test_write_chunk_policies() {
+        let mut png = testing_png();
+        let count = png.chunks().len();
+        let index = png
+            .chunks()
+            .iter()
+            .position(|c| c.chunk_type().to_string() == "miDl")
+            .unwrap();
+
+        assert!(png
+            .write_chunk(
+                chunk_from_strings("miDl", "new").unwrap(),
+                WritePolicy::Fail
+            )
+            .is_err());
+        png.write_chunk(
+            chunk_from_strings("miDl", "new").unwrap(),
+            WritePolicy::Overwrite,
+        )
+        .unwrap();
+        assert_eq!(png.chunks().len(), count);
+        assert_eq!(png.chunks()[index].data(), b"new");
+
+        png.write_chunk(
+            chunk_from_strings("miDl", "more").unwrap(),
+            WritePolicy::Append,
+        )
+        .unwrap();
+        assert_eq!(png.chunks().len(), count + 1);
+        png.write_chunk(chunk_from_strings("neWw", "x").unwrap(), WritePolicy::Fail)
+            .unwrap();
+        assert_eq!(png.chunks().len(), count + 2);
+    }
+
+    #[test]
+    fn test_chunk_offsets() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let mut offset = Png::STANDARD_HEADER.len();
+        for chunk in png.chunks() {
+            assert_eq!(chunk.offset(), Some(offset));
+            offset += chunk.length() + Chunk::METADATA_SIZE;
+        }
+
+        let ihdr_end = Png::STANDARD_HEADER.len() + 13 + Chunk::METADATA_SIZE;
+        assert_eq!(png.chunk_index_at_offset(8), Some(0));
+        assert_eq!(png.chunk_index_at_offset(ihdr_end - 1), Some(0));
+        assert_eq!(png.chunk_index_at_offset(ihdr_end), Some(1));
+        assert_eq!(png.chunk_index_at_offset(0), None);
+        assert_eq!(png.chunk_index_at_offset(PNG_FILE.len()), None);
+
+        png.remove_chunk_at(0).unwrap();
+        png.append_chunk(chunk_from_strings("neWw", "x").unwrap());
+        assert_eq!(png.chunk_index_at_offset(8), None);
+        assert_eq!(png.chunk_index_at_offset(ihdr_end), Some(0));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_replace_chunk_by_type() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let before: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        let old = png
+            .replace_chunk_by_type("RuSt", chunk_from_strings("RuSt", "swapped").unwrap())
+            .unwrap();
+        let after: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(before, after);
+        assert_ne!(old.data(), b"swapped");
+        assert_eq!(png.chunk_by_type("RuSt").unwrap().data(), b"swapped");
+
+        assert!(png
+            .replace_chunk_by_type("teSt", chunk_from_strings("teSt", "x").unwrap())
+            .is_err());
+        assert!(png
+            .replace_chunk_by_type("RuSt", chunk_from_strings("PLTE", "abc").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_remove_chunks_matching() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(chunk_from_strings("TeSt", "one").unwrap());
+        png.append_chunk(chunk_from_strings("TeSt", "two").unwrap());
+        let count = png.chunks().len();
+
+        let matcher = ChunkTypeMatcher::from_str("TeSt").unwrap();
+        let removed = png
+            .remove_chunks_matching(&matcher, RemovePolicy::Protect)
+            .unwrap();
+        let data: Vec<&[u8]> = removed.iter().map(Chunk::data).collect();
+        assert_eq!(data, [&b"one"[..], &b"two"[..]]);
+        assert_eq!(png.chunks().len(), count - 2);
+
+        let matcher = ChunkTypeMatcher::from_str("I*").unwrap();
+        assert!(png
+            .remove_chunks_matching(&matcher, RemovePolicy::Protect)
+            .is_err());
+        assert_eq!(png.chunks().len(), count - 2);
+    }
+
+    #[test]
+    fn test_remove_protected_chunk() {
+        let original = Png::try_from(&PNG_FILE[..]).unwrap();
+        let mut png = original.clone();
+        for chunk_type in ["IHDR", "IDAT", "IEND"] {
+            assert!(png.remove_first(chunk_type, RemovePolicy::Protect).is_err());
+        }
+        assert_eq!(png.as_bytes(), original.as_bytes());
+        png.remove_first("IDAT", RemovePolicy::Force).unwrap();
+        assert!(png.chunk("IDAT").is_none());
+    }
+
+    #[test]
+    fn test_insert_chunk() {
+        let mut png = testing_png();
+        png.insert_chunk(1, chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+        assert_eq!(&png.chunks()[1].chunk_type().to_string(), "TeSt");
+        assert_eq!(png.chunks().len(), 4);
+    }
+
+    #[test]
+    fn test_append_chunk_stays_before_iend() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+        png.append_chunk(chunk_from_strings("pHYs", "123456789").unwrap());
+
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(
+            types,
+            ["IHDR", "sRGB", "gAMA", "pHYs", "pHYs", "IDAT", "RuSt", "TeSt", "IEND"]
+        );
+    }
+
+    #[test]
+    fn test_insert_chunk_lenient_moves_chunk() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.insert_chunk(0, chunk_from_strings("tIME", "1234567").unwrap())
+            .unwrap();
+        assert_eq!(&png.chunks()[1].chunk_type().to_string(), "tIME");
+
+        let end = png.chunks().len();
+        png.insert_chunk(end, chunk_from_strings("gAMA", "1234").unwrap())
+            .unwrap();
+        assert_eq!(&png.chunks()[5].chunk_type().to_string(), "gAMA");
+        assert_eq!(&png.chunks()[6].chunk_type().to_string(), "IDAT");
+    }
+
+    #[test]
+    fn test_insert_chunk_strict_rejects_illegal_position() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.set_insertion_policy(InsertionPolicy::Strict);
+
+        let end = png.chunks().len();
+        let chunk = chunk_from_strings("tEXt", "after IEND").unwrap();
+        assert!(png.insert_chunk(end, chunk.clone()).is_err());
+        assert!(png.insert_chunk(end - 1, chunk).is_ok());
+    }
+
+    #[test]
+    fn test_set_icc_profile() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(png.icc_profile().is_none());
+
+        let profile = IccProfile::new("Test", vec![1, 2, 3]).unwrap();
+        png.set_icc_profile(&profile).unwrap();
+        assert_eq!(&png.chunks()[1].chunk_type().to_string(), "iCCP");
+
+        let replacement = IccProfile::new("Replacement", vec![4, 5, 6]).unwrap();
+        png.set_icc_profile(&replacement).unwrap();
+        assert_eq!(&png.chunks()[1].chunk_type().to_string(), "iCCP");
+        assert_eq!(png.icc_profile().unwrap().unwrap(), replacement);
+
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert_eq!(png.icc_profile().unwrap().unwrap(), replacement);
+    }
+
+    #[test]
+    fn test_color_chunks() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.gamma().unwrap().unwrap(), Gamma(0.45455));
+        assert_eq!(png.srgb().unwrap().unwrap(), RenderingIntent::Perceptual);
+        assert!(png.chromaticities().is_none());
+
+        let chromaticities = Chromaticities {
+            white_point: (0.3127, 0.329),
+            red: (0.64, 0.33),
+            green: (0.3, 0.6),
+            blue: (0.15, 0.06),
+        };
+        png.set_chromaticities(chromaticities);
+        png.set_gamma(Gamma(1.0 / 2.2));
+        let gamma_chunks = png.chunks_of("gAMA").count();
+        assert_eq!(gamma_chunks, 1);
+
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert_eq!(png.chromaticities().unwrap().unwrap(), chromaticities);
+        assert_eq!(png.gamma().unwrap().unwrap(), Gamma(0.45455));
+        let idat = png
+            .chunks()
+            .iter()
+            .position(|c| &c.chunk_type().bytes() == b"IDAT");
+        let chrm = png
+            .chunks()
+            .iter()
+            .position(|c| &c.chunk_type().bytes() == b"cHRM");
+        assert!(chrm < idat);
+    }
+
+    #[test]
+    fn test_set_physical_dimensions() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.remove_first("pHYs", RemovePolicy::Protect).unwrap();
+        assert!(png.physical_dimensions().is_none());
+
+        png.set_physical_dimensions(PhysicalDimensions::from_dpi(300));
+        png.set_physical_dimensions(PhysicalDimensions::from_dpi(72));
+        assert_eq!(png.chunks_of("pHYs").count(), 1);
+        let position = |t: &[u8; 4]| {
+            png.chunks()
+                .iter()
+                .position(|c| &c.chunk_type().bytes() == t)
+        };
+        assert!(position(b"pHYs") < position(b"IDAT"));
+
+        let dpi = png.physical_dimensions().unwrap().unwrap().dpi().unwrap();
+        assert_eq!(dpi.0.round(), 72.0);
+    }
+
+    #[test]
+    fn test_transparency_uses_color_type() {
+        let mut png = crate::encoder::solid_color(2, 2, [255, 0, 0]).unwrap();
+        assert!(png.transparency().is_none());
+
+        png.append_chunk(Transparency::Rgb(255, 0, 0).to_chunk());
+        png.append_chunk(Background::Rgb(0, 0, 0).to_chunk());
+        assert_eq!(
+            png.transparency().unwrap().unwrap(),
+            Transparency::Rgb(255, 0, 0)
+        );
+        assert_eq!(png.background().unwrap().unwrap(), Background::Rgb(0, 0, 0));
+
+        png.remove_first("IHDR", RemovePolicy::Force).unwrap();
+        assert!(png.transparency().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_set_palette_placement() {
+        let mut png = crate::encoder::solid_color(2, 2, [255, 0, 0]).unwrap();
+        png.set_gamma(Gamma(0.45455));
+        png.append_chunk(Background::PaletteIndex(0).to_chunk());
+        assert!(png.palette().is_none());
+
+        let palette = Palette::new(vec![[255, 0, 0]]).unwrap();
+        png.set_palette(&palette);
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "gAMA", "PLTE", "bKGD", "IDAT", "IEND"]);
+        assert_eq!(png.palette().unwrap().unwrap(), palette);
+    }
+
+    #[test]
+    fn test_remove_chunk_at() {
+        let mut png = testing_png();
+        let chunk = png.remove_chunk_at(1).unwrap();
+        assert_eq!(&chunk.chunk_type().to_string(), "miDl");
+        assert_eq!(png.chunks().len(), 2);
+        assert!(png.remove_chunk_at(2).is_err());
+    }
+
+    #[test]
+    fn test_retain_chunks() {
+        let mut png = testing_png();
+        png.retain_chunks(|c| c.chunk_type().is_critical());
+        assert_eq!(png.chunks().len(), 2);
+        assert_eq!(&png.chunks()[1].chunk_type().to_string(), "LASt");
+    }
+
+    #[test]
+    fn test_trailer() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend_from_slice(b"trailing");
+
+        let mut png = Png::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(png.trailer(), b"trailing");
+        assert_eq!(png.as_bytes(), bytes);
+
+        png.set_trailer(Vec::new());
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_chunks_after_iend_are_not_trailer() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let mut chunks = png.chunks().to_vec();
+        chunks.push(chunk_from_strings("TeSt", "Message").unwrap());
+        let mut png = Png::from_chunks(chunks);
+        png.set_trailer(b"trailing".to_vec());
+
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert!(png.chunk("TeSt").is_some());
+        assert_eq!(png.trailer(), b"trailing");
+    }
+
+    #[test]
+    fn test_no_trailer() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(png.trailer().is_empty());
+    }
+
+    #[test]
+    fn test_raw_mode_preserves_bad_chunks() {
+        let mut bytes = PNG_FILE.to_vec();
+        // Corrupt the CRC of the RuSt chunk just before IEND.
+        let crc_index = bytes.len() - 12 - 1;
+        bytes[crc_index] ^= 0xff;
+
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+
+        let mut png = Png::try_from_raw(bytes.as_ref()).unwrap();
+        assert_eq!(png.as_bytes(), bytes);
+        assert!(png.to_string().contains("RuSt (bad CRC)"));
+
+        png.remove_first("pHYs", RemovePolicy::Protect).unwrap();
+        let reparsed = Png::try_from_raw(&png.as_bytes()).unwrap();
+        assert!(!reparsed.chunk("RuSt").unwrap().is_crc_valid());
+    }
+
+    #[test]
+    fn test_parse_with_progress() {
+        let mut events = Vec::new();
+        let png =
+            Png::parse_with_progress(&PNG_FILE, &ParseOptions::default(), &mut |e| events.push(e))
+                .unwrap();
+
+        let total = PNG_FILE.len() as u64;
+        assert_eq!(events.first(), Some(&ProgressEvent::Started { total }));
+        assert_eq!(events.len(), png.chunks().len() + 2);
+        assert_eq!(
+            events[events.len() - 2],
+            ProgressEvent::Advanced {
+                processed: total,
+                total
+            }
+        );
+        assert_eq!(events.last(), Some(&ProgressEvent::Finished));
+    }
+
+    #[test]
+    fn test_parse_options_reserved_bit() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(chunk_from_strings("ruse", "reserved").unwrap());
+        let bytes = png.as_bytes();
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+
+        let options = ParseOptions {
+            reject_invalid_reserved_bit: false,
+            ..ParseOptions::default()
+        };
+        let png = Png::parse_with_options(&bytes, &options).unwrap();
+        assert_eq!(png.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_parse_options_still_check_crc() {
+        let mut bytes = PNG_FILE.to_vec();
+        let crc_index = bytes.len() - 12 - 1;
+        bytes[crc_index] ^= 0xff;
+
+        let options = ParseOptions {
+            reject_invalid_reserved_bit: false,
+            ..ParseOptions::default()
+        };
+        assert!(Png::parse_with_options(&bytes, &options).is_err());
+    }
+
+    #[test]
+    fn test_lazy_crc() {
+        let mut bytes = PNG_FILE.to_vec();
+        let crc_index = bytes.len() - 12 - 1;
+        bytes[crc_index] ^= 0xff;
+
+        let options = ParseOptions {
+            lazy_crc: true,
+            ..ParseOptions::default()
+        };
+        let png = Png::parse_with_options(&bytes, &options).unwrap();
+        assert!(png.chunk("IHDR").unwrap().checked_data().is_ok());
+        assert!(png.chunk("RuSt").unwrap().checked_data().is_err());
+        assert!(png.verify_all().is_err());
+
+        let png = Png::parse_with_options(&PNG_FILE, &options).unwrap();
+        assert!(png.verify_all().is_ok());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_try_from_parallel() {
+        let png = Png::try_from_parallel(&PNG_FILE).unwrap();
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+
+        let mut bytes = PNG_FILE.to_vec();
+        let crc_index = bytes.len() - 12 - 1;
+        bytes[crc_index] ^= 0xff;
+        assert!(Png::try_from_parallel(&bytes).is_err());
+    }
+
     #[test]
     fn test_png_from_image_file() {
         let png = Png::try_from(&PNG_FILE[..]);
@@ -231,10 +1873,331 @@ mod tests {
     fn test_as_bytes() {
         let png = Png::try_from(&PNG_FILE[..]).unwrap();
         let actual = png.as_bytes();
-        let expected: Vec<u8> = PNG_FILE.iter().copied().collect();
+        let expected: Vec<u8> = PNG_FILE.to_vec();
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_chunk_types() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(chunk_from_strings("gAMA", "again").unwrap());
+        let types: Vec<String> = png.chunk_types().iter().map(|t| t.to_string()).collect();
+        assert_eq!(
+            types,
+            ["IHDR", "sRGB", "gAMA", "pHYs", "IDAT", "RuSt", "IEND"]
+        );
+    }
+
+    #[test]
+    fn test_chunks_matching() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let matcher = ChunkTypeMatcher::from_str("*s").unwrap();
+        let types: Vec<String> = png
+            .chunks_matching(&matcher)
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["pHYs"]);
+
+        let matcher = ChunkTypeMatcher::from_str("rust")
+            .unwrap()
+            .ignore_case(true);
+        assert_eq!(png.chunk_matching(&matcher).unwrap().data(), b"hey");
+    }
+
+    #[test]
+    fn test_merge_ancillary_from() {
+        let mut source = Png::try_from(&PNG_FILE[..]).unwrap();
+        source.append_chunk(chunk_from_strings("tEXt", "Author\0sj").unwrap());
+        source.append_chunk(chunk_from_strings("tEXt", "Title\0merged").unwrap());
+        source.append_chunk(chunk_from_strings("prIV", "depends on pixels").unwrap());
+        let ihdr = source.chunk("IHDR").unwrap().clone();
+        let target = || {
+            Png::from_chunks(vec![
+                ihdr.clone(),
+                chunk_from_strings("IDAT", "other pixels").unwrap(),
+                chunk_from_strings("tEXt", "Title\0kept").unwrap(),
+                chunk_from_strings("IEND", "").unwrap(),
+            ])
+        };
+        let types = |png: &Png| {
+            png.chunks()
+                .iter()
+                .map(|c| c.chunk_type().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let mut png = target();
+        let report = png.merge_ancillary_from(&source, &MergePolicy::default());
+        let names = |types: &[ChunkType]| types.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert_eq!(names(&report.copied), ["sRGB", "gAMA", "pHYs", "tEXt"]);
+        assert_eq!(names(&report.kept_existing), ["tEXt"]);
+        assert_eq!(names(&report.unsafe_to_copy), ["prIV"]);
+        assert_eq!(
+            types(&png),
+            ["IHDR", "sRGB", "gAMA", "pHYs", "IDAT", "tEXt", "tEXt", "IEND"]
+        );
+        assert_eq!(png.chunks_of("tEXt").nth(1).unwrap().data(), b"Author\0sj");
+
+        let mut png = target();
+        let policy = MergePolicy {
+            types: vec![ChunkTypeMatcher::from_str("tEXt").unwrap()],
+            on_conflict: Conflict::Replace,
+            ..MergePolicy::default()
+        };
+        png.merge_ancillary_from(&source, &policy);
+        let texts: Vec<&[u8]> = png.chunks_of("tEXt").map(|c| c.data()).collect();
+        assert_eq!(texts, [&b"Author\0sj"[..], b"Title\0merged"]);
+
+        // sRGB and iCCP describe the same thing, so only one is kept.
+        let mut png = target();
+        let profile = IccProfile::new("test", vec![0; 8]).unwrap();
+        png.set_icc_profile(&profile).unwrap();
+        let report = png.merge_ancillary_from(&source, &MergePolicy::default());
+        assert_eq!(names(&report.kept_existing), ["sRGB", "tEXt"]);
+        assert!(png.chunk("sRGB").is_none());
+        let policy = MergePolicy {
+            on_conflict: Conflict::Replace,
+            ..MergePolicy::default()
+        };
+        png.merge_ancillary_from(&source, &policy);
+        assert!(png.chunk("iCCP").is_none());
+        assert!(png.chunk("sRGB").is_some());
+
+        // Between identical images nothing is unsafe.
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let report = png.merge_ancillary_from(&source, &MergePolicy::default());
+        assert!(report.unsafe_to_copy.is_empty());
+        assert!(png.chunk("prIV").is_some());
+    }
+
+    #[test]
+    fn test_edit_session_drops_stale_unsafe_chunks() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(chunk_from_strings("prIV", "depends on pixels").unwrap());
+        png.append_chunk(chunk_from_strings("prIv", "safe to copy").unwrap());
+
+        let mut session = EditSession::new(png.clone());
+        session
+            .png_mut()
+            .remove_first("pHYs", RemovePolicy::Protect)
+            .unwrap();
+        assert!(!session.critical_changed());
+        assert!(session.save().chunk("prIV").is_some());
+
+        session
+            .png_mut()
+            .remove_first("RuSt", RemovePolicy::Protect)
+            .unwrap();
+        session
+            .png_mut()
+            .append_chunk(chunk_from_strings("neWV", "added after the change").unwrap());
+        assert!(session.critical_changed());
+        let stale: Vec<String> = session
+            .stale_chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(stale, ["prIV"]);
+
+        let mut warned = session.clone().unsafe_chunks(UnsafeChunks::Warn);
+        assert!(warned.save().chunk("prIV").is_some());
+
+        let saved = session.save();
+        assert!(saved.chunk("prIV").is_none());
+        assert!(saved.chunk("prIv").is_some());
+        assert!(saved.chunk("neWV").is_some());
+        assert!(saved.chunk("gAMA").is_some());
+        assert!(!session.critical_changed());
+    }
+
+    #[test]
+    fn test_scan() {
+        let types: Vec<String> = scan(&PNG_FILE[..])
+            .map(|c| c.unwrap().chunk_type().to_string())
+            .collect();
+        assert_eq!(
+            types,
+            ["IHDR", "sRGB", "gAMA", "pHYs", "IDAT", "RuSt", "IEND"]
+        );
+
+        let mut reader = &PNG_FILE[..];
+        let found = scan(&mut reader)
+            .map(Result::unwrap)
+            .find(|c| c.chunk_type().to_string() == "RuSt")
+            .unwrap();
+        assert_eq!(found.data(), b"hey");
+        assert_eq!(reader.len(), 12);
+    }
+
+    #[test]
+    fn test_scan_after_iend() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend(testing_chunks()[0].as_bytes());
+        bytes.extend(b"trailer");
+        let types: Vec<String> = scan(bytes.as_slice())
+            .map(|c| c.unwrap().chunk_type().to_string())
+            .collect();
+        assert_eq!(types.len(), 8);
+        assert_eq!(types[7], testing_chunks()[0].chunk_type().to_string());
+    }
+
+    #[test]
+    fn test_limits() {
+        let limited = |options: ParseOptions| {
+            let error = Png::parse_with_options(&PNG_FILE, &options).unwrap_err();
+            let limit = error.downcast_ref::<LimitExceeded>().unwrap().limit;
+            let scanned = scan_with_options(&PNG_FILE[..], &options)
+                .find_map(Result::err)
+                .unwrap();
+            assert_eq!(
+                scanned.downcast_ref::<LimitExceeded>().unwrap().limit,
+                limit
+            );
+            limit
+        };
+        let options = ParseOptions {
+            max_chunk_size: Some(4096),
+            ..ParseOptions::default()
+        };
+        assert_eq!(limited(options), Limit::ChunkSize);
+        let options = ParseOptions {
+            max_total_size: Some(4096),
+            ..ParseOptions::default()
+        };
+        assert_eq!(limited(options), Limit::TotalSize);
+        let options = ParseOptions {
+            max_chunks: Some(6),
+            ..ParseOptions::default()
+        };
+        assert_eq!(limited(options), Limit::Chunks);
+
+        let options = ParseOptions {
+            max_chunk_size: Some(4681),
+            max_total_size: Some(PNG_FILE.len() as u64),
+            max_chunks: Some(7),
+            ..ParseOptions::default()
+        };
+        assert!(Png::parse_with_options(&PNG_FILE, &options).is_ok());
+        assert!(scan_with_options(&PNG_FILE[..], &options).all(|c| c.is_ok()));
+    }
+
+    #[test]
+    fn test_limits_after_iend() {
+        let big = Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![0; 5000]);
+        let small = testing_chunks()[0].clone();
+        let cases = [
+            (
+                big.as_bytes(),
+                ParseOptions {
+                    max_chunk_size: Some(4681),
+                    ..ParseOptions::default()
+                },
+            ),
+            (
+                small.as_bytes(),
+                ParseOptions {
+                    max_chunks: Some(7),
+                    ..ParseOptions::default()
+                },
+            ),
+        ];
+        for (appended, options) in cases {
+            let mut bytes = PNG_FILE.to_vec();
+            bytes.extend(&appended);
+
+            // Both readers stop at IEND and leave the chunk as the trailer.
+            let png = Png::parse_with_options(&bytes, &options).unwrap();
+            let scanned: Vec<Chunk> = scan_with_options(bytes.as_slice(), &options)
+                .collect::<Result<_>>()
+                .unwrap();
+            assert_eq!(png.chunks().len(), 7);
+            assert_eq!(png.trailer(), appended.as_slice());
+            assert_eq!(scanned.len(), png.chunks().len());
+        }
+    }
+
+    fn parse_everything(bytes: &[u8]) {
+        for options in [ParseOptions::default(), ParseOptions::raw()] {
+            let _ = scan_with_options(bytes, &options).count();
+            let Ok(png) = Png::parse_with_options(bytes, &options) else {
+                continue;
+            };
+            let _ = png.ihdr();
+            let _ = png.icc_profile();
+            let _ = png.exif();
+            let _ = png.gamma();
+            let _ = png.chromaticities();
+            let _ = png.physical_dimensions();
+            let _ = png.palette();
+            let _ = png.transparency();
+            let _ = png.background();
+            let _ = png.srgb();
+            let _ = crate::metadata::MetadataMap::from_png(&png);
+        }
+        let _ = crate::detect::detect(bytes);
+    }
+
+    const KNOWN_TYPES: [&str; 13] = [
+        "IHDR", "PLTE", "iCCP", "eXIf", "gAMA", "cHRM", "pHYs", "tRNS", "bKGD", "sRGB", "tEXt",
+        "zTXt", "iTXt",
+    ];
+
+    proptest! {
+        #[test]
+        fn malformed_input_never_panics(
+            edits in prop::collection::vec(
+                (any::<prop::sample::Index>(), 0..3u8, any::<u32>()),
+                1..5,
+            ),
+        ) {
+            let mut bytes = PNG_FILE.to_vec();
+            for (index, edit, value) in edits {
+                let index = index.index(bytes.len());
+                match edit {
+                    0 => bytes[index] = value as u8,
+                    1 => bytes.truncate(index.max(8)),
+                    _ => {
+                        let end = (index + 4).min(bytes.len());
+                        bytes.splice(index..end, value.to_be_bytes());
+                    }
+                }
+            }
+            parse_everything(&bytes);
+        }
+
+        #[test]
+        fn random_chunk_stream_never_panics(data in prop::collection::vec(any::<u8>(), 0..64)) {
+            let mut stream = Png::STANDARD_HEADER.to_vec();
+            stream.extend(data);
+            parse_everything(&stream);
+        }
+
+        #[test]
+        fn known_chunks_with_random_data_never_panic(
+            data in prop::collection::vec(
+                prop::collection::vec(any::<u8>(), 0..40),
+                KNOWN_TYPES.len(),
+            ),
+        ) {
+            let chunks: Vec<Chunk> = KNOWN_TYPES
+                .iter()
+                .zip(data)
+                .map(|(t, data)| Chunk::new(ChunkType::from_str(t).unwrap(), data))
+                .collect();
+            parse_everything(&Png::from_chunks(chunks).as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_scan_errors() {
+        let mut scanner = scan(&PNG_FILE[1..]);
+        assert!(scanner.next().unwrap().is_err());
+        assert!(scanner.next().is_none());
+
+        let truncated = scan(&PNG_FILE[..PNG_FILE.len() - 4]);
+        assert!(truncated.last().unwrap().is_err());
+    }
+
     #[test]
     fn test_png_trait_impls() {
         let chunk_bytes: Vec<u8> = testing_chunks()
@@ -253,8 +2216,126 @@ mod tests {
         let _png_string = format!("{}", png);
     }
 
+    fn idat_data(png: &Png) -> Vec<Vec<u8>> {
+        png.chunks_of("IDAT").map(|c| c.data().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_rechunk_idat() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let stream = idat_data(&png).concat();
+        let chunk_count = png.chunks().len();
+
+        png.rechunk_idat(1000).unwrap();
+        let split = idat_data(&png);
+        assert!(split.iter().rev().skip(1).all(|data| data.len() == 1000));
+        assert_eq!(split.concat(), stream);
+        assert_eq!(png.chunks().len(), chunk_count - 1 + split.len());
+
+        png.rechunk_idat(1 << 20).unwrap();
+        assert_eq!(idat_data(&png), vec![stream]);
+        assert_eq!(png.chunks().len(), chunk_count);
+        assert!(Png::try_from(&png.as_bytes()[..]).is_ok());
+    }
+
+    #[test]
+    fn test_reorder_canonical() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let chunk = |chunk_type| png.chunk(chunk_type).unwrap().clone();
+        // RuSt and tEXt come before IDAT and tIME after it, so they stay on
+        // those sides.
+        let chunks = vec![
+            chunk("RuSt"),
+            chunk("IHDR"),
+            chunk_from_strings("tEXt", "a").unwrap(),
+            chunk("IEND"),
+            chunk("pHYs"),
+            chunk("gAMA"),
+            chunk("sRGB"),
+            chunk("IDAT"),
+            chunk_from_strings("tIME", "b").unwrap(),
+        ];
+        let order = |png: &Png| -> Vec<String> {
+            png.chunk_types().iter().map(|t| t.to_string()).collect()
+        };
+        let mut shuffled = Png::from_chunks(chunks);
+        assert_eq!(
+            order(&shuffled),
+            ["RuSt", "IHDR", "tEXt", "IEND", "pHYs", "gAMA", "sRGB", "IDAT", "tIME"]
+        );
+
+        assert!(shuffled.reorder_canonical());
+        assert_eq!(
+            order(&shuffled),
+            ["IHDR", "gAMA", "sRGB", "RuSt", "tEXt", "pHYs", "IDAT", "tIME", "IEND"]
+        );
+        assert!(crate::validate::validate(&shuffled.as_bytes()).is_empty());
+        assert!(!shuffled.reorder_canonical());
+    }
+
+    #[test]
+    fn test_rechunk_idat_errors() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(png.rechunk_idat(0).is_err());
+
+        let idat = png.chunk("IDAT").unwrap().clone();
+        png.insert_chunk(1, idat).unwrap();
+        assert!(png.rechunk_idat(1000).is_err());
+    }
+
+    #[test]
+    fn test_append_order_is_stable() {
+        let append = |types: &[&str]| {
+            let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+            for chunk_type in types {
+                png.append_chunk(chunk_from_strings(chunk_type, "x").unwrap());
+            }
+            png.as_bytes()
+        };
+        let bytes = append(&["zzZz", "aaAa", "tEXt"]);
+        assert_eq!(bytes, append(&["zzZz", "aaAa", "tEXt"]));
+        let png = Png::try_from(bytes.as_ref()).unwrap();
+        let types: Vec<String> = png.chunks()[6..]
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["zzZz", "aaAa", "tEXt", "IEND"]);
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.rechunk_idat(500).unwrap();
+        let stream = idat_data(&png).concat();
+        let texts = [
+            ("Author", "a"),
+            ("Author", "b"),
+            ("Title", "c"),
+            ("Author", "a"),
+        ];
+        for (keyword, value) in texts {
+            png.append_chunk(MetadataMap::text_chunk(keyword, value).unwrap());
+        }
+        png.append_chunk(chunk_from_strings("emPt", "").unwrap());
+        let size = png.as_bytes().len();
+
+        let report = png.compact(&CompactOptions::default()).unwrap();
+        assert_eq!(report.duplicate_text, ["Author"]);
+        assert_eq!(report.empty_chunks, 1);
+        assert_eq!(report.idat_after, 1);
+        assert!(report.idat_before > 1);
+        assert_eq!(report.bytes_saved, (size - png.as_bytes().len()) as i64);
+        assert_eq!(idat_data(&png), vec![stream]);
+        assert_eq!(
+            MetadataMap::from_png(&png).unwrap().get("Author"),
+            Some("a")
+        );
+        assert_eq!(png.chunks_of("tEXt").count(), 3);
+        assert!(png.chunk("emPt").is_none());
+    }
+
     // This is the raw bytes for a shrunken version of the `dice.png` image on Wikipedia
-    const PNG_FILE: [u8; 4803] = [
+    pub(crate) const PNG_FILE: [u8; 4803] = [
         137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 50, 0, 0, 0, 50, 8,
         6, 0, 0, 0, 30, 63, 136, 177, 0, 0, 0, 1, 115, 82, 71, 66, 0, 174, 206, 28, 233, 0, 0, 0,
         4, 103, 65, 77, 65, 0, 0, 177, 143, 11, 252, 97, 5, 0, 0, 0, 9, 112, 72, 89, 115, 0, 0, 14,
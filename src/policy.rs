@@ -0,0 +1,394 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use toml::{Table, Value};
+
+use crate::budget::parse_size;
+use crate::validate::json_string;
+use crate::{chunk::Chunk, matcher::ChunkTypeMatcher, png::Png, Error, Result};
+
+/// What an asset pipeline accepts in a PNG, written in TOML:
+///
+/// ```toml
+/// allowed = ["sRGB", "gAMA", "pHYs", "tEXt", "te??"]
+/// required = ["sRGB"]
+/// forbidden_keywords = ["Comment", "Author"]
+/// max_ancillary = "16K"
+///
+/// [max_size]
+/// tEXt = "1K"
+/// iCCP = "64K"
+///
+/// [severity]
+/// max-ancillary = "warning"
+/// ```
+///
+/// Chunk types are patterns as for [`ChunkTypeMatcher`]. Without `allowed`
+/// every chunk type is allowed; critical chunks always are. Keywords are
+/// those of `tEXt`, `zTXt` and `iTXt` chunks, and `max_ancillary` caps the
+/// data of all ancillary chunks together. `[severity]` sets how serious a
+/// rule is, by the ids in [`RULES`]; every rule is an error by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    allowed: Option<Vec<ChunkTypeMatcher>>,
+    required: Vec<ChunkTypeMatcher>,
+    forbidden_keywords: Vec<String>,
+    max_ancillary: Option<usize>,
+    max_size: Vec<(ChunkTypeMatcher, usize)>,
+    severity: BTreeMap<&'static str, Severity>,
+}
+
+/// The ids of the rules a [`Policy`] can have, in the order violations of
+/// them are reported.
+pub const RULES: [&str; 5] = [
+    "allowed",
+    "max-size",
+    "forbidden-keyword",
+    "max-ancillary",
+    "required",
+];
+
+const KEYS: [&str; 6] = [
+    "allowed",
+    "required",
+    "forbidden_keywords",
+    "max_ancillary",
+    "max_size",
+    "severity",
+];
+
+/// How serious a [`PolicyViolation`] is. Only errors fail a lint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    #[default]
+    Error,
+}
+
+impl FromStr for Severity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(Box::new(PolicyError::InvalidSeverity(s.to_string()))),
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A place where a file breaks one of a [`Policy`]'s rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyViolation {
+    /// One of [`RULES`].
+    pub rule: &'static str,
+    pub severity: Severity,
+    /// The offset of the offending chunk, if there is one.
+    pub offset: Option<usize>,
+    pub detail: String,
+}
+
+impl Display for PolicyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.severity, self.rule)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {:#x}", offset)?;
+        }
+        write!(f, ": {}", self.detail)
+    }
+}
+
+impl FromStr for Policy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let table: Table = s.parse()?;
+        if let Some(key) = table.keys().find(|k| !KEYS.contains(&k.as_str())) {
+            return Err(Box::new(PolicyError::UnknownKey(key.clone())));
+        }
+        let allowed = match table.contains_key("allowed") {
+            true => Some(matchers(&table, "allowed")?),
+            false => None,
+        };
+        let forbidden_keywords = strings(&table, "forbidden_keywords")?;
+        let max_ancillary = match table.get("max_ancillary") {
+            Some(value) => Some(size("max_ancillary", value)?),
+            None => None,
+        };
+        let mut max_size = Vec::new();
+        for (pattern, value) in section(&table, "max_size")? {
+            max_size.push((
+                ChunkTypeMatcher::from_str(pattern)?,
+                size("max_size", value)?,
+            ));
+        }
+        let mut severity = BTreeMap::new();
+        for (rule, value) in section(&table, "severity")? {
+            let rule = RULES
+                .iter()
+                .find(|r| *r == rule)
+                .ok_or_else(|| PolicyError::UnknownRule(rule.clone()))?;
+            let level = value
+                .as_str()
+                .ok_or_else(|| PolicyError::InvalidValue("severity", value.to_string()))?;
+            severity.insert(*rule, Severity::from_str(level)?);
+        }
+        Ok(Policy {
+            allowed,
+            required: matchers(&table, "required")?,
+            forbidden_keywords,
+            max_ancillary,
+            max_size,
+            severity,
+        })
+    }
+}
+
+impl Policy {
+    pub fn load(path: &Path) -> Result<Policy> {
+        Policy::from_str(&std::fs::read_to_string(path)?)
+    }
+    pub fn severity(&self, rule: &str) -> Severity {
+        self.severity.get(rule).copied().unwrap_or_default()
+    }
+    /// Checks every chunk of `png` against the policy, reporting all the
+    /// violations ordered by rule and then by offset.
+    pub fn lint(&self, png: &Png) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+        let mut violation = |rule: &'static str, offset, detail| {
+            violations.push(PolicyViolation {
+                rule,
+                severity: self.severity(rule),
+                offset,
+                detail,
+            })
+        };
+
+        let mut offset = png.header().len();
+        let mut ancillary = 0;
+        for chunk in png.chunks() {
+            let chunk_type = chunk.chunk_type();
+            let critical = chunk_type.is_critical();
+            if let Some(allowed) = self.allowed.as_ref().filter(|_| !critical) {
+                if !allowed.iter().any(|m| m.matches(chunk_type)) {
+                    let detail = format!("{} is not an allowed chunk type", chunk_type);
+                    violation("allowed", Some(offset), detail);
+                }
+            }
+            let limit = self.max_size.iter().find(|(m, _)| m.matches(chunk_type));
+            if let Some((pattern, max)) = limit.filter(|(_, max)| chunk.length() > *max) {
+                let detail = format!(
+                    "{} holds {} bytes, more than the {} allowed for {}",
+                    chunk_type,
+                    chunk.length(),
+                    max,
+                    pattern
+                );
+                violation("max-size", Some(offset), detail);
+            }
+            if let Some(keyword) = keyword(chunk) {
+                if self.forbidden_keywords.contains(&keyword) {
+                    let detail = format!("{} has the forbidden keyword {:?}", chunk_type, keyword);
+                    violation("forbidden-keyword", Some(offset), detail);
+                }
+            }
+            if !critical {
+                ancillary += chunk.length();
+            }
+            offset += chunk.length() + Chunk::METADATA_SIZE;
+        }
+
+        if let Some(max) = self.max_ancillary.filter(|max| ancillary > *max) {
+            let detail = format!(
+                "ancillary chunks hold {} bytes, more than the {} allowed",
+                ancillary, max
+            );
+            violation("max-ancillary", None, detail);
+        }
+        for pattern in &self.required {
+            if !png.chunks().iter().any(|c| pattern.matches(c.chunk_type())) {
+                violation("required", None, format!("no {} chunk", pattern));
+            }
+        }
+        violations.sort_by_key(|v| RULES.iter().position(|r| *r == v.rule));
+        violations
+    }
+}
+
+/// The violations as a JSON object, with `passed` false if any is an error.
+pub fn to_json(violations: &[PolicyViolation]) -> String {
+    let passed = violations.iter().all(|v| v.severity < Severity::Error);
+    let violations: Vec<String> = violations
+        .iter()
+        .map(|v| {
+            let offset = v
+                .offset
+                .map_or("null".to_string(), |offset| offset.to_string());
+            format!(
+                "{{\"rule\":{},\"severity\":{},\"offset\":{},\"detail\":{}}}",
+                json_string(v.rule),
+                json_string(&v.severity.to_string()),
+                offset,
+                json_string(&v.detail)
+            )
+        })
+        .collect();
+    format!(
+        "{{\"passed\":{},\"violations\":[{}]}}",
+        passed,
+        violations.join(",")
+    )
+}
+
+/// The keyword of a text chunk: its data up to the first NUL.
+pub(crate) fn keyword(chunk: &Chunk) -> Option<String> {
+    if !matches!(&chunk.chunk_type().bytes(), b"tEXt" | b"zTXt" | b"iTXt") {
+        return None;
+    }
+    let keyword = chunk.data().split(|b| *b == 0).next().unwrap_or_default();
+    Some(String::from_utf8_lossy(keyword).into_owned())
+}
+
+fn strings(table: &Table, key: &'static str) -> Result<Vec<String>> {
+    let items = match table.get(key) {
+        None => return Ok(Vec::new()),
+        Some(Value::Array(items)) => items,
+        Some(value) => return Err(Box::new(PolicyError::InvalidValue(key, value.to_string()))),
+    };
+    items
+        .iter()
+        .map(|item| match item {
+            Value::String(s) => Ok(s.clone()),
+            _ => Err(PolicyError::InvalidValue(key, item.to_string()).into()),
+        })
+        .collect()
+}
+
+fn matchers(table: &Table, key: &'static str) -> Result<Vec<ChunkTypeMatcher>> {
+    strings(table, key)?
+        .iter()
+        .map(|s| ChunkTypeMatcher::from_str(s))
+        .collect()
+}
+
+fn section<'a>(table: &'a Table, key: &'static str) -> Result<Vec<(&'a String, &'a Value)>> {
+    match table.get(key) {
+        None => Ok(Vec::new()),
+        Some(Value::Table(section)) => Ok(section.iter().collect()),
+        Some(value) => Err(Box::new(PolicyError::InvalidValue(key, value.to_string()))),
+    }
+}
+
+/// A size given as a number of bytes or a string such as "64K".
+fn size(key: &'static str, value: &Value) -> Result<usize> {
+    match value {
+        Value::Integer(n) => usize::try_from(*n)
+            .map_err(|_| PolicyError::InvalidValue(key, value.to_string()).into()),
+        Value::String(s) => parse_size(s),
+        _ => Err(Box::new(PolicyError::InvalidValue(key, value.to_string()))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PolicyError {
+    UnknownKey(String),
+    UnknownRule(String),
+    InvalidValue(&'static str, String),
+    InvalidSeverity(String),
+}
+impl std::error::Error for PolicyError {}
+impl Display for PolicyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            PolicyError::UnknownKey(key) => {
+                crate::tr!("policy.unknown_key", key = format!("{:?}", key))
+            }
+            PolicyError::UnknownRule(rule) => crate::tr!(
+                "policy.unknown_rule",
+                rule = format!("{:?}", rule),
+                rules = RULES.join(", ")
+            ),
+            PolicyError::InvalidValue(key, value) => {
+                crate::tr!("policy.invalid_value", key = key, value = value)
+            }
+            PolicyError::InvalidSeverity(s) => {
+                crate::tr!("policy.invalid_severity", severity = format!("{:?}", s))
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    const POLICY: &str = r#"
+        allowed = ["sRGB", "gAMA", "tEXt", "Ru??"]
+        required = ["sRGB", "iCCP"]
+        forbidden_keywords = ["Comment"]
+        max_ancillary = 10
+
+        [max_size]
+        "R*" = "2"
+
+        [severity]
+        max-ancillary = "warning"
+    "#;
+
+    #[test]
+    fn test_lint() {
+        let policy = Policy::from_str(POLICY).unwrap();
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let text = Chunk::new("tEXt".parse().unwrap(), b"Comment\0hi".to_vec());
+        png.append_chunk(text);
+        let violations: Vec<String> = policy.lint(&png).iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            violations,
+            [
+                "error allowed at offset 0x3e: pHYs is not an allowed chunk type",
+                "error max-size at offset 0x12a8: RuSt holds 3 bytes, more than the 2 allowed for R*",
+                "error forbidden-keyword at offset 0x12b7: tEXt has the forbidden keyword \"Comment\"",
+                "warning max-ancillary: ancillary chunks hold 24 bytes, more than the 10 allowed",
+                "error required: no iCCP chunk",
+            ]
+        );
+        assert!(to_json(&policy.lint(&png)).starts_with(
+            "{\"passed\":false,\"violations\":[{\"rule\":\"allowed\",\"severity\":\"error\""
+        ));
+        assert!(Policy::default().lint(&png).is_empty());
+        assert_eq!(to_json(&[]), "{\"passed\":true,\"violations\":[]}");
+    }
+
+    #[test]
+    fn test_invalid_policies() {
+        assert!(Policy::from_str("allow = [\"sRGB\"]").is_err());
+        assert!(Policy::from_str("allowed = \"sRGB\"").is_err());
+        assert!(Policy::from_str("required = [\"sRGBX\"]").is_err());
+        assert!(Policy::from_str("max_ancillary = \"lots\"").is_err());
+        assert!(Policy::from_str("[severity]\nallowed = \"fatal\"").is_err());
+        assert!(Policy::from_str("[severity]\nsize = \"info\"").is_err());
+        assert_eq!(
+            Policy::from_str("[severity]\nrequired = \"info\"")
+                .unwrap()
+                .severity("required"),
+            Severity::Info
+        );
+    }
+}
@@ -0,0 +1,335 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use toml::{Table, Value};
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, ordering, png::Png, Error, Result};
+
+/// A reusable set of chunk edits, written in TOML:
+///
+/// ```toml
+/// [[remove]]
+/// type = "tEXt"
+///
+/// [[update]]
+/// type = "coPy"
+/// literal = "Example Corp"
+///
+/// [[add]]
+/// type = "laBl"
+/// placement = "start"
+/// file = "label.bin"
+/// ```
+///
+/// Removals run first, then updates, then additions. `update` replaces the
+/// data of every chunk of its type, or adds one if there is none, so
+/// applying a profile twice gives the same result. Payloads come from exactly
+/// one of `literal`, `file` or `env`. `placement` is `"start"` or `"end"`
+/// (the default) for the first or last position the ordering rules allow,
+/// or a chunk index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub remove: Vec<ChunkType>,
+    pub update: Vec<ChunkEdit>,
+    pub add: Vec<ChunkEdit>,
+}
+
+/// A chunk to add or update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkEdit {
+    pub chunk_type: ChunkType,
+    pub placement: Placement,
+    pub payload: Payload,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Start,
+    End,
+    Index(usize),
+}
+
+/// Where a chunk's data comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Payload {
+    Literal(String),
+    File(PathBuf),
+    /// The value of an environment variable.
+    Env(String),
+}
+
+impl FromStr for Profile {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let table: Table = s.parse()?;
+        if let Some(key) = table
+            .keys()
+            .find(|k| !["remove", "update", "add"].contains(&k.as_str()))
+        {
+            return Err(Box::new(ProfileError::UnknownKey(key.clone())));
+        }
+
+        let remove = entries(&table, "remove")?
+            .into_iter()
+            .map(|entry| chunk_type(entry, "remove"))
+            .collect::<Result<_>>()?;
+        let update = entries(&table, "update")?
+            .into_iter()
+            .map(|entry| ChunkEdit::from_table(entry, "update"))
+            .collect::<Result<_>>()?;
+        let add = entries(&table, "add")?
+            .into_iter()
+            .map(|entry| ChunkEdit::from_table(entry, "add"))
+            .collect::<Result<_>>()?;
+        Ok(Profile {
+            remove,
+            update,
+            add,
+        })
+    }
+}
+
+impl Profile {
+    /// Reads a profile file. Relative `file` payloads are resolved against
+    /// the directory the profile is in.
+    pub fn load(path: &Path) -> Result<Profile> {
+        let mut profile = Profile::from_str(&std::fs::read_to_string(path)?)?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        for edit in profile.update.iter_mut().chain(profile.add.iter_mut()) {
+            if let Payload::File(file) = &mut edit.payload {
+                *file = base.join(&*file);
+            }
+        }
+        Ok(profile)
+    }
+    pub fn apply(&self, png: &mut Png) -> Result<()> {
+        png.retain_chunks(|c| !self.remove.contains(c.chunk_type()));
+
+        for edit in &self.update {
+            let data = edit.payload.resolve()?;
+            let existing: Vec<usize> = png
+                .chunks()
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| c.chunk_type() == &edit.chunk_type)
+                .map(|(i, _)| i)
+                .collect();
+            if existing.is_empty() {
+                edit.insert(png, data)?;
+                continue;
+            }
+            for index in existing {
+                png.remove_chunk_at(index)?;
+                png.insert_chunk(index, Chunk::new(edit.chunk_type.clone(), data.clone()))?;
+            }
+        }
+
+        for edit in &self.add {
+            let data = edit.payload.resolve()?;
+            edit.insert(png, data)?;
+        }
+        Ok(())
+    }
+}
+
+impl ChunkEdit {
+    fn from_table(entry: &Table, section: &'static str) -> Result<ChunkEdit> {
+        let placement = match entry.get("placement") {
+            None => Placement::End,
+            Some(Value::String(s)) if s == "start" => Placement::Start,
+            Some(Value::String(s)) if s == "end" => Placement::End,
+            Some(Value::Integer(i)) if *i >= 0 => Placement::Index(*i as usize),
+            Some(value) => {
+                return Err(Box::new(ProfileError::InvalidValue(
+                    section,
+                    "placement",
+                    value.to_string(),
+                )))
+            }
+        };
+
+        let sources: Vec<(&str, &str)> = ["literal", "file", "env"]
+            .into_iter()
+            .filter_map(|key| entry.get(key).map(|value| (key, value)))
+            .map(|(key, value)| match value {
+                Value::String(s) => Ok((key, s.as_str())),
+                _ => Err(ProfileError::InvalidValue(
+                    section,
+                    "payload",
+                    value.to_string(),
+                )),
+            })
+            .collect::<std::result::Result<_, _>>()?;
+        let payload = match sources.as_slice() {
+            [("literal", s)] => Payload::Literal(s.to_string()),
+            [("file", s)] => Payload::File(PathBuf::from(s)),
+            [("env", s)] => Payload::Env(s.to_string()),
+            _ => return Err(Box::new(ProfileError::PayloadSource(section))),
+        };
+
+        Ok(ChunkEdit {
+            chunk_type: chunk_type(entry, section)?,
+            placement,
+            payload,
+        })
+    }
+    fn insert(&self, png: &mut Png, data: Vec<u8>) -> Result<()> {
+        let chunk = Chunk::new(self.chunk_type.clone(), data);
+        match self.placement {
+            Placement::End => png.append_chunk(chunk),
+            Placement::Start => {
+                let legal = ordering::legal_positions(png.chunks(), &self.chunk_type.bytes());
+                png.insert_chunk(*legal.start(), chunk)?;
+            }
+            Placement::Index(index) => png.insert_chunk(index, chunk)?,
+        }
+        Ok(())
+    }
+}
+
+impl Payload {
+    fn resolve(&self) -> Result<Vec<u8>> {
+        match self {
+            Payload::Literal(s) => Ok(s.as_bytes().to_vec()),
+            Payload::File(path) => Ok(std::fs::read(path)?),
+            Payload::Env(name) => std::env::var(name)
+                .map(String::into_bytes)
+                .map_err(|_| ProfileError::MissingEnv(name.clone()).into()),
+        }
+    }
+}
+
+fn entries<'a>(table: &'a Table, section: &'static str) -> Result<Vec<&'a Table>> {
+    match table.get(section) {
+        None => Ok(Vec::new()),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                item.as_table().ok_or_else(|| {
+                    ProfileError::InvalidValue(section, "entry", item.to_string()).into()
+                })
+            })
+            .collect(),
+        Some(value) => Err(Box::new(ProfileError::InvalidValue(
+            section,
+            "entry",
+            value.to_string(),
+        ))),
+    }
+}
+
+fn chunk_type(entry: &Table, section: &'static str) -> Result<ChunkType> {
+    match entry.get("type") {
+        Some(Value::String(s)) => ChunkType::from_str(s),
+        Some(value) => Err(Box::new(ProfileError::InvalidValue(
+            section,
+            "type",
+            value.to_string(),
+        ))),
+        None => Err(Box::new(ProfileError::MissingType(section))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ProfileError {
+    UnknownKey(String),
+    MissingType(&'static str),
+    InvalidValue(&'static str, &'static str, String),
+    PayloadSource(&'static str),
+    MissingEnv(String),
+}
+impl std::error::Error for ProfileError {}
+impl Display for ProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            ProfileError::UnknownKey(key) => {
+                crate::tr!("profile.unknown_key", key = format!("{:?}", key))
+            }
+            ProfileError::MissingType(section) => {
+                crate::tr!("profile.missing_type", section = section)
+            }
+            ProfileError::InvalidValue(section, key, value) => crate::tr!(
+                "profile.invalid_value",
+                key = key,
+                section = section,
+                value = value
+            ),
+            ProfileError::PayloadSource(section) => {
+                crate::tr!("profile.payload_source", section = section)
+            }
+            ProfileError::MissingEnv(name) => crate::tr!("profile.missing_env", name = name),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    const PROFILE: &str = r#"
+        [[remove]]
+        type = "RuSt"
+
+        [[update]]
+        type = "coPy"
+        literal = "Example Corp"
+
+        [[add]]
+        type = "laBl"
+        placement = "start"
+        literal = "first"
+    "#;
+
+    fn types(png: &Png) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_profile() {
+        let profile = Profile::from_str(PROFILE).unwrap();
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        profile.apply(&mut png).unwrap();
+        assert_eq!(
+            types(&png),
+            ["IHDR", "laBl", "sRGB", "gAMA", "pHYs", "IDAT", "coPy", "IEND"]
+        );
+
+        profile.apply(&mut png).unwrap();
+        assert_eq!(png.chunks_of("coPy").count(), 1);
+        assert_eq!(png.chunks_of("laBl").count(), 2);
+    }
+
+    #[test]
+    fn test_env_payload() {
+        let profile = Profile::from_str(
+            r#"
+            [[add]]
+            type = "teSt"
+            env = "PNGME_PROFILE_TEST_UNSET"
+            "#,
+        )
+        .unwrap();
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(profile.apply(&mut png).is_err());
+    }
+
+    #[test]
+    fn test_invalid_profiles() {
+        assert!(Profile::from_str("[[rename]]\ntype = \"teSt\"").is_err());
+        assert!(Profile::from_str("[[add]]\nliteral = \"x\"").is_err());
+        assert!(Profile::from_str("[[add]]\ntype = \"teSt\"").is_err());
+        assert!(
+            Profile::from_str("[[add]]\ntype = \"teSt\"\nliteral = \"x\"\nenv = \"Y\"").is_err()
+        );
+        assert!(
+            Profile::from_str("[[add]]\ntype = \"teSt\"\nliteral = \"x\"\nplacement = -1").is_err()
+        );
+    }
+}
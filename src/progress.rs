@@ -0,0 +1,11 @@
+/// Progress notifications emitted by long-running operations, so callers can
+/// drive a progress bar or log throughput.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// Work on an input of `total` bytes has begun.
+    Started { total: u64 },
+    /// `processed` of `total` bytes have been handled so far.
+    Advanced { processed: u64, total: u64 },
+    /// The operation completed, successfully or not.
+    Finished,
+}
@@ -0,0 +1,74 @@
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use pngme::progress::ProgressEvent;
+
+const WIDTH: usize = 30;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A single-line progress bar drawn on stderr, fed by library
+/// [`ProgressEvent`]s.
+pub struct ProgressBar {
+    label: String,
+    started: Instant,
+    last_draw: Option<Instant>,
+}
+
+impl ProgressBar {
+    pub fn new(label: &str) -> ProgressBar {
+        ProgressBar {
+            label: label.to_owned(),
+            started: Instant::now(),
+            last_draw: None,
+        }
+    }
+
+    pub fn update(&mut self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::Started { total } => {
+                self.started = Instant::now();
+                self.last_draw = None;
+                self.draw(0, total);
+            }
+            ProgressEvent::Advanced { processed, total } => {
+                let due = self
+                    .last_draw
+                    .is_none_or(|last| last.elapsed() >= REDRAW_INTERVAL);
+                if due || processed == total {
+                    self.draw(processed, total);
+                }
+            }
+            ProgressEvent::Finished => eprintln!(),
+        }
+    }
+
+    fn draw(&mut self, processed: u64, total: u64) {
+        let fraction = if total == 0 {
+            1.0
+        } else {
+            processed as f64 / total as f64
+        };
+        let filled = (fraction * WIDTH as f64) as usize;
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let eta = if processed > 0 && processed < total {
+            format!(
+                "{:.0}s",
+                elapsed * (total - processed) as f64 / processed as f64
+            )
+        } else {
+            "-".to_owned()
+        };
+        eprint!(
+            "\r{} [{}{}] {:>3}% {}/{} bytes ETA {}",
+            self.label,
+            "#".repeat(filled),
+            " ".repeat(WIDTH - filled),
+            (fraction * 100.0) as u32,
+            processed,
+            total,
+            eta
+        );
+        let _ = std::io::stderr().flush();
+        self.last_draw = Some(Instant::now());
+    }
+}
@@ -0,0 +1,147 @@
+use std::str::FromStr;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::{
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    png::{Png, RemovePolicy},
+};
+
+fn to_py_err(e: crate::Error) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+#[pyclass(name = "ChunkType", frozen)]
+pub struct PyChunkType {
+    chunk_type: ChunkType,
+}
+
+#[pymethods]
+impl PyChunkType {
+    #[new]
+    fn new(name: &str) -> PyResult<Self> {
+        let chunk_type = ChunkType::from_str(name).map_err(to_py_err)?;
+        Ok(PyChunkType { chunk_type })
+    }
+    fn is_critical(&self) -> bool {
+        self.chunk_type.is_critical()
+    }
+    fn is_public(&self) -> bool {
+        self.chunk_type.is_public()
+    }
+    fn is_safe_to_copy(&self) -> bool {
+        self.chunk_type.is_safe_to_copy()
+    }
+    fn __str__(&self) -> String {
+        self.chunk_type.to_string()
+    }
+    fn __repr__(&self) -> String {
+        format!("ChunkType('{}')", self.chunk_type)
+    }
+    fn __eq__(&self, other: &Self) -> bool {
+        self.chunk_type == other.chunk_type
+    }
+}
+
+#[pyclass(name = "Chunk", frozen)]
+pub struct PyChunk {
+    chunk: Chunk,
+}
+
+#[pymethods]
+impl PyChunk {
+    #[new]
+    fn new(chunk_type: &str, data: &[u8]) -> PyResult<Self> {
+        let chunk_type = ChunkType::from_str(chunk_type).map_err(to_py_err)?;
+        Ok(PyChunk {
+            chunk: Chunk::new(chunk_type, data.to_vec()),
+        })
+    }
+    #[getter]
+    fn chunk_type(&self) -> PyChunkType {
+        PyChunkType {
+            chunk_type: self.chunk.chunk_type().clone(),
+        }
+    }
+    #[getter]
+    fn data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.chunk.data())
+    }
+    #[getter]
+    fn crc(&self) -> u32 {
+        self.chunk.crc()
+    }
+    fn __len__(&self) -> usize {
+        self.chunk.length()
+    }
+    fn __bytes__<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.chunk.as_bytes())
+    }
+    fn __repr__(&self) -> String {
+        format!(
+            "Chunk('{}', {} bytes)",
+            self.chunk.chunk_type(),
+            self.chunk.length()
+        )
+    }
+}
+
+#[pyclass(name = "Png")]
+pub struct PyPng {
+    png: Png,
+}
+
+#[pymethods]
+impl PyPng {
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<Self> {
+        let png = Png::try_from(data).map_err(to_py_err)?;
+        Ok(PyPng { png })
+    }
+    #[getter]
+    fn chunks(&self) -> Vec<PyChunk> {
+        self.png
+            .chunks()
+            .iter()
+            .map(|chunk| PyChunk {
+                chunk: chunk.clone(),
+            })
+            .collect()
+    }
+    /// Appends a chunk of the given type holding `data`.
+    fn encode(&mut self, chunk_type: &str, data: &[u8]) -> PyResult<()> {
+        let chunk_type = ChunkType::from_str(chunk_type).map_err(to_py_err)?;
+        self.png.append_chunk(Chunk::new(chunk_type, data.to_vec()));
+        Ok(())
+    }
+    /// Returns the data of the first chunk of the given type, or None.
+    fn decode<'py>(&self, py: Python<'py>, chunk_type: &str) -> Option<Bound<'py, PyBytes>> {
+        self.png
+            .chunk(chunk_type)
+            .map(|chunk| PyBytes::new(py, chunk.data()))
+    }
+    fn remove(&mut self, chunk_type: &str) -> PyResult<PyChunk> {
+        let chunk = self
+            .png
+            .remove_first(chunk_type, RemovePolicy::Protect)
+            .map_err(to_py_err)?;
+        Ok(PyChunk { chunk })
+    }
+    fn __bytes__<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.png.as_bytes())
+    }
+    fn __len__(&self) -> usize {
+        self.png.chunks().len()
+    }
+}
+
+#[pymodule(name = "pngme")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyChunkType>()?;
+    m.add_class::<PyChunk>()?;
+    m.add_class::<PyPng>()?;
+    Ok(())
+}
@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use toml::{Table, Value};
+
+use crate::chunk_type::{ChunkClass, ChunkType};
+use crate::{Error, Result};
+
+/// A team's private chunk types, written in TOML:
+///
+/// ```toml
+/// [[chunk]]
+/// type = "teAm"
+/// name = "Team tag"
+/// purpose = "Which team exported the image"
+/// schema = "UTF-8 text"
+/// ```
+///
+/// `schema` is an optional hint at the data's format. Only private types
+/// can be registered, each once, and entries are kept sorted by type so the
+/// same file always describes the chunks the same way.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Registry {
+    entries: BTreeMap<String, Entry>,
+}
+
+/// What a registered chunk type is for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub chunk_type: ChunkType,
+    pub name: String,
+    pub purpose: String,
+    pub schema: Option<String>,
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, self.purpose)?;
+        if let Some(schema) = &self.schema {
+            write!(f, " ({})", schema)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Registry {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let table: Table = s.parse()?;
+        if let Some(key) = table.keys().find(|k| k.as_str() != "chunk") {
+            return Err(Box::new(RegistryError::UnknownKey(key.clone())));
+        }
+        let items = match table.get("chunk") {
+            None => return Ok(Registry::default()),
+            Some(Value::Array(items)) => items,
+            Some(value) => return Err(Box::new(RegistryError::InvalidEntry(value.to_string()))),
+        };
+
+        let mut entries = BTreeMap::new();
+        for item in items {
+            let entry = item
+                .as_table()
+                .ok_or_else(|| RegistryError::InvalidEntry(item.to_string()))?;
+            let entry = Entry::from_table(entry)?;
+            let key = entry.chunk_type.to_string();
+            if entries.contains_key(&key) {
+                return Err(Box::new(RegistryError::Duplicate(key)));
+            }
+            entries.insert(key, entry);
+        }
+        Ok(Registry { entries })
+    }
+}
+
+impl Registry {
+    pub fn load(path: &Path) -> Result<Registry> {
+        Registry::from_str(&std::fs::read_to_string(path)?)
+    }
+    pub fn get(&self, chunk_type: &ChunkType) -> Option<&Entry> {
+        self.entries.get(&chunk_type.to_string())
+    }
+    /// The registered types, sorted.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.values()
+    }
+    /// Fails unless `chunk_type` is registered.
+    pub fn require(&self, chunk_type: &ChunkType) -> Result<&Entry> {
+        self.get(chunk_type)
+            .ok_or_else(|| RegistryError::Unregistered(chunk_type.to_string()).into())
+    }
+}
+
+impl Entry {
+    fn from_table(entry: &Table) -> Result<Entry> {
+        if let Some(key) = entry
+            .keys()
+            .find(|k| !["type", "name", "purpose", "schema"].contains(&k.as_str()))
+        {
+            return Err(Box::new(RegistryError::UnknownKey(key.clone())));
+        }
+        let chunk_type = ChunkType::from_str(&string(entry, "type")?)?;
+        if !matches!(
+            chunk_type.classify(),
+            ChunkClass::PrivateSafe | ChunkClass::PrivateUnsafe
+        ) {
+            return Err(Box::new(RegistryError::NotPrivate(chunk_type.to_string())));
+        }
+        let schema = match entry.contains_key("schema") {
+            true => Some(string(entry, "schema")?),
+            false => None,
+        };
+        Ok(Entry {
+            chunk_type,
+            name: string(entry, "name")?,
+            purpose: string(entry, "purpose")?,
+            schema,
+        })
+    }
+}
+
+fn string(entry: &Table, key: &'static str) -> Result<String> {
+    match entry.get(key) {
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(value) => Err(Box::new(RegistryError::InvalidValue(
+            key,
+            value.to_string(),
+        ))),
+        None => Err(Box::new(RegistryError::Missing(key))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RegistryError {
+    UnknownKey(String),
+    InvalidEntry(String),
+    InvalidValue(&'static str, String),
+    Missing(&'static str),
+    NotPrivate(String),
+    Duplicate(String),
+    Unregistered(String),
+}
+impl std::error::Error for RegistryError {}
+impl Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            RegistryError::UnknownKey(key) => {
+                crate::tr!("registry.unknown_key", key = format!("{:?}", key))
+            }
+            RegistryError::InvalidEntry(value) => {
+                crate::tr!("registry.invalid_entry", value = value)
+            }
+            RegistryError::InvalidValue(key, value) => {
+                crate::tr!("registry.invalid_value", key = key, value = value)
+            }
+            RegistryError::Missing(key) => crate::tr!("registry.missing", key = key),
+            RegistryError::NotPrivate(chunk_type) => {
+                crate::tr!("registry.not_private", chunk_type = chunk_type)
+            }
+            RegistryError::Duplicate(chunk_type) => {
+                crate::tr!("registry.duplicate", chunk_type = chunk_type)
+            }
+            RegistryError::Unregistered(chunk_type) => {
+                crate::tr!("registry.unregistered", chunk_type = chunk_type)
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGISTRY: &str = r#"
+        [[chunk]]
+        type = "teAm"
+        name = "Team tag"
+        purpose = "Which team exported the image"
+        schema = "UTF-8 text"
+
+        [[chunk]]
+        type = "buIl"
+        name = "Build"
+        purpose = "The build that produced the image"
+    "#;
+
+    #[test]
+    fn test_registry() {
+        let registry = Registry::from_str(REGISTRY).unwrap();
+        let types: Vec<String> = registry
+            .entries()
+            .map(|e| e.chunk_type.to_string())
+            .collect();
+        assert_eq!(types, ["buIl", "teAm"]);
+
+        let team = ChunkType::from_str("teAm").unwrap();
+        assert_eq!(
+            registry.get(&team).unwrap().to_string(),
+            "Team tag: Which team exported the image (UTF-8 text)"
+        );
+        assert!(registry.require(&team).is_ok());
+        assert!(registry
+            .require(&ChunkType::from_str("ruSt").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_invalid_registries() {
+        let entry = |fields: &str| format!("[[chunk]]\n{}", fields);
+        assert!(Registry::from_str("[[type]]\nname = \"x\"").is_err());
+        assert!(Registry::from_str(&entry("type = \"teAm\"\nname = \"x\"")).is_err());
+        assert!(
+            Registry::from_str(&entry("type = \"tEXt\"\nname = \"x\"\npurpose = \"y\"")).is_err()
+        );
+        assert!(Registry::from_str(&entry(
+            "type = \"teAm\"\nname = \"x\"\npurpose = \"y\"\nowner = \"z\""
+        ))
+        .is_err());
+        let twice = format!(
+            "{0}\n{0}",
+            entry("type = \"teAm\"\nname = \"x\"\npurpose = \"y\"")
+        );
+        assert!(Registry::from_str(&twice).is_err());
+    }
+}
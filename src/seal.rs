@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use sha2::{Digest, Sha256};
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, png::Png, Error, Result};
+
+/// A SHA-256 manifest of every chunk in a PNG and of the data after IEND,
+/// so that any later change can be detected, including to chunks pngme
+/// does not understand. Unlike CRC-32, SHA-256 cannot be matched by a
+/// deliberately crafted change.
+///
+/// It is stored in its own chunk, which is ancillary, private and unsafe to
+/// copy since it is only valid for the exact chunks it was made over, and
+/// which is itself left out of the manifest. The encoding is the magic
+/// bytes, a version byte, a big-endian `u32` chunk count, then per chunk
+/// its type and the SHA-256 of its serialized form (length, type, data and
+/// CRC), and finally the SHA-256 of the trailing data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Seal {
+    chunks: Vec<([u8; 4], [u8; 32])>,
+    trailer: [u8; 32],
+}
+
+/// A difference between a PNG and its seal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SealMismatch {
+    Added(String),
+    Removed(String),
+    Modified(String),
+    /// The same chunks are present but in a different order.
+    Reordered,
+    Trailer,
+}
+
+impl Display for SealMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SealMismatch::Added(chunk_type) => write!(f, "+ {} was added", chunk_type),
+            SealMismatch::Removed(chunk_type) => write!(f, "- {} was removed", chunk_type),
+            SealMismatch::Modified(chunk_type) => write!(f, "~ {} was modified", chunk_type),
+            SealMismatch::Reordered => write!(f, "~ the chunks were reordered"),
+            SealMismatch::Trailer => write!(f, "~ the data after IEND changed"),
+        }
+    }
+}
+
+impl TryFrom<&Chunk> for Seal {
+    type Error = Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        let data = chunk.data();
+        if !data.starts_with(&Seal::MAGIC) {
+            return Err(Box::new(SealError::MissingMagic));
+        }
+        let header = Seal::MAGIC.len() + 1 + 4;
+        if data.len() < header {
+            return Err(Box::new(SealError::Truncated));
+        }
+        let version = data[Seal::MAGIC.len()];
+        if version != Seal::VERSION {
+            return Err(Box::new(SealError::UnsupportedVersion(version)));
+        }
+        let count = u32::from_be_bytes(data[header - 4..header].try_into()?) as usize;
+        if data.len() != header + count * Seal::ENTRY_LENGTH + 32 {
+            return Err(Box::new(SealError::Truncated));
+        }
+
+        let (entries, trailer) = data[header..].split_at(count * Seal::ENTRY_LENGTH);
+        let chunks = entries
+            .chunks_exact(Seal::ENTRY_LENGTH)
+            .map(|entry| Ok((entry[..4].try_into()?, entry[4..].try_into()?)))
+            .collect::<Result<_>>()?;
+        Ok(Seal {
+            chunks,
+            trailer: trailer.try_into()?,
+        })
+    }
+}
+
+impl Seal {
+    pub const CHUNK_TYPE: [u8; 4] = *b"seAL";
+    pub const MAGIC: [u8; 4] = *b"PMsl";
+    pub const VERSION: u8 = 1;
+    const ENTRY_LENGTH: usize = 4 + 32;
+
+    /// The manifest of `png` as it is now.
+    pub fn of(png: &Png) -> Seal {
+        Seal {
+            chunks: png
+                .chunks()
+                .iter()
+                .filter(|c| c.chunk_type().bytes() != Seal::CHUNK_TYPE)
+                .map(|c| (c.chunk_type().bytes(), Sha256::digest(c.as_bytes()).into()))
+                .collect(),
+            trailer: Sha256::digest(png.trailer()).into(),
+        }
+    }
+    /// The number of chunks covered.
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+    /// How `png` differs from what was sealed. Chunks are matched by hash,
+    /// and a removed and an added chunk of the same type are reported as
+    /// one modified chunk.
+    pub fn mismatches(&self, png: &Png) -> Vec<SealMismatch> {
+        let now = Seal::of(png);
+        let mut mismatches = Vec::new();
+        if now.chunks != self.chunks {
+            let sealed: HashSet<_> = self.chunks.iter().collect();
+            let current: HashSet<_> = now.chunks.iter().collect();
+            let mut removed: Vec<_> = self
+                .chunks
+                .iter()
+                .filter(|c| !current.contains(c))
+                .collect();
+            for chunk in now.chunks.iter().filter(|c| !sealed.contains(c)) {
+                let name = type_name(&chunk.0);
+                match removed.iter().position(|r| r.0 == chunk.0) {
+                    Some(position) => {
+                        removed.remove(position);
+                        mismatches.push(SealMismatch::Modified(name));
+                    }
+                    None => mismatches.push(SealMismatch::Added(name)),
+                }
+            }
+            mismatches.extend(
+                removed
+                    .iter()
+                    .map(|r| SealMismatch::Removed(type_name(&r.0))),
+            );
+            if mismatches.is_empty() {
+                mismatches.push(SealMismatch::Reordered);
+            }
+        }
+        if now.trailer != self.trailer {
+            mismatches.push(SealMismatch::Trailer);
+        }
+        mismatches
+    }
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = Seal::MAGIC.to_vec();
+        data.push(Seal::VERSION);
+        data.extend_from_slice(&(self.chunks.len() as u32).to_be_bytes());
+        for (chunk_type, digest) in &self.chunks {
+            data.extend_from_slice(chunk_type);
+            data.extend_from_slice(digest);
+        }
+        data.extend_from_slice(&self.trailer);
+        Chunk::new(ChunkType::from_bytes_unchecked(Seal::CHUNK_TYPE), data)
+    }
+}
+
+/// Seals `png`, replacing any previous seal.
+pub fn seal(png: &mut Png) -> Seal {
+    png.retain_chunks(|c| c.chunk_type().bytes() != Seal::CHUNK_TYPE);
+    let seal = Seal::of(png);
+    png.append_chunk(seal.to_chunk());
+    seal
+}
+
+/// Checks `png` against its seal, failing if it has none.
+pub fn verify(png: &Png) -> Result<Vec<SealMismatch>> {
+    let chunk = png
+        .chunks()
+        .iter()
+        .find(|c| c.chunk_type().bytes() == Seal::CHUNK_TYPE)
+        .ok_or(SealError::MissingSeal)?;
+    Ok(Seal::try_from(chunk)?.mismatches(png))
+}
+
+/// Chunk types are shown escaped, since a modified one may not be text.
+fn type_name(chunk_type: &[u8; 4]) -> String {
+    chunk_type.escape_ascii().to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SealError {
+    MissingSeal,
+    MissingMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+}
+impl std::error::Error for SealError {}
+impl Display for SealError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            SealError::MissingSeal => crate::tr!("seal.missing_seal"),
+            SealError::MissingMagic => crate::tr!("seal.missing_magic"),
+            SealError::UnsupportedVersion(version) => {
+                crate::tr!("seal.unsupported_version", version = version)
+            }
+            SealError::Truncated => crate::tr!("seal.truncated"),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+    use std::str::FromStr;
+
+    fn sealed() -> Png {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        seal(&mut png);
+        Png::try_from(png.as_bytes().as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_seal_round_trip() {
+        let png = sealed();
+        assert_eq!(verify(&png).unwrap(), []);
+        let chunk = png.chunk("seAL").unwrap();
+        assert_eq!(Seal::try_from(chunk).unwrap().len(), 7);
+    }
+
+    #[test]
+    fn test_detects_changes() {
+        let mut png = sealed();
+        let index = png.chunks().len() - 1;
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        png.insert_chunk(index, Chunk::new(chunk_type, b"new".to_vec()))
+            .unwrap();
+        assert_eq!(
+            verify(&png).unwrap(),
+            [SealMismatch::Added("ruSt".to_string())]
+        );
+
+        let mut png = sealed();
+        png.remove_chunk_at(1).unwrap();
+        let chunk_type = ChunkType::from_str("sRGB").unwrap();
+        png.insert_chunk(1, Chunk::new(chunk_type, vec![1]))
+            .unwrap();
+        png.set_trailer(b"extra".to_vec());
+        assert_eq!(
+            verify(&png).unwrap(),
+            [
+                SealMismatch::Modified("sRGB".to_string()),
+                SealMismatch::Trailer
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsealed() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(verify(&png).is_err());
+    }
+}
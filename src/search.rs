@@ -0,0 +1,152 @@
+use std::fmt::Display;
+
+use regex::bytes::Regex;
+
+use crate::{chunk_type::ChunkType, metadata, png::Png, text_encoding::TextEncoding, Result};
+
+/// What to look for in chunk payloads: a regular expression matched against
+/// the raw bytes, or an exact byte sequence.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Regex(Regex),
+    Bytes(Vec<u8>),
+}
+
+impl Pattern {
+    pub fn regex(pattern: &str) -> Result<Pattern> {
+        Ok(Pattern::Regex(Regex::new(pattern)?))
+    }
+    /// An exact byte sequence written as hex digits, e.g. `deadbeef`.
+    /// Whitespace between bytes is ignored.
+    pub fn hex(pattern: &str) -> Result<Pattern> {
+        match TextEncoding::Hex.encode(pattern) {
+            Ok(bytes) if !bytes.is_empty() => Ok(Pattern::Bytes(bytes)),
+            _ => Err(Box::new(SearchError::InvalidHex(pattern.to_string()))),
+        }
+    }
+    /// The start and end of each non-overlapping match in `haystack`.
+    fn find_all(&self, haystack: &[u8]) -> Vec<(usize, usize)> {
+        match self {
+            Pattern::Regex(regex) => regex
+                .find_iter(haystack)
+                .map(|m| (m.start(), m.end()))
+                .collect(),
+            Pattern::Bytes(needle) => {
+                let mut found = Vec::new();
+                let mut start = 0;
+                while let Some(position) = haystack
+                    .get(start..)
+                    .and_then(|rest| rest.windows(needle.len()).position(|w| w == needle))
+                {
+                    found.push((start + position, start + position + needle.len()));
+                    start += position + needle.len();
+                }
+                found
+            }
+        }
+    }
+}
+
+/// A match inside the payload of one chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadMatch {
+    pub chunk_index: usize,
+    pub chunk_type: ChunkType,
+    /// Where the match starts, relative to the chunk data, or to the
+    /// inflated text when `decompressed` is set.
+    pub offset: usize,
+    pub length: usize,
+    pub decompressed: bool,
+}
+
+/// Searches every chunk payload of `png`. With `decompress`, the text of
+/// compressed `zTXt` and `iTXt` chunks is inflated and searched as well.
+pub fn search(png: &Png, pattern: &Pattern, decompress: bool) -> Vec<PayloadMatch> {
+    let mut matches = Vec::new();
+    for (chunk_index, chunk) in png.chunks().iter().enumerate() {
+        let mut haystacks = vec![(chunk.data().to_vec(), false)];
+        if decompress {
+            if let Some(text) = metadata::inflate_text(chunk) {
+                haystacks.push((text, true));
+            }
+        }
+        for (haystack, decompressed) in haystacks {
+            for (start, end) in pattern.find_all(&haystack) {
+                matches.push(PayloadMatch {
+                    chunk_index,
+                    chunk_type: chunk.chunk_type().clone(),
+                    offset: start,
+                    length: end - start,
+                    decompressed,
+                });
+            }
+        }
+    }
+    matches
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SearchError {
+    InvalidHex(String),
+}
+impl std::error::Error for SearchError {}
+impl Display for SearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            SearchError::InvalidHex(pattern) => {
+                crate::tr!("search.invalid_hex", pattern = format!("{:?}", pattern))
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::png::tests::PNG_FILE;
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_search_regex_and_bytes() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+
+        let found = search(&png, &Pattern::regex("h[a-z]y").unwrap(), false);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].chunk_type.to_string(), "RuSt");
+        assert_eq!(found[0].chunk_index, 5);
+        assert_eq!((found[0].offset, found[0].length), (0, 3));
+
+        let found = search(&png, &Pattern::hex("65 79").unwrap(), false);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].offset, 1);
+
+        assert!(Pattern::hex("abc").is_err());
+        assert!(Pattern::hex("zz").is_err());
+        let error = Pattern::hex("+f").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid hex pattern \"+f\"");
+        assert!(Pattern::regex("(").is_err());
+    }
+
+    #[test]
+    fn test_search_decompressed_text() {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"the token is 42").unwrap();
+        let mut data = b"Comment\0\0".to_vec();
+        data.extend(encoder.finish().unwrap());
+
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.insert_chunk(1, Chunk::new(ChunkType::from_str("zTXt").unwrap(), data))
+            .unwrap();
+
+        let pattern = Pattern::regex("token").unwrap();
+        assert!(search(&png, &pattern, false).is_empty());
+        let found = search(&png, &pattern, true);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].decompressed);
+        assert_eq!(found[0].offset, 4);
+    }
+}
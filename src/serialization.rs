@@ -0,0 +1,105 @@
+//! Serde support, enabled with the `serde` feature. Chunk types serialize as
+//! their four-letter names and payloads as standard base64, so a `Png`
+//! round-trips through JSON or YAML as a readable list of chunks.
+
+use std::str::FromStr;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, png::Png};
+
+impl Serialize for ChunkType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for ChunkType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        ChunkType::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChunkRepr {
+    #[serde(rename = "type")]
+    chunk_type: ChunkType,
+    data: String,
+}
+
+impl Serialize for Chunk {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ChunkRepr {
+            chunk_type: self.chunk_type().clone(),
+            data: STANDARD.encode(self.data()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Chunk {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = ChunkRepr::deserialize(deserializer)?;
+        let data = STANDARD.decode(repr.data).map_err(de::Error::custom)?;
+        Ok(Chunk::new(repr.chunk_type, data))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PngRepr {
+    chunks: Vec<Chunk>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    trailer: String,
+}
+
+impl Serialize for Png {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PngRepr {
+            chunks: self.chunks().to_vec(),
+            trailer: STANDARD.encode(self.trailer()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Png {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = PngRepr::deserialize(deserializer)?;
+        let trailer = STANDARD.decode(repr.trailer).map_err(de::Error::custom)?;
+        let mut png = Png::from_chunks(repr.chunks);
+        png.set_trailer(trailer);
+        Ok(png)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    #[test]
+    fn test_chunk_json() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), b"hey".to_vec());
+        let json = serde_json::to_string(&chunk).unwrap();
+        assert_eq!(json, r#"{"type":"RuSt","data":"aGV5"}"#);
+
+        let parsed: Chunk = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.as_bytes(), chunk.as_bytes());
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let json = serde_json::to_string(&png).unwrap();
+        let parsed: Png = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_invalid_chunk_type() {
+        let result = serde_json::from_str::<Chunk>(r#"{"type":"R1St","data":""}"#);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt::Display;
+
+use sha2::{Digest, Sha256};
+
+use crate::{Error, Result};
+
+/// The chunk type shards are stored in: ancillary, private and safe to copy.
+pub const CHUNK_TYPE: &str = "shRd";
+
+/// One piece of a payload that has been spread across several carrier
+/// images. Every shard records its position, the number of shards and the
+/// SHA-256 of the whole payload, which identifies the set and verifies the
+/// reassembled result.
+///
+/// The encoding is the magic bytes, a version byte, big-endian `u16` index
+/// and total, the 32-byte digest, then the shard's slice of the payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shard {
+    index: u16,
+    total: u16,
+    payload_hash: [u8; 32],
+    data: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for Shard {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        if !bytes.starts_with(&Shard::MAGIC) {
+            return Err(Box::new(ShardError::MissingMagic));
+        }
+        if bytes.len() < Shard::HEADER_LENGTH {
+            return Err(Box::new(ShardError::Truncated));
+        }
+        let version = bytes[Shard::MAGIC.len()];
+        if version != Shard::VERSION {
+            return Err(Box::new(ShardError::UnsupportedVersion(version)));
+        }
+
+        let header = &bytes[Shard::MAGIC.len() + 1..Shard::HEADER_LENGTH];
+        Ok(Shard {
+            index: u16::from_be_bytes([header[0], header[1]]),
+            total: u16::from_be_bytes([header[2], header[3]]),
+            payload_hash: header[4..36].try_into()?,
+            data: bytes[Shard::HEADER_LENGTH..].to_vec(),
+        })
+    }
+}
+
+impl Shard {
+    pub const MAGIC: [u8; 4] = *b"PMsh";
+    pub const VERSION: u8 = 1;
+    const HEADER_LENGTH: usize = 4 + 1 + 2 + 2 + 32;
+
+    /// Zero-based position of this shard within its set.
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+    pub fn total(&self) -> u16 {
+        self.total
+    }
+    pub fn payload_hash(&self) -> &[u8; 32] {
+        &self.payload_hash
+    }
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Shard::MAGIC.to_vec();
+        bytes.push(Shard::VERSION);
+        bytes.extend_from_slice(&self.index.to_be_bytes());
+        bytes.extend_from_slice(&self.total.to_be_bytes());
+        bytes.extend_from_slice(&self.payload_hash);
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+}
+
+/// Splits `payload` into `count` shards of near-equal size.
+pub fn split(payload: &[u8], count: usize) -> Result<Vec<Shard>> {
+    let total: u16 = match count.try_into() {
+        Ok(total) if total > 0 => total,
+        _ => return Err(Box::new(ShardError::InvalidShardCount(count))),
+    };
+    let payload_hash: [u8; 32] = Sha256::digest(payload).into();
+    let size = payload.len().div_ceil(count).max(1);
+
+    Ok((0..total)
+        .map(|index| {
+            let start = (index as usize * size).min(payload.len());
+            let end = (start + size).min(payload.len());
+            Shard {
+                index,
+                total,
+                payload_hash,
+                data: payload[start..end].to_vec(),
+            }
+        })
+        .collect())
+}
+
+/// Reassembles a payload from all of its shards, in any order.
+pub fn join(mut shards: Vec<Shard>) -> Result<Vec<u8>> {
+    let first = shards.first().ok_or(ShardError::MissingShard(0))?;
+    let (total, payload_hash) = (first.total, first.payload_hash);
+    if shards
+        .iter()
+        .any(|s| s.total != total || s.payload_hash != payload_hash)
+    {
+        return Err(Box::new(ShardError::MixedSets));
+    }
+
+    shards.sort_by_key(|s| s.index);
+    shards.dedup_by_key(|s| s.index);
+    for expected in 0..total {
+        if shards.get(expected as usize).map(|s| s.index) != Some(expected) {
+            return Err(Box::new(ShardError::MissingShard(expected)));
+        }
+    }
+
+    let payload: Vec<u8> = shards.into_iter().flat_map(|s| s.data).collect();
+    let actual: [u8; 32] = Sha256::digest(&payload).into();
+    if actual != payload_hash {
+        return Err(Box::new(ShardError::HashMismatch));
+    }
+    Ok(payload)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ShardError {
+    MissingMagic,
+    Truncated,
+    UnsupportedVersion(u8),
+    InvalidShardCount(usize),
+    MissingShard(u16),
+    MixedSets,
+    HashMismatch,
+}
+impl std::error::Error for ShardError {}
+impl Display for ShardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            ShardError::MissingMagic => crate::tr!("shard.missing_magic"),
+            ShardError::Truncated => crate::tr!("shard.truncated"),
+            ShardError::UnsupportedVersion(version) => {
+                crate::tr!("shard.unsupported_version", version = version)
+            }
+            ShardError::InvalidShardCount(count) => {
+                crate::tr!("shard.invalid_shard_count", count = count)
+            }
+            ShardError::MissingShard(index) => crate::tr!("shard.missing_shard", index = index),
+            ShardError::MixedSets => crate::tr!("shard.mixed_sets"),
+            ShardError::HashMismatch => crate::tr!("shard.hash_mismatch"),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_join() {
+        let payload = b"a payload spread over three images".to_vec();
+        let mut shards = split(&payload, 3).unwrap();
+        assert_eq!(shards.len(), 3);
+        assert!(shards.iter().all(|s| s.total() == 3));
+
+        shards.reverse();
+        let parsed: Vec<Shard> = shards
+            .iter()
+            .map(|s| Shard::try_from(s.to_bytes().as_ref()).unwrap())
+            .collect();
+        assert_eq!(join(parsed).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_more_shards_than_bytes() {
+        let shards = split(b"ab", 4).unwrap();
+        assert_eq!(shards.len(), 4);
+        assert!(shards[3].data().is_empty());
+        assert_eq!(join(shards).unwrap(), b"ab");
+    }
+
+    #[test]
+    fn test_missing_and_mixed_shards() {
+        let mut shards = split(b"hello world", 3).unwrap();
+        shards.remove(1);
+        assert!(join(shards.clone()).is_err());
+
+        shards.push(split(b"other", 3).unwrap().remove(1));
+        assert!(join(shards).is_err());
+        assert!(split(b"x", 0).is_err());
+    }
+}
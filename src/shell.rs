@@ -0,0 +1,230 @@
+use std::io::{BufRead, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::png::{EditSession, Png};
+use pngme::tr;
+
+use crate::commands;
+
+const HELP: &str = "\
+Commands:
+  list                      List chunks with their index
+  decode <type> [n]         Print the n-th chunk of a type (default 0)
+  encode <type> <message>   Append a chunk holding the message
+  remove <type> [n]         Remove the n-th chunk of a type (default 0)
+  undo                      Revert the last change
+  save [path]               Write the PNG (defaults to the opened file)
+  help                      Show this help
+  quit                      Leave the shell";
+
+/// An interactive session over a PNG that is parsed once and only written
+/// back on `save`. Unsafe-to-copy chunks are dropped on save once the
+/// critical chunks have been edited.
+pub struct Shell {
+    file_path: PathBuf,
+    session: EditSession,
+    history: Vec<Png>,
+}
+
+impl Shell {
+    pub fn new(file_path: &Path, png: Png) -> Shell {
+        Shell {
+            file_path: file_path.to_owned(),
+            session: EditSession::new(png),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn run(&mut self) {
+        let stdin = std::io::stdin();
+        let mut lines = stdin.lock().lines();
+
+        loop {
+            print!("pngme> ");
+            std::io::stdout().flush().expect("Failed to flush stdout");
+
+            let line = match lines.next() {
+                Some(line) => line.expect("Failed to read command"),
+                None => break,
+            };
+            match self.execute(&line, &mut std::io::stdout()) {
+                Ok(ControlFlow::Continue(())) => {}
+                Ok(ControlFlow::Break(())) => break,
+                Err(e) => eprintln!("{}", tr!("shell.error", error = e)),
+            }
+        }
+    }
+
+    /// Runs one line typed at the prompt, writing what it prints to `out`.
+    /// Breaks once the user asks to leave.
+    pub fn execute(&mut self, line: &str, out: &mut impl Write) -> pngme::Result<ControlFlow<()>> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["quit"] | ["exit"] => return Ok(ControlFlow::Break(())),
+            ["help"] => writeln!(out, "{}", HELP)?,
+            ["list"] => {
+                for (index, chunk) in self.session.png().chunks().iter().enumerate() {
+                    let entry = tr!(
+                        "shell.list_entry",
+                        index = format!("{:>4}", index),
+                        chunk_type = chunk.chunk_type(),
+                        length = chunk.length()
+                    );
+                    writeln!(out, "{}", entry)?;
+                }
+            }
+            ["decode", chunk_type, rest @ ..] => {
+                let index = self.find(chunk_type, rest)?;
+                let message = self.session.png().chunks()[index].data_as_string()?;
+                writeln!(out, "{}", message)?;
+            }
+            ["encode", chunk_type, message @ ..] if !message.is_empty() => {
+                let chunk_type = ChunkType::from_str(chunk_type)?;
+                let chunk = Chunk::new(chunk_type, message.join(" ").into_bytes());
+                self.checkpoint();
+                self.session.png_mut().append_chunk(chunk);
+            }
+            ["remove", chunk_type, rest @ ..] => {
+                let index = self.find(chunk_type, rest)?;
+                self.checkpoint();
+                let chunk = self.session.png_mut().remove_chunk_at(index)?;
+                let removed = tr!(
+                    "shell.removed",
+                    chunk_type = chunk.chunk_type(),
+                    length = chunk.length()
+                );
+                writeln!(out, "{}", removed)?;
+            }
+            ["undo"] => match self.history.pop() {
+                Some(png) => *self.session.png_mut() = png,
+                None => return Err(tr!("shell.nothing_to_undo").into()),
+            },
+            ["save"] => commands::write_png(&self.file_path, &self.session.save())?,
+            ["save", path] => commands::write_png(Path::new(path), &self.session.save())?,
+            words => {
+                let command = format!("{:?}", words.join(" "));
+                return Err(tr!("shell.unknown_command", command = command).into());
+            }
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Resolves `<type> [n]` to the index of the n-th chunk of that type.
+    fn find(&self, chunk_type: &str, rest: &[&str]) -> pngme::Result<usize> {
+        let n = match rest {
+            [] => 0,
+            [n] => n.parse()?,
+            _ => return Err(tr!("shell.too_many_indices").into()),
+        };
+        self.session
+            .png()
+            .chunks()
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.chunk_type().to_string() == chunk_type)
+            .nth(n)
+            .map(|(index, _)| index)
+            .ok_or_else(|| tr!("shell.no_chunk", chunk_type = chunk_type, index = n).into())
+    }
+
+    fn checkpoint(&mut self) {
+        self.history.push(self.session.png().clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell() -> Shell {
+        let png = pngme::encoder::solid_color(8, 8, [0x20, 0x40, 0x80]).unwrap();
+        Shell::new(Path::new("unused.png"), png)
+    }
+
+    /// Runs `line`, returning what it printed.
+    fn run(shell: &mut Shell, line: &str) -> pngme::Result<String> {
+        let mut out = Vec::new();
+        assert_eq!(shell.execute(line, &mut out)?, ControlFlow::Continue(()));
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_parsing() {
+        let mut shell = shell();
+        let mut out = Vec::new();
+        assert_eq!(
+            shell.execute("  ", &mut out).unwrap(),
+            ControlFlow::Continue(())
+        );
+        assert_eq!(
+            shell.execute("quit", &mut out).unwrap(),
+            ControlFlow::Break(())
+        );
+        assert_eq!(
+            shell.execute(" exit ", &mut out).unwrap(),
+            ControlFlow::Break(())
+        );
+        assert!(out.is_empty());
+
+        assert!(run(&mut shell, "frobnicate").is_err());
+        assert!(run(&mut shell, "encode ruSt").is_err());
+        assert!(run(&mut shell, "encode r1St hi").is_err());
+        assert!(run(&mut shell, "help").unwrap().starts_with("Commands:"));
+
+        run(&mut shell, "encode ruSt hello   there").unwrap();
+        let listing = run(&mut shell, "list").unwrap();
+        assert_eq!(listing.lines().nth(2).unwrap(), "   2  ruSt  11 bytes");
+        assert_eq!(run(&mut shell, "decode ruSt").unwrap(), "hello there\n");
+    }
+
+    #[test]
+    fn test_find_with_index() {
+        let mut shell = shell();
+        run(&mut shell, "encode ruSt first").unwrap();
+        run(&mut shell, "encode ruSt second").unwrap();
+
+        assert_eq!(run(&mut shell, "decode ruSt 0").unwrap(), "first\n");
+        assert_eq!(run(&mut shell, "decode ruSt 1").unwrap(), "second\n");
+        assert!(run(&mut shell, "decode ruSt 2").is_err());
+        assert!(run(&mut shell, "decode ruSt one").is_err());
+        assert!(run(&mut shell, "decode ruSt 0 1").is_err());
+        assert!(run(&mut shell, "decode teSt").is_err());
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut shell = shell();
+        run(&mut shell, "encode ruSt first").unwrap();
+        run(&mut shell, "encode ruSt second").unwrap();
+        let removed = run(&mut shell, "remove ruSt 0").unwrap();
+        assert_eq!(removed, "removed ruSt (5 bytes)\n");
+        assert_eq!(run(&mut shell, "decode ruSt").unwrap(), "second\n");
+
+        run(&mut shell, "undo").unwrap();
+        assert_eq!(run(&mut shell, "decode ruSt").unwrap(), "first\n");
+        run(&mut shell, "undo").unwrap();
+        run(&mut shell, "undo").unwrap();
+        assert!(run(&mut shell, "decode ruSt").is_err());
+        assert!(run(&mut shell, "undo").is_err());
+    }
+
+    #[test]
+    fn test_save() {
+        let dir = std::env::temp_dir().join(format!("pngme-shell-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("saved.png");
+        let mut shell = shell();
+        run(&mut shell, "encode ruSt kept").unwrap();
+        run(&mut shell, &format!("save {}", path.display())).unwrap();
+
+        let saved = Png::try_from(std::fs::read(&path).unwrap().as_slice()).unwrap();
+        assert_eq!(saved.chunk("ruSt").unwrap().data(), b"kept");
+        assert!(run(&mut shell, "save a b").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
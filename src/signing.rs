@@ -0,0 +1,126 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, png::Png, Result};
+
+/// The chunk type signatures are stored in: ancillary and private, and
+/// unsafe to copy because it is only valid for the critical chunks it was
+/// made over.
+pub const CHUNK_TYPE: &str = "siGN";
+
+/// The bytes a signature covers: every critical chunk, in file order, in
+/// its serialized form including length, type and CRC. Ancillary chunks,
+/// the signature itself among them, are not covered, so metadata can be
+/// edited without invalidating the signature.
+pub fn signed_bytes(png: &Png) -> Vec<u8> {
+    png.chunks()
+        .iter()
+        .filter(|c| c.chunk_type().is_critical())
+        .flat_map(Chunk::as_bytes)
+        .collect()
+}
+
+/// Signs the critical chunks of `png` and stores the signature in a
+/// [`CHUNK_TYPE`] chunk, replacing any previous signature.
+pub fn sign(png: &mut Png, key: &SigningKey) {
+    let signature = key.sign(&signed_bytes(png));
+    png.retain_chunks(|c| c.chunk_type().to_string() != CHUNK_TYPE);
+    let chunk_type = ChunkType::from_str(CHUNK_TYPE).unwrap();
+    png.append_chunk(Chunk::new(chunk_type, signature.to_bytes().to_vec()));
+}
+
+/// Checks the stored signature against the critical chunks of `png`.
+pub fn verify(png: &Png, key: &VerifyingKey) -> Result<()> {
+    let chunk = png
+        .chunk(CHUNK_TYPE)
+        .ok_or(SigningError::MissingSignature)?;
+    let signature =
+        Signature::from_slice(chunk.checked_data()?).map_err(|_| SigningError::InvalidSignature)?;
+    key.verify(&signed_bytes(png), &signature)
+        .map_err(|_| SigningError::InvalidSignature)?;
+    Ok(())
+}
+
+/// Reads a 32-byte key given either as raw bytes or as hex text.
+pub fn parse_key(bytes: &[u8]) -> Result<[u8; 32]> {
+    if let Ok(key) = bytes.try_into() {
+        return Ok(key);
+    }
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| SigningError::InvalidKey)?
+        .trim();
+    if text.len() != 64 || !text.is_ascii() {
+        return Err(Box::new(SigningError::InvalidKey));
+    }
+    let mut key = [0u8; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16)
+            .map_err(|_| SigningError::InvalidKey)?;
+    }
+    Ok(key)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SigningError {
+    MissingSignature,
+    InvalidSignature,
+    InvalidKey,
+}
+impl std::error::Error for SigningError {}
+impl Display for SigningError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            SigningError::MissingSignature => {
+                crate::tr!("signing.missing_signature", chunk_type = CHUNK_TYPE)
+            }
+            SigningError::InvalidSignature => crate::tr!("signing.invalid_signature"),
+            SigningError::InvalidKey => crate::tr!("signing.invalid_key"),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::{tests::PNG_FILE, RemovePolicy};
+
+    fn key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32])
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        sign(&mut png, &key());
+        sign(&mut png, &key());
+        assert_eq!(png.chunks_of(CHUNK_TYPE).count(), 1);
+
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert!(verify(&png, &key().verifying_key()).is_ok());
+
+        let other = SigningKey::from_bytes(&[8; 32]).verifying_key();
+        assert!(verify(&png, &other).is_err());
+    }
+
+    #[test]
+    fn test_ancillary_edits_keep_signature() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        sign(&mut png, &key());
+        png.remove_first("gAMA", RemovePolicy::Protect).unwrap();
+        assert!(verify(&png, &key().verifying_key()).is_ok());
+
+        png.remove_first("IDAT", RemovePolicy::Force).unwrap();
+        assert!(verify(&png, &key().verifying_key()).is_err());
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key(&[1; 32]).unwrap(), [1; 32]);
+        let hex = format!("{}\n", "ab".repeat(32));
+        assert_eq!(parse_key(hex.as_bytes()).unwrap(), [0xab; 32]);
+        assert!(parse_key(b"short").is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// What a payload appears to be, judged from its contents alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadType {
+    Png,
+    Jpeg,
+    Gif,
+    Zip,
+    Gzip,
+    Pdf,
+    Json,
+    /// UTF-8 without control characters other than tabs and line breaks.
+    Text,
+    /// A serialized protocol buffer message. Protobuf has no header, so
+    /// this is a guess: every byte must form a well-formed field.
+    Protobuf,
+    Binary,
+}
+
+/// File signatures, checked in order against the start of a payload.
+const MAGIC: [(&[u8], PayloadType); 8] = [
+    (b"\x89PNG\r\n\x1a\n", PayloadType::Png),
+    (b"\xff\xd8\xff", PayloadType::Jpeg),
+    (b"GIF87a", PayloadType::Gif),
+    (b"GIF89a", PayloadType::Gif),
+    (b"PK\x03\x04", PayloadType::Zip),
+    (b"PK\x05\x06", PayloadType::Zip),
+    (b"\x1f\x8b", PayloadType::Gzip),
+    (b"%PDF-", PayloadType::Pdf),
+];
+
+impl Display for PayloadType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.mime_type())
+    }
+}
+
+impl PayloadType {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            PayloadType::Png => "image/png",
+            PayloadType::Jpeg => "image/jpeg",
+            PayloadType::Gif => "image/gif",
+            PayloadType::Zip => "application/zip",
+            PayloadType::Gzip => "application/gzip",
+            PayloadType::Pdf => "application/pdf",
+            PayloadType::Json => "application/json",
+            PayloadType::Text => "text/plain",
+            PayloadType::Protobuf => "application/x-protobuf",
+            PayloadType::Binary => "application/octet-stream",
+        }
+    }
+    /// The usual file extension, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            PayloadType::Png => "png",
+            PayloadType::Jpeg => "jpg",
+            PayloadType::Gif => "gif",
+            PayloadType::Zip => "zip",
+            PayloadType::Gzip => "gz",
+            PayloadType::Pdf => "pdf",
+            PayloadType::Json => "json",
+            PayloadType::Text => "txt",
+            PayloadType::Protobuf => "pb",
+            PayloadType::Binary => "bin",
+        }
+    }
+    /// `path` with this type's extension added, unless it already ends in
+    /// it or, for JPEG, in `.jpeg`.
+    pub fn with_extension(self, path: &Path) -> PathBuf {
+        let current = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        match current.as_deref() {
+            Some(ext) if ext == self.extension() => path.to_owned(),
+            Some("jpeg") if self == PayloadType::Jpeg => path.to_owned(),
+            _ => {
+                let mut name = path.as_os_str().to_owned();
+                name.push(".");
+                name.push(self.extension());
+                PathBuf::from(name)
+            }
+        }
+    }
+}
+
+/// The type of `data`, from its signature if it has one and otherwise from
+/// what its bytes look like.
+pub fn sniff(data: &[u8]) -> PayloadType {
+    if let Some((_, payload_type)) = MAGIC.iter().find(|(magic, _)| data.starts_with(magic)) {
+        return *payload_type;
+    }
+    if let Ok(text) = std::str::from_utf8(data) {
+        let trimmed = text.trim_start();
+        if (trimmed.starts_with('{') || trimmed.starts_with('['))
+            && serde_json::from_str::<serde_json::Value>(text).is_ok()
+        {
+            return PayloadType::Json;
+        }
+        if !text
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r'))
+        {
+            return PayloadType::Text;
+        }
+    }
+    if is_protobuf(data) {
+        return PayloadType::Protobuf;
+    }
+    PayloadType::Binary
+}
+
+/// Whether `data` parses as a sequence of protobuf fields with valid field
+/// numbers and wire types whose lengths end exactly at the end of the data.
+fn is_protobuf(mut data: &[u8]) -> bool {
+    if data.is_empty() {
+        return false;
+    }
+    while !data.is_empty() {
+        let Some(key) = read_varint(&mut data) else {
+            return false;
+        };
+        if key >> 3 == 0 || key >> 3 > 0x1fff_ffff {
+            return false;
+        }
+        let length = match key & 7 {
+            0 => match read_varint(&mut data) {
+                Some(_) => 0,
+                None => return false,
+            },
+            1 => 8,
+            2 => match read_varint(&mut data).and_then(|n| usize::try_from(n).ok()) {
+                Some(n) => n,
+                None => return false,
+            },
+            5 => 4,
+            _ => return false,
+        };
+        if length > data.len() {
+            return false;
+        }
+        data = &data[length..];
+    }
+    true
+}
+
+/// Reads a base-128 varint of at most ten bytes from the front of `data`.
+fn read_varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for (i, byte) in data.iter().take(10).enumerate() {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *data = &data[i + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(&PNG_FILE), PayloadType::Png);
+        assert_eq!(sniff(b"\xff\xd8\xff\xe0\x00\x10JFIF"), PayloadType::Jpeg);
+        assert_eq!(sniff(b"PK\x03\x04\x14\x00"), PayloadType::Zip);
+        assert_eq!(sniff(b"\x1f\x8b\x08\x00"), PayloadType::Gzip);
+        assert_eq!(sniff(b" {\"a\": [1, 2]}\n"), PayloadType::Json);
+        assert_eq!(sniff(b"{not json"), PayloadType::Text);
+        assert_eq!(sniff("héllo\tworld\n".as_bytes()), PayloadType::Text);
+        // Field 1 as a varint of 150, then field 2 as the string "hi".
+        assert_eq!(sniff(b"\x08\x96\x01\x12\x02hi"), PayloadType::Protobuf);
+        assert_eq!(sniff(b"\x12\x09hi"), PayloadType::Binary);
+        assert_eq!(sniff(b"\x00\x01\x02"), PayloadType::Binary);
+    }
+
+    #[test]
+    fn test_with_extension() {
+        let png = PayloadType::Png;
+        assert_eq!(png.with_extension(Path::new("out")), Path::new("out.png"));
+        assert_eq!(
+            png.with_extension(Path::new("out.PNG")),
+            Path::new("out.PNG")
+        );
+        assert_eq!(
+            png.with_extension(Path::new("out.bin")),
+            Path::new("out.bin.png")
+        );
+        let jpeg = PayloadType::Jpeg;
+        assert_eq!(
+            jpeg.with_extension(Path::new("a.jpeg")),
+            Path::new("a.jpeg")
+        );
+    }
+}
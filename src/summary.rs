@@ -0,0 +1,305 @@
+use std::fmt::Display;
+
+use crate::{chunk::Chunk, chunk_type::STANDARD_CHUNK_TYPES, png::Png, validate::json_string};
+
+/// A run of consecutive chunks of one type, so a stream of dozens of IDAT
+/// chunks reads as a single entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkGroup {
+    pub chunk_type: String,
+    pub count: usize,
+    /// Payload bytes across the run, excluding chunk metadata.
+    pub data_size: usize,
+}
+
+/// Where the bytes of a PNG file go: image data, standard ancillary chunks,
+/// and embedded payloads (non-standard chunks and trailing data).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeSummary {
+    pub groups: Vec<ChunkGroup>,
+    pub file_size: usize,
+    /// Compressed image data across all IDAT chunks.
+    pub image_data_size: usize,
+    /// Bytes, including chunk metadata, taken by standard ancillary chunks.
+    pub ancillary_size: usize,
+    /// Bytes, including chunk metadata, taken by non-standard chunks plus any
+    /// data after IEND.
+    pub payload_size: usize,
+}
+
+impl SizeSummary {
+    pub fn new(png: &Png) -> SizeSummary {
+        let mut groups: Vec<ChunkGroup> = Vec::new();
+        let mut image_data_size = 0;
+        let mut ancillary_size = 0;
+        let mut payload_size = png.trailer().len();
+
+        for chunk in png.chunks() {
+            let chunk_type = chunk.chunk_type().to_string();
+            let total_size = chunk.length() + Chunk::METADATA_SIZE;
+
+            if chunk_type == "IDAT" {
+                image_data_size += chunk.length();
+            } else if !STANDARD_CHUNK_TYPES.contains(&chunk_type.as_str()) {
+                payload_size += total_size;
+            } else if !chunk.chunk_type().is_critical() {
+                ancillary_size += total_size;
+            }
+
+            match groups.last_mut() {
+                Some(group) if group.chunk_type == chunk_type => {
+                    group.count += 1;
+                    group.data_size += chunk.length();
+                }
+                _ => groups.push(ChunkGroup {
+                    chunk_type,
+                    count: 1,
+                    data_size: chunk.length(),
+                }),
+            }
+        }
+
+        SizeSummary {
+            groups,
+            file_size: png.as_bytes().len(),
+            image_data_size,
+            ancillary_size,
+            payload_size,
+        }
+    }
+    /// The share of the file taken by `size` bytes, as a percentage.
+    pub fn percentage(&self, size: usize) -> f64 {
+        if self.file_size == 0 {
+            0.0
+        } else {
+            size as f64 * 100.0 / self.file_size as f64
+        }
+    }
+}
+
+impl Display for SizeSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let idat_count: usize = self
+            .groups
+            .iter()
+            .filter(|g| g.chunk_type == "IDAT")
+            .map(|g| g.count)
+            .sum();
+        writeln!(f, "File size: {} bytes", self.file_size)?;
+        writeln!(
+            f,
+            "Image data: {} bytes in {} IDAT chunks ({:.1}%)",
+            self.image_data_size,
+            idat_count,
+            self.percentage(self.image_data_size)
+        )?;
+        writeln!(
+            f,
+            "Ancillary chunks: {} bytes ({:.1}%)",
+            self.ancillary_size,
+            self.percentage(self.ancillary_size)
+        )?;
+        writeln!(
+            f,
+            "Embedded payloads: {} bytes ({:.1}%)",
+            self.payload_size,
+            self.percentage(self.payload_size)
+        )
+    }
+}
+
+/// Chunk sizes of one type, aggregated across files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeStats {
+    pub chunk_type: String,
+    pub count: usize,
+    /// Payload bytes across all chunks, excluding chunk metadata.
+    pub data_size: usize,
+    pub min_size: usize,
+    pub max_size: usize,
+}
+
+impl TypeStats {
+    pub fn mean_size(&self) -> f64 {
+        self.data_size as f64 / self.count as f64
+    }
+    /// Bytes taken by the chunks including their length, type and CRC.
+    pub fn total_size(&self) -> usize {
+        self.data_size + self.count * Chunk::METADATA_SIZE
+    }
+}
+
+/// Counts and sizes per chunk type across any number of files, in the order
+/// the types are first seen.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkStats {
+    pub types: Vec<TypeStats>,
+    pub files: usize,
+    pub file_size: usize,
+}
+
+impl ChunkStats {
+    pub fn add(&mut self, png: &Png) {
+        self.files += 1;
+        self.file_size += png.as_bytes().len();
+        for chunk in png.chunks() {
+            let chunk_type = chunk.chunk_type().to_string();
+            let size = chunk.length();
+            match self.types.iter_mut().find(|t| t.chunk_type == chunk_type) {
+                Some(stats) => {
+                    stats.count += 1;
+                    stats.data_size += size;
+                    stats.min_size = stats.min_size.min(size);
+                    stats.max_size = stats.max_size.max(size);
+                }
+                None => self.types.push(TypeStats {
+                    chunk_type,
+                    count: 1,
+                    data_size: size,
+                    min_size: size,
+                    max_size: size,
+                }),
+            }
+        }
+    }
+    /// The share of all files taken by chunks of one type, as a percentage.
+    pub fn percentage(&self, stats: &TypeStats) -> f64 {
+        if self.file_size == 0 {
+            0.0
+        } else {
+            stats.total_size() as f64 * 100.0 / self.file_size as f64
+        }
+    }
+    pub fn to_json(&self) -> String {
+        let types: Vec<String> = self
+            .types
+            .iter()
+            .map(|t| {
+                format!(
+                    "{{\"type\":{},\"count\":{},\"data_size\":{},\"total_size\":{},\"min_size\":{},\"max_size\":{},\"mean_size\":{:.1},\"percentage\":{:.2}}}",
+                    json_string(&t.chunk_type),
+                    t.count,
+                    t.data_size,
+                    t.total_size(),
+                    t.min_size,
+                    t.max_size,
+                    t.mean_size(),
+                    self.percentage(t)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"files\":{},\"file_size\":{},\"types\":[{}]}}",
+            self.files,
+            self.file_size,
+            types.join(",")
+        )
+    }
+}
+
+impl Display for ChunkStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<4} {:>7} {:>11} {:>9} {:>9} {:>11} {:>6}",
+            "TYPE", "COUNT", "BYTES", "MIN", "MAX", "MEAN", "SHARE"
+        )?;
+        for t in &self.types {
+            writeln!(
+                f,
+                "{:<4} {:>7} {:>11} {:>9} {:>9} {:>11.1} {:>5.1}%",
+                t.chunk_type,
+                t.count,
+                t.data_size,
+                t.min_size,
+                t.max_size,
+                t.mean_size(),
+                self.percentage(t)
+            )?;
+        }
+        writeln!(f, "{} file(s), {} bytes", self.files, self.file_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use crate::png::tests::PNG_FILE;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    #[test]
+    fn test_consecutive_idat_chunks_are_grouped() {
+        let png = Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("IDAT", &[0; 10]),
+            chunk("IDAT", &[0; 20]),
+            chunk("ruSt", b"hidden"),
+            chunk("IEND", &[]),
+        ]);
+        let summary = SizeSummary::new(&png);
+
+        assert_eq!(summary.groups.len(), 4);
+        assert_eq!(
+            summary.groups[1],
+            ChunkGroup {
+                chunk_type: "IDAT".to_string(),
+                count: 2,
+                data_size: 30
+            }
+        );
+        assert_eq!(summary.image_data_size, 30);
+        assert_eq!(summary.payload_size, 6 + Chunk::METADATA_SIZE);
+        assert_eq!(summary.ancillary_size, 0);
+    }
+
+    #[test]
+    fn test_file_sizes_add_up() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let summary = SizeSummary::new(&png);
+
+        assert_eq!(summary.file_size, PNG_FILE.len());
+        assert_eq!(summary.payload_size, 3 + Chunk::METADATA_SIZE);
+        assert!(summary.image_data_size > summary.ancillary_size);
+    }
+
+    #[test]
+    fn test_chunk_stats_across_files() {
+        let mut stats = ChunkStats::default();
+        stats.add(&Png::try_from(&PNG_FILE[..]).unwrap());
+        stats.add(&Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("ruSt", b"a longer payload"),
+            chunk("IEND", &[]),
+        ]));
+
+        assert_eq!(stats.files, 2);
+        let rust = stats.types.iter().find(|t| t.chunk_type == "RuSt").unwrap();
+        assert_eq!((rust.count, rust.data_size), (1, 3));
+        let ihdr = &stats.types[0];
+        assert_eq!((ihdr.chunk_type.as_str(), ihdr.count), ("IHDR", 2));
+        assert_eq!((ihdr.min_size, ihdr.max_size), (13, 13));
+
+        let share: f64 = stats.types.iter().map(|t| stats.percentage(t)).sum();
+        let signatures = 2.0 * 8.0 * 100.0 / stats.file_size as f64;
+        assert!((share + signatures - 100.0).abs() < 1e-9);
+
+        let json: serde_json::Value = serde_json::from_str(&stats.to_json()).unwrap();
+        assert_eq!(json["types"][0]["type"], "IHDR");
+        assert_eq!(json["types"][0]["count"], 2);
+
+        // Types read with --raw need not be letters.
+        let mut raw = stats.types[0].clone();
+        raw.chunk_type = "\u{e9}\"\\\u{1}".to_string();
+        stats.types.push(raw);
+        let json: serde_json::Value = serde_json::from_str(&stats.to_json()).unwrap();
+        assert_eq!(
+            json["types"][stats.types.len() - 1]["type"],
+            "\u{e9}\"\\\u{1}"
+        );
+    }
+}
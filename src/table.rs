@@ -0,0 +1,294 @@
+use std::fmt::Display;
+
+use crate::{chunk::Chunk, validate::json_string};
+
+/// How a [`Table`] is written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Aligned columns for reading in a terminal.
+    Text,
+    /// An array with one object per row.
+    Json,
+    /// Comma-separated values with a header row, quoted per RFC 4180.
+    Csv,
+    /// Tab-separated values with a header row. Tabs and line breaks in
+    /// values are written as `\t`, `\n` and `\r`, and backslashes doubled.
+    Tsv,
+}
+
+/// One cell of a [`Table`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Text(String),
+    Number(u64),
+    /// Written as `null` in JSON and left blank otherwise.
+    Empty,
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Text(s.to_string())
+    }
+}
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Text(s)
+    }
+}
+impl From<usize> for Value {
+    fn from(n: usize) -> Self {
+        Value::Number(n as u64)
+    }
+}
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Empty, Into::into)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Text(s) => write!(f, "{}", s),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Empty => Ok(()),
+        }
+    }
+}
+
+/// Rows with named columns that any command can write in every [`Format`],
+/// so tabular output only has to be built once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    pub fn new(columns: &[&'static str]) -> Table {
+        Table {
+            columns: columns.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+    /// Adds a row, which must have a value for every column.
+    pub fn push(&mut self, row: Vec<Value>) {
+        assert_eq!(row.len(), self.columns.len(), "row does not fit the table");
+        self.rows.push(row);
+    }
+    pub fn rows(&self) -> &[Vec<Value>] {
+        &self.rows
+    }
+    /// The table in `format`, ending with a line break unless it is JSON.
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Text => self.render_text(),
+            Format::Json => self.render_json(),
+            Format::Csv => self.render_separated(',', |v| csv_field(&v.to_string())),
+            Format::Tsv => self.render_separated('\t', |v| tsv_field(&v.to_string())),
+        }
+    }
+    fn render_text(&self) -> String {
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(Value::to_string).collect())
+            .collect();
+        let widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                cells
+                    .iter()
+                    .map(|row| row[i].chars().count())
+                    .fold(column.len(), usize::max)
+            })
+            .collect();
+        let line = |cells: Vec<String>| {
+            let padded: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<1$}", cell, width))
+                .collect();
+            format!("{}\n", padded.join("  ").trim_end())
+        };
+        let header = self.columns.iter().map(|c| c.to_uppercase()).collect();
+        std::iter::once(line(header))
+            .chain(cells.into_iter().map(line))
+            .collect()
+    }
+    fn render_json(&self) -> String {
+        let rows: Vec<String> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let fields: Vec<String> = self
+                    .columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, value)| {
+                        let value = match value {
+                            Value::Text(s) => json_string(s),
+                            Value::Number(n) => n.to_string(),
+                            Value::Empty => "null".to_string(),
+                        };
+                        format!("{}:{}", json_string(column), value)
+                    })
+                    .collect();
+                format!("{{{}}}", fields.join(","))
+            })
+            .collect();
+        format!("[{}]", rows.join(","))
+    }
+    fn render_separated(&self, separator: char, field: impl Fn(&Value) -> String) -> String {
+        let separator = separator.to_string();
+        let mut out = format!("{}\n", self.columns.join(&separator));
+        for row in &self.rows {
+            let fields: Vec<String> = row.iter().map(&field).collect();
+            out.push_str(&fields.join(&separator));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// One chunk of one file, in the column layout shared by every command that
+/// lists chunks so that their CSV and TSV output can be combined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkRecord {
+    pub file: String,
+    /// The position of the chunk in the file, counting from 0.
+    pub index: usize,
+    pub offset: Option<usize>,
+    pub chunk_type: String,
+    pub length: usize,
+    pub crc: u32,
+    /// Any of `critical`, `private`, `safe-to-copy` and `bad-crc`, in that
+    /// order, followed by whatever the command adds.
+    pub flags: Vec<&'static str>,
+}
+
+impl ChunkRecord {
+    pub const COLUMNS: [&'static str; 7] =
+        ["file", "index", "offset", "type", "length", "crc", "flags"];
+
+    pub fn new(file: &str, index: usize, chunk: &Chunk) -> ChunkRecord {
+        let chunk_type = chunk.chunk_type();
+        let flags = [
+            (chunk_type.is_critical(), "critical"),
+            (!chunk_type.is_public(), "private"),
+            (chunk_type.is_safe_to_copy(), "safe-to-copy"),
+            (!chunk.is_crc_valid(), "bad-crc"),
+        ];
+        ChunkRecord {
+            file: file.to_string(),
+            index,
+            offset: chunk.offset(),
+            chunk_type: chunk_type.to_string(),
+            length: chunk.length(),
+            crc: chunk.crc(),
+            flags: flags.iter().filter(|f| f.0).map(|f| f.1).collect(),
+        }
+    }
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.contains(&flag)
+    }
+    /// The record's values, in the order of [`ChunkRecord::COLUMNS`]. Flags
+    /// are separated by spaces.
+    pub fn to_row(&self) -> Vec<Value> {
+        vec![
+            self.file.as_str().into(),
+            self.index.into(),
+            self.offset.into(),
+            self.chunk_type.as_str().into(),
+            self.length.into(),
+            format!("{:#010x}", self.crc).into(),
+            self.flags.join(" ").into(),
+        ]
+    }
+}
+
+/// `s` as a CSV field, quoted per RFC 4180 if it holds a comma, quote or
+/// line break.
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// `s` as a TSV field, with tabs, line breaks and backslashes escaped.
+pub fn tsv_field(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::{tests::PNG_FILE, Png};
+
+    fn table() -> Table {
+        let mut table = Table::new(&["name", "size", "note"]);
+        table.push(vec!["a.png".into(), 12.into(), Value::Empty]);
+        table.push(vec!["b, c.png".into(), 3.into(), "tab\there".into()]);
+        table
+    }
+
+    #[test]
+    fn test_render() {
+        let table = table();
+        assert_eq!(
+            table.render(Format::Text),
+            "NAME      SIZE  NOTE\na.png     12\nb, c.png  3     tab\there\n"
+        );
+        assert_eq!(
+            table.render(Format::Csv),
+            "name,size,note\na.png,12,\n\"b, c.png\",3,tab\there\n"
+        );
+        assert_eq!(
+            table.render(Format::Tsv),
+            "name\tsize\tnote\na.png\t12\t\nb, c.png\t3\ttab\\there\n"
+        );
+        let json: serde_json::Value = serde_json::from_str(&table.render(Format::Json)).unwrap();
+        assert_eq!(json[0]["size"], 12);
+        assert!(json[0]["note"].is_null());
+        assert_eq!(json[1]["name"], "b, c.png");
+    }
+
+    #[test]
+    fn test_chunk_record() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let records: Vec<ChunkRecord> = png
+            .chunks()
+            .iter()
+            .enumerate()
+            .map(|(i, c)| ChunkRecord::new("dice.png", i, c))
+            .collect();
+        assert_eq!(records[0].offset, Some(8));
+        assert_eq!(records[0].flags, ["critical"]);
+        assert_eq!(records[5].flags, ["critical", "private", "safe-to-copy"]);
+
+        let row = records[0].to_row();
+        assert_eq!(row.len(), ChunkRecord::COLUMNS.len());
+        assert_eq!(row[5].to_string(), format!("{:#010x}", records[0].crc));
+    }
+
+    #[test]
+    fn test_fields() {
+        assert_eq!(csv_field("a.png"), "a.png");
+        assert_eq!(csv_field("a,\"b\".png"), "\"a,\"\"b\"\".png\"");
+        assert_eq!(tsv_field("a\\b\r\n"), "a\\\\b\\r\\n");
+    }
+}
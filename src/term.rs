@@ -0,0 +1,121 @@
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clap::ValueEnum;
+use pngme::chunk_type::ChunkType;
+
+static STDOUT_COLOR: AtomicBool = AtomicBool::new(false);
+static STDERR_COLOR: AtomicBool = AtomicBool::new(false);
+
+/// When to color output with ANSI escape codes.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// When writing to a terminal, unless NO_COLOR is set or TERM is dumb
+    Auto,
+    Always,
+    Never,
+}
+
+/// What a piece of output is, which the theme decides the look of.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Critical chunk types, without which there is no image.
+    Critical,
+    /// Failures such as bad CRCs and rule violations.
+    Error,
+    /// Problems that do not fail the command.
+    Warning,
+    /// Checks that passed.
+    Ok,
+    /// Payload previews and other secondary detail.
+    Dim,
+}
+
+/// The SGR parameters each [`Role`] is drawn with.
+struct Theme {
+    critical: &'static str,
+    error: &'static str,
+    warning: &'static str,
+    ok: &'static str,
+    dim: &'static str,
+}
+
+const THEME: Theme = Theme {
+    critical: "1;36",
+    error: "1;31",
+    warning: "1;33",
+    ok: "32",
+    dim: "2",
+};
+
+impl Theme {
+    fn sgr(&self, role: Role) -> &'static str {
+        match role {
+            Role::Critical => self.critical,
+            Role::Error => self.error,
+            Role::Warning => self.warning,
+            Role::Ok => self.ok,
+            Role::Dim => self.dim,
+        }
+    }
+}
+
+/// Decides once whether stdout and stderr are colored.
+pub fn init(choice: ColorChoice) {
+    let auto = || {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let dumb = std::env::var_os("TERM").is_some_and(|term| term == "dumb");
+        !no_color && !dumb
+    };
+    let (stdout, stderr) = match choice {
+        ColorChoice::Always => (true, true),
+        ColorChoice::Never => (false, false),
+        ColorChoice::Auto if auto() => (
+            std::io::stdout().is_terminal(),
+            std::io::stderr().is_terminal(),
+        ),
+        ColorChoice::Auto => (false, false),
+    };
+    STDOUT_COLOR.store(stdout, Ordering::Relaxed);
+    STDERR_COLOR.store(stderr, Ordering::Relaxed);
+}
+
+/// A value shown in the theme's look for its role, or as it is when color
+/// is off.
+pub struct Painted<T> {
+    value: T,
+    sgr: Option<&'static str>,
+}
+
+impl<T: Display> Display for Painted<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.sgr {
+            Some(sgr) => write!(f, "\x1b[{}m{}\x1b[0m", sgr, self.value),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
+
+/// `value` styled for `role` on stdout.
+pub fn paint<T: Display>(value: T, role: Role) -> Painted<T> {
+    painted(value, Some(role), &STDOUT_COLOR)
+}
+
+/// `value` styled for `role` on stderr.
+pub fn paint_stderr<T: Display>(value: T, role: Role) -> Painted<T> {
+    painted(value, Some(role), &STDERR_COLOR)
+}
+
+/// A chunk type on stdout, highlighted if it is critical.
+pub fn chunk_type(chunk_type: &ChunkType) -> Painted<&ChunkType> {
+    let role = chunk_type.is_critical().then_some(Role::Critical);
+    painted(chunk_type, role, &STDOUT_COLOR)
+}
+
+fn painted<T>(value: T, role: Option<Role>, enabled: &AtomicBool) -> Painted<T> {
+    let sgr = role
+        .filter(|_| enabled.load(Ordering::Relaxed))
+        .map(|role| THEME.sgr(role));
+    Painted { value, sgr }
+}
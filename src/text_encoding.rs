@@ -0,0 +1,158 @@
+use std::fmt::Display;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::Result;
+
+/// How payload bytes are turned into text for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    /// Every byte is the Unicode code point of the same value, as in `tEXt`
+    /// chunks.
+    Latin1,
+    Hex,
+    Base64,
+}
+
+impl TextEncoding {
+    /// Guesses how to show `data`: UTF-8 if it is valid UTF-8 text, Latin-1
+    /// if every byte is printable in Latin-1, and `None` for binary data.
+    /// Control characters other than tabs and line breaks mean binary.
+    pub fn detect(data: &[u8]) -> Option<TextEncoding> {
+        let is_control = |c: char| c.is_control() && !matches!(c, '\t' | '\n' | '\r');
+        if let Ok(text) = std::str::from_utf8(data) {
+            return match text.chars().any(is_control) {
+                false => Some(TextEncoding::Utf8),
+                true => None,
+            };
+        }
+        match data.iter().any(|b| is_control(*b as char)) {
+            false => Some(TextEncoding::Latin1),
+            true => None,
+        }
+    }
+    /// With `lossy`, invalid UTF-8 is replaced with U+FFFD instead of
+    /// failing; the other encodings cannot fail.
+    pub fn decode(self, data: &[u8], lossy: bool) -> Result<String> {
+        Ok(match self {
+            TextEncoding::Utf8 if lossy => String::from_utf8_lossy(data).into_owned(),
+            TextEncoding::Utf8 => std::str::from_utf8(data)?.to_string(),
+            TextEncoding::Latin1 => data.iter().map(|b| *b as char).collect(),
+            TextEncoding::Hex => data.iter().map(|b| format!("{:02x}", b)).collect(),
+            TextEncoding::Base64 => STANDARD.encode(data),
+        })
+    }
+    /// Turns text written in this encoding back into bytes, the inverse of
+    /// [`TextEncoding::decode`]. Whitespace in hex and base64 is ignored.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>> {
+        let invalid = || TextEncodingError::InvalidInput(self);
+        let compact = || -> String { text.chars().filter(|c| !c.is_whitespace()).collect() };
+        match self {
+            TextEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            TextEncoding::Latin1 => text
+                .chars()
+                .map(|c| u8::try_from(u32::from(c)).map_err(|_| invalid().into()))
+                .collect(),
+            TextEncoding::Hex => {
+                let digits = compact();
+                // from_str_radix alone would also take a sign, as in "+f".
+                if !digits.len().is_multiple_of(2) || !digits.bytes().all(|b| b.is_ascii_hexdigit())
+                {
+                    return Err(Box::new(invalid()));
+                }
+                Ok((0..digits.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+                    .collect())
+            }
+            TextEncoding::Base64 => Ok(STANDARD.decode(compact()).map_err(|_| invalid())?),
+        }
+    }
+}
+
+impl Display for TextEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextEncoding::Utf8 => write!(f, "utf8"),
+            TextEncoding::Latin1 => write!(f, "latin1"),
+            TextEncoding::Hex => write!(f, "hex"),
+            TextEncoding::Base64 => write!(f, "base64"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TextEncodingError {
+    InvalidInput(TextEncoding),
+}
+impl std::error::Error for TextEncodingError {}
+impl Display for TextEncodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            TextEncodingError::InvalidInput(TextEncoding::Latin1) => {
+                crate::tr!("text_encoding.not_latin1")
+            }
+            TextEncodingError::InvalidInput(encoding) => {
+                crate::tr!("text_encoding.invalid_input", encoding = encoding)
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            TextEncoding::detect("héllo\n".as_bytes()),
+            Some(TextEncoding::Utf8)
+        );
+        assert_eq!(TextEncoding::detect(b"caf\xe9"), Some(TextEncoding::Latin1));
+        assert_eq!(TextEncoding::detect(b"\x89PNG\r\n\x1a\n"), None);
+        assert_eq!(TextEncoding::detect(b"nul\0"), None);
+        assert_eq!(TextEncoding::detect(b""), Some(TextEncoding::Utf8));
+    }
+
+    #[test]
+    fn test_decode() {
+        let data = b"caf\xe9";
+        assert!(TextEncoding::Utf8.decode(data, false).is_err());
+        assert_eq!(
+            TextEncoding::Utf8.decode(data, true).unwrap(),
+            "caf\u{fffd}"
+        );
+        assert_eq!(TextEncoding::Latin1.decode(data, false).unwrap(), "café");
+        assert_eq!(TextEncoding::Hex.decode(data, false).unwrap(), "636166e9");
+        assert_eq!(
+            TextEncoding::Base64.decode(data, false).unwrap(),
+            "Y2Fm6Q=="
+        );
+    }
+
+    #[test]
+    fn test_encode_inverts_decode() {
+        let data = b"\x00caf\xe9\xff";
+        for encoding in [
+            TextEncoding::Latin1,
+            TextEncoding::Hex,
+            TextEncoding::Base64,
+        ] {
+            let text = encoding.decode(data, false).unwrap();
+            assert_eq!(encoding.encode(&text).unwrap(), data, "{}", encoding);
+        }
+        assert_eq!(
+            TextEncoding::Hex.encode("de ad\nBE EF").unwrap(),
+            [0xde, 0xad, 0xbe, 0xef]
+        );
+        assert!(TextEncoding::Hex.encode("abc").is_err());
+        assert!(TextEncoding::Hex.encode("zz").is_err());
+        assert!(TextEncoding::Hex.encode("é1").is_err());
+        assert!(TextEncoding::Hex.encode("+f").is_err());
+        assert!(TextEncoding::Base64.encode("not base64!").is_err());
+        assert!(TextEncoding::Latin1.encode("日本").is_err());
+    }
+}
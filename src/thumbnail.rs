@@ -0,0 +1,199 @@
+//! Small previews of an image for showing in a terminal.
+
+use crate::image_data::PixelBuffer;
+
+/// How a [`Thumbnail`] is drawn as text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Style {
+    /// Two pixels per character cell using `▀` with 24-bit foreground and
+    /// background colors, which keeps pixels roughly square.
+    #[default]
+    HalfBlock,
+    /// One pixel per two character cells, drawn as colored spaces.
+    Block,
+    /// Characters chosen by brightness, for terminals without color.
+    Ascii,
+}
+
+impl Style {
+    /// How many pixels one character cell covers across and down.
+    fn cell(self) -> (usize, usize) {
+        match self {
+            Style::HalfBlock => (1, 2),
+            Style::Block => (2, 1),
+            Style::Ascii => (1, 2),
+        }
+    }
+}
+
+/// A downscaled copy of an image as 8-bit RGB, with transparency
+/// composited over black.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    style: Style,
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+/// Darkest to brightest.
+const ASCII_RAMP: &[u8] = b" .:-=+*#%@";
+
+impl Thumbnail {
+    /// Scales `image` down to fit `columns` character cells across when
+    /// drawn in `style`, keeping its aspect ratio. Images that already fit
+    /// are not scaled up.
+    pub fn new(image: &PixelBuffer, columns: usize, style: Style) -> Thumbnail {
+        let (cell_width, cell_height) = style.cell();
+        let (source_width, source_height) = (image.width() as usize, image.height() as usize);
+        let width = (columns / cell_width).clamp(1, source_width);
+        // Character cells are about twice as tall as they are wide; scale
+        // the height so that pixels come out square.
+        let height = (source_height * width * cell_width * cell_height)
+            .div_ceil(source_width * 2)
+            .max(1);
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let (top, bottom) = span(y, height, source_height);
+            for x in 0..width {
+                let (left, right) = span(x, width, source_width);
+                pixels.push(average(image, left..right, top..bottom));
+            }
+        }
+        Thumbnail {
+            style,
+            width,
+            height,
+            pixels,
+        }
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y * self.width + x]
+    }
+    /// The thumbnail as lines of text, one per row of character cells, with
+    /// ANSI escape codes unless the style is [`Style::Ascii`].
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        match self.style {
+            Style::HalfBlock => {
+                for y in (0..self.height).step_by(2) {
+                    for x in 0..self.width {
+                        let [r, g, b] = self.pixel(x, y);
+                        let [br, bg, bb] = match y + 1 < self.height {
+                            true => self.pixel(x, y + 1),
+                            false => [0, 0, 0],
+                        };
+                        out.push_str(&format!(
+                            "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀",
+                            r, g, b, br, bg, bb
+                        ));
+                    }
+                    out.push_str("\x1b[0m\n");
+                }
+            }
+            Style::Block => {
+                for y in 0..self.height {
+                    for x in 0..self.width {
+                        let [r, g, b] = self.pixel(x, y);
+                        out.push_str(&format!("\x1b[48;2;{};{};{}m  ", r, g, b));
+                    }
+                    out.push_str("\x1b[0m\n");
+                }
+            }
+            Style::Ascii => {
+                for y in (0..self.height).step_by(2) {
+                    for x in 0..self.width {
+                        let below = match y + 1 < self.height {
+                            true => luma(self.pixel(x, y + 1)),
+                            false => 0,
+                        };
+                        let luma = (luma(self.pixel(x, y)) as usize + below as usize) / 2;
+                        let index = luma * (ASCII_RAMP.len() - 1) / 255;
+                        out.push(ASCII_RAMP[index] as char);
+                    }
+                    out.push('\n');
+                }
+            }
+        }
+        out
+    }
+}
+
+/// The source range covered by target pixel `index` of `count`.
+fn span(index: usize, count: usize, source: usize) -> (usize, usize) {
+    let start = index * source / count;
+    let end = ((index + 1) * source / count).max(start + 1);
+    (start, end)
+}
+
+fn average(
+    image: &PixelBuffer,
+    columns: std::ops::Range<usize>,
+    rows: std::ops::Range<usize>,
+) -> [u8; 3] {
+    let mut sum = [0u64; 3];
+    let mut count = 0u64;
+    for y in rows {
+        for x in columns.clone() {
+            let [r, g, b, a] = image.rgba(x as u32, y as u32);
+            for (total, channel) in sum.iter_mut().zip([r, g, b]) {
+                *total += channel as u64 * a as u64 / u16::MAX as u64;
+            }
+            count += 1;
+        }
+    }
+    sum.map(|total| ((total / count.max(1)) >> 8) as u8)
+}
+
+fn luma([r, g, b]: [u8; 3]) -> u8 {
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder;
+    use crate::png::tests::PNG_FILE;
+    use crate::png::Png;
+
+    #[test]
+    fn test_downscale_keeps_aspect_ratio() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let pixels = png.decode_pixels().unwrap();
+
+        let thumbnail = Thumbnail::new(&pixels, 20, Style::HalfBlock);
+        assert_eq!((thumbnail.width(), thumbnail.height()), (20, 20));
+        let thumbnail = Thumbnail::new(&pixels, 20, Style::Block);
+        assert_eq!((thumbnail.width(), thumbnail.height()), (10, 10));
+        // Never scaled up.
+        let thumbnail = Thumbnail::new(&pixels, 500, Style::HalfBlock);
+        assert_eq!(thumbnail.width(), 50);
+    }
+
+    #[test]
+    fn test_solid_color() {
+        let png = encoder::solid_color(8, 8, [255, 128, 0]).unwrap();
+        let pixels = png.decode_pixels().unwrap();
+        let thumbnail = Thumbnail::new(&pixels, 4, Style::HalfBlock);
+        assert!(thumbnail.pixels.iter().all(|p| *p == [255, 128, 0]));
+
+        let text = thumbnail.render();
+        assert_eq!(text.lines().count(), thumbnail.height() / 2);
+        assert!(text.starts_with("\x1b[38;2;255;128;0m\x1b[48;2;255;128;0m▀"));
+    }
+
+    #[test]
+    fn test_ascii() {
+        let png = encoder::solid_color(4, 4, [255, 255, 255]).unwrap();
+        let pixels = png.decode_pixels().unwrap();
+        let thumbnail = Thumbnail::new(&pixels, 4, Style::Ascii);
+        assert_eq!(thumbnail.render(), "@@@@\n@@@@\n");
+    }
+}
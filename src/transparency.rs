@@ -0,0 +1,195 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, ihdr::Ihdr, Result};
+
+/// The simple transparency stored in a `tRNS` chunk. Its layout depends on
+/// the color type in `IHDR`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transparency {
+    /// The gray level that is fully transparent.
+    Gray(u16),
+    /// The RGB color that is fully transparent.
+    Rgb(u16, u16, u16),
+    /// Alpha values for the first palette entries; the rest are opaque.
+    Palette(Vec<u8>),
+}
+
+/// The default background color stored in a `bKGD` chunk. Its layout depends
+/// on the color type in `IHDR`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Background {
+    Gray(u16),
+    Rgb(u16, u16, u16),
+    /// An index into the palette.
+    PaletteIndex(u8),
+}
+
+impl Display for Transparency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transparency::Gray(gray) => write!(f, "gray {} is transparent", gray),
+            Transparency::Rgb(r, g, b) => write!(f, "rgb({}, {}, {}) is transparent", r, g, b),
+            Transparency::Palette(alphas) => {
+                write!(f, "alpha for {} palette entries", alphas.len())
+            }
+        }
+    }
+}
+
+impl Display for Background {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Background::Gray(gray) => write!(f, "gray {}", gray),
+            Background::Rgb(r, g, b) => write!(f, "rgb({}, {}, {})", r, g, b),
+            Background::PaletteIndex(index) => write!(f, "palette entry {}", index),
+        }
+    }
+}
+
+impl Transparency {
+    pub const CHUNK_TYPE: [u8; 4] = *b"tRNS";
+
+    /// Parses a `tRNS` chunk for an image of the given IHDR color type.
+    pub fn from_chunk(chunk: &Chunk, color_type: u8) -> Result<Transparency> {
+        let data = expect_chunk_type(chunk, Transparency::CHUNK_TYPE)?;
+        match (color_type, data.len()) {
+            (Ihdr::GRAYSCALE, 2) => Ok(Transparency::Gray(read_u16(data, 0))),
+            (Ihdr::TRUECOLOR, 6) => Ok(Transparency::Rgb(
+                read_u16(data, 0),
+                read_u16(data, 2),
+                read_u16(data, 4),
+            )),
+            (Ihdr::INDEXED, 1..=256) => Ok(Transparency::Palette(data.to_vec())),
+            (Ihdr::GRAYSCALE | Ihdr::TRUECOLOR | Ihdr::INDEXED, length) => Err(Box::new(
+                TransparencyError::InvalidLength(Transparency::CHUNK_TYPE, length),
+            )),
+            _ => Err(Box::new(TransparencyError::NotAllowed(
+                Transparency::CHUNK_TYPE,
+                color_type,
+            ))),
+        }
+    }
+    pub fn to_chunk(&self) -> Chunk {
+        let data = match self {
+            Transparency::Gray(gray) => gray.to_be_bytes().to_vec(),
+            Transparency::Rgb(r, g, b) => rgb_bytes(*r, *g, *b),
+            Transparency::Palette(alphas) => alphas.clone(),
+        };
+        Chunk::new(ChunkType::from_str("tRNS").unwrap(), data)
+    }
+}
+
+impl Background {
+    pub const CHUNK_TYPE: [u8; 4] = *b"bKGD";
+
+    /// Parses a `bKGD` chunk for an image of the given IHDR color type.
+    pub fn from_chunk(chunk: &Chunk, color_type: u8) -> Result<Background> {
+        let data = expect_chunk_type(chunk, Background::CHUNK_TYPE)?;
+        match (color_type, data.len()) {
+            (Ihdr::GRAYSCALE | Ihdr::GRAYSCALE_ALPHA, 2) => Ok(Background::Gray(read_u16(data, 0))),
+            (Ihdr::TRUECOLOR | Ihdr::TRUECOLOR_ALPHA, 6) => Ok(Background::Rgb(
+                read_u16(data, 0),
+                read_u16(data, 2),
+                read_u16(data, 4),
+            )),
+            (Ihdr::INDEXED, 1) => Ok(Background::PaletteIndex(data[0])),
+            (_, length) => Err(Box::new(TransparencyError::InvalidLength(
+                Background::CHUNK_TYPE,
+                length,
+            ))),
+        }
+    }
+    pub fn to_chunk(&self) -> Chunk {
+        let data = match self {
+            Background::Gray(gray) => gray.to_be_bytes().to_vec(),
+            Background::Rgb(r, g, b) => rgb_bytes(*r, *g, *b),
+            Background::PaletteIndex(index) => vec![*index],
+        };
+        Chunk::new(ChunkType::from_str("bKGD").unwrap(), data)
+    }
+}
+
+fn expect_chunk_type(chunk: &Chunk, chunk_type: [u8; 4]) -> Result<&[u8]> {
+    if chunk.chunk_type().bytes() != chunk_type {
+        return Err(Box::new(TransparencyError::WrongChunkType(
+            chunk_type,
+            chunk.chunk_type().to_string(),
+        )));
+    }
+    Ok(chunk.data())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn rgb_bytes(r: u16, g: u16, b: u16) -> Vec<u8> {
+    [r, g, b].iter().flat_map(|c| c.to_be_bytes()).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TransparencyError {
+    WrongChunkType([u8; 4], String),
+    InvalidLength([u8; 4], usize),
+    NotAllowed([u8; 4], u8),
+}
+impl std::error::Error for TransparencyError {}
+impl Display for TransparencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            TransparencyError::WrongChunkType(expected, found) => crate::tr!(
+                "transparency.wrong_chunk_type",
+                expected = expected.escape_ascii(),
+                found = found
+            ),
+            TransparencyError::InvalidLength(chunk_type, length) => crate::tr!(
+                "transparency.invalid_length",
+                chunk_type = chunk_type.escape_ascii(),
+                length = length
+            ),
+            TransparencyError::NotAllowed(chunk_type, color_type) => crate::tr!(
+                "transparency.not_allowed",
+                chunk_type = chunk_type.escape_ascii(),
+                color_type = color_type
+            ),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transparency_depends_on_color_type() {
+        let chunk = Transparency::Rgb(1, 2, 3).to_chunk();
+        assert_eq!(
+            Transparency::from_chunk(&chunk, Ihdr::TRUECOLOR).unwrap(),
+            Transparency::Rgb(1, 2, 3)
+        );
+        assert!(Transparency::from_chunk(&chunk, Ihdr::GRAYSCALE).is_err());
+        assert_eq!(
+            Transparency::from_chunk(&chunk, Ihdr::INDEXED).unwrap(),
+            Transparency::Palette(vec![0, 1, 0, 2, 0, 3])
+        );
+        assert!(Transparency::from_chunk(&chunk, Ihdr::TRUECOLOR_ALPHA).is_err());
+    }
+
+    #[test]
+    fn test_background() {
+        let chunk = Background::Gray(7).to_chunk();
+        assert_eq!(
+            Background::from_chunk(&chunk, Ihdr::GRAYSCALE_ALPHA).unwrap(),
+            Background::Gray(7)
+        );
+        assert!(Background::from_chunk(&chunk, Ihdr::INDEXED).is_err());
+
+        let chunk = Background::PaletteIndex(4).to_chunk();
+        assert_eq!(
+            Background::from_chunk(&chunk, Ihdr::INDEXED).unwrap(),
+            Background::PaletteIndex(4)
+        );
+    }
+}
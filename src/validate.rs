@@ -0,0 +1,526 @@
+use std::fmt::Display;
+
+use crate::{chunk::Chunk, png::Png};
+
+/// A requirement of the PNG specification that [`validate`] checks, with
+/// what to do about a file that breaks it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    /// A short, stable name for scripts to match on.
+    pub id: &'static str,
+    /// The section of the PNG specification (third edition) the rule is from.
+    pub section: &'static str,
+    pub requirement: &'static str,
+    pub fix: &'static str,
+}
+
+pub const RULES: [Rule; 14] = [
+    Rule {
+        id: "signature",
+        section: "5.2",
+        requirement: "A PNG starts with the eight bytes 89 50 4E 47 0D 0A 1A 0A.",
+        fix: "Check that the file is a PNG; if only the signature is damaged, restore it with \
+              a hex editor or read the file with --skip-signature.",
+    },
+    Rule {
+        id: "truncated",
+        section: "5.3",
+        requirement: "A chunk's length, type, data and CRC must all be present.",
+        fix: "The file was cut short; `pngme repair --truncate` keeps the complete chunks and \
+              ends the file with IEND.",
+    },
+    Rule {
+        id: "length",
+        section: "5.3",
+        requirement: "A chunk's data is at most 2^31 - 1 bytes long.",
+        fix: "The length field is corrupt; the chunks after it cannot be located reliably.",
+    },
+    Rule {
+        id: "chunk-type",
+        section: "5.3",
+        requirement: "A chunk type is four ASCII letters (A-Z, a-z).",
+        fix: "The chunk is corrupt, or the previous chunk's length is wrong; remove it with \
+              `pngme remove-at --offset`.",
+    },
+    Rule {
+        id: "reserved-bit",
+        section: "5.4",
+        requirement: "The third letter of a chunk type is uppercase, the reserved bit being 0.",
+        fix:
+            "Readers may reject the chunk; rename it or remove it with `pngme remove-at --offset`.",
+    },
+    Rule {
+        id: "crc",
+        section: "5.3",
+        requirement: "A chunk's CRC is the CRC-32 of its type and data.",
+        fix: "The type or data was changed after the CRC was computed; restore the file from a \
+              backup, or remove the chunk with `pngme remove-at --offset`.",
+    },
+    Rule {
+        id: "ancillary-crc",
+        section: "5.3",
+        requirement: "An ancillary chunk's CRC is the CRC-32 of its type and data.",
+        fix: "Decoders that check it will skip the chunk; remove it with \
+              `pngme remove-at --offset` or restore the file from a backup.",
+    },
+    Rule {
+        id: "ihdr-first",
+        section: "5.6",
+        requirement: "IHDR is the first chunk and appears exactly once.",
+        fix: "Restore the header from a copy of the image; without it the pixels cannot be read.",
+    },
+    Rule {
+        id: "idat-present",
+        section: "5.6",
+        requirement: "An image has at least one IDAT chunk.",
+        fix: "The image data is missing; restore it from a copy of the image.",
+    },
+    Rule {
+        id: "idat-consecutive",
+        section: "5.6",
+        requirement: "IDAT chunks follow one another with no other chunks in between.",
+        fix: "Move the chunks between them, for example with `pngme remove-at` and `pngme encode`.",
+    },
+    Rule {
+        id: "idat-empty",
+        section: "11.2.4",
+        requirement: "Each IDAT chunk carries part of the compressed image data.",
+        fix: "Remove the empty chunk with `pngme remove-at --offset --force-critical`.",
+    },
+    Rule {
+        id: "iend-last",
+        section: "5.6",
+        requirement: "IEND is the last chunk and appears exactly once.",
+        fix: "`pngme repair --truncate` ends the file with IEND.",
+    },
+    Rule {
+        id: "trailing-data",
+        section: "5.6",
+        requirement: "Nothing follows the IEND chunk.",
+        fix: "Decoders ignore the bytes, but `pngme scrub --strip-trailer` removes them along \
+              with the metadata chunks.",
+    },
+    Rule {
+        id: "plte-length",
+        section: "11.2.3",
+        requirement: "PLTE holds 1 to 256 entries of three bytes each.",
+        fix: "The palette is corrupt; restore it from a copy of the image.",
+    },
+];
+
+impl Rule {
+    pub fn by_id(id: &str) -> Option<&'static Rule> {
+        RULES.iter().find(|r| r.id == id)
+    }
+}
+
+/// The decoder a file is validated for, which decides the rules that
+/// matter; real-world decoders tolerate much that the specification forbids.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationProfile {
+    /// Every rule in [`RULES`].
+    #[default]
+    Strict,
+    /// What web browsers still display.
+    Browser,
+    /// What libpng reads with its default settings.
+    Libpng,
+}
+
+impl ValidationProfile {
+    /// The ids of the rules this profile does not enforce.
+    pub fn tolerated(&self) -> &'static [&'static str] {
+        match self {
+            ValidationProfile::Strict => &[],
+            ValidationProfile::Browser => &[
+                "ancillary-crc",
+                "reserved-bit",
+                "idat-empty",
+                "iend-last",
+                "trailing-data",
+            ],
+            ValidationProfile::Libpng => &[
+                "ancillary-crc",
+                "reserved-bit",
+                "idat-empty",
+                "trailing-data",
+            ],
+        }
+    }
+    pub fn enforces(&self, rule: &Rule) -> bool {
+        !self.tolerated().contains(&rule.id)
+    }
+}
+
+/// A place where a file breaks a [`Rule`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: &'static Rule,
+    /// The byte range that breaks the rule.
+    pub offset: usize,
+    pub length: usize,
+    pub detail: String,
+}
+
+impl Violation {
+    fn new(id: &str, offset: usize, length: usize, detail: String) -> Violation {
+        Violation {
+            rule: Rule::by_id(id).expect("the rule is in RULES"),
+            offset,
+            length,
+            detail,
+        }
+    }
+    /// Hexdump lines of `bytes` around the violation, marking with `>` the
+    /// lines that contain the offending bytes.
+    pub fn hexdump(&self, bytes: &[u8]) -> Vec<String> {
+        const WIDTH: usize = 16;
+        let end = (self.offset + self.length.clamp(1, 2 * WIDTH)).min(bytes.len());
+        let first = (self.offset / WIDTH).saturating_sub(1) * WIDTH;
+        let last = (end.div_ceil(WIDTH) + 1) * WIDTH;
+        (first..last.min(bytes.len()))
+            .step_by(WIDTH)
+            .map(|start| {
+                let line = &bytes[start..(start + WIDTH).min(bytes.len())];
+                let marked = start < end && self.offset < start + WIDTH;
+                let hex: Vec<String> = line.iter().map(|b| format!("{:02x}", b)).collect();
+                let text: String = line
+                    .iter()
+                    .map(|&b| match b {
+                        0x20..=0x7e => b as char,
+                        _ => '.',
+                    })
+                    .collect();
+                format!(
+                    "{} {:08x}  {:<47}  |{}|",
+                    if marked { '>' } else { ' ' },
+                    start,
+                    hex.join(" "),
+                    text
+                )
+            })
+            .collect()
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at offset {:#x}: {}",
+            self.rule.id, self.offset, self.detail
+        )
+    }
+}
+
+/// Checks `bytes` against the rules `profile` enforces, carrying on past
+/// problems so that all of them are reported.
+pub fn validate_for(bytes: &[u8], profile: ValidationProfile) -> Vec<Violation> {
+    validate(bytes)
+        .into_iter()
+        .filter(|v| profile.enforces(v.rule))
+        .collect()
+}
+
+/// Checks `bytes` against every rule in [`RULES`], carrying on past
+/// problems so that all of them are reported. Scanning stops only where the
+/// chunk boundaries can no longer be trusted.
+pub fn validate(bytes: &[u8]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut idx = Png::STANDARD_HEADER.len();
+    if !bytes.starts_with(&Png::STANDARD_HEADER) {
+        violations.push(Violation::new(
+            "signature",
+            0,
+            Png::STANDARD_HEADER.len().min(bytes.len()),
+            "the file does not start with the PNG signature".to_string(),
+        ));
+    }
+
+    // The type and offset of each chunk that could be read.
+    let mut chunks: Vec<([u8; 4], usize, usize)> = Vec::new();
+    while idx < bytes.len() {
+        let Some(header) = bytes.get(idx..idx + 8) else {
+            violations.push(Violation::new(
+                "truncated",
+                idx,
+                bytes.len() - idx,
+                format!("{} bytes left, too few for a chunk", bytes.len() - idx),
+            ));
+            break;
+        };
+        let length = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        let chunk_type: [u8; 4] = header[4..].try_into().unwrap();
+        let name = String::from_utf8_lossy(&chunk_type).into_owned();
+        if length > Chunk::MAX_LENGTH {
+            violations.push(Violation::new(
+                "length",
+                idx,
+                Chunk::LENGTH_SIZE,
+                format!("{} chunk claims {} bytes", name, length),
+            ));
+            break;
+        }
+        if !chunk_type.iter().all(u8::is_ascii_alphabetic) {
+            violations.push(Violation::new(
+                "chunk-type",
+                idx + Chunk::LENGTH_SIZE,
+                Chunk::CHUNK_TYPE_SIZE,
+                format!("{:?} is not four letters", name),
+            ));
+        } else if chunk_type[2].is_ascii_lowercase() {
+            violations.push(Violation::new(
+                "reserved-bit",
+                idx + Chunk::LENGTH_SIZE + 2,
+                1,
+                format!("{} has a lowercase third letter", name),
+            ));
+        }
+
+        let end = idx + length + Chunk::METADATA_SIZE;
+        if end > bytes.len() {
+            violations.push(Violation::new(
+                "truncated",
+                idx,
+                bytes.len() - idx,
+                format!(
+                    "{} chunk needs {} bytes but only {} remain",
+                    name,
+                    length + Chunk::METADATA_SIZE,
+                    bytes.len() - idx
+                ),
+            ));
+            chunks.push((chunk_type, idx, length));
+            break;
+        }
+        let crc_start = end - Chunk::CRC_SIZE;
+        let stored = u32::from_be_bytes(bytes[crc_start..end].try_into().unwrap());
+        let actual = crc::crc32::checksum_ieee(&bytes[idx + Chunk::LENGTH_SIZE..crc_start]);
+        if stored != actual {
+            violations.push(Violation::new(
+                match chunk_type[0].is_ascii_uppercase() {
+                    true => "crc",
+                    false => "ancillary-crc",
+                },
+                crc_start,
+                Chunk::CRC_SIZE,
+                format!(
+                    "{} chunk stores {:#010x} but its contents give {:#010x}",
+                    name, stored, actual
+                ),
+            ));
+        }
+        if &chunk_type == b"PLTE" && (length == 0 || !length.is_multiple_of(3) || length > 768) {
+            violations.push(Violation::new(
+                "plte-length",
+                idx,
+                Chunk::LENGTH_SIZE,
+                format!("PLTE chunk is {} bytes long", length),
+            ));
+        }
+        if &chunk_type == b"IDAT" && length == 0 {
+            let detail = "IDAT chunk is empty".to_string();
+            violations.push(Violation::new(
+                "idat-empty",
+                idx,
+                Chunk::LENGTH_SIZE,
+                detail,
+            ));
+        }
+        chunks.push((chunk_type, idx, length));
+        idx = end;
+        if &chunk_type == b"IEND" {
+            if idx < bytes.len() {
+                violations.push(Violation::new(
+                    "trailing-data",
+                    idx,
+                    bytes.len() - idx,
+                    format!("{} bytes follow IEND", bytes.len() - idx),
+                ));
+            }
+            break;
+        }
+    }
+
+    check_ordering(&chunks, bytes.len(), &mut violations);
+    violations.sort_by_key(|v| v.offset);
+    violations
+}
+
+fn check_ordering(
+    chunks: &[([u8; 4], usize, usize)],
+    file_length: usize,
+    violations: &mut Vec<Violation>,
+) {
+    let span =
+        |(_, offset, length): &([u8; 4], usize, usize)| (*offset, length + Chunk::METADATA_SIZE);
+    match chunks.first() {
+        Some(first) if &first.0 != b"IHDR" => {
+            let (offset, length) = span(first);
+            let name = String::from_utf8_lossy(&first.0);
+            let detail = format!("the first chunk is {}", name);
+            violations.push(Violation::new("ihdr-first", offset, length, detail));
+        }
+        None => violations.push(Violation::new(
+            "ihdr-first",
+            file_length,
+            0,
+            "the file has no chunks".to_string(),
+        )),
+        _ => {}
+    }
+    for chunk in chunks.iter().skip(1).filter(|c| &c.0 == b"IHDR") {
+        let (offset, length) = span(chunk);
+        let detail = "IHDR appears again".to_string();
+        violations.push(Violation::new("ihdr-first", offset, length, detail));
+    }
+
+    let idats: Vec<usize> = (0..chunks.len())
+        .filter(|&i| &chunks[i].0 == b"IDAT")
+        .collect();
+    match (idats.first(), idats.last()) {
+        (Some(&first), Some(&last)) => {
+            for chunk in &chunks[first..last] {
+                if &chunk.0 != b"IDAT" {
+                    let (offset, length) = span(chunk);
+                    let detail = format!(
+                        "{} sits between IDAT chunks",
+                        String::from_utf8_lossy(&chunk.0)
+                    );
+                    violations.push(Violation::new("idat-consecutive", offset, length, detail));
+                }
+            }
+        }
+        _ if !chunks.is_empty() => violations.push(Violation::new(
+            "idat-present",
+            chunks[0].1,
+            0,
+            "the file has no IDAT chunk".to_string(),
+        )),
+        _ => {}
+    }
+
+    if chunks.last().is_none_or(|c| &c.0 != b"IEND") {
+        violations.push(Violation::new(
+            "iend-last",
+            file_length,
+            0,
+            "the file does not end with IEND".to_string(),
+        ));
+    }
+}
+
+/// The violations as a JSON object, including the hexdump window around
+/// each.
+pub fn to_json(bytes: &[u8], violations: &[Violation]) -> String {
+    let violations: Vec<String> = violations
+        .iter()
+        .map(|v| {
+            let window: Vec<String> = v.hexdump(bytes).iter().map(|l| json_string(l)).collect();
+            format!(
+                "{{\"rule\":{},\"section\":{},\"requirement\":{},\"offset\":{},\"length\":{},\"detail\":{},\"fix\":{},\"hexdump\":[{}]}}",
+                json_string(v.rule.id),
+                json_string(v.rule.section),
+                json_string(v.rule.requirement),
+                v.offset,
+                v.length,
+                json_string(&v.detail),
+                json_string(v.rule.fix),
+                window.join(",")
+            )
+        })
+        .collect();
+    format!(
+        "{{\"valid\":{},\"violations\":[{}]}}",
+        violations.is_empty(),
+        violations.join(",")
+    )
+}
+
+/// `s` as a quoted and escaped JSON string.
+pub fn json_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    fn ids(bytes: &[u8]) -> Vec<&'static str> {
+        validate(bytes).iter().map(|v| v.rule.id).collect()
+    }
+
+    #[test]
+    fn test_valid_file() {
+        assert!(validate(&PNG_FILE).is_empty());
+    }
+
+    #[test]
+    fn test_reports_every_violation() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes[0] = 0;
+        let iend = bytes.len() - Chunk::METADATA_SIZE;
+        bytes[iend - 1] ^= 0xff;
+        bytes.truncate(iend);
+        assert_eq!(ids(&bytes), ["signature", "crc", "iend-last"]);
+
+        let crc = validate(&bytes).remove(1);
+        assert_eq!(crc.offset, iend - Chunk::CRC_SIZE);
+        assert_eq!(crc.rule.section, "5.3");
+    }
+
+    #[test]
+    fn test_truncated_chunk() {
+        let bytes = &PNG_FILE[..100];
+        assert_eq!(ids(bytes), ["truncated", "iend-last"]);
+    }
+
+    #[test]
+    fn test_profiles() {
+        let mut bytes = PNG_FILE.to_vec();
+        let iend = bytes.len() - Chunk::METADATA_SIZE;
+        // The last byte of the ancillary sRGB chunk's CRC.
+        bytes[45] ^= 0xff;
+        bytes.extend_from_slice(b"trailer");
+        let ids = |bytes: &[u8], profile| -> Vec<&str> {
+            validate_for(bytes, profile)
+                .iter()
+                .map(|v| v.rule.id)
+                .collect()
+        };
+        assert_eq!(
+            ids(&bytes, ValidationProfile::Strict),
+            ["ancillary-crc", "trailing-data"]
+        );
+        assert!(ids(&bytes, ValidationProfile::Browser).is_empty());
+        assert!(ids(&bytes, ValidationProfile::Libpng).is_empty());
+
+        bytes.truncate(iend);
+        assert!(ids(&bytes, ValidationProfile::Browser).is_empty());
+        assert_eq!(ids(&bytes, ValidationProfile::Libpng), ["iend-last"]);
+    }
+
+    #[test]
+    fn test_hexdump_window() {
+        let violation = Violation::new("crc", 0x25, 4, String::new());
+        let lines = violation.hexdump(&PNG_FILE);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("  00000010"));
+        assert!(lines[1].starts_with("> 00000020"));
+        assert!(lines[2].starts_with("  00000030"));
+
+        let json = to_json(&PNG_FILE, &[violation]);
+        assert!(json.starts_with("{\"valid\":false,\"violations\":[{\"rule\":\"crc\""));
+    }
+}
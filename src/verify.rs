@@ -0,0 +1,177 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use sha2::{Digest, Sha256};
+
+use crate::{png::Png, Error, Result};
+
+/// A digest that a chunk's payload is expected to hash to, written as
+/// `sha256:<hex>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpectedHash {
+    Sha256([u8; 32]),
+}
+
+impl FromStr for ExpectedHash {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (algorithm, digest) = s
+            .split_once(':')
+            .ok_or_else(|| VerifyError::InvalidHash(s.to_string()))?;
+        if algorithm != "sha256" {
+            return Err(Box::new(VerifyError::UnsupportedAlgorithm(
+                algorithm.to_string(),
+            )));
+        }
+        if digest.len() != 64 || !digest.is_ascii() {
+            return Err(Box::new(VerifyError::InvalidHash(s.to_string())));
+        }
+
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&digest[i * 2..i * 2 + 2], 16)
+                .map_err(|_| VerifyError::InvalidHash(s.to_string()))?;
+        }
+        Ok(ExpectedHash::Sha256(bytes))
+    }
+}
+
+/// The outcome of a single check within a [`VerifyReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyCheck {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Whether a chunk exists, is intact and, optionally, carries the expected
+/// payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    pub chunk_type: String,
+    pub checks: Vec<VerifyCheck>,
+}
+
+impl VerifyReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+    /// The first failed check, if any.
+    pub fn failure(&self) -> Option<&VerifyCheck> {
+        self.checks.iter().find(|c| !c.passed)
+    }
+}
+
+impl Display for VerifyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "verify {}", self.chunk_type)?;
+        for check in &self.checks {
+            let status = if check.passed { "pass" } else { "fail" };
+            writeln!(f, "  {}: {} ({})", check.name, status, check.detail)?;
+        }
+        let result = if self.passed() { "PASS" } else { "FAIL" };
+        writeln!(f, "result: {}", result)
+    }
+}
+
+/// Checks that `png` contains a chunk of type `chunk_type` whose CRCs are
+/// valid and, if `expected` is given, that the first such chunk's payload
+/// hashes to it. Checks that depend on a failed one are skipped.
+pub fn verify(png: &Png, chunk_type: &str, expected: Option<&ExpectedHash>) -> VerifyReport {
+    let chunks: Vec<_> = png.chunks_of(chunk_type).collect();
+    let mut report = VerifyReport {
+        chunk_type: chunk_type.to_string(),
+        checks: vec![VerifyCheck {
+            name: "present",
+            passed: !chunks.is_empty(),
+            detail: format!("{} chunks", chunks.len()),
+        }],
+    };
+    if chunks.is_empty() {
+        return report;
+    }
+
+    let bad_crcs = chunks.iter().filter(|c| !c.is_crc_valid()).count();
+    report.checks.push(VerifyCheck {
+        name: "crc",
+        passed: bad_crcs == 0,
+        detail: format!("{} of {} chunks invalid", bad_crcs, chunks.len()),
+    });
+
+    if let Some(ExpectedHash::Sha256(expected)) = expected {
+        let actual: [u8; 32] = Sha256::digest(chunks[0].data()).into();
+        report.checks.push(VerifyCheck {
+            name: "sha256",
+            passed: &actual == expected,
+            detail: hex(&actual),
+        });
+    }
+    report
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum VerifyError {
+    InvalidHash(String),
+    UnsupportedAlgorithm(String),
+}
+impl std::error::Error for VerifyError {}
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            VerifyError::InvalidHash(hash) => {
+                crate::tr!("verify.invalid_hash", hash = format!("{:?}", hash))
+            }
+            VerifyError::UnsupportedAlgorithm(algorithm) => {
+                crate::tr!("verify.unsupported_algorithm", algorithm = algorithm)
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    // sha256("hey"), the payload of the fixture's RuSt chunk.
+    const HEY_SHA256: &str =
+        "sha256:fa690b82061edfd2852629aeba8a8977b57e40fcb77d1a7a28b26cba62591204";
+
+    #[test]
+    fn test_verify_passes() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let expected = ExpectedHash::from_str(HEY_SHA256).unwrap();
+        let report = verify(&png, "RuSt", Some(&expected));
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.checks.len(), 3);
+    }
+
+    #[test]
+    fn test_verify_missing_chunk() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let report = verify(&png, "teSt", None);
+        assert!(!report.passed());
+        assert_eq!(report.failure().unwrap().name, "present");
+        assert_eq!(report.checks.len(), 1);
+    }
+
+    #[test]
+    fn test_verify_hash_mismatch() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let expected = ExpectedHash::from_str(&format!("sha256:{}", "0".repeat(64))).unwrap();
+        let report = verify(&png, "RuSt", Some(&expected));
+        assert_eq!(report.failure().unwrap().name, "sha256");
+    }
+
+    #[test]
+    fn test_invalid_expected_hash() {
+        assert!(ExpectedHash::from_str("md5:abcd").is_err());
+        assert!(ExpectedHash::from_str("sha256:xyz").is_err());
+    }
+}
@@ -0,0 +1,51 @@
+use std::str::FromStr;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, png::Png};
+
+/// A JavaScript-facing wrapper around [`Png`]. Byte slices and vectors map to
+/// `Uint8Array` on the JS side.
+#[wasm_bindgen(js_name = Png)]
+pub struct WasmPng {
+    png: Png,
+}
+
+#[wasm_bindgen(js_class = Png)]
+impl WasmPng {
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: &[u8]) -> Result<WasmPng, JsError> {
+        let png = Png::try_from(bytes).map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(WasmPng { png })
+    }
+
+    /// Returns the data of the first chunk of the given type, if any.
+    #[wasm_bindgen(js_name = chunkByType)]
+    pub fn chunk_by_type(&self, chunk_type: &str) -> Option<Vec<u8>> {
+        self.png
+            .chunk(chunk_type)
+            .map(|chunk| chunk.data().to_vec())
+    }
+
+    #[wasm_bindgen(js_name = appendChunk)]
+    pub fn append_chunk(&mut self, chunk_type: &str, data: &[u8]) -> Result<(), JsError> {
+        let chunk_type =
+            ChunkType::from_str(chunk_type).map_err(|e| JsError::new(&e.to_string()))?;
+        self.png.append_chunk(Chunk::new(chunk_type, data.to_vec()));
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = chunkTypes)]
+    pub fn chunk_types(&self) -> Vec<String> {
+        self.png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.png.as_bytes()
+    }
+}
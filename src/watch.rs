@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use pngme::tr;
+use sha2::{Digest, Sha256};
+
+use crate::commands::outln;
+
+/// Runs a pngme command on every PNG that is added to or modified in a
+/// directory. Each run is a separate pngme process, so one failure does not
+/// stop the watch.
+pub struct DirWatcher {
+    dir: PathBuf,
+    recursive: bool,
+    /// The command's arguments, with `{}` standing for the file.
+    on_add: Vec<String>,
+    /// How long a file must go unchanged before it is processed, so that it
+    /// is not read while still being written.
+    debounce: Duration,
+    /// Each processed file's digest after the command ran, so that the
+    /// command's own writes do not trigger it again.
+    processed: HashMap<PathBuf, [u8; 32]>,
+}
+
+impl DirWatcher {
+    pub fn new(
+        dir: &Path,
+        on_add: &str,
+        recursive: bool,
+        debounce: Duration,
+    ) -> pngme::Result<DirWatcher> {
+        let on_add = split_args(on_add)?;
+        if !on_add.iter().any(|arg| arg.contains("{}")) {
+            return Err(tr!("error.on_add_placeholder").into());
+        }
+        Ok(DirWatcher {
+            dir: dir.to_owned(),
+            recursive,
+            on_add,
+            debounce,
+            processed: HashMap::new(),
+        })
+    }
+
+    /// Watches until the process is interrupted or watching fails.
+    pub fn run(&mut self) -> pngme::Result<()> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        let mode = match self.recursive {
+            true => RecursiveMode::Recursive,
+            false => RecursiveMode::NonRecursive,
+        };
+        watcher.watch(&self.dir, mode)?;
+        log::info!("Watching {}", self.dir.display());
+
+        let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+        loop {
+            match rx.recv_timeout(self.debounce / 4) {
+                Ok(event) => {
+                    for path in changed_pngs(event?) {
+                        pending.insert(path, Instant::now());
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+            let settled: Vec<PathBuf> = pending
+                .iter()
+                .filter(|(_, changed)| changed.elapsed() >= self.debounce)
+                .map(|(path, _)| path.clone())
+                .collect();
+            for path in settled {
+                pending.remove(&path);
+                self.process(&path);
+            }
+        }
+    }
+
+    fn process(&mut self, path: &Path) {
+        let Ok(bytes) = std::fs::read(path) else {
+            log::debug!("{} is gone, skipping", path.display());
+            return;
+        };
+        let digest: [u8; 32] = Sha256::digest(&bytes).into();
+        if self.processed.get(path) == Some(&digest) {
+            log::debug!("{} is unchanged since it was processed", path.display());
+            return;
+        }
+
+        // The path is spliced in as an OS string, so names that are not
+        // valid Unicode reach the command intact.
+        let args: Vec<OsString> = self
+            .on_add
+            .iter()
+            .map(|arg| {
+                let mut spliced = OsString::new();
+                for (i, part) in arg.split("{}").enumerate() {
+                    if i > 0 {
+                        spliced.push(path);
+                    }
+                    spliced.push(part);
+                }
+                spliced
+            })
+            .collect();
+        let shown: Vec<_> = args.iter().map(|arg| arg.to_string_lossy()).collect();
+        log::info!("Running pngme {}", shown.join(" "));
+        let status = std::env::current_exe().and_then(|exe| Command::new(exe).args(&args).status());
+        match status {
+            Ok(status) if status.success() => {
+                outln!("{}", tr!("output.watch_ok", file = path.display()))
+            }
+            Ok(status) => match status.code() {
+                Some(code) => log::error!("{}: failed with exit code {}", path.display(), code),
+                None => log::error!("{}: terminated by a signal", path.display()),
+            },
+            Err(e) => log::error!("{}: {}", path.display(), e),
+        }
+        if let Ok(bytes) = std::fs::read(path) {
+            self.processed
+                .insert(path.to_owned(), Sha256::digest(&bytes).into());
+        }
+    }
+}
+
+/// The PNG files an event adds or modifies.
+fn changed_pngs(event: Event) -> Vec<PathBuf> {
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return Vec::new();
+    }
+    event
+        .paths
+        .into_iter()
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+        })
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Splits a command line into arguments as a POSIX shell would, honouring
+/// single and double quotes and backslash escapes but nothing else.
+fn split_args(line: &str) -> pngme::Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg = None::<String>;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => args.extend(arg.take()),
+            '\'' => {
+                let quoted: String = chars.by_ref().take_while(|&c| c != '\'').collect();
+                arg.get_or_insert_with(String::new).push_str(&quoted);
+            }
+            '"' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => arg.extend(chars.next()),
+                        Some(c) => arg.push(c),
+                        None => {
+                            let line = format!("{:?}", line);
+                            return Err(tr!("error.unterminated_quote", line = line).into());
+                        }
+                    }
+                }
+            }
+            '\\' => arg.get_or_insert_with(String::new).extend(chars.next()),
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+    Ok(args)
+}
@@ -0,0 +1,277 @@
+//! Runs the `pngme` binary against files in a scratch directory.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::str::FromStr;
+
+use pngme::{chunk::Chunk, chunk_type::ChunkType, png::Png};
+
+/// An empty directory for one test, removed when dropped.
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new(name: &str) -> Scratch {
+        let dir = std::env::temp_dir().join(format!("pngme-cli-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Scratch(dir)
+    }
+    fn path(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+    /// Writes a small solid image with the given extra chunks before `IEND`.
+    fn png(&self, name: &str, chunks: &[(&str, &str)]) -> PathBuf {
+        let mut png = pngme::encoder::solid_color(8, 8, [0x20, 0x40, 0x80]).unwrap();
+        for (chunk_type, data) in chunks {
+            let chunk_type = ChunkType::from_str(chunk_type).unwrap();
+            png.append_chunk(Chunk::new(chunk_type, data.as_bytes().to_vec()));
+        }
+        let path = self.path(name);
+        std::fs::write(&path, png.as_bytes()).unwrap();
+        path
+    }
+    fn pngme(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_pngme"))
+            .args(["--lang", "en", "--color", "never"])
+            .args(args)
+            .env("PNGME_CONFIG", self.path("config.toml"))
+            .current_dir(&self.0)
+            .output()
+            .unwrap()
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn chunk_types(path: &Path) -> Vec<String> {
+    let png = Png::try_from(std::fs::read(path).unwrap().as_slice()).unwrap();
+    png.chunks()
+        .iter()
+        .map(|c| c.chunk_type().to_string())
+        .collect()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_unsafe_chunks_dropped_when_image_data_changes() {
+    let scratch = Scratch::new("unsafe");
+    let file = scratch.png("in.png", &[("ruSt", "safe"), ("ruST", "unsafe")]);
+
+    let removed = scratch.path("removed.png");
+    std::fs::copy(&file, &removed).unwrap();
+    stdout(&scratch.pngme(&["remove", "removed.png", "ruSt"]));
+    assert_eq!(chunk_types(&removed), ["IHDR", "IDAT", "ruST", "IEND"]);
+
+    stdout(&scratch.pngme(&["rechunk", "in.png", "--idat-size", "8", "-o", "out.png"]));
+    let types = chunk_types(&scratch.path("out.png"));
+    assert!(types.contains(&"ruSt".to_string()));
+    assert!(!types.contains(&"ruST".to_string()));
+}
+
+#[test]
+fn test_encode_lsb_drops_unsafe_chunks() {
+    let scratch = Scratch::new("lsb");
+    scratch.png("in.png", &[("ruSt", "safe"), ("ruST", "unsafe")]);
+    std::fs::write(scratch.path("message.txt"), "hi").unwrap();
+
+    let args = [
+        "encode",
+        "in.png",
+        "--mode",
+        "lsb",
+        "--input-file",
+        "message.txt",
+    ];
+    stdout(&scratch.pngme(&args));
+    let types = chunk_types(&scratch.path("in.png"));
+    assert!(types.contains(&"ruSt".to_string()));
+    assert!(!types.contains(&"ruST".to_string()));
+    let decoded = stdout(&scratch.pngme(&["decode", "in.png", "--mode", "lsb"]));
+    assert_eq!(decoded.trim_end(), "hi");
+}
+
+#[test]
+fn test_raw_decode_accepts_bad_crc() {
+    let scratch = Scratch::new("raw");
+    let file = scratch.png("bad.png", &[("ruSt", "message")]);
+    let mut bytes = std::fs::read(&file).unwrap();
+    // The CRC of ruSt is the last four bytes before IEND.
+    let crc = bytes.len() - 12 - 1;
+    bytes[crc] ^= 0xff;
+    std::fs::write(&file, bytes).unwrap();
+
+    assert!(!scratch
+        .pngme(&["decode", "bad.png", "ruSt"])
+        .status
+        .success());
+    let decoded = stdout(&scratch.pngme(&["--raw", "decode", "bad.png", "ruSt"]));
+    assert_eq!(decoded.trim_end(), "message");
+}
+
+#[test]
+fn test_encode_trailer_writes_output_path() {
+    let scratch = Scratch::new("trailer");
+    let file = scratch.png("in.png", &[]);
+    let before = std::fs::read(&file).unwrap();
+
+    stdout(&scratch.pngme(&["encode", "in.png", "--trailer", "hello", "out.png"]));
+    assert_eq!(std::fs::read(&file).unwrap(), before);
+    let decoded = stdout(&scratch.pngme(&["decode", "out.png", "--trailer"]));
+    assert_eq!(decoded.trim_end(), "hello");
+}
+
+#[test]
+fn test_deterministic_encode() {
+    let scratch = Scratch::new("deterministic");
+    let file = scratch.png("a.png", &[]);
+    std::fs::copy(&file, scratch.path("b.png")).unwrap();
+
+    for name in ["a.png", "b.png"] {
+        let args = [
+            "--deterministic",
+            "encode",
+            name,
+            "ruSt",
+            "hi",
+            "--meta",
+            "author=sj",
+        ];
+        stdout(&scratch.pngme(&args));
+    }
+    let a = std::fs::read(scratch.path("a.png")).unwrap();
+    assert_eq!(a, std::fs::read(scratch.path("b.png")).unwrap());
+
+    let args = [
+        "--deterministic",
+        "encode",
+        "a.png",
+        "teSt",
+        "hi",
+        "--timestamp",
+    ];
+    assert!(!scratch.pngme(&args).status.success());
+    assert_eq!(std::fs::read(scratch.path("a.png")).unwrap(), a);
+}
+
+/// Byte offset within the file of each chunk.
+fn chunk_offsets(path: &Path) -> Vec<usize> {
+    let png = Png::try_from(std::fs::read(path).unwrap().as_slice()).unwrap();
+    let mut offset = 8;
+    png.chunks()
+        .iter()
+        .map(|c| {
+            let start = offset;
+            offset += c.as_bytes().len();
+            start
+        })
+        .collect()
+}
+
+#[test]
+fn test_remove_dry_run_reports_one_removal() {
+    let scratch = Scratch::new("remove-dry-run");
+    let file = scratch.png("in.png", &[("tEXt", "a"), ("tEXt", "b"), ("tEXt", "c")]);
+    let before = std::fs::read(&file).unwrap();
+
+    let output = stdout(&scratch.pngme(&["remove", "in.png", "tEXt", "--dry-run"]));
+    let changes: Vec<&str> = output.lines().skip(1).collect();
+    let offset = chunk_offsets(&file)[2];
+    assert_eq!(changes.len(), 1, "{}", output);
+    assert!(changes[0].starts_with("- tEXt[0] "), "{}", output);
+    assert!(
+        changes[0].ends_with(&format!(" at offset {}", offset)),
+        "{}",
+        output
+    );
+    assert_eq!(std::fs::read(&file).unwrap(), before);
+}
+
+#[test]
+fn test_encode_dry_run_reports_one_addition() {
+    let scratch = Scratch::new("encode-dry-run");
+    let file = scratch.png("in.png", &[("ruSt", "a"), ("ruSt", "b")]);
+    let before = std::fs::read(&file).unwrap();
+
+    let output =
+        stdout(&scratch.pngme(&["encode", "in.png", "ruSt", "c", "--append", "--dry-run"]));
+    let changes: Vec<&str> = output.lines().skip(1).collect();
+    let offset = *chunk_offsets(&file).last().unwrap();
+    assert_eq!(changes.len(), 1, "{}", output);
+    assert!(changes[0].starts_with("+ ruSt[2] "), "{}", output);
+    assert!(
+        changes[0].ends_with(&format!(" at offset {}", offset)),
+        "{}",
+        output
+    );
+    assert_eq!(std::fs::read(&file).unwrap(), before);
+}
+
+#[test]
+fn test_max_growth_counts_history() {
+    let scratch = Scratch::new("max-growth");
+    let file = scratch.png("in.png", &[]);
+    let before = std::fs::read(&file).unwrap();
+
+    let args = ["encode", "in.png", "ruSt", "hi", "--max-growth", "20"];
+    let output = scratch.pngme(
+        &["--history"]
+            .iter()
+            .chain(&args)
+            .copied()
+            .collect::<Vec<_>>(),
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("over the budget"), "{}", stderr);
+    assert_eq!(std::fs::read(&file).unwrap(), before);
+
+    stdout(&scratch.pngme(&args));
+    assert_eq!(chunk_types(&file), ["IHDR", "IDAT", "ruSt", "IEND"]);
+}
+
+#[test]
+fn test_grep_exit_codes() {
+    let scratch = Scratch::new("grep");
+    scratch.png("in.png", &[("ruSt", "needle")]);
+    std::fs::write(scratch.path("bad.png"), b"not a png").unwrap();
+
+    let code = |args: &[&str]| scratch.pngme(args).status.code();
+    assert_eq!(code(&["grep", "needle", "in.png"]), Some(0));
+    assert_eq!(code(&["grep", "haystack", "in.png"]), Some(1));
+    assert_eq!(code(&["grep", "needle", "in.png", "bad.png"]), Some(2));
+    assert_eq!(code(&["grep", "--hex", "xyz", "in.png"]), Some(2));
+}
+
+#[test]
+fn test_repair_truncated_writes_a_new_file() {
+    let scratch = Scratch::new("repair");
+    let file = scratch.png("in.png", &[("ruSt", "cut short")]);
+    let mut bytes = std::fs::read(&file).unwrap();
+    // Cut into ruSt, then into IDAT.
+    let offsets = chunk_offsets(&file);
+    bytes.truncate(offsets[2] + 10);
+    std::fs::write(&file, &bytes).unwrap();
+
+    stdout(&scratch.pngme(&["repair", "in.png", "--truncate"]));
+    assert_eq!(std::fs::read(&file).unwrap(), bytes);
+    let repaired = scratch.path("in.repaired.png");
+    assert_eq!(chunk_types(&repaired), ["IHDR", "IDAT", "IEND"]);
+
+    bytes.truncate(offsets[1] + 10);
+    std::fs::write(&file, &bytes).unwrap();
+    let output = scratch.pngme(&["repair", "in.png", "out.png", "--truncate"]);
+    assert!(!output.status.success());
+    assert!(!scratch.path("out.png").exists());
+}